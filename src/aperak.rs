@@ -0,0 +1,416 @@
+use pyo3::prelude::*;
+
+use crate::common::{DateTimePeriod, Party, Reference};
+use crate::directory::SegmentGroup;
+use crate::document::{segment_of, Document};
+use crate::header::{component, InterchangeHeader, MessageHeader};
+use crate::{Message, Order, Segment};
+
+/// An application error reported in APERAK: the ERC error code, an optional FTX+AAO
+/// description and the line it concerns (RFF+LI).
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationError {
+    #[pyo3(get, set)]
+    code: String,
+    #[pyo3(get, set)]
+    text: Option<String>,
+    #[pyo3(get, set)]
+    line_number: Option<String>,
+    /// 3055 agency responsible for the error code list, e.g. 9 (GS1)
+    #[pyo3(get, set)]
+    code_list_agency: Option<String>,
+}
+
+#[pymethods]
+impl ApplicationError {
+    #[new]
+    #[pyo3(signature = (code, text=None, line_number=None, code_list_agency=None))]
+    fn new(
+        code: String,
+        text: Option<String>,
+        line_number: Option<String>,
+        code_list_agency: Option<String>,
+    ) -> Self {
+        ApplicationError {
+            code,
+            text,
+            line_number,
+            code_list_agency,
+        }
+    }
+}
+
+impl ApplicationError {
+    fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let erc = group.find_segment("ERC")?;
+        let line_number = group
+            .groups_by_trigger("RFF")
+            .filter_map(|reference| reference.find_segment("RFF"))
+            .find(|rff| component(rff, 0, 0).as_deref() == Some("LI"))
+            .and_then(|rff| component(rff, 0, 1));
+        Some(ApplicationError {
+            code: component(erc, 0, 0).unwrap_or_default(),
+            text: group
+                .find_segment("FTX")
+                .and_then(|ftx| component(ftx, 3, 0)),
+            line_number,
+            code_list_agency: component(erc, 0, 2),
+        })
+    }
+
+    fn to_segments(&self) -> Vec<Segment> {
+        let agency = self.code_list_agency.as_deref().unwrap_or_default();
+        let mut segments = vec![segment_of("ERC", &[&[&self.code, "", agency]])];
+        if let Some(text) = &self.text {
+            segments.push(segment_of("FTX", &[&["AAO"], &[], &[], &[text]]));
+        }
+        if let Some(line_number) = &self.line_number {
+            segments.push(segment_of("RFF", &[&["LI", line_number]]));
+        }
+        segments
+    }
+}
+
+/// An APERAK application error and acknowledgement message.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ApplicationAcknowledgement {
+    document: Document,
+}
+
+#[pymethods]
+impl ApplicationAcknowledgement {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<ApplicationAcknowledgement> {
+        Ok(ApplicationAcknowledgement {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> ApplicationAcknowledgement {
+        ApplicationAcknowledgement {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    /// BGM 1225: 27 when the referenced message was not accepted, 29 when it was.
+    fn get_message_function(&self) -> Option<String> {
+        self.document.message_function()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    /// The UNH message reference of the message being acknowledged (RFF+ACW).
+    fn get_referenced_message(&self) -> Option<String> {
+        self.get_references()
+            .into_iter()
+            .find(|reference| reference.qualifier == "ACW")
+            .and_then(|reference| reference.number)
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    fn get_errors(&self) -> Vec<ApplicationError> {
+        self.tree()
+            .groups_by_trigger("ERC")
+            .filter_map(ApplicationError::from_group)
+            .collect()
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl ApplicationAcknowledgement {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("APERAK")
+    }
+}
+
+/// Builds an APERAK answering a received message, referring back to it automatically.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ApplicationAcknowledgementBuilder {
+    document: Document,
+    document_number: String,
+    dates: Vec<Segment>,
+    back_references: Vec<Segment>,
+    references: Vec<Segment>,
+    parties: Vec<Segment>,
+    errors: Vec<ApplicationError>,
+}
+
+#[pymethods]
+impl ApplicationAcknowledgementBuilder {
+    #[new]
+    #[pyo3(signature = (document_number, message_reference="1".to_string(), release="96A".to_string()))]
+    fn new(
+        document_number: String,
+        message_reference: String,
+        release: String,
+    ) -> PyResult<ApplicationAcknowledgementBuilder> {
+        let mut document = Document::new(Default::default(), Default::default());
+        document.set_message_header(&MessageHeader::new(
+            message_reference,
+            "APERAK".to_string(),
+            "D".to_string(),
+            release,
+            "UN".to_string(),
+            None,
+        ))?;
+        Ok(ApplicationAcknowledgementBuilder {
+            document,
+            document_number,
+            dates: Vec::new(),
+            back_references: Vec::new(),
+            references: Vec::new(),
+            parties: Vec::new(),
+            errors: Vec::new(),
+        })
+    }
+
+    fn with_interchange<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        header: InterchangeHeader,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.document.syntax_version = header.syntax_version;
+        slf.document.delimiters = header.syntax_version.default_delimiters();
+        slf.document.set_interchange_header(&header)?;
+        Ok(slf)
+    }
+
+    /// Refers to the message being acknowledged: its UNH reference (RFF+ACW) with its
+    /// document date (DTM+171), and its document number under the usual qualifier for its
+    /// type, such as RFF+ON for an order.
+    fn for_message<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        message: &Message,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.back_references =
+            back_references(message.service_segments.get("UNH"), &message.segments);
+        Ok(slf)
+    }
+
+    /// Refers to the order being acknowledged, as `for_message` does.
+    fn for_order<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        order: &Order,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.back_references = back_references(order.message_header.as_ref(), &order.segments);
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (qualifier, value, format="102"))]
+    fn with_date<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.dates
+            .push(segment_of("DTM", &[&[qualifier, value, format]]));
+        Ok(slf)
+    }
+
+    fn with_reference<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        number: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.references
+            .push(segment_of("RFF", &[&[qualifier, number]]));
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (qualifier, id, code_list_agency="9"))]
+    fn with_party<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        id: &str,
+        code_list_agency: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.parties.push(segment_of(
+            "NAD",
+            &[&[qualifier], &[id, "", code_list_agency]],
+        ));
+        Ok(slf)
+    }
+
+    fn add_error<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        error: ApplicationError,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.errors.push(error);
+        Ok(slf)
+    }
+
+    /// Writes the message and checks it against the APERAK structure. The message function
+    /// is 27 (not accepted) when errors were added and 29 (accepted) otherwise.
+    fn build(&self) -> PyResult<ApplicationAcknowledgement> {
+        let mut document = self.document.clone();
+        let function = if self.errors.is_empty() { "29" } else { "27" };
+        let body = &mut document.segments;
+        body.push(segment_of(
+            "BGM",
+            &[&["313"], &[&self.document_number], &[function]],
+        ));
+        body.extend(self.dates.iter().cloned());
+        body.extend(self.back_references.iter().cloned());
+        body.extend(self.references.iter().cloned());
+        body.extend(self.parties.iter().cloned());
+        for error in &self.errors {
+            body.extend(error.to_segments());
+        }
+        document.close();
+        document.check_structure("APERAK")?;
+        Ok(ApplicationAcknowledgement { document })
+    }
+}
+
+// RFF+ACW with the document date, followed by the document number of the original message
+fn back_references(header: Option<&Segment>, body: &[Segment]) -> Vec<Segment> {
+    let mut references = Vec::new();
+    if let Some(reference) = header.and_then(|h| component(h, 0, 0)) {
+        references.push(segment_of("RFF", &[&["ACW", &reference]]));
+        let issued = body
+            .iter()
+            .filter(|s| s.tag == "DTM")
+            .find(|dtm| component(dtm, 0, 0).as_deref() == Some("137"));
+        if let Some(issued) = issued {
+            let value = component(issued, 0, 1).unwrap_or_default();
+            let format = component(issued, 0, 2).unwrap_or_default();
+            references.push(segment_of("DTM", &[&["171", &value, &format]]));
+        }
+    }
+
+    let qualifier = match header.and_then(|h| component(h, 1, 0)).as_deref() {
+        Some("ORDERS") | Some("ORDCHG") => Some("ON"),
+        Some("INVOIC") => Some("IV"),
+        Some("DESADV") => Some("AAK"),
+        _ => None,
+    };
+    let number = body
+        .iter()
+        .find(|s| s.tag == "BGM")
+        .and_then(|bgm| component(bgm, 1, 0));
+    if let (Some(qualifier), Some(number)) = (qualifier, number) {
+        references.push(segment_of("RFF", &[&[qualifier, &number]]));
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    #[test]
+    fn test_aperak_refers_to_order() {
+        Python::with_gil(|py| {
+            let order = Order::from_edifact(
+                "UNH+ME000001+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240115:102'
+LIN+1++4000862141404:SRV'
+QTY+21:48'
+UNT+6+ME000001'"
+                    .to_string(),
+            )
+            .unwrap();
+
+            let builder = ApplicationAcknowledgementBuilder::new(
+                "APE001".to_string(),
+                "1".to_string(),
+                "96A".to_string(),
+            )
+            .unwrap();
+            let builder = Bound::new(py, builder).unwrap();
+            ApplicationAcknowledgementBuilder::for_order(builder.borrow_mut(), &order).unwrap();
+            ApplicationAcknowledgementBuilder::with_date(
+                builder.borrow_mut(),
+                "137",
+                "20240116",
+                "102",
+            )
+            .unwrap();
+            ApplicationAcknowledgementBuilder::add_error(
+                builder.borrow_mut(),
+                ApplicationError::new(
+                    "45".to_string(),
+                    Some("Unknown item".to_string()),
+                    Some("1".to_string()),
+                    Some("9".to_string()),
+                ),
+            )
+            .unwrap();
+            let acknowledgement = builder.borrow().build().unwrap();
+
+            assert_eq!(
+                acknowledgement.to_edifact(),
+                "UNH+1+APERAK:D:96A:UN'
+BGM+313+APE001+27'
+DTM+137:20240116:102'
+RFF+ACW:ME000001'
+DTM+171:20240115:102'
+RFF+ON:PO12345'
+ERC+45::9'
+FTX+AAO+++Unknown item'
+RFF+LI:1'
+UNT+10+1'
+"
+            );
+
+            let parsed =
+                ApplicationAcknowledgement::from_edifact(&acknowledgement.to_edifact()).unwrap();
+            assert_eq!(parsed.get_referenced_message().as_deref(), Some("ME000001"));
+            assert_eq!(
+                parsed.get_references()[0].dates[0].value.as_deref(),
+                Some("20240115")
+            );
+            assert_eq!(
+                parsed.get_errors(),
+                vec![ApplicationError::new(
+                    "45".to_string(),
+                    Some("Unknown item".to_string()),
+                    Some("1".to_string()),
+                    Some("9".to_string()),
+                )]
+            );
+        });
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+
+use crate::directory::{is_service_segment, Directory, MessageDefinition, SegmentGroup};
+use crate::document::Document;
+use crate::header::{trim_elements, InterchangeHeader, MessageHeader};
+use crate::{EdifactError, Message, Segment};
+
+/// A data element given from Python, either a simple value or the components of a composite.
+#[derive(Debug, Clone, FromPyObject)]
+pub(crate) enum ElementValue {
+    Simple(String),
+    Composite(Vec<String>),
+}
+
+impl ElementValue {
+    fn into_components(self) -> Vec<String> {
+        match self {
+            ElementValue::Simple(value) => vec![value],
+            ElementValue::Composite(components) => components,
+        }
+    }
+}
+
+/// Builds a message of any type segment by segment. With a directory, every segment is
+/// placed in the message structure as it is added and rejected when the structure does not
+/// allow it there; `build` then checks that nothing mandatory is missing.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    document: Document,
+    message_type: String,
+    definition: Option<MessageDefinition>,
+}
+
+#[pymethods]
+impl MessageBuilder {
+    #[new]
+    #[pyo3(signature = (
+        message_type,
+        version="D".to_string(),
+        release="01B".to_string(),
+        agency="UN".to_string(),
+        association_code=None,
+        message_reference="1".to_string(),
+        directory=None,
+    ))]
+    pub fn new(
+        message_type: &str,
+        version: String,
+        release: String,
+        agency: String,
+        association_code: Option<String>,
+        message_reference: String,
+        directory: Option<&Directory>,
+    ) -> PyResult<MessageBuilder> {
+        let definition = directory
+            .map(|directory| directory.require_message(message_type, Some(&release)))
+            .transpose()?
+            .cloned();
+        let mut document = Document::new(Default::default(), Default::default());
+        document.set_message_header(&MessageHeader::new(
+            message_reference,
+            message_type.to_string(),
+            version,
+            release,
+            agency,
+            association_code,
+        ))?;
+        Ok(MessageBuilder {
+            document,
+            message_type: message_type.to_string(),
+            definition,
+        })
+    }
+
+    fn with_interchange<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        header: InterchangeHeader,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.set_interchange(&header)?;
+        Ok(slf)
+    }
+
+    /// Adds a segment to the body. Each element is a string or a list of components, e.g.
+    /// `add_segment("DTM", [["137", "20240301", "102"]])`.
+    fn add_segment<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        tag: &str,
+        elements: Vec<ElementValue>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let elements = elements
+            .into_iter()
+            .map(ElementValue::into_components)
+            .collect();
+        slf.push(tag, elements)?;
+        Ok(slf)
+    }
+
+    #[getter]
+    fn message_type(&self) -> String {
+        self.message_type.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    /// The body arranged into segment groups, when a directory was given.
+    fn tree(&self) -> Option<SegmentGroup> {
+        self.definition
+            .as_ref()
+            .map(|definition| definition.build_tree(&self.document.segments).0)
+    }
+
+    /// Closes the message with UNT (and UNZ when there is an interchange header) and checks
+    /// it against the structure, when a directory was given.
+    pub fn build(&self) -> PyResult<Message> {
+        let document = self.close()?;
+        let mut service_segments = HashMap::new();
+        for segment in [
+            &document.interchange_header,
+            &document.message_header,
+            &document.message_trailer,
+            &document.interchange_trailer,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            service_segments.insert(segment.tag.clone(), segment.clone());
+        }
+        Ok(Message {
+            segments: document.segments,
+            service_segments,
+        })
+    }
+
+    pub fn to_edifact(&self) -> PyResult<String> {
+        Ok(self.close()?.to_edifact())
+    }
+}
+
+// The same builder for use from Rust, taking the builder by value and handing it back
+impl MessageBuilder {
+    pub fn interchange(mut self, header: &InterchangeHeader) -> PyResult<Self> {
+        self.set_interchange(header)?;
+        Ok(self)
+    }
+
+    pub fn segment(mut self, tag: &str, elements: Vec<Vec<String>>) -> Result<Self, EdifactError> {
+        self.push(tag, elements)?;
+        Ok(self)
+    }
+}
+
+impl MessageBuilder {
+    fn set_interchange(&mut self, header: &InterchangeHeader) -> PyResult<()> {
+        self.document.syntax_version = header.syntax_version;
+        self.document.delimiters = header.syntax_version.default_delimiters();
+        self.document.set_interchange_header(header)
+    }
+
+    fn push(&mut self, tag: &str, elements: Vec<Vec<String>>) -> Result<(), EdifactError> {
+        if tag.len() != 3
+            || !tag
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            return Err(EdifactError::new(format!("Invalid segment tag '{}'", tag)));
+        }
+        if is_service_segment(tag) {
+            return Err(EdifactError::new(format!(
+                "{} is a service segment and is added by the builder",
+                tag
+            )));
+        }
+        let position = self.document.segments.len();
+
+        self.document.segments.push(Segment::new(
+            tag.to_string(),
+            trim_elements(elements),
+            position,
+        ));
+        if let Some(definition) = &self.definition {
+            // Earlier segments keep their place, so only issues at the new segment are new;
+            // mandatory entries still to come are reported without a position
+            let issues: Vec<String> = definition
+                .build_tree(&self.document.segments)
+                .1
+                .iter()
+                .filter(|issue| issue.position == Some(position))
+                .map(|issue| issue.to_string())
+                .collect();
+            if !issues.is_empty() {
+                self.document.segments.pop();
+                return Err(EdifactError::new(format!(
+                    "Segment {} cannot follow here in {}: {}",
+                    tag,
+                    self.message_type,
+                    issues.join("; ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&self) -> Result<Document, EdifactError> {
+        let mut document = self.document.clone();
+        document.close();
+        if let Some(definition) = &self.definition {
+            let issues: Vec<String> = definition
+                .build_tree(&document.segments)
+                .1
+                .iter()
+                .map(|issue| issue.to_string())
+                .collect();
+            if !issues.is_empty() {
+                return Err(EdifactError::new(format!(
+                    "Invalid {}: {}",
+                    self.message_type,
+                    issues.join("; ")
+                )));
+            }
+        }
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn elements(values: &[&[&str]]) -> Vec<Vec<String>> {
+        values
+            .iter()
+            .map(|element| element.iter().map(|value| value.to_string()).collect())
+            .collect()
+    }
+
+    fn builder(directory: Option<&Directory>) -> MessageBuilder {
+        MessageBuilder::new(
+            "INVOIC",
+            "D".to_string(),
+            "96A".to_string(),
+            "UN".to_string(),
+            Some("EAN008".to_string()),
+            "42".to_string(),
+            directory,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_message_builder_without_directory() {
+        let builder = builder(None)
+            .segment("BGM", elements(&[&["380"], &["INV001"], &["9"]]))
+            .unwrap()
+            .segment("DTM", elements(&[&["137", "20240301", "102"]]))
+            .unwrap()
+            .segment(
+                "FTX",
+                elements(&[&["AAI"], &[], &[], &["Fragile: handle with care"]]),
+            )
+            .unwrap();
+        assert!(builder.clone().segment("UNT", elements(&[&["1"]])).is_err());
+        assert!(builder.clone().segment("bgm", elements(&[&["1"]])).is_err());
+        assert_eq!(
+            builder.to_edifact().unwrap(),
+            "UNH+42+INVOIC:D:96A:UN:EAN008'
+BGM+380+INV001+9'
+DTM+137:20240301:102'
+FTX+AAI+++Fragile?: handle with care'
+UNT+5+42'
+"
+        );
+
+        let message = builder.build().unwrap();
+        assert_eq!(message.segments.len(), 3);
+        assert_eq!(
+            message.service_segments["UNT"].elements,
+            vec![vec!["5".to_string()], vec!["42".to_string()]]
+        );
+    }
+
+    #[test]
+    fn test_message_builder_enforces_structure() {
+        let directory = Directory::new();
+        let mut builder = builder(Some(&directory));
+        // BGM is mandatory and comes first
+        assert!(builder.push("DTM", elements(&[&["137"]])).is_err());
+        builder
+            .push("BGM", elements(&[&["380"], &["INV001"], &["9"]]))
+            .unwrap();
+        // DTM is mandatory as well, so the parties cannot come yet
+        assert!(builder
+            .push("NAD", elements(&[&["SU"], &["4012345000016"]]))
+            .is_err());
+        let mut builder = builder
+            .segment("DTM", elements(&[&["137"]]))
+            .unwrap()
+            .segment("NAD", elements(&[&["SU"], &["4012345000016"]]))
+            .unwrap()
+            .segment("LIN", elements(&[&["1"]]))
+            .unwrap()
+            .segment("QTY", elements(&[&["47", "10"]]))
+            .unwrap();
+        // BGM belongs in the header, not in a line, and a rejected segment leaves the
+        // builder as it was
+        assert!(builder
+            .push("BGM", elements(&[&["380"], &["INV002"], &["9"]]))
+            .is_err());
+        assert_eq!(builder.segments().len(), 5);
+        assert_eq!(builder.tree().unwrap().groups_by_trigger("LIN").count(), 1);
+
+        // The summary section is still missing
+        assert!(builder.build().is_err());
+        let message = builder
+            .segment("UNS", elements(&[&["S"]]))
+            .unwrap()
+            .segment("MOA", elements(&[&["77", "100"]]))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(message.segments.len(), 7);
+    }
+}
@@ -0,0 +1,796 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use pyo3::prelude::*;
+
+use crate::directory::SegmentGroup;
+use crate::header::component;
+use crate::{EdifactError, Segment};
+
+// Joins the non-empty components of an element, as used for free-text name and address lines
+fn joined(segment: &Segment, element: usize, components: std::ops::Range<usize>) -> Option<String> {
+    let parts: Vec<String> = components
+        .filter_map(|index| component(segment, element, index))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// A decimal read digit by digit as an integer count of 10^-scale units, so amounts can be
+/// calculated without the rounding errors of binary floating point.
+pub(crate) fn parse_exact(value: &str) -> Option<(i128, u32)> {
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (whole, fraction) = digits.split_once(['.', ',']).unwrap_or((digits, ""));
+    if whole.len() + fraction.len() == 0
+        || whole.len() + fraction.len() > 30
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let units: i128 = format!("{}{}", whole, fraction).parse().ok()?;
+    Some((if negative { -units } else { units }, fraction.len() as u32))
+}
+
+/// The exact sum of two decimals read by `parse_exact`, at the finer of their scales.
+pub(crate) fn add_exact(left: (i128, u32), right: (i128, u32)) -> Option<(i128, u32)> {
+    let scale = left.1.max(right.1);
+    let rescale = |(units, from): (i128, u32)| units.checked_mul(10i128.checked_pow(scale - from)?);
+    Some((rescale(left)?.checked_add(rescale(right)?)?, scale))
+}
+
+/// Formats an exact decimal without trailing zero decimals.
+pub(crate) fn format_exact((units, scale): (i128, u32)) -> String {
+    let sign = if units < 0 { "-" } else { "" };
+    let digits = format!(
+        "{:0>width$}",
+        units.unsigned_abs(),
+        width = scale as usize + 1
+    );
+    let (whole, fraction) = digits.split_at(digits.len() - scale as usize);
+    match fraction.trim_end_matches('0') {
+        "" => format!("{}{}", sign, whole),
+        fraction => format!("{}{}.{}", sign, whole, fraction),
+    }
+}
+
+// Rounds a count of 10^-scale units to whole cents, half away from zero
+fn round_to_cents(units: i128, scale: u32) -> Option<i64> {
+    let cents = if scale <= 2 {
+        units.checked_mul(10i128.pow(2 - scale))?
+    } else {
+        let divisor = 10i128.checked_pow(scale - 2)?;
+        let (quotient, remainder) = (units / divisor, units % divisor);
+        quotient + (remainder.abs() * 2 >= divisor) as i128 * units.signum()
+    };
+    i64::try_from(cents).ok()
+}
+
+/// Whether the value is a plain decimal number: an optional sign, digits and at most one
+/// decimal mark, with no exponent and nothing like `NaN` or `inf`.
+pub(crate) fn is_decimal(value: &str) -> bool {
+    parse_exact(value).is_some()
+}
+
+/// Reads a monetary amount as whole cents, rounding half away from zero.
+pub(crate) fn parse_cents(value: &str) -> Option<i64> {
+    let (units, scale) = parse_exact(value)?;
+    round_to_cents(units, scale)
+}
+
+/// Reads a monetary amount with at most two decimals as whole cents, without rounding.
+pub(crate) fn parse_whole_cents(value: &str) -> Option<i64> {
+    let (units, scale) = parse_exact(value)?;
+    if scale > 2 {
+        return None;
+    }
+    round_to_cents(units, scale)
+}
+
+/// The amount of `quantity` at `price`, in whole cents.
+pub(crate) fn multiply_cents(quantity: &str, price: &str) -> Option<i64> {
+    let (quantity, quantity_scale) = parse_exact(quantity)?;
+    let (price, price_scale) = parse_exact(price)?;
+    round_to_cents(quantity.checked_mul(price)?, quantity_scale + price_scale)
+}
+
+/// `rate` percent of an amount in cents, rounded to whole cents.
+pub(crate) fn percentage_cents(cents: i64, rate: &str) -> Option<i64> {
+    let (rate, scale) = parse_exact(rate)?;
+    // cents × rate / 100 is a count of cents at the rate's scale plus two
+    round_to_cents((cents as i128).checked_mul(rate)?, scale + 4)
+}
+
+/// Formats an amount in cents with two decimals.
+pub(crate) fn format_cents(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+fn dates(group: &SegmentGroup) -> Vec<DateTimePeriod> {
+    group
+        .segments_by_tag("DTM")
+        .map(DateTimePeriod::from_segment)
+        .collect()
+}
+
+/// A DTM date, time or period (C507), kept in its transmitted form along with the format
+/// code (2379) needed to interpret it.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateTimePeriod {
+    #[pyo3(get)]
+    pub(crate) qualifier: String,
+    #[pyo3(get)]
+    pub(crate) value: Option<String>,
+    #[pyo3(get)]
+    pub(crate) format: Option<String>,
+}
+
+#[pymethods]
+impl DateTimePeriod {
+    /// Converts formats 102 (CCYYMMDD), 203 (CCYYMMDDHHMM) and 204 (CCYYMMDDHHMMSS) to a
+    /// datetime. Other formats, periods included, return None.
+    pub(crate) fn to_datetime(&self) -> Option<NaiveDateTime> {
+        let value = self.value.as_deref()?;
+        match self.format.as_deref() {
+            Some("102") => NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(|date| date.and_time(NaiveTime::MIN)),
+            Some("203") => NaiveDateTime::parse_from_str(value, "%Y%m%d%H%M").ok(),
+            Some("204") => NaiveDateTime::parse_from_str(value, "%Y%m%d%H%M%S").ok(),
+            _ => None,
+        }
+    }
+}
+
+impl DateTimePeriod {
+    /// Checks a value against its format code: 101 (YYMMDD), 102, 203, 204 and 718
+    /// (CCYYMMDD-CCYYMMDD) are parsed, other codes only need to be three digits.
+    pub(crate) fn check(value: &str, format: &str) -> Result<(), EdifactError> {
+        let period = |value: &str| DateTimePeriod {
+            qualifier: String::new(),
+            value: Some(value.to_string()),
+            format: Some(format.to_string()),
+        };
+        let valid = match format {
+            "101" => NaiveDate::parse_from_str(value, "%y%m%d").is_ok(),
+            "102" | "203" | "204" => period(value).to_datetime().is_some(),
+            "718" => value.split_once('-').is_some_and(|(start, end)| {
+                [start, end]
+                    .iter()
+                    .all(|date| NaiveDate::parse_from_str(date, "%Y%m%d").is_ok())
+            }),
+            _ => {
+                if format.len() != 3 || !format.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(EdifactError::new(format!(
+                        "Invalid date/time format code '{}'",
+                        format
+                    )));
+                }
+                true
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(EdifactError::new(format!(
+                "'{}' is not a valid date/time in format {}",
+                value, format
+            )))
+        }
+    }
+
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        DateTimePeriod {
+            qualifier: component(segment, 0, 0).unwrap_or_default(),
+            value: component(segment, 0, 1),
+            format: component(segment, 0, 2),
+        }
+    }
+}
+
+/// An RFF reference (C506) with the dates that qualify it.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    #[pyo3(get)]
+    pub(crate) qualifier: String,
+    #[pyo3(get)]
+    pub(crate) number: Option<String>,
+    #[pyo3(get)]
+    pub(crate) line_number: Option<String>,
+    #[pyo3(get)]
+    pub(crate) dates: Vec<DateTimePeriod>,
+}
+
+impl Reference {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        Reference {
+            qualifier: component(segment, 0, 0).unwrap_or_default(),
+            number: component(segment, 0, 1),
+            line_number: component(segment, 0, 2),
+            dates: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let mut reference = Reference::from_segment(group.find_segment("RFF")?);
+        reference.dates = dates(group);
+        Some(reference)
+    }
+}
+
+/// A COM communication number (C076), such as a phone number or e-mail address.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Communication {
+    #[pyo3(get)]
+    pub(crate) number: String,
+    #[pyo3(get)]
+    pub(crate) channel: Option<String>,
+}
+
+impl Communication {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        Communication {
+            number: component(segment, 0, 0).unwrap_or_default(),
+            channel: component(segment, 0, 1),
+        }
+    }
+}
+
+/// A CTA contact with its COM communication numbers.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contact {
+    #[pyo3(get)]
+    pub(crate) function: Option<String>,
+    #[pyo3(get)]
+    pub(crate) department: Option<String>,
+    #[pyo3(get)]
+    pub(crate) name: Option<String>,
+    #[pyo3(get)]
+    pub(crate) communications: Vec<Communication>,
+}
+
+impl Contact {
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("CTA")?;
+        Some(Contact {
+            function: component(segment, 0, 0),
+            department: component(segment, 1, 0),
+            name: component(segment, 1, 1),
+            communications: group
+                .segments_by_tag("COM")
+                .map(Communication::from_segment)
+                .collect(),
+        })
+    }
+}
+
+/// A LOC place or location (C517). Within a line the location may carry the quantity and
+/// dates allocated to it.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    #[pyo3(get)]
+    pub(crate) qualifier: String,
+    #[pyo3(get)]
+    pub(crate) id: Option<String>,
+    #[pyo3(get)]
+    pub(crate) code_list_agency: Option<String>,
+    #[pyo3(get)]
+    pub(crate) name: Option<String>,
+    #[pyo3(get)]
+    pub(crate) quantity: Option<Quantity>,
+    #[pyo3(get)]
+    pub(crate) dates: Vec<DateTimePeriod>,
+}
+
+impl Location {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        Location {
+            qualifier: component(segment, 0, 0).unwrap_or_default(),
+            id: component(segment, 1, 0),
+            code_list_agency: component(segment, 1, 2),
+            name: component(segment, 1, 3),
+            quantity: None,
+            dates: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let mut location = Location::from_segment(group.find_segment("LOC")?);
+        location.quantity = group.find_segment("QTY").map(Quantity::from_segment);
+        location.dates = dates(group);
+        Some(location)
+    }
+}
+
+/// A NAD party with its locations, references and contacts.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Party {
+    #[pyo3(get)]
+    pub(crate) qualifier: String,
+    #[pyo3(get)]
+    pub(crate) id: Option<String>,
+    #[pyo3(get)]
+    pub(crate) code_list_agency: Option<String>,
+    #[pyo3(get)]
+    pub(crate) name: Option<String>,
+    #[pyo3(get)]
+    pub(crate) street: Option<String>,
+    #[pyo3(get)]
+    pub(crate) city: Option<String>,
+    #[pyo3(get)]
+    pub(crate) country_subdivision: Option<String>,
+    #[pyo3(get)]
+    pub(crate) postal_code: Option<String>,
+    #[pyo3(get)]
+    pub(crate) country: Option<String>,
+    #[pyo3(get)]
+    pub(crate) locations: Vec<Location>,
+    #[pyo3(get)]
+    pub(crate) references: Vec<Reference>,
+    #[pyo3(get)]
+    pub(crate) contacts: Vec<Contact>,
+}
+
+impl Party {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        Party {
+            qualifier: component(segment, 0, 0).unwrap_or_default(),
+            id: component(segment, 1, 0),
+            code_list_agency: component(segment, 1, 2),
+            // The structured party name, falling back to the unstructured name and address
+            name: joined(segment, 3, 0..5).or_else(|| joined(segment, 2, 0..5)),
+            street: joined(segment, 4, 0..4),
+            city: component(segment, 5, 0),
+            country_subdivision: component(segment, 6, 0),
+            postal_code: component(segment, 7, 0),
+            country: component(segment, 8, 0),
+            locations: Vec::new(),
+            references: Vec::new(),
+            contacts: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let mut party = Party::from_segment(group.find_segment("NAD")?);
+        party.locations = group
+            .segments_by_tag("LOC")
+            .map(Location::from_segment)
+            .collect();
+        party.references = group
+            .groups_by_trigger("RFF")
+            .filter_map(Reference::from_group)
+            .collect();
+        party.contacts = group
+            .groups_by_trigger("CTA")
+            .filter_map(Contact::from_group)
+            .collect();
+        Some(party)
+    }
+}
+
+/// A QTY quantity (C186).
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quantity {
+    #[pyo3(get)]
+    pub(crate) qualifier: String,
+    #[pyo3(get)]
+    pub(crate) value: Option<String>,
+    #[pyo3(get)]
+    pub(crate) unit: Option<String>,
+}
+
+impl Quantity {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        Quantity {
+            qualifier: component(segment, 0, 0).unwrap_or_default(),
+            value: component(segment, 0, 1),
+            unit: component(segment, 0, 2),
+        }
+    }
+}
+
+/// A MEA measurement such as a net weight or a dimension, with the value in its unit (C174).
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Measurement {
+    /// 6311: e.g. PD (physical dimensions), AAE (measurement)
+    #[pyo3(get)]
+    pub(crate) qualifier: String,
+    /// 6313: e.g. AAB (unit gross weight), HT (height)
+    #[pyo3(get)]
+    pub(crate) dimension: Option<String>,
+    #[pyo3(get)]
+    pub(crate) unit: Option<String>,
+    #[pyo3(get)]
+    pub(crate) value: Option<String>,
+}
+
+impl Measurement {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        Measurement {
+            qualifier: component(segment, 0, 0).unwrap_or_default(),
+            dimension: component(segment, 1, 0),
+            unit: component(segment, 2, 0),
+            value: component(segment, 2, 1),
+        }
+    }
+}
+
+/// A QVR quantity variance: the difference between an expected and an actual quantity,
+/// with the kind of discrepancy (4221) and the reason for it (C960).
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discrepancy {
+    #[pyo3(get)]
+    pub(crate) difference: Option<String>,
+    #[pyo3(get)]
+    pub(crate) quantity_qualifier: Option<String>,
+    /// 4221: e.g. BP (shipment partial), CP (over-shipped)
+    #[pyo3(get)]
+    pub(crate) discrepancy_code: Option<String>,
+    /// 4295: e.g. AG (damaged in transit)
+    #[pyo3(get)]
+    pub(crate) reason: Option<String>,
+    #[pyo3(get)]
+    pub(crate) dates: Vec<DateTimePeriod>,
+}
+
+impl Discrepancy {
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let qvr = group.find_segment("QVR")?;
+        Some(Discrepancy {
+            difference: component(qvr, 0, 0),
+            quantity_qualifier: component(qvr, 0, 1),
+            discrepancy_code: component(qvr, 1, 0),
+            reason: component(qvr, 2, 0),
+            dates: dates(group),
+        })
+    }
+}
+
+/// A MOA monetary amount (C516).
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonetaryAmount {
+    #[pyo3(get)]
+    pub(crate) qualifier: String,
+    #[pyo3(get)]
+    pub(crate) amount: Option<String>,
+    #[pyo3(get)]
+    pub(crate) currency: Option<String>,
+}
+
+impl MonetaryAmount {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        MonetaryAmount {
+            qualifier: component(segment, 0, 0).unwrap_or_default(),
+            amount: component(segment, 0, 1),
+            currency: component(segment, 0, 2),
+        }
+    }
+}
+
+/// A PRI price (C509).
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Price {
+    #[pyo3(get)]
+    pub(crate) qualifier: String,
+    #[pyo3(get)]
+    pub(crate) amount: Option<String>,
+    #[pyo3(get)]
+    pub(crate) price_type: Option<String>,
+    #[pyo3(get)]
+    pub(crate) basis: Option<String>,
+    #[pyo3(get)]
+    pub(crate) unit: Option<String>,
+}
+
+impl Price {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        Price {
+            qualifier: component(segment, 0, 0).unwrap_or_default(),
+            amount: component(segment, 0, 1),
+            price_type: component(segment, 0, 2),
+            basis: component(segment, 0, 4),
+            unit: component(segment, 0, 5),
+        }
+    }
+}
+
+/// A CUX currency (C504) with the exchange rate and the dates it applies to.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Currency {
+    #[pyo3(get)]
+    pub(crate) usage: String,
+    #[pyo3(get)]
+    pub(crate) currency: Option<String>,
+    #[pyo3(get)]
+    pub(crate) qualifier: Option<String>,
+    #[pyo3(get)]
+    pub(crate) target_currency: Option<String>,
+    #[pyo3(get)]
+    pub(crate) rate: Option<String>,
+    #[pyo3(get)]
+    pub(crate) dates: Vec<DateTimePeriod>,
+}
+
+impl Currency {
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("CUX")?;
+        Some(Currency {
+            usage: component(segment, 0, 0).unwrap_or_default(),
+            currency: component(segment, 0, 1),
+            qualifier: component(segment, 0, 2),
+            target_currency: component(segment, 1, 1),
+            rate: component(segment, 2, 0),
+            dates: dates(group),
+        })
+    }
+}
+
+/// PAT payment terms: the terms basis (C110) and the time of payment (C112), along with any
+/// DTM, PCD and MOA in the same group.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentTerms {
+    #[pyo3(get)]
+    pub(crate) type_qualifier: String,
+    #[pyo3(get)]
+    pub(crate) terms_id: Option<String>,
+    #[pyo3(get)]
+    pub(crate) time_reference: Option<String>,
+    #[pyo3(get)]
+    pub(crate) time_relation: Option<String>,
+    #[pyo3(get)]
+    pub(crate) period_type: Option<String>,
+    #[pyo3(get)]
+    pub(crate) period_count: Option<String>,
+    #[pyo3(get)]
+    pub(crate) dates: Vec<DateTimePeriod>,
+    #[pyo3(get)]
+    pub(crate) percentage: Option<String>,
+    #[pyo3(get)]
+    pub(crate) amount: Option<MonetaryAmount>,
+}
+
+impl PaymentTerms {
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("PAT")?;
+        Some(PaymentTerms {
+            type_qualifier: component(segment, 0, 0).unwrap_or_default(),
+            terms_id: component(segment, 1, 0),
+            time_reference: component(segment, 2, 0),
+            time_relation: component(segment, 2, 1),
+            period_type: component(segment, 2, 2),
+            period_count: component(segment, 2, 3),
+            dates: dates(group),
+            percentage: group
+                .find_segment("PCD")
+                .and_then(|pcd| component(pcd, 0, 1)),
+            amount: group.find_segment("MOA").map(MonetaryAmount::from_segment),
+        })
+    }
+}
+
+/// A TDT transport stage with its dates and the locations (and their dates) it passes
+/// through.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transport {
+    #[pyo3(get)]
+    pub(crate) stage_qualifier: String,
+    #[pyo3(get)]
+    pub(crate) conveyance_reference: Option<String>,
+    #[pyo3(get)]
+    pub(crate) mode: Option<String>,
+    #[pyo3(get)]
+    pub(crate) means: Option<String>,
+    #[pyo3(get)]
+    pub(crate) carrier_id: Option<String>,
+    #[pyo3(get)]
+    pub(crate) carrier_name: Option<String>,
+    /// C222 identification of the means of transport, e.g. a vessel's IMO or call sign
+    #[pyo3(get)]
+    pub(crate) vessel_id: Option<String>,
+    #[pyo3(get)]
+    pub(crate) vessel_name: Option<String>,
+    #[pyo3(get)]
+    pub(crate) locations: Vec<Location>,
+    #[pyo3(get)]
+    pub(crate) dates: Vec<DateTimePeriod>,
+}
+
+impl Transport {
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("TDT")?;
+        Some(Transport {
+            stage_qualifier: component(segment, 0, 0).unwrap_or_default(),
+            conveyance_reference: component(segment, 1, 0),
+            mode: component(segment, 2, 0),
+            means: component(segment, 3, 0),
+            carrier_id: component(segment, 4, 0),
+            carrier_name: component(segment, 4, 3),
+            vessel_id: component(segment, 7, 0),
+            vessel_name: component(segment, 7, 3),
+            // Locations either open their own group with dates, or follow TDT directly
+            locations: group
+                .segments_by_tag("LOC")
+                .map(Location::from_segment)
+                .chain(
+                    group
+                        .groups_by_trigger("LOC")
+                        .filter_map(Location::from_group),
+                )
+                .collect(),
+            dates: dates(group),
+        })
+    }
+}
+
+/// An ALC allowance or charge with its quantity, percentage, amounts and rate.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowanceCharge {
+    /// 5463: A for an allowance, C for a charge
+    #[pyo3(get)]
+    pub(crate) indicator: String,
+    #[pyo3(get)]
+    pub(crate) reference: Option<String>,
+    #[pyo3(get)]
+    pub(crate) settlement: Option<String>,
+    #[pyo3(get)]
+    pub(crate) calculation_sequence: Option<String>,
+    #[pyo3(get)]
+    pub(crate) service_code: Option<String>,
+    #[pyo3(get)]
+    pub(crate) description: Option<String>,
+    #[pyo3(get)]
+    pub(crate) quantity: Option<Quantity>,
+    #[pyo3(get)]
+    pub(crate) percentage: Option<String>,
+    #[pyo3(get)]
+    pub(crate) amounts: Vec<MonetaryAmount>,
+    #[pyo3(get)]
+    pub(crate) rate: Option<String>,
+}
+
+impl AllowanceCharge {
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("ALC")?;
+        // The quantity, percentage, amount and rate each open their own nested group
+        let nested = |tag: &'static str| {
+            group
+                .groups_by_trigger(tag)
+                .filter_map(move |g| g.find_segment(tag))
+        };
+        Some(AllowanceCharge {
+            indicator: component(segment, 0, 0).unwrap_or_default(),
+            reference: component(segment, 1, 0),
+            settlement: component(segment, 2, 0),
+            calculation_sequence: component(segment, 3, 0),
+            service_code: component(segment, 4, 0),
+            description: component(segment, 4, 3),
+            quantity: nested("QTY").next().map(Quantity::from_segment),
+            percentage: nested("PCD").next().and_then(|pcd| component(pcd, 0, 1)),
+            amounts: nested("MOA").map(MonetaryAmount::from_segment).collect(),
+            rate: nested("RTE").next().and_then(|rte| component(rte, 0, 1)),
+        })
+    }
+}
+
+/// An item number (C212) with its type code (7143), e.g. SRV for a GTIN.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemNumber {
+    #[pyo3(get)]
+    pub(crate) number: String,
+    #[pyo3(get)]
+    pub(crate) number_type: Option<String>,
+}
+
+impl ItemNumber {
+    pub(crate) fn from_element(segment: &Segment, element: usize) -> Option<Self> {
+        Some(ItemNumber {
+            number: component(segment, element, 0)?,
+            number_type: component(segment, element, 1),
+        })
+    }
+}
+
+/// A PIA additional product identification, holding up to five item numbers.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductIdentification {
+    #[pyo3(get)]
+    pub(crate) function: String,
+    #[pyo3(get)]
+    pub(crate) item_numbers: Vec<ItemNumber>,
+}
+
+impl ProductIdentification {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        ProductIdentification {
+            function: component(segment, 0, 0).unwrap_or_default(),
+            item_numbers: (1..=5)
+                .filter_map(|element| ItemNumber::from_element(segment, element))
+                .collect(),
+        }
+    }
+}
+
+/// An IMD item description, either coded or free text.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemDescription {
+    #[pyo3(get)]
+    pub(crate) format: Option<String>,
+    #[pyo3(get)]
+    pub(crate) characteristic: Option<String>,
+    #[pyo3(get)]
+    pub(crate) code: Option<String>,
+    #[pyo3(get)]
+    pub(crate) description: Option<String>,
+}
+
+impl ItemDescription {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        ItemDescription {
+            format: component(segment, 0, 0),
+            characteristic: component(segment, 1, 0),
+            code: component(segment, 2, 0),
+            description: joined(segment, 2, 3..5),
+        }
+    }
+}
+
+/// A TAX duty, tax or fee with its amounts, e.g. VAT at a rate in a category.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tax {
+    /// 5283: 7 for a tax, 5 for a customs duty
+    #[pyo3(get)]
+    pub(crate) function: String,
+    #[pyo3(get)]
+    pub(crate) tax_type: Option<String>,
+    #[pyo3(get)]
+    pub(crate) rate: Option<String>,
+    #[pyo3(get)]
+    pub(crate) category: Option<String>,
+    #[pyo3(get)]
+    pub(crate) amounts: Vec<MonetaryAmount>,
+}
+
+impl Tax {
+    pub(crate) fn from_segment(segment: &Segment) -> Self {
+        Tax {
+            function: component(segment, 0, 0).unwrap_or_default(),
+            tax_type: component(segment, 1, 0),
+            rate: component(segment, 4, 3),
+            category: component(segment, 5, 0),
+            amounts: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let mut tax = Tax::from_segment(group.find_segment("TAX")?);
+        tax.amounts = group
+            .segments_by_tag("MOA")
+            .map(MonetaryAmount::from_segment)
+            .collect();
+        Some(tax)
+    }
+}
@@ -0,0 +1,736 @@
+use pyo3::prelude::*;
+
+use crate::common::{DateTimePeriod, Location, Measurement, Party, Reference, Transport};
+use crate::directory::SegmentGroup;
+use crate::document::{segment_of, Document};
+use crate::header::{component, InterchangeHeader, MessageHeader};
+use crate::transport::Equipment;
+use crate::validation::{self, ValidationIssue};
+use crate::{EdifactError, Message, Segment};
+
+// Container messages sharing the EQD based model; BAPLIE nests EQD below the LOC+147
+// stowage position, the others start a group with EQD
+const CONTAINER_MESSAGES: &[&str] = &["BAPLIE", "COPRAR", "CODECO"];
+
+/// The ISO 6346 check digit for the owner code, category and serial number (the first ten
+/// characters of a container id), or None when they are malformed.
+pub(crate) fn check_digit(id: &str) -> Option<u32> {
+    let characters: Vec<char> = id.chars().take(10).collect();
+    if characters.len() != 10
+        || !characters[..4].iter().all(char::is_ascii_uppercase)
+        || !characters[4..].iter().all(char::is_ascii_digit)
+    {
+        return None;
+    }
+    let sum: u32 = characters
+        .iter()
+        .enumerate()
+        .map(|(index, &character)| {
+            let value = match character.to_digit(10) {
+                Some(digit) => digit,
+                // Letters count from 10 for A, skipping the multiples of 11
+                None => {
+                    let value = character as u32 - 'A' as u32 + 10;
+                    value + (value - 1) / 10
+                }
+            };
+            value << index
+        })
+        .sum();
+    Some(sum % 11 % 10)
+}
+
+/// Whether an eleven character container id ends with its ISO 6346 check digit.
+pub(crate) fn is_valid_container_id(id: &str) -> bool {
+    id.len() == 11
+        && id.is_ascii()
+        && check_digit(id)
+            .zip(id[10..].parse::<u32>().ok())
+            .is_some_and(|(expected, actual)| expected == actual)
+}
+
+/// A stowage position on board a vessel from LOC+147, coded as bay, row and tier (ISO
+/// 9711: BBBRRTT).
+#[pyclass(eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StowagePosition {
+    #[pyo3(get)]
+    bay: String,
+    #[pyo3(get)]
+    row: String,
+    #[pyo3(get)]
+    tier: String,
+}
+
+#[pymethods]
+impl StowagePosition {
+    #[new]
+    fn new(bay: u32, row: u32, tier: u32) -> Self {
+        StowagePosition {
+            bay: format!("{:03}", bay),
+            row: format!("{:02}", row),
+            tier: format!("{:02}", tier),
+        }
+    }
+
+    /// Decodes a seven digit BBBRRTT position, or the older six digit BBRRTT form.
+    #[staticmethod]
+    pub(crate) fn from_code(code: &str) -> PyResult<StowagePosition> {
+        let bay_digits = match code.len() {
+            7 => 3,
+            6 => 2,
+            _ => 0,
+        };
+        if bay_digits == 0 || !code.chars().all(|c| c.is_ascii_digit()) {
+            return Err(EdifactError::new(format!("Invalid stowage position '{}'", code)).into());
+        }
+        Ok(StowagePosition {
+            bay: format!("{:0>3}", &code[..bay_digits]),
+            row: code[bay_digits..bay_digits + 2].to_string(),
+            tier: code[bay_digits + 2..].to_string(),
+        })
+    }
+
+    pub(crate) fn code(&self) -> String {
+        format!("{}{}{}", self.bay, self.row, self.tier)
+    }
+
+    fn __str__(&self) -> String {
+        self.code()
+    }
+}
+
+/// A container in a bay plan, loading or discharge order or gate report: the EQD equipment
+/// with its stowage position, ports, weights, references and dates.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    #[pyo3(get)]
+    equipment: Equipment,
+    #[pyo3(get)]
+    position: Option<StowagePosition>,
+    /// LOC+9 place of loading
+    #[pyo3(get)]
+    port_of_loading: Option<String>,
+    /// LOC+11 place of discharge
+    #[pyo3(get)]
+    port_of_discharge: Option<String>,
+    #[pyo3(get)]
+    locations: Vec<Location>,
+    #[pyo3(get)]
+    weights: Vec<Measurement>,
+    #[pyo3(get)]
+    references: Vec<Reference>,
+    #[pyo3(get)]
+    dates: Vec<DateTimePeriod>,
+}
+
+#[pymethods]
+impl Container {
+    #[getter]
+    fn id(&self) -> Option<String> {
+        self.equipment.id.clone()
+    }
+
+    /// Whether the container id ends with its ISO 6346 check digit.
+    fn has_valid_id(&self) -> bool {
+        self.equipment
+            .id
+            .as_deref()
+            .is_some_and(is_valid_container_id)
+    }
+
+    /// The verified gross mass (MEA with VGM) when reported, otherwise the gross weight
+    /// (MEA+WT in BAPLIE, or a G/AAB measurement).
+    fn gross_weight(&self) -> Option<Measurement> {
+        let dimension = |code: &str| {
+            self.weights
+                .iter()
+                .find(|weight| weight.dimension.as_deref() == Some(code))
+        };
+        dimension("VGM")
+            .or_else(|| self.weights.iter().find(|weight| weight.qualifier == "WT"))
+            .or_else(|| dimension("G"))
+            .or_else(|| dimension("AAB"))
+            .cloned()
+    }
+}
+
+impl Container {
+    // The EQD group and, in BAPLIE, the stowage position group enclosing it
+    fn from_groups(equipment: &SegmentGroup, position: Option<&SegmentGroup>) -> Option<Self> {
+        let groups: Vec<&SegmentGroup> = position.into_iter().chain([equipment]).collect();
+        let segments = |tag: &'static str| {
+            groups
+                .clone()
+                .into_iter()
+                .flat_map(move |group| group.segments_by_tag(tag))
+        };
+        let locations: Vec<Location> = segments("LOC").map(Location::from_segment).collect();
+        let port = |qualifier: &str| {
+            locations
+                .iter()
+                .find(|location| location.qualifier == qualifier)
+                .and_then(|location| location.id.clone())
+        };
+        Some(Container {
+            equipment: Equipment::from_group(equipment)?,
+            position: port("147").and_then(|code| StowagePosition::from_code(&code).ok()),
+            port_of_loading: port("9"),
+            port_of_discharge: port("11"),
+            weights: segments("MEA").map(Measurement::from_segment).collect(),
+            references: segments("RFF").map(Reference::from_segment).collect(),
+            dates: segments("DTM").map(DateTimePeriod::from_segment).collect(),
+            locations,
+        })
+    }
+}
+
+/// A BAPLIE bay plan, COPRAR loading or discharge order or CODECO gate-in/gate-out report,
+/// all describing containers with the same model.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ContainerMessage {
+    document: Document,
+    message_type: String,
+}
+
+#[pymethods]
+impl ContainerMessage {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<ContainerMessage> {
+        ContainerMessage::new(Document::parse(content)?)
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> PyResult<ContainerMessage> {
+        ContainerMessage::new(Document::from_message(message))
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    /// BAPLIE, COPRAR or CODECO.
+    #[getter]
+    fn message_type(&self) -> String {
+        self.message_type.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    /// The vessel or other means of transport (TDT) the message is about.
+    fn get_transport(&self) -> Vec<Transport> {
+        self.tree()
+            .groups_by_trigger("TDT")
+            .filter_map(Transport::from_group)
+            .collect()
+    }
+
+    fn get_containers(&self) -> Vec<Container> {
+        self.tree()
+            .child_groups()
+            .filter_map(|group| {
+                let trigger = group.direct_segments().next()?;
+                match trigger.tag.as_str() {
+                    "EQD" => Container::from_groups(group, None),
+                    "LOC" if component(trigger, 0, 0).as_deref() == Some("147") => {
+                        Container::from_groups(group.groups_by_trigger("EQD").next()?, Some(group))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Checks the message against its structure and every container id against its ISO
+    /// 6346 check digit.
+    fn validate(&self) -> PyResult<Vec<ValidationIssue>> {
+        let mut issues = self.document.structure_issues(&self.message_type)?;
+        for segment in self.document.segments.iter().filter(|s| s.tag == "EQD") {
+            if component(segment, 0, 0).as_deref() != Some("CN") {
+                continue;
+            }
+            let id = component(segment, 1, 0).unwrap_or_default();
+            if !is_valid_container_id(&id) {
+                issues.push(ValidationIssue::element(
+                    validation::INVALID_VALUE,
+                    "EQD",
+                    Some(segment.position),
+                    1,
+                    0,
+                    format!("Container id '{}' fails the ISO 6346 check digit", id),
+                ));
+            }
+        }
+        Ok(issues)
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl ContainerMessage {
+    fn new(document: Document) -> PyResult<ContainerMessage> {
+        let message_type = document
+            .message_header
+            .as_ref()
+            .and_then(|header| component(header, 1, 0))
+            .filter(|message_type| CONTAINER_MESSAGES.contains(&message_type.as_str()))
+            .ok_or_else(|| EdifactError::new("Not a BAPLIE, COPRAR or CODECO message"))?;
+        Ok(ContainerMessage {
+            document,
+            message_type,
+        })
+    }
+
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree(&self.message_type)
+    }
+}
+
+/// Builds a BAPLIE, COPRAR or CODECO. Container ids are checked against their ISO 6346
+/// check digit as they are added; COPRAR and CODECO end with the CNT+16 container count.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ContainerMessageBuilder {
+    document: Document,
+    message_type: String,
+    document_name: Option<String>,
+    document_number: String,
+    dates: Vec<Segment>,
+    transport: Vec<Segment>,
+    containers: Vec<Vec<Segment>>,
+}
+
+#[pymethods]
+impl ContainerMessageBuilder {
+    #[new]
+    #[pyo3(signature = (
+        message_type,
+        document_number,
+        document_name=None,
+        message_reference="1".to_string(),
+        release="95B".to_string(),
+        association_code=None,
+    ))]
+    fn new(
+        message_type: &str,
+        document_number: String,
+        document_name: Option<String>,
+        message_reference: String,
+        release: String,
+        association_code: Option<String>,
+    ) -> PyResult<ContainerMessageBuilder> {
+        if !CONTAINER_MESSAGES.contains(&message_type) {
+            return Err(EdifactError::new(format!(
+                "Unsupported container message type '{}'",
+                message_type
+            ))
+            .into());
+        }
+        let mut document = Document::new(Default::default(), Default::default());
+        document.set_message_header(&MessageHeader::new(
+            message_reference,
+            message_type.to_string(),
+            "D".to_string(),
+            release,
+            "UN".to_string(),
+            association_code,
+        ))?;
+        Ok(ContainerMessageBuilder {
+            document,
+            message_type: message_type.to_string(),
+            document_name,
+            document_number,
+            dates: Vec::new(),
+            transport: Vec::new(),
+            containers: Vec::new(),
+        })
+    }
+
+    fn with_interchange<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        header: InterchangeHeader,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.document.syntax_version = header.syntax_version;
+        slf.document.delimiters = header.syntax_version.default_delimiters();
+        slf.document.set_interchange_header(&header)?;
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (qualifier, value, format="203"))]
+    fn with_date<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.dates
+            .push(segment_of("DTM", &[&[qualifier, value, format]]));
+        Ok(slf)
+    }
+
+    /// Sets the vessel as the main carriage (TDT+20), with the voyage number, the vessel's
+    /// call sign or IMO number and the ports (LOC+5 departure, LOC+61 next port of call)
+    /// and dates (DTM+178 arrival, DTM+136 departure) of the call.
+    #[pyo3(signature = (
+        voyage,
+        vessel_id,
+        vessel_name=None,
+        carrier=None,
+        port=None,
+        next_port=None,
+        arrival=None,
+        departure=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn with_vessel<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        voyage: &str,
+        vessel_id: &str,
+        vessel_name: Option<&str>,
+        carrier: Option<&str>,
+        port: Option<&str>,
+        next_port: Option<&str>,
+        arrival: Option<&str>,
+        departure: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let carrier: &[&str] = match carrier {
+            Some(carrier) => &[carrier, "172", "20"],
+            None => &[],
+        };
+        let tdt = segment_of(
+            "TDT",
+            &[
+                &["20"],
+                &[voyage],
+                &[],
+                &[],
+                carrier,
+                &[],
+                &[],
+                &[vessel_id, "", "", vessel_name.unwrap_or_default()],
+            ],
+        );
+        let locations = [("5", port), ("61", next_port)]
+            .into_iter()
+            .filter_map(|(qualifier, port)| Some(segment_of("LOC", &[&[qualifier], &[port?]])));
+        let dates = [("178", arrival), ("136", departure)]
+            .into_iter()
+            .filter_map(|(qualifier, date)| Some(segment_of("DTM", &[&[qualifier, date?, "203"]])));
+        slf.transport = vec![tdt];
+        // BAPLIE lists the ports before the dates, the others give each port its own group
+        if slf.message_type == "BAPLIE" {
+            slf.transport.extend(locations);
+            slf.transport.extend(dates);
+        } else {
+            slf.transport.extend(dates);
+            slf.transport.extend(locations);
+        }
+        Ok(slf)
+    }
+
+    /// Adds a container (EQD+CN). The position is a BBBRRTT stowage code and the weight
+    /// is the gross weight in `weight_unit`; the full/empty indicator is 5 or 4.
+    #[pyo3(signature = (
+        id,
+        size_type,
+        full_empty=None,
+        position=None,
+        weight=None,
+        weight_unit="KGM",
+        port_of_loading=None,
+        port_of_discharge=None,
+        booking=None,
+        seals=Vec::new(),
+        status=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn add_container<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        id: &str,
+        size_type: &str,
+        full_empty: Option<&str>,
+        position: Option<&str>,
+        weight: Option<&str>,
+        weight_unit: &str,
+        port_of_loading: Option<&str>,
+        port_of_discharge: Option<&str>,
+        booking: Option<&str>,
+        seals: Vec<String>,
+        status: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        if !is_valid_container_id(id) {
+            return Err(EdifactError::new(format!(
+                "Container id '{}' fails the ISO 6346 check digit",
+                id
+            ))
+            .into());
+        }
+        let position = position
+            .map(StowagePosition::from_code)
+            .transpose()?
+            .map(|position| segment_of("LOC", &[&["147"], &[&position.code(), "", "5"]]));
+        let ports = [("9", port_of_loading), ("11", port_of_discharge)]
+            .into_iter()
+            .filter_map(|(qualifier, port)| Some(segment_of("LOC", &[&[qualifier], &[port?]])));
+        let equipment = segment_of(
+            "EQD",
+            &[
+                &["CN"],
+                &[id],
+                &[size_type],
+                &[],
+                &[status.unwrap_or_default()],
+                &[full_empty.unwrap_or_default()],
+            ],
+        );
+
+        let mut segments = Vec::new();
+        if slf.message_type == "BAPLIE" {
+            // The stowage position opens the group; a reference is mandatory, BM:1 being
+            // the usual placeholder when there is no bill of lading
+            segments.extend(position);
+            segments.extend(weight.map(|w| segment_of("MEA", &[&["WT"], &[], &[weight_unit, w]])));
+            segments.extend(ports);
+            segments.push(segment_of("RFF", &[&["BM", booking.unwrap_or("1")]]));
+            segments.push(equipment);
+        } else {
+            segments.push(equipment);
+            segments.extend(booking.map(|booking| segment_of("RFF", &[&["BN", booking]])));
+            segments.extend(position);
+            segments.extend(ports);
+            segments
+                .extend(weight.map(|w| segment_of("MEA", &[&["AAE"], &["G"], &[weight_unit, w]])));
+            segments.extend(seals.iter().map(|seal| segment_of("SEL", &[&[seal]])));
+        }
+        slf.containers.push(segments);
+        Ok(slf)
+    }
+
+    /// Writes the message and checks it against the structure for its type.
+    fn build(&self) -> PyResult<ContainerMessage> {
+        let mut document = self.document.clone();
+        let body = &mut document.segments;
+        let name = self.document_name.as_deref().unwrap_or_default();
+        body.push(segment_of(
+            "BGM",
+            &[&[name], &[&self.document_number], &["9"]],
+        ));
+        body.extend(self.dates.iter().cloned());
+        body.extend(self.transport.iter().cloned());
+        for segments in &self.containers {
+            body.extend(segments.iter().cloned());
+        }
+        if self.message_type != "BAPLIE" {
+            let count = self.containers.len().to_string();
+            body.push(segment_of("CNT", &[&["16", &count]]));
+        }
+        document.close();
+
+        document.check_structure(&self.message_type)?;
+        ContainerMessage::new(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    #[test]
+    fn test_container_check_digit() {
+        assert_eq!(check_digit("CSQU305438"), Some(3));
+        assert!(is_valid_container_id("CSQU3054383"));
+        assert!(is_valid_container_id("MSKU1234565"));
+        assert!(!is_valid_container_id("MSKU1234566"));
+        assert!(!is_valid_container_id("MSK1234565"));
+    }
+
+    #[test]
+    fn test_stowage_position() {
+        Python::with_gil(|_py| {
+            let position = StowagePosition::from_code("0120386").unwrap();
+            assert_eq!(
+                (
+                    position.bay.as_str(),
+                    position.row.as_str(),
+                    position.tier.as_str()
+                ),
+                ("012", "03", "86")
+            );
+            assert_eq!(StowagePosition::from_code("120386").unwrap(), position);
+            assert_eq!(StowagePosition::new(12, 3, 86).code(), "0120386");
+            assert!(StowagePosition::from_code("12A0386").is_err());
+        });
+    }
+
+    #[test]
+    fn test_bay_plan_round_trip() {
+        Python::with_gil(|py| {
+            let builder = ContainerMessageBuilder::new(
+                "BAPLIE",
+                "BP0001".to_string(),
+                None,
+                "1".to_string(),
+                "95B".to_string(),
+                Some("SMDG20".to_string()),
+            )
+            .unwrap();
+            let builder = Bound::new(py, builder).unwrap();
+            ContainerMessageBuilder::with_date(builder.borrow_mut(), "137", "202403011200", "203")
+                .unwrap();
+            ContainerMessageBuilder::with_vessel(
+                builder.borrow_mut(),
+                "V123",
+                "9321483",
+                Some("EMMA MAERSK"),
+                None,
+                Some("NLRTM"),
+                Some("USNYC"),
+                None,
+                Some("202403051800"),
+            )
+            .unwrap();
+            ContainerMessageBuilder::add_container(
+                builder.borrow_mut(),
+                "MSKU1234565",
+                "22G1",
+                Some("5"),
+                Some("0120386"),
+                Some("21500"),
+                "KGM",
+                Some("NLRTM"),
+                Some("USNYC"),
+                None,
+                Vec::new(),
+                None,
+            )
+            .unwrap();
+            let bay_plan = builder.borrow().build().unwrap();
+            assert_eq!(
+                bay_plan.to_edifact(),
+                "UNH+1+BAPLIE:D:95B:UN:SMDG20'
+BGM++BP0001+9'
+DTM+137:202403011200:203'
+TDT+20+V123++++++9321483:::EMMA MAERSK'
+LOC+5+NLRTM'
+LOC+61+USNYC'
+DTM+136:202403051800:203'
+LOC+147+0120386::5'
+MEA+WT++KGM:21500'
+LOC+9+NLRTM'
+LOC+11+USNYC'
+RFF+BM:1'
+EQD+CN+MSKU1234565+22G1+++5'
+UNT+14+1'
+"
+            );
+
+            let parsed = ContainerMessage::from_edifact(&bay_plan.to_edifact()).unwrap();
+            assert_eq!(parsed.message_type, "BAPLIE");
+            assert_eq!(parsed.validate().unwrap(), Vec::new());
+            assert_eq!(
+                parsed.get_transport()[0].vessel_name.as_deref(),
+                Some("EMMA MAERSK")
+            );
+            let containers = parsed.get_containers();
+            assert_eq!(containers.len(), 1);
+            let container = &containers[0];
+            assert_eq!(container.id().as_deref(), Some("MSKU1234565"));
+            assert_eq!(container.position.as_ref().unwrap().bay, "012");
+            assert_eq!(container.port_of_discharge.as_deref(), Some("USNYC"));
+            assert_eq!(
+                container.gross_weight().unwrap().value.as_deref(),
+                Some("21500")
+            );
+
+            assert!(ContainerMessageBuilder::add_container(
+                builder.borrow_mut(),
+                "MSKU1234566",
+                "22G1",
+                None,
+                None,
+                None,
+                "KGM",
+                None,
+                None,
+                None,
+                Vec::new(),
+                None
+            )
+            .is_err());
+        });
+    }
+
+    #[test]
+    fn test_gate_report_validation() {
+        Python::with_gil(|_py| {
+            let report = ContainerMessage::from_edifact(
+                "UNH+1+CODECO:D:95B:UN:ITG14'
+BGM+34+GATE0001+9'
+DTM+137:202403011200:203'
+EQD+CN+CSQU3054383+45G1:102:5++2+5'
+RFF+BN:BKG0001'
+DTM+7:202403011130:203'
+LOC+165+NLRTM:139:6'
+MEA+AAE+VGM+KGM:28750'
+SEL+SEAL001'
+EQD+CN+CSQU3054384+22G1++2+4'
+CNT+16:2'
+UNT+12+1'",
+            )
+            .unwrap();
+            let containers = report.get_containers();
+            assert_eq!(containers.len(), 2);
+            assert_eq!(containers[0].equipment.seals, vec!["SEAL001".to_string()]);
+            assert_eq!(containers[0].references[0].qualifier, "BN");
+            assert_eq!(
+                containers[0].gross_weight().unwrap().value.as_deref(),
+                Some("28750")
+            );
+            assert_eq!(containers[1].equipment.full_empty.as_deref(), Some("4"));
+            assert!(containers[0].has_valid_id());
+            assert!(!containers[1].has_valid_id());
+
+            let issues = report.validate().unwrap();
+            assert_eq!(issues.len(), 1);
+            assert_eq!(issues[0].code, validation::INVALID_VALUE);
+            assert_eq!(issues[0].position, Some(9));
+
+            assert!(ContainerMessage::from_edifact("UNH+1+ORDERS:D:96A:UN'UNT+2+1'").is_err());
+        });
+    }
+}
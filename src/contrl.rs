@@ -0,0 +1,561 @@
+use chrono::NaiveDateTime;
+use pyo3::prelude::*;
+
+use crate::directory::SegmentGroup;
+use crate::document::{segment_of, Document};
+use crate::header::{component, trim_elements, MessageHeader};
+use crate::interchange::Interchange;
+use crate::syntax::SyntaxVersion;
+use crate::validation::ValidationIssue;
+use crate::{EdifactError, Message, Segment};
+
+/// 0083 action codes reported for an interchange or message.
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ControlAction {
+    Rejected = 4,
+    /// This level and all lower levels acknowledged unless explicitly rejected
+    Acknowledged = 7,
+    /// Received, but not yet checked
+    Received = 8,
+}
+
+#[pymethods]
+impl ControlAction {
+    #[staticmethod]
+    pub(crate) fn from_code(code: &str) -> Option<ControlAction> {
+        match code {
+            "4" => Some(ControlAction::Rejected),
+            "7" => Some(ControlAction::Acknowledged),
+            "8" => Some(ControlAction::Received),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn code(&self) -> String {
+        (*self as u8).to_string()
+    }
+}
+
+/// A syntax error reported in a CONTRL: the 0085 error code with the service segment (for
+/// UCI and UCM) or the segment position in the message (UCS, UNH being 1) and the data
+/// element and component positions (S011), all counted from 1.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlError {
+    #[pyo3(get)]
+    code: Option<String>,
+    #[pyo3(get)]
+    tag: Option<String>,
+    #[pyo3(get)]
+    segment_position: Option<usize>,
+    #[pyo3(get)]
+    element: Option<usize>,
+    #[pyo3(get)]
+    component: Option<usize>,
+}
+
+impl ControlError {
+    // Error code, service segment tag and S011 starting at the given element of UCI or UCM
+    fn from_service(segment: &Segment, element: usize) -> Option<Self> {
+        let code = component(segment, element, 0)?;
+        Some(ControlError {
+            code: Some(code),
+            tag: component(segment, element + 1, 0),
+            segment_position: None,
+            element: position(segment, element + 2, 0),
+            component: position(segment, element + 2, 1),
+        })
+    }
+}
+
+fn position(segment: &Segment, element: usize, index: usize) -> Option<usize> {
+    component(segment, element, index).and_then(|value| value.parse().ok())
+}
+
+/// The UCM response to one message of the acknowledged interchange.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageResponse {
+    #[pyo3(get)]
+    message_reference: String,
+    #[pyo3(get)]
+    message_type: Option<String>,
+    #[pyo3(get)]
+    action: Option<ControlAction>,
+    #[pyo3(get)]
+    errors: Vec<ControlError>,
+}
+
+impl MessageResponse {
+    fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let ucm = group.find_segment("UCM")?;
+        let mut errors: Vec<ControlError> =
+            ControlError::from_service(ucm, 3).into_iter().collect();
+        for segment_group in group.groups_by_trigger("UCS") {
+            let Some(ucs) = segment_group.find_segment("UCS") else {
+                continue;
+            };
+            let segment_position = position(ucs, 0, 0);
+            if let Some(code) = component(ucs, 1, 0) {
+                errors.push(ControlError {
+                    code: Some(code),
+                    tag: None,
+                    segment_position,
+                    element: None,
+                    component: None,
+                });
+            }
+            for ucd in segment_group.segments_by_tag("UCD") {
+                errors.push(ControlError {
+                    code: component(ucd, 0, 0),
+                    tag: None,
+                    segment_position,
+                    element: position(ucd, 1, 0),
+                    component: position(ucd, 1, 1),
+                });
+            }
+        }
+        Some(MessageResponse {
+            message_reference: component(ucm, 0, 0).unwrap_or_default(),
+            message_type: component(ucm, 1, 0),
+            action: component(ucm, 2, 0).and_then(|code| ControlAction::from_code(&code)),
+            errors,
+        })
+    }
+}
+
+/// A CONTRL syntax and service report, either received from a partner or generated to
+/// acknowledge an interchange.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ControlReport {
+    document: Document,
+}
+
+#[pymethods]
+impl ControlReport {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<ControlReport> {
+        Ok(ControlReport {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> ControlReport {
+        ControlReport {
+            document: Document::from_message(message),
+        }
+    }
+
+    /// Acknowledges an interchange. Findings on the envelope or outside any message reject
+    /// the interchange in UCI; findings within a message reject that message in UCM, with
+    /// UCS and UCD pointing at the erroneous segments and data elements. Without findings
+    /// the interchange's own service segment validation is used.
+    #[staticmethod]
+    #[pyo3(signature = (interchange, control_reference, findings=None, message_reference="1".to_string(), prepared=None))]
+    fn for_interchange(
+        interchange: &Interchange,
+        control_reference: String,
+        findings: Option<Vec<ValidationIssue>>,
+        message_reference: String,
+        prepared: Option<NaiveDateTime>,
+    ) -> PyResult<ControlReport> {
+        let received = interchange
+            .interchange_header
+            .as_ref()
+            .ok_or_else(|| EdifactError::new("Interchange has no UNB to acknowledge"))?;
+        let findings = findings.unwrap_or_else(|| interchange.validate_service_segments());
+        let version = interchange.syntax_version;
+
+        let mut document = Document::new(version.default_delimiters(), version);
+        if let Some(header) = interchange.get_interchange_header()? {
+            document.set_interchange_header(&header.reply(
+                control_reference,
+                prepared,
+                Some("CONTRL".to_string()),
+            ))?;
+        }
+        let (message_version, release) = match version {
+            SyntaxVersion::V4 => ("4", "1"),
+            _ => ("D", "3"),
+        };
+        document.set_message_header(&MessageHeader::new(
+            message_reference,
+            "CONTRL".to_string(),
+            message_version.to_string(),
+            release.to_string(),
+            "UN".to_string(),
+            None,
+        ))?;
+
+        // Each finding belongs to the message whose UNH...UNT span holds it, if any
+        let spans: Vec<(usize, usize)> = interchange
+            .transfers
+            .iter()
+            .map(|transfer| {
+                let start = transfer.service_segments.get("UNH").map(|s| s.position);
+                let end = transfer
+                    .service_segments
+                    .get("UNT")
+                    .or(transfer.segments.last())
+                    .map(|s| s.position);
+                (
+                    start.or(end).unwrap_or_default(),
+                    end.or(start).unwrap_or_default(),
+                )
+            })
+            .collect();
+        let message_of = |finding: &ValidationIssue| {
+            let position = finding.position?;
+            if matches!(finding.tag.as_str(), "UNB" | "UNZ" | "UNG" | "UNE") {
+                return None;
+            }
+            spans
+                .iter()
+                .position(|(start, end)| (*start..=*end).contains(&position))
+        };
+
+        let mut body = Vec::new();
+        let interchange_errors: Vec<&ValidationIssue> = findings
+            .iter()
+            .filter(|f| message_of(f).is_none())
+            .collect();
+        let mut uci: Vec<Vec<String>> = vec![
+            vec![component(received, 4, 0).unwrap_or_default()],
+            received.elements.get(1).cloned().unwrap_or_default(),
+            received.elements.get(2).cloned().unwrap_or_default(),
+        ];
+        match interchange_errors.first() {
+            Some(error) => {
+                uci.push(vec![ControlAction::Rejected.code()]);
+                uci.extend(service_error(error));
+            }
+            None => uci.push(vec![ControlAction::Acknowledged.code()]),
+        }
+        body.push(segment("UCI", uci));
+
+        if interchange_errors.is_empty() {
+            for (index, transfer) in interchange.transfers.iter().enumerate() {
+                let errors: Vec<&ValidationIssue> = findings
+                    .iter()
+                    .filter(|f| message_of(f) == Some(index))
+                    .collect();
+                body.extend(message_response(transfer, spans[index].0, &errors));
+            }
+        }
+        document.segments = body;
+        document.close();
+        Ok(ControlReport { document })
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    /// The control reference (UCI 0020) of the interchange being acknowledged.
+    fn get_control_reference(&self) -> Option<String> {
+        self.uci().and_then(|uci| component(uci, 0, 0))
+    }
+
+    fn get_sender(&self) -> Option<String> {
+        self.uci().and_then(|uci| component(uci, 1, 0))
+    }
+
+    fn get_recipient(&self) -> Option<String> {
+        self.uci().and_then(|uci| component(uci, 2, 0))
+    }
+
+    fn get_action(&self) -> Option<ControlAction> {
+        self.uci()
+            .and_then(|uci| component(uci, 3, 0))
+            .and_then(|code| ControlAction::from_code(&code))
+    }
+
+    /// The error reported against the interchange itself in UCI, if any.
+    fn get_interchange_error(&self) -> Option<ControlError> {
+        self.uci()
+            .and_then(|uci| ControlError::from_service(uci, 4))
+    }
+
+    fn get_message_responses(&self) -> Vec<MessageResponse> {
+        self.document
+            .tree("CONTRL")
+            .groups_by_trigger("UCM")
+            .filter_map(MessageResponse::from_group)
+            .collect()
+    }
+
+    /// Whether the interchange and every message in it were accepted.
+    fn is_accepted(&self) -> bool {
+        self.get_action() != Some(ControlAction::Rejected)
+            && self
+                .get_message_responses()
+                .iter()
+                .all(|response| response.action != Some(ControlAction::Rejected))
+    }
+
+    /// Matches this report against an interchange that was sent, giving the outcome of each
+    /// of its messages. Messages without their own UCM take the interchange's action, as
+    /// acknowledging an interchange acknowledges what it carries unless explicitly rejected.
+    fn reconcile(&self, interchange: &Interchange) -> PyResult<Vec<MessageResponse>> {
+        let sent = interchange
+            .interchange_header
+            .as_ref()
+            .and_then(|header| component(header, 4, 0));
+        if sent != self.get_control_reference() {
+            return Err(EdifactError::new(format!(
+                "CONTRL acknowledges interchange {} rather than {}",
+                self.get_control_reference().unwrap_or_default(),
+                sent.unwrap_or_default()
+            ))
+            .into());
+        }
+
+        let responses = self.get_message_responses();
+        let action = self.get_action();
+        Ok(interchange
+            .transfers
+            .iter()
+            .map(|transfer| {
+                let header = transfer.service_segments.get("UNH");
+                let reference = header.and_then(|h| component(h, 0, 0)).unwrap_or_default();
+                responses
+                    .iter()
+                    .find(|response| response.message_reference == reference)
+                    .cloned()
+                    .unwrap_or_else(|| MessageResponse {
+                        message_reference: reference,
+                        message_type: header.and_then(|h| component(h, 1, 0)),
+                        action,
+                        errors: Vec::new(),
+                    })
+            })
+            .collect())
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl ControlReport {
+    fn uci(&self) -> Option<&Segment> {
+        self.document.segments.iter().find(|s| s.tag == "UCI")
+    }
+}
+
+fn segment(tag: &str, elements: Vec<Vec<String>>) -> Segment {
+    Segment::new(tag.to_string(), trim_elements(elements), 0)
+}
+
+// 0085 error code, 0013 service segment tag and S011 element position, as carried by UCI
+// and UCM
+fn service_error(error: &ValidationIssue) -> Vec<Vec<String>> {
+    let mut elements = vec![vec![error.code.clone()], vec![error.tag.clone()]];
+    if let Some(element) = error.element {
+        elements.push(vec![
+            (element + 1).to_string(),
+            (error.component.unwrap_or_default() + 1).to_string(),
+        ]);
+    }
+    elements
+}
+
+// UCM for one message, followed by a UCS for each erroneous segment with its UCDs
+fn message_response(
+    transfer: &Message,
+    message_start: usize,
+    errors: &[&ValidationIssue],
+) -> Vec<Segment> {
+    let header = transfer.service_segments.get("UNH");
+    let mut ucm: Vec<Vec<String>> = vec![
+        vec![header.and_then(|h| component(h, 0, 0)).unwrap_or_default()],
+        header
+            .and_then(|h| h.elements.get(1).cloned())
+            .unwrap_or_default(),
+    ];
+    let (service_errors, segment_errors): (Vec<&ValidationIssue>, Vec<&ValidationIssue>) = errors
+        .iter()
+        .partition(|error| matches!(error.tag.as_str(), "UNH" | "UNT"));
+    if errors.is_empty() {
+        ucm.push(vec![ControlAction::Acknowledged.code()]);
+    } else {
+        ucm.push(vec![ControlAction::Rejected.code()]);
+        if let Some(error) = service_errors.first() {
+            ucm.extend(service_error(error));
+        }
+    }
+    let mut segments = vec![segment("UCM", ucm)];
+
+    let mut reported: Vec<usize> = Vec::new();
+    for error in &segment_errors {
+        let Some(position) = error.position else {
+            continue;
+        };
+        if reported.contains(&position) {
+            continue;
+        }
+        reported.push(position);
+
+        let in_segment: Vec<&&ValidationIssue> = segment_errors
+            .iter()
+            .filter(|e| e.position == Some(position))
+            .collect();
+        // UNH is segment 1 of the message
+        let segment_position = (position - message_start + 1).to_string();
+        let segment_error = in_segment.iter().find(|e| e.element.is_none());
+        segments.push(match segment_error {
+            Some(e) => segment_of("UCS", &[&[&segment_position], &[&e.code]]),
+            None => segment_of("UCS", &[&[&segment_position]]),
+        });
+        for e in in_segment.iter().filter(|e| e.element.is_some()) {
+            let element = (e.element.unwrap_or_default() + 1).to_string();
+            let component = (e.component.unwrap_or_default() + 1).to_string();
+            segments.push(segment_of("UCD", &[&[&e.code], &[&element, &component]]));
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const INTERCHANGE: &str = "UNB+UNOC:3+SENDER1:14+RECEIVER1:14+240115:1030+REF001'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO1+9'
+UNT+3+1'
+UNH+2+ORDERS:D:96A:UN'
+BGM+220+PO2+9'
+DTM+137:2024011X:102'
+UNT+4+2'
+UNZ+2+REF001'";
+
+    fn lines(report: &ControlReport) -> Vec<String> {
+        report.to_edifact().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_contrl_acknowledges_clean_interchange() {
+        Python::with_gil(|_py| {
+            let interchange = Interchange::from_edifact(INTERCHANGE).unwrap();
+            let report = ControlReport::for_interchange(
+                &interchange,
+                "CTL1".to_string(),
+                None,
+                "1".to_string(),
+                None,
+            )
+            .unwrap();
+            assert_eq!(
+                lines(&report),
+                vec![
+                    "UNB+UNOC:3+RECEIVER1:14+SENDER1:14+240115:1030+CTL1++CONTRL'",
+                    "UNH+1+CONTRL:D:3:UN'",
+                    "UCI+REF001+SENDER1:14+RECEIVER1:14+7'",
+                    "UCM+1+ORDERS:D:96A:UN+7'",
+                    "UCM+2+ORDERS:D:96A:UN+7'",
+                    "UNT+5+1'",
+                    "UNZ+1+CTL1'",
+                ]
+            );
+            assert!(report.is_accepted());
+        });
+    }
+
+    #[test]
+    fn test_contrl_reports_findings_at_their_level() {
+        Python::with_gil(|_py| {
+            let interchange = Interchange::from_edifact(INTERCHANGE).unwrap();
+            // DTM is segment 6 of the interchange and segment 3 of message 2
+            let findings = vec![ValidationIssue::element(
+                validation::INVALID_VALUE,
+                "DTM",
+                Some(6),
+                0,
+                1,
+                "Invalid date".to_string(),
+            )];
+            let report = ControlReport::for_interchange(
+                &interchange,
+                "CTL1".to_string(),
+                Some(findings),
+                "1".to_string(),
+                None,
+            )
+            .unwrap();
+            assert_eq!(
+                lines(&report)[2..7],
+                [
+                    "UCI+REF001+SENDER1:14+RECEIVER1:14+7'",
+                    "UCM+1+ORDERS:D:96A:UN+7'",
+                    "UCM+2+ORDERS:D:96A:UN+4'",
+                    "UCS+3'",
+                    "UCD+12+1:2'",
+                ]
+            );
+
+            let parsed = ControlReport::from_edifact(&report.to_edifact()).unwrap();
+            assert!(!parsed.is_accepted());
+            let responses = parsed.reconcile(&interchange).unwrap();
+            assert_eq!(responses[0].action, Some(ControlAction::Acknowledged));
+            assert_eq!(responses[1].action, Some(ControlAction::Rejected));
+            assert_eq!(
+                responses[1].errors,
+                vec![ControlError {
+                    code: Some("12".to_string()),
+                    tag: None,
+                    segment_position: Some(3),
+                    element: Some(1),
+                    component: Some(2),
+                }]
+            );
+        });
+    }
+
+    #[test]
+    fn test_contrl_rejects_interchange_on_envelope_errors() {
+        Python::with_gil(|_py| {
+            let interchange =
+                Interchange::from_edifact(&INTERCHANGE.replace("UNZ+2+REF001", "UNZ+2+REF002"))
+                    .unwrap();
+            let report = ControlReport::for_interchange(
+                &interchange,
+                "CTL1".to_string(),
+                None,
+                "1".to_string(),
+                None,
+            )
+            .unwrap();
+            assert_eq!(
+                lines(&report)[2],
+                "UCI+REF001+SENDER1:14+RECEIVER1:14+4+28+UNZ+2:1'"
+            );
+            assert_eq!(report.get_message_responses(), Vec::new());
+            assert_eq!(
+                report.get_interchange_error().unwrap().code.as_deref(),
+                Some("28")
+            );
+        });
+    }
+}
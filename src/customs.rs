@@ -0,0 +1,927 @@
+use pyo3::prelude::*;
+
+use crate::common::{
+    format_cents, parse_cents, percentage_cents, DateTimePeriod, Location, Measurement,
+    MonetaryAmount, Party, Reference, Tax,
+};
+use crate::desadv::Packaging;
+use crate::directory::SegmentGroup;
+use crate::document::{segment_of, Document};
+use crate::header::{component, InterchangeHeader, MessageHeader};
+use crate::{EdifactError, Message, Segment};
+
+// Joins the free text (C108) of the FTX segments directly in a group
+fn free_text(group: &SegmentGroup) -> Option<String> {
+    let text: Vec<String> = group
+        .segments_by_tag("FTX")
+        .flat_map(|ftx| (0..5).filter_map(move |index| component(ftx, 3, index)))
+        .collect();
+    (!text.is_empty()).then(|| text.join(" "))
+}
+
+fn duties<'a>(groups: impl Iterator<Item = &'a SegmentGroup>) -> Vec<Tax> {
+    groups.filter_map(Tax::from_group).collect()
+}
+
+fn errors(group: &SegmentGroup) -> Vec<CustomsError> {
+    group
+        .groups_by_trigger("ERP")
+        .flat_map(CustomsError::from_group)
+        .collect()
+}
+
+/// An error reported by customs: the ERC error code with the ERP error point (message
+/// section and goods item number) and any free text.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomsError {
+    #[pyo3(get)]
+    code: String,
+    #[pyo3(get)]
+    text: Option<String>,
+    /// ERP 1049 message section
+    #[pyo3(get)]
+    section: Option<String>,
+    /// ERP 1052 number of the goods item in error
+    #[pyo3(get)]
+    item_number: Option<String>,
+    #[pyo3(get)]
+    references: Vec<Reference>,
+}
+
+impl CustomsError {
+    // One error per ERC in the ERP group
+    fn from_group(group: &SegmentGroup) -> Vec<Self> {
+        let Some(point) = group.find_segment("ERP") else {
+            return Vec::new();
+        };
+        let text = free_text(group);
+        let references: Vec<Reference> = group
+            .segments_by_tag("RFF")
+            .map(Reference::from_segment)
+            .collect();
+        group
+            .segments_by_tag("ERC")
+            .map(|erc| CustomsError {
+                code: component(erc, 0, 0).unwrap_or_default(),
+                text: text.clone(),
+                section: component(point, 0, 0),
+                item_number: component(point, 0, 1),
+                references: references.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A CST goods item of a customs declaration or response: the commodity code, description,
+/// origin, weights and packages, the parties and related numbers (GIR), and the duties and
+/// taxes assessed on it.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomsGoodsItem {
+    #[pyo3(get)]
+    item_number: Option<String>,
+    /// CST C246 customs goods identifier, e.g. the tariff code
+    #[pyo3(get)]
+    commodity_code: Option<String>,
+    #[pyo3(get)]
+    description: Option<String>,
+    /// LOC+27 country of origin
+    #[pyo3(get)]
+    origin: Option<String>,
+    #[pyo3(get)]
+    locations: Vec<Location>,
+    #[pyo3(get)]
+    measurements: Vec<Measurement>,
+    #[pyo3(get)]
+    amounts: Vec<MonetaryAmount>,
+    #[pyo3(get)]
+    packages: Vec<Packaging>,
+    #[pyo3(get)]
+    references: Vec<Reference>,
+    /// Parties of the goods item, including those given for its GIR related numbers
+    #[pyo3(get)]
+    parties: Vec<Party>,
+    /// GIR C206 identification numbers, such as serial or container numbers
+    #[pyo3(get)]
+    related_numbers: Vec<String>,
+    #[pyo3(get)]
+    duties: Vec<Tax>,
+    #[pyo3(get)]
+    errors: Vec<CustomsError>,
+}
+
+#[pymethods]
+impl CustomsGoodsItem {
+    /// The customs value (MOA+40).
+    fn customs_value(&self) -> Option<String> {
+        self.amounts
+            .iter()
+            .find(|amount| amount.qualifier == "40")
+            .and_then(|amount| amount.amount.clone())
+    }
+
+    /// The net (MEA dimension AAA) or gross (AAB) weight.
+    #[pyo3(signature = (gross=false))]
+    fn weight(&self, gross: bool) -> Option<Measurement> {
+        let dimension = if gross { "AAB" } else { "AAA" };
+        self.measurements
+            .iter()
+            .find(|measurement| measurement.dimension.as_deref() == Some(dimension))
+            .cloned()
+    }
+
+    /// The amount (MOA+124) assessed for a duty or tax type, e.g. CUD or VAT.
+    fn duty_amount(&self, tax_type: &str) -> Option<String> {
+        tax_amount(&self.duties, tax_type)
+    }
+}
+
+impl CustomsGoodsItem {
+    fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("CST")?;
+        let locations: Vec<Location> = group
+            .segments_by_tag("LOC")
+            .map(Location::from_segment)
+            .collect();
+        let related = || group.groups_by_trigger("GIR");
+        Some(CustomsGoodsItem {
+            item_number: component(segment, 0, 0),
+            commodity_code: component(segment, 1, 0),
+            description: free_text(group),
+            origin: locations
+                .iter()
+                .find(|location| location.qualifier == "27")
+                .and_then(|location| location.id.clone()),
+            locations,
+            measurements: group
+                .segments_by_tag("MEA")
+                .map(Measurement::from_segment)
+                .collect(),
+            amounts: group
+                .segments_by_tag("MOA")
+                .map(MonetaryAmount::from_segment)
+                .collect(),
+            packages: group
+                .groups_by_trigger("PAC")
+                .filter_map(Packaging::from_group)
+                .collect(),
+            references: group
+                .groups_by_trigger("RFF")
+                .filter_map(Reference::from_group)
+                .collect(),
+            parties: group
+                .groups_by_trigger("NAD")
+                .chain(related().flat_map(|gir| gir.groups_by_trigger("NAD")))
+                .filter_map(Party::from_group)
+                .collect(),
+            related_numbers: related()
+                .filter_map(|gir| gir.find_segment("GIR"))
+                .flat_map(|gir| {
+                    (1..gir.elements.len()).filter_map(|element| component(gir, element, 0))
+                })
+                .collect(),
+            duties: duties(group.groups_by_trigger("TAX")),
+            errors: errors(group),
+        })
+    }
+}
+
+// Sums the MOA+124 amounts of the duties of a type
+fn tax_amount(duties: &[Tax], tax_type: &str) -> Option<String> {
+    let amounts: Vec<i64> = duties
+        .iter()
+        .filter(|duty| duty.tax_type.as_deref() == Some(tax_type))
+        .flat_map(|duty| &duty.amounts)
+        .filter(|amount| amount.qualifier == "124")
+        .filter_map(|amount| amount.amount.as_deref().and_then(parse_cents))
+        .collect();
+    (!amounts.is_empty()).then(|| format_cents(amounts.iter().sum()))
+}
+
+/// A CUSDEC customs declaration: the header with the declarant and other parties, and the
+/// goods items following UNS+D, with the duties and taxes totalled after UNS+S.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct CustomsDeclaration {
+    document: Document,
+}
+
+#[pymethods]
+impl CustomsDeclaration {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<CustomsDeclaration> {
+        Ok(CustomsDeclaration {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> CustomsDeclaration {
+        CustomsDeclaration {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    /// BGM 1001, e.g. 929 for an import declaration.
+    fn get_document_name(&self) -> Option<String> {
+        self.document.document_name()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    fn get_goods_items(&self) -> Vec<CustomsGoodsItem> {
+        self.tree()
+            .groups_by_trigger("CST")
+            .filter_map(CustomsGoodsItem::from_group)
+            .collect()
+    }
+
+    /// The duty and tax totals following UNS+S.
+    fn get_duties(&self) -> Vec<Tax> {
+        let tree = self.tree();
+        let summary = tree.segments_by_tag("UNS").last().map(|uns| uns.position);
+        duties(tree.groups_by_trigger("TAX").filter(|group| {
+            let position = group.direct_segments().next().map(|s| s.position);
+            matches!((position, summary), (Some(p), Some(s)) if p > s)
+        }))
+    }
+
+    fn get_duty_amount(&self, tax_type: &str) -> Option<String> {
+        tax_amount(&self.get_duties(), tax_type)
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl CustomsDeclaration {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("CUSDEC")
+    }
+}
+
+/// A CUSRES customs response to a declaration, with the duties assessed, the processing
+/// status and any errors, both for the declaration and per goods item.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct CustomsResponse {
+    document: Document,
+}
+
+#[pymethods]
+impl CustomsResponse {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<CustomsResponse> {
+        Ok(CustomsResponse {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> CustomsResponse {
+        CustomsResponse {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_name(&self) -> Option<String> {
+        self.document.document_name()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    /// The number customs gave the declaration, from RFF+ABT.
+    fn get_declaration_number(&self) -> Option<String> {
+        self.get_references()
+            .into_iter()
+            .find(|reference| reference.qualifier == "ABT")
+            .and_then(|reference| reference.number)
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    /// GIS 7365 processing indicators, giving the status of the declaration.
+    fn get_processing_indicators(&self) -> Vec<String> {
+        self.tree()
+            .segments_by_tag("GIS")
+            .filter_map(|gis| component(gis, 0, 0))
+            .collect()
+    }
+
+    fn get_duties(&self) -> Vec<Tax> {
+        duties(self.tree().groups_by_trigger("TAX"))
+    }
+
+    fn get_duty_amount(&self, tax_type: &str) -> Option<String> {
+        tax_amount(&self.get_duties(), tax_type)
+    }
+
+    /// Errors in the declaration as a whole; errors in goods items are on the items.
+    fn get_errors(&self) -> Vec<CustomsError> {
+        errors(&self.tree())
+    }
+
+    fn get_goods_items(&self) -> Vec<CustomsGoodsItem> {
+        self.tree()
+            .groups_by_trigger("CST")
+            .filter_map(CustomsGoodsItem::from_group)
+            .collect()
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl CustomsResponse {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("CUSRES")
+    }
+}
+
+fn invalid(value: &str, what: &str) -> EdifactError {
+    EdifactError::new(format!("Invalid {} '{}'", what, value))
+}
+
+// The amount in cents of one duty or tax type across the goods items, for the summary
+#[derive(Debug, Clone)]
+struct Assessment {
+    tax_type: &'static str,
+    function: &'static str,
+    amount: i64,
+}
+
+/// Builds a CUSDEC, computing the customs duty and import VAT of every goods item and the
+/// totals of the declaration. Duty is assessed on the customs value, and VAT on the customs
+/// value plus duty.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct CustomsDeclarationBuilder {
+    document: Document,
+    document_number: String,
+    declaration_type: String,
+    currency: Option<String>,
+    locations: Vec<Segment>,
+    dates: Vec<Segment>,
+    references: Vec<Segment>,
+    parties: Vec<Segment>,
+    items: Vec<(Vec<Segment>, Vec<Assessment>)>,
+}
+
+#[pymethods]
+impl CustomsDeclarationBuilder {
+    #[new]
+    #[pyo3(signature = (
+        document_number,
+        declaration_type="929".to_string(),
+        message_reference="1".to_string(),
+        release="96B".to_string(),
+    ))]
+    fn new(
+        document_number: String,
+        declaration_type: String,
+        message_reference: String,
+        release: String,
+    ) -> PyResult<CustomsDeclarationBuilder> {
+        let mut document = Document::new(Default::default(), Default::default());
+        document.set_message_header(&MessageHeader::new(
+            message_reference,
+            "CUSDEC".to_string(),
+            "D".to_string(),
+            release,
+            "UN".to_string(),
+            None,
+        ))?;
+        Ok(CustomsDeclarationBuilder {
+            document,
+            document_number,
+            declaration_type,
+            currency: None,
+            locations: Vec::new(),
+            dates: Vec::new(),
+            references: Vec::new(),
+            parties: Vec::new(),
+            items: Vec::new(),
+        })
+    }
+
+    fn with_interchange<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        header: InterchangeHeader,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.document.syntax_version = header.syntax_version;
+        slf.document.delimiters = header.syntax_version.default_delimiters();
+        slf.document.set_interchange_header(&header)?;
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (qualifier, value, format="102"))]
+    fn with_date<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.dates
+            .push(segment_of("DTM", &[&[qualifier, value, format]]));
+        Ok(slf)
+    }
+
+    /// Adds a location of the declaration, e.g. LOC+22 for the customs office.
+    fn with_location<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        id: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.locations
+            .push(segment_of("LOC", &[&[qualifier], &[id]]));
+        Ok(slf)
+    }
+
+    fn with_reference<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        number: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.references
+            .push(segment_of("RFF", &[&[qualifier, number]]));
+        Ok(slf)
+    }
+
+    /// Adds a party such as the declarant (DT), importer (IM) or exporter (EX), with its
+    /// structured name and address when given.
+    #[pyo3(signature = (
+        qualifier,
+        id,
+        code_list_agency="9",
+        name=None,
+        street=None,
+        city=None,
+        postal_code=None,
+        country=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn with_party<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        id: &str,
+        code_list_agency: &str,
+        name: Option<&str>,
+        street: Option<&str>,
+        city: Option<&str>,
+        postal_code: Option<&str>,
+        country: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.parties.push(segment_of(
+            "NAD",
+            &[
+                &[qualifier],
+                &[id, "", code_list_agency],
+                &[],
+                &[name.unwrap_or_default()],
+                &[street.unwrap_or_default()],
+                &[city.unwrap_or_default()],
+                &[],
+                &[postal_code.unwrap_or_default()],
+                &[country.unwrap_or_default()],
+            ],
+        ));
+        Ok(slf)
+    }
+
+    /// Sets the currency of the amounts (C516 6345).
+    fn with_currency<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        currency: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.currency = Some(currency.to_string());
+        Ok(slf)
+    }
+
+    /// Adds a goods item, assessing customs duty (TAX+5+CUD) at `duty_rate` percent of the
+    /// customs value and import VAT (TAX+7+VAT) at `vat_rate` percent of the customs value
+    /// plus duty. Weights are in kilograms; parties are (qualifier, id) pairs, e.g. the
+    /// consignor (CZ) of the item.
+    #[pyo3(signature = (
+        commodity_code,
+        description,
+        customs_value,
+        duty_rate=None,
+        vat_rate=None,
+        origin=None,
+        net_weight=None,
+        gross_weight=None,
+        package_count=None,
+        package_type=None,
+        parties=Vec::new(),
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn add_goods_item<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        commodity_code: &str,
+        description: &str,
+        customs_value: &str,
+        duty_rate: Option<&str>,
+        vat_rate: Option<&str>,
+        origin: Option<&str>,
+        net_weight: Option<&str>,
+        gross_weight: Option<&str>,
+        package_count: Option<&str>,
+        package_type: Option<&str>,
+        parties: Vec<(String, String)>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let value =
+            parse_cents(customs_value).ok_or_else(|| invalid(customs_value, "customs value"))?;
+        let currency = slf.currency.as_deref().unwrap_or_default();
+        let money = |qualifier: &str, value: i64| {
+            segment_of("MOA", &[&[qualifier, &format_cents(value), currency]])
+        };
+
+        let number = (slf.items.len() + 1).to_string();
+        let mut segments = vec![
+            segment_of("CST", &[&[&number], &[commodity_code]]),
+            segment_of("FTX", &[&["AAA"], &[], &[], &[description]]),
+        ];
+        segments.extend(origin.map(|origin| segment_of("LOC", &[&["27"], &[origin]])));
+        for (dimension, weight) in [("AAA", net_weight), ("AAB", gross_weight)] {
+            if let Some(weight) = weight {
+                segments.push(segment_of(
+                    "MEA",
+                    &[&["AAE"], &[dimension], &["KGM", weight]],
+                ));
+            }
+        }
+        segments.push(money("40", value));
+        if let Some(count) = package_count {
+            segments.push(segment_of(
+                "PAC",
+                &[&[count], &[], &[package_type.unwrap_or_default()]],
+            ));
+        }
+        for (qualifier, id) in &parties {
+            segments.push(segment_of("NAD", &[&[qualifier], &[id, "", "9"]]));
+        }
+
+        let mut assessments = Vec::new();
+        let mut duty = 0;
+        for (function, tax_type, rate) in [("5", "CUD", duty_rate), ("7", "VAT", vat_rate)] {
+            let Some(rate) = rate else { continue };
+            let basis = value + duty;
+            let assessed = percentage_cents(basis, rate).ok_or_else(|| invalid(rate, "rate"))?;
+            if tax_type == "CUD" {
+                duty = assessed;
+            }
+            segments.push(segment_of(
+                "TAX",
+                &[&[function], &[tax_type], &[], &[], &["", "", "", rate]],
+            ));
+            segments.push(money("125", basis));
+            segments.push(money("124", assessed));
+            assessments.push(Assessment {
+                tax_type,
+                function,
+                amount: assessed,
+            });
+        }
+        slf.items.push((segments, assessments));
+        Ok(slf)
+    }
+
+    /// Writes the declaration with its duty and tax totals and checks it against the
+    /// CUSDEC structure.
+    fn build(&self) -> PyResult<CustomsDeclaration> {
+        let mut document = self.document.clone();
+        let body = &mut document.segments;
+        body.push(segment_of(
+            "BGM",
+            &[&[&self.declaration_type], &[&self.document_number], &["9"]],
+        ));
+        for group in [
+            &self.locations,
+            &self.dates,
+            &self.references,
+            &self.parties,
+        ] {
+            body.extend(group.iter().cloned());
+        }
+        body.push(segment_of("UNS", &[&["D"]]));
+        for (segments, _) in &self.items {
+            body.extend(segments.iter().cloned());
+        }
+        body.push(segment_of("UNS", &[&["S"]]));
+        body.push(segment_of("CNT", &[&["5", &self.items.len().to_string()]]));
+
+        // Totals per duty or tax type, in order of first use
+        let mut totals: Vec<Assessment> = Vec::new();
+        for assessment in self.items.iter().flat_map(|(_, a)| a) {
+            match totals
+                .iter_mut()
+                .find(|t| t.tax_type == assessment.tax_type)
+            {
+                Some(total) => total.amount += assessment.amount,
+                None => totals.push(assessment.clone()),
+            }
+        }
+        let currency = self.currency.as_deref().unwrap_or_default();
+        for total in totals {
+            body.push(segment_of("TAX", &[&[total.function], &[total.tax_type]]));
+            body.push(segment_of(
+                "MOA",
+                &[&["124", &format_cents(total.amount), currency]],
+            ));
+        }
+        document.close();
+
+        document.check_structure("CUSDEC")?;
+        Ok(CustomsDeclaration { document })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    #[test]
+    fn test_customs_declaration_builder() {
+        Python::with_gil(|py| {
+            let builder = CustomsDeclarationBuilder::new(
+                "DEC0001".to_string(),
+                "929".to_string(),
+                "1".to_string(),
+                "96B".to_string(),
+            )
+            .unwrap();
+            let builder = Bound::new(py, builder).unwrap();
+            CustomsDeclarationBuilder::with_location(builder.borrow_mut(), "22", "NL000396")
+                .unwrap();
+            CustomsDeclarationBuilder::with_date(builder.borrow_mut(), "137", "20240301", "102")
+                .unwrap();
+            CustomsDeclarationBuilder::with_party(
+                builder.borrow_mut(),
+                "DT",
+                "NL123456789",
+                "9",
+                Some("Declarant BV"),
+                None,
+                Some("Rotterdam"),
+                None,
+                Some("NL"),
+            )
+            .unwrap();
+            CustomsDeclarationBuilder::with_currency(builder.borrow_mut(), "EUR").unwrap();
+            CustomsDeclarationBuilder::add_goods_item(
+                builder.borrow_mut(),
+                "84713000",
+                "Laptops",
+                "10000",
+                Some("0"),
+                Some("21"),
+                Some("CN"),
+                Some("250"),
+                Some("300"),
+                Some("10"),
+                Some("CT"),
+                vec![("CZ".to_string(), "CN987654".to_string())],
+            )
+            .unwrap();
+            CustomsDeclarationBuilder::add_goods_item(
+                builder.borrow_mut(),
+                "62034231",
+                "Cotton trousers",
+                "2000",
+                Some("12"),
+                Some("21"),
+                Some("BD"),
+                None,
+                None,
+                None,
+                None,
+                Vec::new(),
+            )
+            .unwrap();
+            let declaration = builder.borrow().build().unwrap();
+            assert_eq!(
+                declaration.to_edifact(),
+                "UNH+1+CUSDEC:D:96B:UN'
+BGM+929+DEC0001+9'
+LOC+22+NL000396'
+DTM+137:20240301:102'
+NAD+DT+NL123456789::9++Declarant BV++Rotterdam+++NL'
+UNS+D'
+CST+1+84713000'
+FTX+AAA+++Laptops'
+LOC+27+CN'
+MEA+AAE+AAA+KGM:250'
+MEA+AAE+AAB+KGM:300'
+MOA+40:10000.00:EUR'
+PAC+10++CT'
+NAD+CZ+CN987654::9'
+TAX+5+CUD+++:::0'
+MOA+125:10000.00:EUR'
+MOA+124:0.00:EUR'
+TAX+7+VAT+++:::21'
+MOA+125:10000.00:EUR'
+MOA+124:2100.00:EUR'
+CST+2+62034231'
+FTX+AAA+++Cotton trousers'
+LOC+27+BD'
+MOA+40:2000.00:EUR'
+TAX+5+CUD+++:::12'
+MOA+125:2000.00:EUR'
+MOA+124:240.00:EUR'
+TAX+7+VAT+++:::21'
+MOA+125:2240.00:EUR'
+MOA+124:470.40:EUR'
+UNS+S'
+CNT+5:2'
+TAX+5+CUD'
+MOA+124:240.00:EUR'
+TAX+7+VAT'
+MOA+124:2570.40:EUR'
+UNT+37+1'
+"
+            );
+
+            let parsed = CustomsDeclaration::from_edifact(&declaration.to_edifact()).unwrap();
+            assert_eq!(parsed.get_duty_amount("VAT").as_deref(), Some("2570.40"));
+            assert_eq!(parsed.get_duties().len(), 2);
+            let items = parsed.get_goods_items();
+            assert_eq!(items.len(), 2);
+            assert_eq!(items[0].commodity_code.as_deref(), Some("84713000"));
+            assert_eq!(items[0].origin.as_deref(), Some("CN"));
+            assert_eq!(items[0].customs_value().as_deref(), Some("10000.00"));
+            assert_eq!(items[0].weight(true).unwrap().value.as_deref(), Some("300"));
+            assert_eq!(items[0].packages.len(), 1);
+            assert_eq!(items[0].parties[0].qualifier, "CZ");
+            assert_eq!(items[1].duty_amount("CUD").as_deref(), Some("240.00"));
+        });
+    }
+
+    #[test]
+    fn test_customs_duty_rounds_in_cents() {
+        Python::with_gil(|py| {
+            let builder = CustomsDeclarationBuilder::new(
+                "DEC0002".to_string(),
+                "929".to_string(),
+                "1".to_string(),
+                "96B".to_string(),
+            )
+            .unwrap();
+            let builder = Bound::new(py, builder).unwrap();
+            CustomsDeclarationBuilder::with_currency(builder.borrow_mut(), "EUR").unwrap();
+            for _ in 0..2 {
+                CustomsDeclarationBuilder::add_goods_item(
+                    builder.borrow_mut(),
+                    "84713000",
+                    "Laptop bag",
+                    "10.05",
+                    Some("10"),
+                    Some("21"),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Vec::new(),
+                )
+                .unwrap();
+            }
+            let declaration = builder.borrow().build().unwrap();
+            let items = declaration.get_goods_items();
+            assert_eq!(items[0].duty_amount("CUD").as_deref(), Some("1.01"));
+            assert_eq!(items[0].duty_amount("VAT").as_deref(), Some("2.32"));
+            assert_eq!(declaration.get_duty_amount("CUD").as_deref(), Some("2.02"));
+            assert_eq!(declaration.get_duty_amount("VAT").as_deref(), Some("4.64"));
+        });
+    }
+
+    #[test]
+    fn test_customs_response() {
+        Python::with_gil(|_py| {
+            let response = CustomsResponse::from_edifact(
+                "UNH+1+CUSRES:D:96B:UN'
+BGM+962+RES0001+9'
+DTM+137:20240301:102'
+GIS+4'
+RFF+ABT:24NL000396ABC123'
+NAD+DT+NL123456789::9'
+TAX+5+CUD'
+MOA+124:240.00:EUR'
+TAX+7+VAT'
+MOA+124:2570.40:EUR'
+ERP+1'
+ERC+12'
+FTX+AAO+++Missing transport document'
+CST+1+84713000'
+GIR+3+SN001:BN+SN002:BN'
+NAD+CZ+CN987654::9'
+ERP+3:1'
+ERC+15'
+ERC+37'
+CST+2+62034231'
+TAX+5+CUD+++:::12'
+MOA+124:240.00:EUR'
+UNT+23+1'",
+            )
+            .unwrap();
+            assert_eq!(
+                response.get_declaration_number().as_deref(),
+                Some("24NL000396ABC123")
+            );
+            assert_eq!(response.get_processing_indicators(), vec!["4".to_string()]);
+            assert_eq!(response.get_duty_amount("VAT").as_deref(), Some("2570.40"));
+            assert_eq!(
+                response.get_errors(),
+                vec![CustomsError {
+                    code: "12".to_string(),
+                    text: Some("Missing transport document".to_string()),
+                    section: Some("1".to_string()),
+                    item_number: None,
+                    references: Vec::new(),
+                }]
+            );
+
+            let items = response.get_goods_items();
+            assert_eq!(
+                items[0].related_numbers,
+                vec!["SN001".to_string(), "SN002".to_string()]
+            );
+            assert_eq!(items[0].parties[0].id.as_deref(), Some("CN987654"));
+            let codes: Vec<(&str, Option<&str>)> = items[0]
+                .errors
+                .iter()
+                .map(|error| (error.code.as_str(), error.item_number.as_deref()))
+                .collect();
+            assert_eq!(codes, vec![("15", Some("1")), ("37", Some("1"))]);
+            assert_eq!(items[1].duty_amount("CUD").as_deref(), Some("240.00"));
+        });
+    }
+}
@@ -0,0 +1,565 @@
+use pyo3::prelude::*;
+
+use crate::common::{DateTimePeriod, Party, Quantity, Reference};
+use crate::directory::SegmentGroup;
+use crate::document::{segment_of, Document};
+use crate::header::{component, InterchangeHeader, MessageHeader};
+use crate::{EdifactError, Message, OrderLine, Segment};
+
+/// Checks the GS1 mod-10 check digit that ends an SSCC (18 digits).
+pub(crate) fn is_valid_sscc(sscc: &str) -> bool {
+    if sscc.len() != 18 || !sscc.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let digits: Vec<u32> = sscc.chars().filter_map(|c| c.to_digit(10)).collect();
+    // Weights alternate 3, 1, 3, ... starting from the digit next to the check digit
+    let sum: u32 = digits[..17]
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+    (10 - sum % 10) % 10 == digits[17]
+}
+
+/// One PAC packaging entry of a package level: how many packages of which type, and the
+/// SSCC labels (GIN+BJ) marked on them.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packaging {
+    #[pyo3(get)]
+    count: Option<String>,
+    #[pyo3(get)]
+    package_type: Option<String>,
+    #[pyo3(get)]
+    quantities: Vec<Quantity>,
+    #[pyo3(get)]
+    sscc: Vec<String>,
+}
+
+impl Packaging {
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let pac = group.find_segment("PAC")?;
+        let sscc = group
+            .groups_by_trigger("PCI")
+            .flat_map(|marking| marking.groups_by_trigger("GIN"))
+            .filter_map(|identity| identity.find_segment("GIN"))
+            .filter(|gin| component(gin, 0, 0).as_deref() == Some("BJ"))
+            .flat_map(|gin| (1..=5).filter_map(|element| component(gin, element, 0)))
+            .collect();
+        Some(Packaging {
+            count: component(pac, 0, 0),
+            package_type: component(pac, 2, 0),
+            quantities: group
+                .segments_by_tag("QTY")
+                .map(Quantity::from_segment)
+                .collect(),
+            sscc,
+        })
+    }
+
+    fn to_segments(&self) -> Vec<Segment> {
+        let mut segments = vec![segment_of(
+            "PAC",
+            &[
+                &[self.count.as_deref().unwrap_or_default()],
+                &[],
+                &[self.package_type.as_deref().unwrap_or_default()],
+            ],
+        )];
+        if !self.sscc.is_empty() {
+            // 33E: marked with a serial shipping container code
+            segments.push(segment_of("PCI", &[&["33E"]]));
+            for sscc in &self.sscc {
+                segments.push(segment_of("GIN", &[&["BJ"], &[sscc]]));
+            }
+        }
+        segments
+    }
+}
+
+/// A level of the CPS packing hierarchy (a pallet, a carton, ...) with its packaging, the
+/// line items packed directly in it and the packages it contains.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Package {
+    #[pyo3(get)]
+    pub(crate) id: Option<String>,
+    #[pyo3(get)]
+    parent_id: Option<String>,
+    #[pyo3(get, set)]
+    packaging_level: Option<String>,
+    #[pyo3(get)]
+    packaging: Vec<Packaging>,
+    #[pyo3(get)]
+    pub(crate) items: Vec<OrderLine>,
+    #[pyo3(get)]
+    pub(crate) children: Vec<Package>,
+}
+
+#[pymethods]
+impl Package {
+    #[new]
+    #[pyo3(signature = (package_type=None, count="1".to_string(), sscc=None))]
+    fn new(package_type: Option<String>, count: String, sscc: Option<String>) -> PyResult<Package> {
+        if let Some(sscc) = sscc.as_deref().filter(|sscc| !is_valid_sscc(sscc)) {
+            return Err(EdifactError::new(format!("Invalid SSCC '{}'", sscc)).into());
+        }
+        Ok(Package {
+            id: None,
+            parent_id: None,
+            packaging_level: None,
+            packaging: vec![Packaging {
+                count: Some(count),
+                package_type,
+                quantities: Vec::new(),
+                sscc: sscc.into_iter().collect(),
+            }],
+            items: Vec::new(),
+            children: Vec::new(),
+        })
+    }
+
+    fn add_child(&mut self, child: Package) {
+        self.children.push(child);
+    }
+
+    /// Packs a line item (LIN with a QTY+12 despatch quantity) directly in this package.
+    #[pyo3(signature = (line_number, item_number, quantity, unit=None, item_type="SRV"))]
+    fn add_item(
+        &mut self,
+        line_number: &str,
+        item_number: &str,
+        quantity: &str,
+        unit: Option<&str>,
+        item_type: &str,
+    ) {
+        let mut line = OrderLine::from_group(SegmentGroup::flat(
+            "LIN",
+            vec![segment_of(
+                "LIN",
+                &[&[line_number], &[], &[item_number, item_type]],
+            )],
+        ));
+        line.add_segment(segment_of(
+            "QTY",
+            &[&["12", quantity, unit.unwrap_or_default()]],
+        ));
+        self.items.push(line);
+    }
+
+    /// Every SSCC on this package level.
+    #[getter]
+    pub(crate) fn sscc(&self) -> Vec<String> {
+        self.packaging
+            .iter()
+            .flat_map(|packaging| packaging.sscc.iter().cloned())
+            .collect()
+    }
+}
+
+impl Package {
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let cps = group.find_segment("CPS")?;
+        Some(Package {
+            id: component(cps, 0, 0),
+            parent_id: component(cps, 1, 0),
+            packaging_level: component(cps, 2, 0),
+            packaging: group
+                .groups_by_trigger("PAC")
+                .filter_map(Packaging::from_group)
+                .collect(),
+            items: Document::lines(group),
+            children: Vec::new(),
+        })
+    }
+
+    // Rebuilds the hierarchy from the CPS parent references. Packages whose parent is not
+    // found stay at the top level.
+    pub(crate) fn nest(packages: Vec<Package>) -> Vec<Package> {
+        let mut nested: Vec<Package> = Vec::new();
+        for package in packages {
+            let parent = package
+                .parent_id
+                .as_deref()
+                .and_then(|id| Package::find_mut(&mut nested, id));
+            match parent {
+                Some(parent) => parent.children.push(package),
+                None => nested.push(package),
+            }
+        }
+        nested
+    }
+
+    fn find_mut<'a>(packages: &'a mut [Package], id: &str) -> Option<&'a mut Package> {
+        for package in packages {
+            if package.id.as_deref() == Some(id) {
+                return Some(package);
+            }
+            if let Some(found) = Package::find_mut(&mut package.children, id) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    fn item_count(&self) -> usize {
+        self.items.len() + self.children.iter().map(Package::item_count).sum::<usize>()
+    }
+
+    // Writes this package and its descendants depth first, numbering CPS levels from
+    // `next_id` onwards
+    fn write(&self, parent_id: Option<&str>, next_id: &mut usize, body: &mut Vec<Segment>) {
+        let id = next_id.to_string();
+        *next_id += 1;
+        body.push(segment_of(
+            "CPS",
+            &[
+                &[&id],
+                &[parent_id.unwrap_or_default()],
+                &[self.packaging_level.as_deref().unwrap_or_default()],
+            ],
+        ));
+        for packaging in &self.packaging {
+            body.extend(packaging.to_segments());
+        }
+        for item in &self.items {
+            body.extend(item.segments());
+        }
+        for child in &self.children {
+            child.write(Some(&id), next_id, body);
+        }
+    }
+}
+
+/// A DESADV despatch advice with its packing hierarchy.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct DespatchAdvice {
+    document: Document,
+}
+
+#[pymethods]
+impl DespatchAdvice {
+    #[staticmethod]
+    pub(crate) fn from_edifact(content: &str) -> PyResult<DespatchAdvice> {
+        Ok(DespatchAdvice {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> DespatchAdvice {
+        DespatchAdvice {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    /// BGM document name code: 351 for a despatch advice.
+    fn get_document_name(&self) -> Option<String> {
+        self.document.document_name()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    /// The top-level packages, each holding the packages it contains.
+    pub(crate) fn get_packages(&self) -> Vec<Package> {
+        let packages = self
+            .tree()
+            .groups_by_trigger("CPS")
+            .filter_map(Package::from_group)
+            .collect();
+        Package::nest(packages)
+    }
+
+    /// Every line item, whichever package it is packed in.
+    pub(crate) fn get_lines(&self) -> Vec<OrderLine> {
+        self.tree()
+            .groups_by_trigger("CPS")
+            .flat_map(Document::lines)
+            .collect()
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl DespatchAdvice {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("DESADV")
+    }
+}
+
+/// Builds a DESADV from header details and a packing tree.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct DespatchAdviceBuilder {
+    document: Document,
+    document_number: String,
+    dates: Vec<Segment>,
+    references: Vec<Segment>,
+    parties: Vec<Segment>,
+    packages: Vec<Package>,
+}
+
+#[pymethods]
+impl DespatchAdviceBuilder {
+    #[new]
+    #[pyo3(signature = (document_number, message_reference="1".to_string(), release="96A".to_string()))]
+    fn new(
+        document_number: String,
+        message_reference: String,
+        release: String,
+    ) -> PyResult<DespatchAdviceBuilder> {
+        let mut document = Document::new(Default::default(), Default::default());
+        document.set_message_header(&MessageHeader::new(
+            message_reference,
+            "DESADV".to_string(),
+            "D".to_string(),
+            release,
+            "UN".to_string(),
+            None,
+        ))?;
+        Ok(DespatchAdviceBuilder {
+            document,
+            document_number,
+            dates: Vec::new(),
+            references: Vec::new(),
+            parties: Vec::new(),
+            packages: Vec::new(),
+        })
+    }
+
+    fn with_interchange<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        header: InterchangeHeader,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.document.syntax_version = header.syntax_version;
+        slf.document.delimiters = header.syntax_version.default_delimiters();
+        slf.document.set_interchange_header(&header)?;
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (qualifier, value, format="102"))]
+    fn with_date<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.dates
+            .push(segment_of("DTM", &[&[qualifier, value, format]]));
+        Ok(slf)
+    }
+
+    fn with_reference<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        number: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.references
+            .push(segment_of("RFF", &[&[qualifier, number]]));
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (qualifier, id, code_list_agency="9"))]
+    fn with_party<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        id: &str,
+        code_list_agency: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.parties.push(segment_of(
+            "NAD",
+            &[&[qualifier], &[id, "", code_list_agency]],
+        ));
+        Ok(slf)
+    }
+
+    fn add_package<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        package: Package,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.packages.push(package);
+        Ok(slf)
+    }
+
+    /// Writes the message and checks it against the DESADV structure.
+    fn build(&self) -> PyResult<DespatchAdvice> {
+        let mut document = self.document.clone();
+        let body = &mut document.segments;
+        body.push(segment_of(
+            "BGM",
+            &[&["351"], &[&self.document_number], &["9"]],
+        ));
+        body.extend(self.dates.iter().cloned());
+        body.extend(self.references.iter().cloned());
+        body.extend(self.parties.iter().cloned());
+
+        let mut next_id = 1;
+        for package in &self.packages {
+            package.write(None, &mut next_id, body);
+        }
+        let line_count: usize = self.packages.iter().map(Package::item_count).sum();
+        body.push(segment_of("CNT", &[&["2", &line_count.to_string()]]));
+        document.close();
+
+        document.check_structure("DESADV")?;
+        Ok(DespatchAdvice { document })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const DESPATCH: &str = "UNH+1+DESADV:D:96A:UN'
+BGM+351+DES587441+9'
+DTM+11:20240122:102'
+RFF+ON:PO12345'
+NAD+SU+4012345000016::9'
+CPS+1'
+PAC+2++201'
+CPS+2+1'
+PAC+1++CT'
+PCI+33E'
+GIN+BJ+354123450000000014'
+LIN+1++4000862141404:SRV'
+QTY+12:24'
+CPS+3+1'
+PAC+1++CT'
+PCI+33E'
+GIN+BJ+354123450000000021'
+LIN+2++4000862141411:SRV'
+QTY+12:12'
+CPS+4'
+PAC+1++201'
+CNT+2:2'
+UNT+22+1'";
+
+    #[test]
+    fn test_sscc_check_digit() {
+        assert!(is_valid_sscc("354123450000000014"));
+        assert!(!is_valid_sscc("354123450000000015"));
+        assert!(!is_valid_sscc("35412345000000001"));
+    }
+
+    #[test]
+    fn test_despatch_advice_packing_hierarchy() {
+        Python::with_gil(|_py| {
+            let despatch = DespatchAdvice::from_edifact(DESPATCH).unwrap();
+            assert_eq!(despatch.get_document_number().as_deref(), Some("DES587441"));
+            assert_eq!(
+                despatch.get_references()[0].number.as_deref(),
+                Some("PO12345")
+            );
+
+            let packages = despatch.get_packages();
+            assert_eq!(packages.len(), 2);
+            let pallet = &packages[0];
+            assert_eq!(pallet.packaging[0].package_type.as_deref(), Some("201"));
+            assert_eq!(pallet.children.len(), 2);
+            assert_eq!(pallet.children[0].sscc(), vec!["354123450000000014"]);
+            assert_eq!(
+                pallet.children[1].items[0].line_number().as_deref(),
+                Some("2")
+            );
+            assert_eq!(despatch.get_lines().len(), 2);
+        });
+    }
+
+    #[test]
+    fn test_despatch_advice_builder() {
+        Python::with_gil(|py| {
+            let mut carton = Package::new(
+                Some("CT".to_string()),
+                "1".to_string(),
+                Some("354123450000000014".to_string()),
+            )
+            .unwrap();
+            carton.add_item("1", "4000862141404", "24", Some("PCE"), "SRV");
+            let mut pallet = Package::new(Some("201".to_string()), "1".to_string(), None).unwrap();
+            pallet.add_child(carton);
+            assert!(Package::new(
+                None,
+                "1".to_string(),
+                Some("354123450000000015".to_string())
+            )
+            .is_err());
+
+            let builder =
+                DespatchAdviceBuilder::new("DES1".to_string(), "1".to_string(), "96A".to_string())
+                    .unwrap();
+            let builder = Bound::new(py, builder).unwrap();
+            DespatchAdviceBuilder::with_date(builder.borrow_mut(), "11", "20240122", "102")
+                .unwrap();
+            DespatchAdviceBuilder::with_reference(builder.borrow_mut(), "ON", "PO12345").unwrap();
+            DespatchAdviceBuilder::with_party(builder.borrow_mut(), "SU", "4012345000016", "9")
+                .unwrap();
+            DespatchAdviceBuilder::add_package(builder.borrow_mut(), pallet).unwrap();
+            let despatch = builder.borrow().build().unwrap();
+
+            let edifact = despatch.to_edifact();
+            let lines: Vec<&str> = edifact.lines().collect();
+            assert_eq!(
+                lines,
+                vec![
+                    "UNH+1+DESADV:D:96A:UN'",
+                    "BGM+351+DES1+9'",
+                    "DTM+11:20240122:102'",
+                    "RFF+ON:PO12345'",
+                    "NAD+SU+4012345000016::9'",
+                    "CPS+1'",
+                    "PAC+1++201'",
+                    "CPS+2+1'",
+                    "PAC+1++CT'",
+                    "PCI+33E'",
+                    "GIN+BJ+354123450000000014'",
+                    "LIN+1++4000862141404:SRV'",
+                    "QTY+12:24:PCE'",
+                    "CNT+2:1'",
+                    "UNT+15+1'",
+                ]
+            );
+
+            let parsed = DespatchAdvice::from_edifact(&edifact).unwrap();
+            assert_eq!(parsed.get_packages()[0].children[0].items.len(), 1);
+        });
+    }
+}
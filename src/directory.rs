@@ -0,0 +1,1157 @@
+use pyo3::prelude::*;
+use std::sync::OnceLock;
+
+use crate::service;
+use crate::syntax::SyntaxVersion;
+use crate::validation::{self, ValidationIssue};
+use crate::{EdifactError, Segment};
+
+// Built-in message structures, written in the indented layout parsed by `MessageDefinition::parse`
+const BUILTIN_MESSAGES: &[&str] = &[
+    include_str!("directory/orders.txt"),
+    include_str!("directory/ordrsp.txt"),
+    include_str!("directory/ordchg.txt"),
+    include_str!("directory/desadv.txt"),
+    include_str!("directory/invoic.txt"),
+    include_str!("directory/pricat.txt"),
+    include_str!("directory/invrpt.txt"),
+    include_str!("directory/slsrpt.txt"),
+    include_str!("directory/recadv.txt"),
+    include_str!("directory/remadv.txt"),
+    include_str!("directory/contrl.txt"),
+    include_str!("directory/aperak.txt"),
+    include_str!("directory/iftmin.txt"),
+    include_str!("directory/iftsta.txt"),
+    include_str!("directory/baplie.txt"),
+    include_str!("directory/coprar.txt"),
+    include_str!("directory/codeco.txt"),
+    include_str!("directory/cusdec.txt"),
+    include_str!("directory/cusres.txt"),
+    include_str!("directory/paymul.txt"),
+    include_str!("directory/payord.txt"),
+];
+
+// Service segments belong to the envelope rather than to a message structure
+pub(crate) fn is_service_segment(tag: &str) -> bool {
+    matches!(tag, "UNA" | "UNB" | "UNG" | "UNH" | "UNT" | "UNE" | "UNZ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Status {
+    Mandatory,
+    Conditional,
+}
+
+impl Status {
+    fn parse(value: &str) -> Result<Self, EdifactError> {
+        match value {
+            "M" => Ok(Status::Mandatory),
+            "C" => Ok(Status::Conditional),
+            other => Err(EdifactError::new(format!("Unknown status '{}'", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SegmentEntry {
+    pub(crate) tag: String,
+    pub(crate) status: Status,
+    pub(crate) max_repeat: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GroupEntry {
+    pub(crate) name: String,
+    pub(crate) status: Status,
+    pub(crate) max_repeat: usize,
+    pub(crate) entries: Vec<StructureEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum StructureEntry {
+    Segment(SegmentEntry),
+    Group(GroupEntry),
+}
+
+impl StructureEntry {
+    // The tag of the segment that starts this entry
+    pub(crate) fn trigger(&self) -> &str {
+        match self {
+            StructureEntry::Segment(segment) => &segment.tag,
+            StructureEntry::Group(group) => group.entries[0].trigger(),
+        }
+    }
+
+    fn status(&self) -> Status {
+        match self {
+            StructureEntry::Segment(segment) => segment.status,
+            StructureEntry::Group(group) => group.status,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            StructureEntry::Segment(segment) => &segment.tag,
+            StructureEntry::Group(group) => &group.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MessageDefinition {
+    pub(crate) message_type: String,
+    pub(crate) version: String,
+    pub(crate) release: String,
+    pub(crate) agency: String,
+    pub(crate) entries: Vec<StructureEntry>,
+}
+
+impl MessageDefinition {
+    /// Parses a message structure. The first line holds the message type, version, release
+    /// and agency; every following line is `TAG STATUS MAX_REPEAT`, with groups (`SGn`)
+    /// owning the lines indented beneath them.
+    pub(crate) fn parse(text: &str) -> Result<Self, EdifactError> {
+        let mut lines = text
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|line| (line.len() - line.trim_start().len(), line.trim()));
+
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| EdifactError::new("Message definition is empty"))?;
+        let header: Vec<&str> = header.split_whitespace().collect();
+        if header.len() != 4 {
+            return Err(EdifactError::new(format!(
+                "Expected 'TYPE VERSION RELEASE AGENCY', got '{}'",
+                header.join(" ")
+            )));
+        }
+
+        let lines: Vec<(usize, &str)> = lines.collect();
+        let mut index = 0;
+        let entries = parse_entries(&lines, &mut index, 0)?;
+        if index < lines.len() {
+            return Err(EdifactError::new(format!(
+                "Unexpected indentation at '{}'",
+                lines[index].1
+            )));
+        }
+
+        Ok(MessageDefinition {
+            message_type: header[0].to_string(),
+            version: header[1].to_string(),
+            release: header[2].to_string(),
+            agency: header[3].to_string(),
+            entries,
+        })
+    }
+
+    pub(crate) fn build_tree(&self, segments: &[Segment]) -> (SegmentGroup, Vec<ValidationIssue>) {
+        let segments: Vec<&Segment> = segments
+            .iter()
+            .filter(|s| !is_service_segment(&s.tag))
+            .collect();
+        let mut builder = TreeBuilder {
+            segments: &segments,
+            position: 0,
+            issues: Vec::new(),
+        };
+        let mut ancestors = Vec::new();
+        let nodes = builder.build_level(&self.entries, &mut ancestors);
+
+        let root = SegmentGroup {
+            name: self.message_type.clone(),
+            nodes,
+        };
+        (root, builder.issues)
+    }
+
+    /// Arranges the segments of one occurrence of the group started by `trigger`, wherever
+    /// the group sits in the message, e.g. a single LIN group read on its own.
+    pub(crate) fn build_group(&self, trigger: &str, segments: &[Segment]) -> Option<SegmentGroup> {
+        let (nodes, _) = self.arrange_group(trigger, segments)?;
+        nodes.into_iter().find_map(|node| match node {
+            Node::Group(group) => Some(group),
+            Node::Segment(_) => None,
+        })
+    }
+
+    /// Where one occurrence of the group started by `trigger` departs from the structure.
+    pub(crate) fn group_issues(&self, trigger: &str, segments: &[Segment]) -> Vec<ValidationIssue> {
+        self.arrange_group(trigger, segments)
+            .map(|(_, issues)| issues)
+            .unwrap_or_default()
+    }
+
+    fn arrange_group(
+        &self,
+        trigger: &str,
+        segments: &[Segment],
+    ) -> Option<(Vec<Node>, Vec<ValidationIssue>)> {
+        // A single occurrence, so a second trigger is reported rather than starting another
+        let entry = match find_group(&self.entries, trigger)? {
+            StructureEntry::Group(group) => StructureEntry::Group(GroupEntry {
+                max_repeat: 1,
+                ..group.clone()
+            }),
+            StructureEntry::Segment(_) => return None,
+        };
+        let segments: Vec<&Segment> = segments.iter().collect();
+        let mut builder = TreeBuilder {
+            segments: &segments,
+            position: 0,
+            issues: Vec::new(),
+        };
+        let nodes = builder.build_level(std::slice::from_ref(&entry), &mut Vec::new());
+        Some((nodes, builder.issues))
+    }
+
+    /// Where the entry started by `tag` comes among the entries of the group started by
+    /// `group`, or of the message itself when there is no group.
+    pub(crate) fn entry_index(&self, group: Option<&str>, tag: &str) -> Option<usize> {
+        let entries = match group {
+            None => &self.entries,
+            Some(trigger) => match find_group(&self.entries, trigger)? {
+                StructureEntry::Group(group) => &group.entries,
+                StructureEntry::Segment(_) => return None,
+            },
+        };
+        entries.iter().position(|entry| entry.trigger() == tag)
+    }
+
+    /// The tags of every segment that can occur in the group started by `trigger`.
+    pub(crate) fn group_tags(&self, trigger: &str) -> Vec<&str> {
+        fn collect<'a>(entries: &'a [StructureEntry], tags: &mut Vec<&'a str>) {
+            for entry in entries {
+                match entry {
+                    StructureEntry::Segment(segment) => tags.push(&segment.tag),
+                    StructureEntry::Group(group) => collect(&group.entries, tags),
+                }
+            }
+        }
+        let mut tags = Vec::new();
+        if let Some(StructureEntry::Group(group)) = find_group(&self.entries, trigger) {
+            collect(&group.entries, &mut tags);
+        }
+        tags
+    }
+}
+
+// The first group, at any depth, started by the given segment tag
+fn find_group<'a>(entries: &'a [StructureEntry], trigger: &str) -> Option<&'a StructureEntry> {
+    entries.iter().find_map(|entry| match entry {
+        StructureEntry::Group(_) if entry.trigger() == trigger => Some(entry),
+        StructureEntry::Group(group) => find_group(&group.entries, trigger),
+        StructureEntry::Segment(_) => None,
+    })
+}
+
+fn parse_entries(
+    lines: &[(usize, &str)],
+    index: &mut usize,
+    indent: usize,
+) -> Result<Vec<StructureEntry>, EdifactError> {
+    let mut entries = Vec::new();
+
+    while *index < lines.len() {
+        let (line_indent, line) = lines[*index];
+        if line_indent < indent {
+            break;
+        }
+        if line_indent > indent {
+            return Err(EdifactError::new(format!(
+                "Unexpected indentation at '{}'",
+                line
+            )));
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 3 {
+            return Err(EdifactError::new(format!(
+                "Expected 'TAG STATUS MAX_REPEAT', got '{}'",
+                line
+            )));
+        }
+        let status = Status::parse(parts[1])?;
+        let max_repeat = parts[2]
+            .parse::<usize>()
+            .map_err(|_| EdifactError::new(format!("Invalid repeat count in '{}'", line)))?;
+        *index += 1;
+
+        if is_group_name(parts[0]) {
+            let child_indent = match lines.get(*index) {
+                Some((child_indent, _)) if *child_indent > indent => *child_indent,
+                _ => {
+                    return Err(EdifactError::new(format!(
+                        "Segment group {} has no segments",
+                        parts[0]
+                    )))
+                }
+            };
+            let children = parse_entries(lines, index, child_indent)?;
+            if !matches!(children[0], StructureEntry::Segment(_)) {
+                return Err(EdifactError::new(format!(
+                    "Segment group {} must start with a segment",
+                    parts[0]
+                )));
+            }
+            entries.push(StructureEntry::Group(GroupEntry {
+                name: parts[0].to_string(),
+                status,
+                max_repeat,
+                entries: children,
+            }));
+        } else {
+            entries.push(StructureEntry::Segment(SegmentEntry {
+                tag: parts[0].to_string(),
+                status,
+                max_repeat,
+            }));
+        }
+    }
+
+    Ok(entries)
+}
+
+pub(crate) fn is_group_name(name: &str) -> bool {
+    name.len() > 2 && name.starts_with("SG") && name[2..].chars().all(|c| c.is_ascii_digit())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CharacterClass {
+    Alphabetic,
+    Numeric,
+    Alphanumeric,
+}
+
+/// Data element representation such as `a4`, `n..6` or `an..35`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Representation {
+    pub(crate) class: CharacterClass,
+    pub(crate) min_length: usize,
+    pub(crate) max_length: usize,
+}
+
+impl Representation {
+    fn parse(value: &str) -> Result<Self, EdifactError> {
+        let (class, length) = if let Some(length) = value.strip_prefix("an") {
+            (CharacterClass::Alphanumeric, length)
+        } else if let Some(length) = value.strip_prefix('a') {
+            (CharacterClass::Alphabetic, length)
+        } else if let Some(length) = value.strip_prefix('n') {
+            (CharacterClass::Numeric, length)
+        } else {
+            return Err(EdifactError::new(format!(
+                "Unknown representation '{}'",
+                value
+            )));
+        };
+
+        let (variable, length) = match length.strip_prefix("..") {
+            Some(length) => (true, length),
+            None => (false, length),
+        };
+        let max_length = length
+            .parse::<usize>()
+            .map_err(|_| EdifactError::new(format!("Unknown representation '{}'", value)))?;
+
+        Ok(Representation {
+            class,
+            min_length: if variable { 1 } else { max_length },
+            max_length,
+        })
+    }
+
+    // Returns the syntax error code and a description when the value does not fit
+    pub(crate) fn check(&self, value: &str, decimal: char) -> Option<(&'static str, String)> {
+        let length = match self.class {
+            CharacterClass::Numeric => {
+                let digits = value.strip_prefix('-').unwrap_or(value);
+                let digits = digits.replacen(decimal, "", 1);
+                if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                    return Some((
+                        validation::INVALID_CHARACTERS,
+                        format!("'{}' is not numeric", value),
+                    ));
+                }
+                digits.len()
+            }
+            CharacterClass::Alphabetic => {
+                if !value.chars().all(|c| c.is_alphabetic() || c == ' ') {
+                    return Some((
+                        validation::INVALID_CHARACTERS,
+                        format!("'{}' is not alphabetic", value),
+                    ));
+                }
+                value.chars().count()
+            }
+            CharacterClass::Alphanumeric => value.chars().count(),
+        };
+
+        if length > self.max_length {
+            Some((
+                validation::DATA_ELEMENT_TOO_LONG,
+                format!("'{}' is longer than {} characters", value, self.max_length),
+            ))
+        } else if length < self.min_length {
+            Some((
+                validation::DATA_ELEMENT_TOO_SHORT,
+                format!("'{}' is shorter than {} characters", value, self.min_length),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DataElement {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) status: Status,
+    pub(crate) representation: Representation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CompositeElement {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) status: Status,
+    pub(crate) components: Vec<DataElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ElementDefinition {
+    Simple(DataElement),
+    Composite(CompositeElement),
+}
+
+impl ElementDefinition {
+    pub(crate) fn id(&self) -> &str {
+        match self {
+            ElementDefinition::Simple(element) => &element.id,
+            ElementDefinition::Composite(composite) => &composite.id,
+        }
+    }
+
+    pub(crate) fn status(&self) -> Status {
+        match self {
+            ElementDefinition::Simple(element) => element.status,
+            ElementDefinition::Composite(composite) => composite.status,
+        }
+    }
+
+    // A simple data element behaves like a composite with a single component
+    pub(crate) fn components(&self) -> &[DataElement] {
+        match self {
+            ElementDefinition::Simple(element) => std::slice::from_ref(element),
+            ElementDefinition::Composite(composite) => &composite.components,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SegmentDefinition {
+    pub(crate) tag: String,
+    pub(crate) name: String,
+    pub(crate) elements: Vec<ElementDefinition>,
+}
+
+impl SegmentDefinition {
+    /// Parses segment definitions. A segment line holds the tag and name; elements are
+    /// indented beneath it as `ID STATUS REPRESENTATION Name`, and composites as
+    /// `ID STATUS Name` with their components indented once more.
+    pub(crate) fn parse_all(text: &str) -> Result<Vec<Self>, EdifactError> {
+        let mut definitions: Vec<SegmentDefinition> = Vec::new();
+
+        for line in text.lines() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - line.trim_start().len();
+            let line = line.trim();
+            let malformed =
+                || EdifactError::new(format!("Malformed segment definition '{}'", line));
+
+            if indent == 0 {
+                let (tag, name) = line.split_once(' ').ok_or_else(malformed)?;
+                definitions.push(SegmentDefinition {
+                    tag: tag.to_string(),
+                    name: name.to_string(),
+                    elements: Vec::new(),
+                });
+                continue;
+            }
+
+            let segment = definitions.last_mut().ok_or_else(malformed)?;
+            let mut parts = line.splitn(3, ' ');
+            let (Some(id), Some(status), Some(rest)) = (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(malformed());
+            };
+            let status = Status::parse(status)?;
+            let is_composite = id.starts_with(|c: char| c.is_ascii_alphabetic());
+
+            if is_composite {
+                segment
+                    .elements
+                    .push(ElementDefinition::Composite(CompositeElement {
+                        id: id.to_string(),
+                        name: rest.to_string(),
+                        status,
+                        components: Vec::new(),
+                    }));
+                continue;
+            }
+
+            let (representation, name) = rest.split_once(' ').ok_or_else(malformed)?;
+            let element = DataElement {
+                id: id.to_string(),
+                name: name.to_string(),
+                status,
+                representation: Representation::parse(representation)?,
+            };
+            match segment.elements.last_mut() {
+                Some(ElementDefinition::Composite(composite)) if indent > 2 => {
+                    composite.components.push(element)
+                }
+                _ if indent > 2 => return Err(malformed()),
+                _ => segment.elements.push(ElementDefinition::Simple(element)),
+            }
+        }
+
+        Ok(definitions)
+    }
+
+    // Finds the element and component index of a data element by its identifier
+    pub(crate) fn locate(&self, id: &str) -> Option<(usize, usize)> {
+        self.elements
+            .iter()
+            .enumerate()
+            .find_map(|(index, element)| {
+                element
+                    .components()
+                    .iter()
+                    .position(|c| c.id == id)
+                    .map(|component| (index, component))
+            })
+    }
+
+    pub(crate) fn validate(&self, segment: &Segment, decimal: char) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let issue = |code: &str, element: usize, component: usize, message: String| {
+            ValidationIssue::element(
+                code,
+                &segment.tag,
+                Some(segment.position),
+                element,
+                component,
+                message,
+            )
+        };
+
+        if segment.elements.len() > self.elements.len() {
+            issues.push(issue(
+                validation::TOO_MANY_CONSTITUENTS,
+                self.elements.len(),
+                0,
+                format!(
+                    "{} has at most {} data elements",
+                    self.tag,
+                    self.elements.len()
+                ),
+            ));
+        }
+
+        for (index, definition) in self.elements.iter().enumerate() {
+            let values = segment
+                .elements
+                .get(index)
+                .map(Vec::as_slice)
+                .unwrap_or(&[]);
+            if values.iter().all(String::is_empty) {
+                if definition.status() == Status::Mandatory {
+                    issues.push(issue(
+                        validation::MISSING,
+                        index,
+                        0,
+                        format!("Mandatory {} is missing", definition.id()),
+                    ));
+                }
+                continue;
+            }
+
+            let components = definition.components();
+            if values.len() > components.len() {
+                issues.push(issue(
+                    validation::TOO_MANY_CONSTITUENTS,
+                    index,
+                    components.len(),
+                    format!(
+                        "{} has at most {} components",
+                        definition.id(),
+                        components.len()
+                    ),
+                ));
+            }
+
+            for (position, component) in components.iter().enumerate() {
+                match values.get(position).filter(|v| !v.is_empty()) {
+                    None if component.status == Status::Mandatory => issues.push(issue(
+                        validation::MISSING,
+                        index,
+                        position,
+                        format!("Mandatory {} is missing", component.id),
+                    )),
+                    None => {}
+                    Some(value) => {
+                        if let Some((code, message)) =
+                            component.representation.check(value, decimal)
+                        {
+                            issues.push(issue(
+                                code,
+                                index,
+                                position,
+                                format!("{}: {}", component.id, message),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+/// Names for the elements of a segment and their components: the EDED/EDCD identifiers
+/// where the segment is defined, with `_2`, `_3`... on an identifier that repeats, and
+/// positional names counting from one (`e1`, `c1`) beyond the definition or without one.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SegmentNames<'a> {
+    definition: Option<&'a SegmentDefinition>,
+}
+
+impl<'a> SegmentNames<'a> {
+    pub(crate) fn new(definition: Option<&'a SegmentDefinition>) -> Self {
+        SegmentNames { definition }
+    }
+
+    fn elements(&self) -> &'a [ElementDefinition] {
+        self.definition.map_or(&[], |d| d.elements.as_slice())
+    }
+
+    fn components(&self, element: usize) -> &'a [DataElement] {
+        self.elements()
+            .get(element)
+            .map_or(&[], ElementDefinition::components)
+    }
+
+    pub(crate) fn is_composite(&self, element: usize) -> bool {
+        matches!(
+            self.elements().get(element),
+            Some(ElementDefinition::Composite(_))
+        )
+    }
+
+    pub(crate) fn element(&self, index: usize) -> String {
+        let ids: Vec<&str> = self.elements().iter().map(ElementDefinition::id).collect();
+        identifier(&ids, index).unwrap_or_else(|| format!("e{}", index + 1))
+    }
+
+    pub(crate) fn component(&self, element: usize, index: usize) -> String {
+        let ids: Vec<&str> = self
+            .components(element)
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect();
+        identifier(&ids, index).unwrap_or_else(|| format!("c{}", index + 1))
+    }
+
+    pub(crate) fn element_index(&self, name: &str) -> Option<usize> {
+        (0..self.elements().len())
+            .find(|&index| self.element(index) == name)
+            .or_else(|| position(name, 'e'))
+    }
+
+    pub(crate) fn component_index(&self, element: usize, name: &str) -> Option<usize> {
+        (0..self.components(element).len())
+            .find(|&index| self.component(element, index) == name)
+            .or_else(|| position(name, 'c'))
+    }
+}
+
+fn identifier(ids: &[&str], index: usize) -> Option<String> {
+    let id = ids.get(index)?;
+    match ids[..index].iter().filter(|other| *other == id).count() {
+        0 => Some(id.to_string()),
+        repeats => Some(format!("{}_{}", id, repeats + 1)),
+    }
+}
+
+fn position(name: &str, prefix: char) -> Option<usize> {
+    name.strip_prefix(prefix)?
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_sub(1))
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Segment(Segment),
+    Group(SegmentGroup),
+}
+
+struct TreeBuilder<'a> {
+    segments: &'a [&'a Segment],
+    position: usize,
+    issues: Vec<ValidationIssue>,
+}
+
+impl TreeBuilder<'_> {
+    fn current_position(&self) -> Option<usize> {
+        self.segments.get(self.position).map(|s| s.position)
+    }
+
+    fn build_level<'d>(
+        &mut self,
+        entries: &'d [StructureEntry],
+        ancestors: &mut Vec<&'d [StructureEntry]>,
+    ) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut counts = vec![0usize; entries.len()];
+        let mut index = 0;
+
+        while let Some(segment) = self.segments.get(self.position) {
+            let found = entries[index..]
+                .iter()
+                .position(|entry| entry.trigger() == segment.tag);
+
+            // Hand the segment back to an enclosing level that can still accept it, either
+            // because nothing here takes it or because it has used up its repetitions here
+            // (a repeated group trigger starts the next occurrence of the group)
+            let accepted_above = || {
+                ancestors
+                    .iter()
+                    .any(|remaining| remaining.iter().any(|entry| entry.trigger() == segment.tag))
+            };
+            let exhausted = found.is_some_and(|offset| match &entries[index + offset] {
+                StructureEntry::Segment(entry) => counts[index + offset] >= entry.max_repeat,
+                StructureEntry::Group(_) => false,
+            });
+            if exhausted && accepted_above() {
+                break;
+            }
+
+            let offset = match found {
+                Some(offset) => offset,
+                None => {
+                    if accepted_above() {
+                        break;
+                    }
+                    self.issues.push(ValidationIssue::segment(
+                        validation::NOT_SUPPORTED_IN_POSITION,
+                        &segment.tag,
+                        Some(segment.position),
+                        format!("Segment {} is not expected here", segment.tag),
+                    ));
+                    nodes.push(Node::Segment((*segment).clone()));
+                    self.position += 1;
+                    continue;
+                }
+            };
+
+            self.check_missing(
+                &entries[index..index + offset],
+                &counts[index..index + offset],
+            );
+            index += offset;
+            counts[index] += 1;
+
+            match &entries[index] {
+                StructureEntry::Segment(entry) => {
+                    if counts[index] > entry.max_repeat {
+                        self.issues.push(ValidationIssue::segment(
+                            validation::TOO_MANY_REPETITIONS,
+                            &segment.tag,
+                            Some(segment.position),
+                            format!(
+                                "Segment {} occurs more than {} times",
+                                entry.tag, entry.max_repeat
+                            ),
+                        ));
+                    }
+                    nodes.push(Node::Segment((*segment).clone()));
+                    self.position += 1;
+                }
+                StructureEntry::Group(group) => {
+                    if counts[index] > group.max_repeat {
+                        self.issues.push(ValidationIssue::segment(
+                            validation::TOO_MANY_GROUP_REPETITIONS,
+                            &segment.tag,
+                            Some(segment.position),
+                            format!(
+                                "Segment group {} occurs more than {} times",
+                                group.name, group.max_repeat
+                            ),
+                        ));
+                    }
+                    ancestors.push(&entries[index..]);
+                    let children = self.build_level(&group.entries, ancestors);
+                    ancestors.pop();
+                    nodes.push(Node::Group(SegmentGroup {
+                        name: group.name.clone(),
+                        nodes: children,
+                    }));
+                }
+            }
+        }
+
+        self.check_missing(&entries[index..], &counts[index..]);
+        nodes
+    }
+
+    fn check_missing(&mut self, entries: &[StructureEntry], counts: &[usize]) {
+        for (entry, count) in entries.iter().zip(counts) {
+            if *count == 0 && entry.status() == Status::Mandatory {
+                self.issues.push(ValidationIssue::segment(
+                    validation::MISSING,
+                    entry.trigger(),
+                    self.current_position(),
+                    format!("Mandatory {} is missing", entry.name()),
+                ));
+            }
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct SegmentGroup {
+    #[pyo3(get)]
+    pub(crate) name: String,
+    pub(crate) nodes: Vec<Node>,
+}
+
+#[pymethods]
+impl SegmentGroup {
+    fn trigger(&self) -> Option<Segment> {
+        self.direct_segments().next().cloned()
+    }
+
+    fn get_segment(&self, tag: &str) -> Option<Segment> {
+        self.find_segment(tag).cloned()
+    }
+
+    fn get_all_segments(&self, tag: &str) -> Vec<Segment> {
+        self.direct_segments()
+            .filter(|s| s.tag == tag)
+            .cloned()
+            .collect()
+    }
+
+    fn get_groups(&self, name: &str) -> Vec<SegmentGroup> {
+        self.groups(name).cloned().collect()
+    }
+
+    fn segments(&self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        self.walk(&mut |_, segment| segments.push(segment.clone()));
+        segments
+    }
+}
+
+impl SegmentGroup {
+    /// A group holding the given segments without any nested groups.
+    pub(crate) fn flat(name: &str, segments: Vec<Segment>) -> Self {
+        SegmentGroup {
+            name: name.to_string(),
+            nodes: segments.into_iter().map(Node::Segment).collect(),
+        }
+    }
+
+    pub(crate) fn push_segment(&mut self, segment: Segment) {
+        self.nodes.push(Node::Segment(segment));
+    }
+
+    pub(crate) fn direct_segments(&self) -> impl Iterator<Item = &Segment> {
+        self.nodes.iter().filter_map(|node| match node {
+            Node::Segment(segment) => Some(segment),
+            Node::Group(_) => None,
+        })
+    }
+
+    pub(crate) fn find_segment(&self, tag: &str) -> Option<&Segment> {
+        self.direct_segments().find(|s| s.tag == tag)
+    }
+
+    pub(crate) fn groups<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a SegmentGroup> {
+        self.nodes.iter().filter_map(move |node| match node {
+            Node::Group(group) if group.name == name => Some(group),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn child_groups(&self) -> impl Iterator<Item = &SegmentGroup> {
+        self.nodes.iter().filter_map(|node| match node {
+            Node::Group(group) => Some(group),
+            Node::Segment(_) => None,
+        })
+    }
+
+    pub(crate) fn segments_by_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Segment> {
+        self.direct_segments().filter(move |s| s.tag == tag)
+    }
+
+    /// Nested groups identified by their trigger segment, which unlike group numbers stays
+    /// the same across directory releases.
+    pub(crate) fn groups_by_trigger<'a>(
+        &'a self,
+        tag: &'a str,
+    ) -> impl Iterator<Item = &'a SegmentGroup> {
+        self.nodes.iter().filter_map(move |node| match node {
+            Node::Group(group) if group.trigger_tag() == Some(tag) => Some(group),
+            _ => None,
+        })
+    }
+
+    fn trigger_tag(&self) -> Option<&str> {
+        self.direct_segments().next().map(|s| s.tag.as_str())
+    }
+
+    // Visits every segment in document order along with the name of its enclosing group
+    pub(crate) fn walk<'a>(&'a self, visit: &mut dyn FnMut(&'a str, &'a Segment)) {
+        for node in &self.nodes {
+            match node {
+                Node::Segment(segment) => visit(&self.name, segment),
+                Node::Group(group) => group.walk(visit),
+            }
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Directory {
+    messages: Vec<MessageDefinition>,
+    // Loaded segment definitions; service segments fall back to the built-in ones
+    segments: Vec<SegmentDefinition>,
+}
+
+#[pymethods]
+impl Directory {
+    #[new]
+    pub(crate) fn new() -> Self {
+        let messages = BUILTIN_MESSAGES
+            .iter()
+            .map(|text| MessageDefinition::parse(text).expect("built-in message definition"))
+            .collect();
+        Directory {
+            messages,
+            segments: Vec::new(),
+        }
+    }
+
+    fn load_message_definition(&mut self, text: &str) -> PyResult<()> {
+        let definition = MessageDefinition::parse(text)?;
+        self.messages.retain(|m| {
+            !(m.message_type == definition.message_type && m.release == definition.release)
+        });
+        self.messages.push(definition);
+        Ok(())
+    }
+
+    /// Loads segment definitions (EDSD) with their data elements (EDED) and composites
+    /// (EDCD), in the indented layout of the built-in service segment directory. A segment
+    /// that is already defined is replaced.
+    pub(crate) fn load_segment_definitions(&mut self, text: &str) -> PyResult<()> {
+        for definition in SegmentDefinition::parse_all(text)? {
+            self.segments.retain(|s| s.tag != definition.tag);
+            self.segments.push(definition);
+        }
+        Ok(())
+    }
+
+    fn message_types(&self) -> Vec<String> {
+        let mut types: Vec<String> = self
+            .messages
+            .iter()
+            .map(|m| m.message_type.clone())
+            .collect();
+        types.dedup();
+        types
+    }
+
+    #[pyo3(signature = (message_type, segments, release=None))]
+    fn build_tree(
+        &self,
+        message_type: &str,
+        segments: Vec<Segment>,
+        release: Option<&str>,
+    ) -> PyResult<SegmentGroup> {
+        let definition = self.require_message(message_type, release)?;
+        Ok(definition.build_tree(&segments).0)
+    }
+
+    #[pyo3(signature = (message_type, segments, release=None))]
+    fn validate(
+        &self,
+        message_type: &str,
+        segments: Vec<Segment>,
+        release: Option<&str>,
+    ) -> PyResult<Vec<ValidationIssue>> {
+        let definition = self.require_message(message_type, release)?;
+        Ok(definition.build_tree(&segments).1)
+    }
+}
+
+impl Directory {
+    /// The built-in message structures, parsed once.
+    pub(crate) fn builtin() -> &'static Directory {
+        static BUILTIN: OnceLock<Directory> = OnceLock::new();
+        BUILTIN.get_or_init(Directory::new)
+    }
+
+    /// Looks up a message structure, preferring an exact release match and otherwise
+    /// falling back to any loaded release of the same message type.
+    pub(crate) fn message(
+        &self,
+        message_type: &str,
+        release: Option<&str>,
+    ) -> Option<&MessageDefinition> {
+        let mut candidates = self
+            .messages
+            .iter()
+            .filter(|m| m.message_type == message_type);
+        match release {
+            Some(release) => self
+                .messages
+                .iter()
+                .find(|m| m.message_type == message_type && m.release == release)
+                .or_else(|| candidates.next()),
+            None => candidates.next(),
+        }
+    }
+
+    /// Looks up a segment definition, falling back to the service segments of the given
+    /// syntax version.
+    pub(crate) fn segment(&self, tag: &str, version: SyntaxVersion) -> Option<&SegmentDefinition> {
+        self.segments
+            .iter()
+            .find(|s| s.tag == tag)
+            .or_else(|| service::service_segment_definition(tag, version))
+    }
+
+    pub(crate) fn require_message(
+        &self,
+        message_type: &str,
+        release: Option<&str>,
+    ) -> Result<&MessageDefinition, EdifactError> {
+        self.message(message_type, release)
+            .ok_or_else(|| EdifactError::new(format!("No message definition for {}", message_type)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    fn segments(content: &str) -> Vec<Segment> {
+        crate::Order::from_edifact(content.to_string())
+            .unwrap()
+            .segments
+    }
+
+    #[test]
+    fn test_parse_message_definition() {
+        Python::with_gil(|_py| {
+            let definition = MessageDefinition::parse(
+                "ORDERS D 96A UN
+BGM M 1
+SG1 C 10
+  RFF M 1
+  DTM C 5
+UNS M 1",
+            )
+            .unwrap();
+
+            assert_eq!(definition.message_type, "ORDERS");
+            assert_eq!(definition.release, "96A");
+            assert_eq!(definition.entries.len(), 3);
+            assert_eq!(definition.entries[1].trigger(), "RFF");
+            match &definition.entries[1] {
+                StructureEntry::Group(group) => {
+                    assert_eq!(group.name, "SG1");
+                    assert_eq!(group.max_repeat, 10);
+                    assert_eq!(group.entries.len(), 2);
+                }
+                _ => panic!("expected a segment group"),
+            }
+
+            assert!(MessageDefinition::parse("ORDERS D 96A UN\nSG1 C 10\nBGM M 1").is_err());
+        });
+    }
+
+    #[test]
+    fn test_build_tree_groups_segments() {
+        Python::with_gil(|_py| {
+            let directory = Directory::new();
+            let definition = directory.message("ORDERS", Some("96A")).unwrap();
+            let (tree, issues) = definition.build_tree(&segments(
+                "UNH+1+ORDERS:D:96A:UN'
+BGM+220+123456+9'
+DTM+137:20240119:102'
+NAD+BY+5021376940009::9'
+CTA+OC+:JOHN'
+NAD+SU+5012345678900::9'
+NAD+DP+5012345678917::9'
+LIN+1++ITEM123:BP'
+QTY+21:5'
+PRI+AAA:10.00'
+LIN+2++ITEM456:BP'
+QTY+21:2'
+UNS+S'
+UNT+13+1'",
+            ));
+
+            assert_eq!(issues, Vec::new());
+            assert_eq!(tree.name, "ORDERS");
+            let parties: Vec<&SegmentGroup> = tree.groups("SG2").collect();
+            assert_eq!(parties.len(), 3);
+            assert_eq!(parties[0].groups("SG5").count(), 1);
+
+            let lines: Vec<&SegmentGroup> = tree.groups("SG25").collect();
+            assert_eq!(lines.len(), 2);
+            assert_eq!(lines[0].find_segment("QTY").unwrap().elements[0][1], "5");
+            assert_eq!(lines[0].groups("SG28").count(), 1);
+            assert_eq!(tree.segments().len(), 12);
+        });
+    }
+
+    #[test]
+    fn test_build_tree_reports_structure_issues() {
+        Python::with_gil(|_py| {
+            let directory = Directory::new();
+            let definition = directory.message("ORDERS", None).unwrap();
+            let (tree, issues) = definition.build_tree(&segments(
+                "BGM+220+123456+9'
+LIN+1++ITEM123:BP'
+XYZ+1'
+QTY+21:5'",
+            ));
+
+            let codes: Vec<&str> = issues.iter().map(|i| i.code.as_str()).collect();
+            assert_eq!(codes, vec!["13", "15", "13"]);
+            assert_eq!(issues[0].tag, "DTM");
+            assert_eq!(issues[1].tag, "XYZ");
+            assert_eq!(issues[2].tag, "UNS");
+
+            // Unknown segments stay in place so nothing is lost
+            let line = tree.groups("SG25").next().unwrap();
+            assert_eq!(line.get_all_segments("XYZ").len(), 1);
+            assert_eq!(line.get_all_segments("QTY").len(), 1);
+        });
+    }
+}
@@ -0,0 +1,236 @@
+use pyo3::prelude::*;
+
+use crate::common::{DateTimePeriod, Party, Reference};
+use crate::directory::{Directory, SegmentGroup};
+use crate::header::{self, component, InterchangeHeader, MessageHeader};
+use crate::syntax::SyntaxVersion;
+use crate::validation::ValidationIssue;
+use crate::{check_service_segment, Delimiters, EdifactError, Message, OrderLine, Parser, Segment};
+
+/// The envelope and body of a single message, shared by the typed message views
+/// (`OrderResponse`, `DespatchAdvice`, ...). Service segments are kept apart from the body
+/// so the body can be arranged into the message's segment groups.
+#[derive(Debug, Clone)]
+pub(crate) struct Document {
+    pub(crate) interchange_header: Option<Segment>,
+    pub(crate) message_header: Option<Segment>,
+    pub(crate) segments: Vec<Segment>,
+    pub(crate) message_trailer: Option<Segment>,
+    pub(crate) interchange_trailer: Option<Segment>,
+    pub(crate) delimiters: Delimiters,
+    pub(crate) syntax_version: SyntaxVersion,
+}
+
+impl Document {
+    pub(crate) fn new(delimiters: Delimiters, syntax_version: SyntaxVersion) -> Self {
+        Document {
+            interchange_header: None,
+            message_header: None,
+            segments: Vec::new(),
+            message_trailer: None,
+            interchange_trailer: None,
+            delimiters,
+            syntax_version,
+        }
+    }
+
+    pub(crate) fn parse(content: &str) -> PyResult<Self> {
+        let mut parser = Parser::new();
+        let segments = parser.parse(content)?;
+
+        let mut document = Document::new(parser.delimiters, parser.syntax_version);
+        for segment in segments {
+            match segment.tag.as_str() {
+                "UNB" => document.interchange_header = Some(segment),
+                "UNH" => document.message_header = Some(segment),
+                "UNT" => document.message_trailer = Some(segment),
+                "UNZ" => document.interchange_trailer = Some(segment),
+                _ => document.segments.push(segment),
+            }
+        }
+        Ok(document)
+    }
+
+    /// A document over a message taken from an `Interchange`.
+    pub(crate) fn from_message(message: &Message) -> Self {
+        let mut document = Document::new(Delimiters::default(), SyntaxVersion::default());
+        document.message_header = message.service_segments.get("UNH").cloned();
+        document.message_trailer = message.service_segments.get("UNT").cloned();
+        document.segments = message.segments.clone();
+        document
+    }
+
+    pub(crate) fn set_interchange_header(&mut self, header: &InterchangeHeader) -> PyResult<()> {
+        let segment = header.to_segment();
+        check_service_segment(&segment, self.syntax_version)?;
+        self.interchange_header = Some(segment);
+        Ok(())
+    }
+
+    pub(crate) fn set_message_header(&mut self, header: &MessageHeader) -> PyResult<()> {
+        let segment = header.to_segment();
+        check_service_segment(&segment, self.syntax_version)?;
+        self.message_header = Some(segment);
+        Ok(())
+    }
+
+    pub(crate) fn message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.message_header
+            .as_ref()
+            .map(MessageHeader::from_segment)
+            .transpose()
+    }
+
+    /// Arranges the body into segment groups using the built-in structure for the message
+    /// type and the release named in UNH.
+    pub(crate) fn tree(&self, message_type: &str) -> SegmentGroup {
+        let release = self
+            .message_header
+            .as_ref()
+            .and_then(|header| header.get_component(1, 2))
+            .map(String::as_str);
+        Directory::builtin()
+            .message(message_type, release)
+            .unwrap_or_else(|| panic!("built-in {} definition", message_type))
+            .build_tree(&self.segments)
+            .0
+    }
+
+    /// Where the body departs from the built-in structure for the release named in UNH.
+    pub(crate) fn structure_issues(
+        &self,
+        message_type: &str,
+    ) -> Result<Vec<ValidationIssue>, EdifactError> {
+        let release = self
+            .message_header
+            .as_ref()
+            .and_then(|header| component(header, 1, 2));
+        let definition = Directory::builtin().require_message(message_type, release.as_deref())?;
+        Ok(definition.build_tree(&self.segments).1)
+    }
+
+    /// Checks a built message against the structure for its type, reporting every issue.
+    pub(crate) fn check_structure(&self, message_type: &str) -> Result<(), EdifactError> {
+        let issues = self.structure_issues(message_type)?;
+        if issues.is_empty() {
+            return Ok(());
+        }
+        let details: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+        Err(EdifactError::new(format!(
+            "Invalid {}: {}",
+            message_type,
+            details.join("; ")
+        )))
+    }
+
+    fn bgm(&self) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.tag == "BGM")
+    }
+
+    /// BGM C002 document name code, e.g. 380 for a commercial invoice.
+    pub(crate) fn document_name(&self) -> Option<String> {
+        self.bgm().and_then(|bgm| component(bgm, 0, 0))
+    }
+
+    pub(crate) fn document_number(&self) -> Option<String> {
+        self.bgm().and_then(|bgm| component(bgm, 1, 0))
+    }
+
+    /// BGM 1225 message function code, e.g. 9 (original) or 5 (replace).
+    pub(crate) fn message_function(&self) -> Option<String> {
+        self.bgm().and_then(|bgm| component(bgm, 2, 0))
+    }
+
+    pub(crate) fn dates(tree: &SegmentGroup) -> Vec<DateTimePeriod> {
+        tree.segments_by_tag("DTM")
+            .map(DateTimePeriod::from_segment)
+            .collect()
+    }
+
+    pub(crate) fn references(tree: &SegmentGroup) -> Vec<Reference> {
+        tree.groups_by_trigger("RFF")
+            .filter_map(Reference::from_group)
+            .collect()
+    }
+
+    pub(crate) fn parties(tree: &SegmentGroup) -> Vec<Party> {
+        tree.groups_by_trigger("NAD")
+            .filter_map(Party::from_group)
+            .collect()
+    }
+
+    pub(crate) fn lines(group: &SegmentGroup) -> Vec<OrderLine> {
+        let lines = group
+            .groups_by_trigger("LIN")
+            .map(|group| OrderLine::from_group(group.clone()))
+            .collect();
+        OrderLine::nest(lines)
+    }
+
+    pub(crate) fn to_edifact(&self) -> String {
+        let mut result = String::new();
+        if self.delimiters != self.syntax_version.default_delimiters() {
+            result.push_str(&self.delimiters.to_una());
+            result.push('\n');
+        }
+        let segments = self
+            .interchange_header
+            .iter()
+            .chain(&self.message_header)
+            .chain(&self.segments)
+            .chain(&self.message_trailer)
+            .chain(&self.interchange_trailer);
+        for segment in segments {
+            result.push_str(&segment.to_edifact(&self.delimiters));
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Numbers the segments in document order and adds the UNT and UNZ trailers.
+    pub(crate) fn close(&mut self) {
+        let mut position = 0;
+        for segment in self
+            .interchange_header
+            .iter_mut()
+            .chain(&mut self.message_header)
+            .chain(&mut self.segments)
+        {
+            segment.position = position;
+            position += 1;
+        }
+
+        let message_reference = self
+            .message_header
+            .as_ref()
+            .and_then(|h| component(h, 0, 0))
+            .unwrap_or_default();
+        let mut trailer = segment_of(
+            "UNT",
+            &[
+                &[&(self.segments.len() + 2).to_string()],
+                &[&message_reference],
+            ],
+        );
+        trailer.position = position;
+        self.message_trailer = Some(trailer);
+
+        if let Some(header) = &self.interchange_header {
+            let control_reference = InterchangeHeader::from_segment(header)
+                .map(|h| h.control_reference().to_string())
+                .unwrap_or_default();
+            let mut trailer = segment_of("UNZ", &[&["1"], &[&control_reference]]);
+            trailer.position = position + 1;
+            self.interchange_trailer = Some(trailer);
+        }
+    }
+}
+
+/// Builds a segment from string elements, dropping trailing empty components and elements.
+pub(crate) fn segment_of(tag: &str, elements: &[&[&str]]) -> Segment {
+    let elements = elements
+        .iter()
+        .map(|element| element.iter().map(|c| c.to_string()).collect())
+        .collect();
+    Segment::new(tag.to_string(), header::trim_elements(elements), 0)
+}
@@ -0,0 +1,485 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use pyo3::prelude::*;
+
+use crate::syntax::SyntaxVersion;
+use crate::{EdifactError, Segment};
+
+// Reads a component, treating empty strings as absent
+pub(crate) fn component(segment: &Segment, element: usize, component: usize) -> Option<String> {
+    segment
+        .get_component(element, component)
+        .filter(|v| !v.is_empty())
+        .cloned()
+}
+
+// Drops trailing empty components and elements so the segment serialises compactly
+pub(crate) fn trim_elements(mut elements: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    for element in elements.iter_mut() {
+        while element.last().is_some_and(String::is_empty) {
+            element.pop();
+        }
+    }
+    while elements.last().is_some_and(Vec::is_empty) {
+        elements.pop();
+    }
+    elements
+}
+
+fn optional(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+/// Parses the S004 preparation date and time in the date format of the syntax version.
+pub(crate) fn parse_preparation_time(
+    date: &str,
+    time: &str,
+    version: SyntaxVersion,
+) -> Option<NaiveDateTime> {
+    if date.len() != version.date_length() {
+        return None;
+    }
+    let date = NaiveDate::parse_from_str(date, version.date_format()).ok()?;
+    let time = NaiveTime::parse_from_str(time, "%H%M").ok()?;
+    Some(date.and_time(time))
+}
+
+/// Sender or recipient of an interchange (S002/S003). For syntax versions 1 to 3 the
+/// third component is the routing address, which is reported as `internal_id`.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterchangeParty {
+    #[pyo3(get, set)]
+    id: String,
+    #[pyo3(get, set)]
+    qualifier: Option<String>,
+    #[pyo3(get, set)]
+    internal_id: Option<String>,
+    #[pyo3(get, set)]
+    internal_sub_id: Option<String>,
+}
+
+#[pymethods]
+impl InterchangeParty {
+    #[new]
+    #[pyo3(signature = (id, qualifier=None, internal_id=None, internal_sub_id=None))]
+    pub(crate) fn new(
+        id: String,
+        qualifier: Option<String>,
+        internal_id: Option<String>,
+        internal_sub_id: Option<String>,
+    ) -> Self {
+        InterchangeParty {
+            id,
+            qualifier,
+            internal_id,
+            internal_sub_id,
+        }
+    }
+}
+
+impl InterchangeParty {
+    fn from_element(segment: &Segment, element: usize) -> Self {
+        InterchangeParty {
+            id: component(segment, element, 0).unwrap_or_default(),
+            qualifier: component(segment, element, 1),
+            internal_id: component(segment, element, 2),
+            internal_sub_id: component(segment, element, 3),
+        }
+    }
+
+    fn to_element(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            optional(&self.qualifier),
+            optional(&self.internal_id),
+            optional(&self.internal_sub_id),
+        ]
+    }
+}
+
+/// Typed view over a UNB interchange header.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterchangeHeader {
+    #[pyo3(get, set)]
+    pub(crate) syntax_identifier: String,
+    #[pyo3(get, set)]
+    pub(crate) syntax_version: SyntaxVersion,
+    #[pyo3(get, set)]
+    sender: InterchangeParty,
+    #[pyo3(get, set)]
+    recipient: InterchangeParty,
+    #[pyo3(get, set)]
+    prepared: Option<NaiveDateTime>,
+    #[pyo3(get, set)]
+    control_reference: String,
+    #[pyo3(get, set)]
+    recipient_password: Option<String>,
+    #[pyo3(get, set)]
+    recipient_password_qualifier: Option<String>,
+    #[pyo3(get, set)]
+    application_reference: Option<String>,
+    #[pyo3(get, set)]
+    processing_priority: Option<String>,
+    #[pyo3(get, set)]
+    acknowledgement_request: Option<String>,
+    #[pyo3(get, set)]
+    agreement_identifier: Option<String>,
+    #[pyo3(get, set)]
+    test_indicator: bool,
+}
+
+#[pymethods]
+impl InterchangeHeader {
+    #[new]
+    #[pyo3(signature = (
+        sender,
+        recipient,
+        control_reference,
+        prepared=None,
+        syntax_identifier="UNOA".to_string(),
+        syntax_version=SyntaxVersion::V4,
+        application_reference=None,
+        test_indicator=false,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        sender: InterchangeParty,
+        recipient: InterchangeParty,
+        control_reference: String,
+        prepared: Option<NaiveDateTime>,
+        syntax_identifier: String,
+        syntax_version: SyntaxVersion,
+        application_reference: Option<String>,
+        test_indicator: bool,
+    ) -> Self {
+        InterchangeHeader {
+            syntax_identifier,
+            syntax_version,
+            sender,
+            recipient,
+            prepared,
+            control_reference,
+            recipient_password: None,
+            recipient_password_qualifier: None,
+            application_reference,
+            processing_priority: None,
+            acknowledgement_request: None,
+            agreement_identifier: None,
+            test_indicator,
+        }
+    }
+
+    #[staticmethod]
+    pub(crate) fn from_segment(segment: &Segment) -> PyResult<InterchangeHeader> {
+        if segment.tag != "UNB" {
+            return Err(
+                EdifactError::new(format!("Expected a UNB segment, got {}", segment.tag)).into(),
+            );
+        }
+
+        let syntax_version = SyntaxVersion::detect(segment)
+            .ok_or_else(|| EdifactError::new("UNB has no valid syntax version number"))?;
+        let prepared = match (component(segment, 3, 0), component(segment, 3, 1)) {
+            (Some(date), Some(time)) => Some(
+                parse_preparation_time(&date, &time, syntax_version).ok_or_else(|| {
+                    EdifactError::new(format!("Invalid preparation date/time {}:{}", date, time))
+                })?,
+            ),
+            _ => None,
+        };
+
+        Ok(InterchangeHeader {
+            syntax_identifier: component(segment, 0, 0).unwrap_or_default(),
+            syntax_version,
+            sender: InterchangeParty::from_element(segment, 1),
+            recipient: InterchangeParty::from_element(segment, 2),
+            prepared,
+            control_reference: component(segment, 4, 0).unwrap_or_default(),
+            recipient_password: component(segment, 5, 0),
+            recipient_password_qualifier: component(segment, 5, 1),
+            application_reference: component(segment, 6, 0),
+            processing_priority: component(segment, 7, 0),
+            acknowledgement_request: component(segment, 8, 0),
+            agreement_identifier: component(segment, 9, 0),
+            test_indicator: component(segment, 10, 0).as_deref() == Some("1"),
+        })
+    }
+
+    pub(crate) fn to_segment(&self) -> Segment {
+        let prepared = match self.prepared {
+            Some(prepared) => vec![
+                prepared
+                    .format(self.syntax_version.date_format())
+                    .to_string(),
+                prepared.format("%H%M").to_string(),
+            ],
+            None => vec![],
+        };
+
+        let elements = vec![
+            vec![
+                self.syntax_identifier.clone(),
+                self.syntax_version.number().to_string(),
+            ],
+            self.sender.to_element(),
+            self.recipient.to_element(),
+            prepared,
+            vec![self.control_reference.clone()],
+            vec![
+                optional(&self.recipient_password),
+                optional(&self.recipient_password_qualifier),
+            ],
+            vec![optional(&self.application_reference)],
+            vec![optional(&self.processing_priority)],
+            vec![optional(&self.acknowledgement_request)],
+            vec![optional(&self.agreement_identifier)],
+            vec![if self.test_indicator {
+                "1".to_string()
+            } else {
+                String::new()
+            }],
+        ];
+
+        Segment::new("UNB".to_string(), trim_elements(elements), 0)
+    }
+}
+
+impl InterchangeHeader {
+    /// The header of an interchange sent back to this one's sender, such as a response or
+    /// acknowledgement: the parties are swapped and passwords and priorities are dropped.
+    pub(crate) fn reply(
+        &self,
+        control_reference: String,
+        prepared: Option<NaiveDateTime>,
+        application_reference: Option<String>,
+    ) -> InterchangeHeader {
+        InterchangeHeader {
+            sender: self.recipient.clone(),
+            recipient: self.sender.clone(),
+            prepared: prepared.or(self.prepared),
+            control_reference,
+            recipient_password: None,
+            recipient_password_qualifier: None,
+            application_reference,
+            processing_priority: None,
+            acknowledgement_request: None,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn control_reference(&self) -> &str {
+        &self.control_reference
+    }
+}
+
+/// Typed view over a UNH message header, including the S010 status of the transfer used
+/// when a message is sent in several parts.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageHeader {
+    #[pyo3(get, set)]
+    pub(crate) message_reference: String,
+    #[pyo3(get, set)]
+    pub(crate) message_type: String,
+    #[pyo3(get, set)]
+    pub(crate) version: String,
+    #[pyo3(get, set)]
+    pub(crate) release: String,
+    #[pyo3(get, set)]
+    pub(crate) agency: String,
+    #[pyo3(get, set)]
+    pub(crate) association_code: Option<String>,
+    #[pyo3(get, set)]
+    pub(crate) common_access_reference: Option<String>,
+    #[pyo3(get, set)]
+    pub(crate) transfer_sequence: Option<u32>,
+    #[pyo3(get, set)]
+    pub(crate) first_and_last_transfer: Option<String>,
+}
+
+#[pymethods]
+impl MessageHeader {
+    #[new]
+    #[pyo3(signature = (
+        message_reference,
+        message_type,
+        version="D".to_string(),
+        release="01B".to_string(),
+        agency="UN".to_string(),
+        association_code=None,
+    ))]
+    pub(crate) fn new(
+        message_reference: String,
+        message_type: String,
+        version: String,
+        release: String,
+        agency: String,
+        association_code: Option<String>,
+    ) -> Self {
+        MessageHeader {
+            message_reference,
+            message_type,
+            version,
+            release,
+            agency,
+            association_code,
+            common_access_reference: None,
+            transfer_sequence: None,
+            first_and_last_transfer: None,
+        }
+    }
+
+    #[staticmethod]
+    pub(crate) fn from_segment(segment: &Segment) -> PyResult<MessageHeader> {
+        if segment.tag != "UNH" {
+            return Err(
+                EdifactError::new(format!("Expected a UNH segment, got {}", segment.tag)).into(),
+            );
+        }
+
+        let transfer_sequence = component(segment, 3, 0)
+            .map(|v| {
+                v.parse::<u32>().map_err(|_| {
+                    EdifactError::new(format!("Invalid sequence of transfers '{}'", v))
+                })
+            })
+            .transpose()?;
+
+        Ok(MessageHeader {
+            message_reference: component(segment, 0, 0).unwrap_or_default(),
+            message_type: component(segment, 1, 0).unwrap_or_default(),
+            version: component(segment, 1, 1).unwrap_or_default(),
+            release: component(segment, 1, 2).unwrap_or_default(),
+            agency: component(segment, 1, 3).unwrap_or_default(),
+            association_code: component(segment, 1, 4),
+            common_access_reference: component(segment, 2, 0),
+            transfer_sequence,
+            first_and_last_transfer: component(segment, 3, 1),
+        })
+    }
+
+    /// True when this header marks one part of a message sent in several transfers.
+    fn is_partial(&self) -> bool {
+        self.transfer_sequence.is_some()
+    }
+
+    /// True for the final part of a multi-part message (S010 first and last transfer `F`).
+    fn is_final_transfer(&self) -> bool {
+        self.first_and_last_transfer.as_deref() == Some("F")
+    }
+
+    pub(crate) fn to_segment(&self) -> Segment {
+        let elements = vec![
+            vec![self.message_reference.clone()],
+            vec![
+                self.message_type.clone(),
+                self.version.clone(),
+                self.release.clone(),
+                self.agency.clone(),
+                optional(&self.association_code),
+            ],
+            vec![optional(&self.common_access_reference)],
+            vec![
+                self.transfer_sequence
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+                optional(&self.first_and_last_transfer),
+            ],
+        ];
+
+        Segment::new("UNH".to_string(), trim_elements(elements), 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    fn parse(segment: &str) -> Segment {
+        crate::Parser::new().parse_segment(segment, 0).unwrap()
+    }
+
+    #[test]
+    fn test_interchange_header_from_segment() {
+        Python::with_gil(|_py| {
+            let segment = parse(
+                "UNB+UNOC:4+5412345678908:14:DEPT1+8798765432106:14+20240119:1205+REF123+PASS:AA+ORDERS+A+1++1'",
+            );
+            let header = InterchangeHeader::from_segment(&segment).unwrap();
+
+            assert_eq!(header.syntax_identifier, "UNOC");
+            assert_eq!(header.syntax_version, SyntaxVersion::V4);
+            assert_eq!(header.sender.id, "5412345678908");
+            assert_eq!(header.sender.qualifier.as_deref(), Some("14"));
+            assert_eq!(header.sender.internal_id.as_deref(), Some("DEPT1"));
+            assert_eq!(header.recipient.internal_id, None);
+            assert_eq!(
+                header.prepared,
+                NaiveDate::from_ymd_opt(2024, 1, 19)
+                    .unwrap()
+                    .and_hms_opt(12, 5, 0)
+            );
+            assert_eq!(header.control_reference, "REF123");
+            assert_eq!(header.recipient_password.as_deref(), Some("PASS"));
+            assert_eq!(header.recipient_password_qualifier.as_deref(), Some("AA"));
+            assert_eq!(header.application_reference.as_deref(), Some("ORDERS"));
+            assert_eq!(header.processing_priority.as_deref(), Some("A"));
+            assert_eq!(header.acknowledgement_request.as_deref(), Some("1"));
+            assert!(header.test_indicator);
+
+            assert_eq!(header.to_segment().elements, segment.elements);
+        });
+    }
+
+    #[test]
+    fn test_interchange_header_syntax_version_3_dates() {
+        Python::with_gil(|_py| {
+            let segment = parse("UNB+UNOA:3+SENDER+RECEIVER+240119:0830+REF1'");
+            let header = InterchangeHeader::from_segment(&segment).unwrap();
+
+            assert_eq!(
+                header.prepared,
+                NaiveDate::from_ymd_opt(2024, 1, 19)
+                    .unwrap()
+                    .and_hms_opt(8, 30, 0)
+            );
+            assert!(!header.test_indicator);
+            assert_eq!(header.to_segment().elements, segment.elements);
+
+            assert!(
+                InterchangeHeader::from_segment(&parse("UNB+UNOA:3+S+R+241319:0830+REF1'"))
+                    .is_err()
+            );
+            assert!(InterchangeHeader::from_segment(&parse("UNH+1+ORDERS:D:96A:UN'")).is_err());
+        });
+    }
+
+    #[test]
+    fn test_message_header_from_segment() {
+        Python::with_gil(|_py| {
+            let segment = parse("UNH+ME0001+ORDERS:D:96A:UN:EAN008+CAR123+2:F'");
+            let header = MessageHeader::from_segment(&segment).unwrap();
+
+            assert_eq!(header.message_reference, "ME0001");
+            assert_eq!(header.message_type, "ORDERS");
+            assert_eq!(header.version, "D");
+            assert_eq!(header.release, "96A");
+            assert_eq!(header.agency, "UN");
+            assert_eq!(header.association_code.as_deref(), Some("EAN008"));
+            assert_eq!(header.common_access_reference.as_deref(), Some("CAR123"));
+            assert_eq!(header.transfer_sequence, Some(2));
+            assert!(header.is_partial());
+            assert!(header.is_final_transfer());
+            assert_eq!(header.to_segment().elements, segment.elements);
+
+            let plain = MessageHeader::from_segment(&parse("UNH+1+ORDERS:D:01B:UN'")).unwrap();
+            assert!(!plain.is_partial());
+            assert_eq!(plain.to_segment().elements.len(), 2);
+
+            assert!(MessageHeader::from_segment(&parse("UNH+1+ORDERS:D:01B:UN++X:C'")).is_err());
+        });
+    }
+}
@@ -0,0 +1,469 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+
+use crate::directory::{Directory, Node};
+use crate::header::{InterchangeHeader, MessageHeader};
+#[cfg(feature = "serde")]
+use crate::json;
+use crate::service;
+use crate::syntax::SyntaxVersion;
+use crate::validation::{self, ValidationIssue};
+use crate::xml;
+use crate::{Delimiters, Message, Parser, Segment};
+
+/// A parsed interchange: the UNB/UNZ envelope and the messages between them. Messages
+/// transferred in several parts (UNH S010) are reassembled into one logical message.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Interchange {
+    #[pyo3(get)]
+    pub(crate) interchange_header: Option<Segment>,
+    #[pyo3(get)]
+    interchange_trailer: Option<Segment>,
+    #[pyo3(get)]
+    pub(crate) messages: Vec<Message>,
+    // Messages exactly as transferred, before reassembly
+    pub(crate) transfers: Vec<Message>,
+    // Every segment in document order, kept for lossless serialisation
+    pub(crate) segments: Vec<Segment>,
+    pub(crate) delimiters: Delimiters,
+    #[pyo3(get)]
+    pub(crate) syntax_version: SyntaxVersion,
+}
+
+#[pymethods]
+impl Interchange {
+    #[staticmethod]
+    pub(crate) fn from_edifact(content: &str) -> PyResult<Interchange> {
+        let mut parser = Parser::new();
+        let segments = parser.parse(content)?;
+
+        Ok(Interchange::from_segments(
+            segments,
+            parser.delimiters,
+            parser.syntax_version,
+        ))
+    }
+
+    pub(crate) fn get_interchange_header(&self) -> PyResult<Option<InterchangeHeader>> {
+        self.interchange_header
+            .as_ref()
+            .map(InterchangeHeader::from_segment)
+            .transpose()
+    }
+
+    fn get_messages(&self, message_type: &str) -> Vec<Message> {
+        self.messages
+            .iter()
+            .filter(|m| {
+                m.service_segments
+                    .get("UNH")
+                    .and_then(|h| h.get_component(1, 0))
+                    .is_some_and(|t| t == message_type)
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn validate_service_segments(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let version =
+            service::check_interchange_header(self.interchange_header.as_ref(), &mut issues);
+
+        for transfer in &self.transfers {
+            let trailer = transfer.service_segments.get("UNT");
+            if trailer.is_none() {
+                issues.push(ValidationIssue::segment(
+                    validation::MISSING,
+                    "UNT",
+                    transfer.segments.last().map(|s| s.position),
+                    "Message trailer is missing".to_string(),
+                ));
+            }
+            service::check_message(
+                transfer.service_segments.get("UNH"),
+                &transfer.segments,
+                trailer,
+                version,
+                &mut issues,
+            );
+        }
+
+        // Anything outside UNH...UNT other than the envelope itself is misplaced
+        let mut in_message = false;
+        for segment in &self.segments {
+            match segment.tag.as_str() {
+                "UNH" => in_message = true,
+                "UNT" => in_message = false,
+                "UNB" | "UNZ" | "UNG" | "UNE" => {}
+                _ if !in_message => issues.push(ValidationIssue::segment(
+                    validation::INVALID_OCCURRENCE_OUTSIDE_MESSAGE,
+                    &segment.tag,
+                    Some(segment.position),
+                    format!("Segment {} is outside a message", segment.tag),
+                )),
+                _ => {}
+            }
+        }
+
+        service::check_interchange_trailer(
+            self.interchange_header.as_ref(),
+            self.interchange_trailer.as_ref(),
+            Some(self.transfers.len()),
+            version,
+            &mut issues,
+        );
+        issues
+    }
+
+    pub(crate) fn to_edifact(&self) -> String {
+        let mut result = String::new();
+        if self.delimiters != self.syntax_version.default_delimiters() {
+            result.push_str(&self.delimiters.to_una());
+            result.push('\n');
+        }
+        for segment in &self.segments {
+            result.push_str(&segment.to_edifact(&self.delimiters));
+            result.push('\n');
+        }
+        result
+    }
+
+    /// The interchange as JSON: its service characters and every segment, or with a
+    /// directory, the segments with named elements and each message arranged into its
+    /// segment groups.
+    #[cfg(feature = "serde")]
+    #[pyo3(signature = (directory=None))]
+    fn to_json(&self, directory: Option<&Directory>) -> PyResult<String> {
+        Ok(json::to_json(self, directory)?)
+    }
+
+    /// Reads an interchange written by `to_json` in either shape.
+    #[cfg(feature = "serde")]
+    #[staticmethod]
+    #[pyo3(signature = (content, directory=None))]
+    fn from_json(content: &str, directory: Option<&Directory>) -> PyResult<Interchange> {
+        Ok(json::from_json(content, directory)?)
+    }
+
+    /// The interchange as XML: its service characters as attributes, the envelope segments
+    /// and each message as in `Message.to_xml`.
+    #[pyo3(signature = (directory=None))]
+    fn to_xml(&self, directory: Option<&Directory>) -> PyResult<String> {
+        Ok(xml::interchange_to_xml(self, directory)?)
+    }
+
+    /// Reads an interchange written by `to_xml`, with or without a directory.
+    #[staticmethod]
+    #[pyo3(signature = (content, directory=None))]
+    fn from_xml(content: &str, directory: Option<&Directory>) -> PyResult<Interchange> {
+        Ok(xml::interchange_from_xml(content, directory)?)
+    }
+}
+
+impl Interchange {
+    /// Builds an interchange from segments read back from another rendering, numbering
+    /// them in order. Without delimiters, those of the declared syntax version apply.
+    pub(crate) fn from_read_segments(
+        mut segments: Vec<Segment>,
+        delimiters: Option<Delimiters>,
+    ) -> Interchange {
+        for (position, segment) in segments.iter_mut().enumerate() {
+            segment.position = position;
+        }
+        let version = declared_version(&segments).unwrap_or_default();
+        let delimiters = delimiters.unwrap_or_else(|| version.default_delimiters());
+        Interchange::from_segments(segments, delimiters, version)
+    }
+
+    /// The envelope segments with each UNH...UNT run as a message group, arranged as in
+    /// `Message::arranged`.
+    pub(crate) fn arranged(&self, directory: Option<&Directory>) -> Vec<Node> {
+        let close = |segments: Vec<Segment>| {
+            Node::Group(Message::from_segments(segments).arranged(directory))
+        };
+        let mut nodes = Vec::new();
+        let mut current: Option<Vec<Segment>> = None;
+
+        for segment in &self.segments {
+            match (segment.tag.as_str(), current.as_mut()) {
+                ("UNH", _) => {
+                    nodes.extend(current.take().map(close));
+                    current = Some(vec![segment.clone()]);
+                }
+                ("UNT", Some(segments)) => {
+                    segments.push(segment.clone());
+                    nodes.extend(current.take().map(close));
+                }
+                (_, Some(segments)) => segments.push(segment.clone()),
+                (_, None) => nodes.push(Node::Segment(segment.clone())),
+            }
+        }
+        nodes.extend(current.map(close));
+        nodes
+    }
+
+    /// Splits parsed segments into the envelope and its messages.
+    pub(crate) fn from_segments(
+        segments: Vec<Segment>,
+        delimiters: Delimiters,
+        syntax_version: SyntaxVersion,
+    ) -> Interchange {
+        let mut interchange_header = None;
+        let mut interchange_trailer = None;
+        let mut transfers = Vec::new();
+        let mut current: Option<Message> = None;
+
+        for segment in &segments {
+            match segment.tag.as_str() {
+                "UNB" => interchange_header = Some(segment.clone()),
+                "UNZ" => interchange_trailer = Some(segment.clone()),
+                "UNH" => {
+                    transfers.extend(current.take());
+                    let mut message = Message::new();
+                    message
+                        .service_segments
+                        .insert("UNH".to_string(), segment.clone());
+                    current = Some(message);
+                }
+                "UNT" => {
+                    if let Some(mut message) = current.take() {
+                        message
+                            .service_segments
+                            .insert("UNT".to_string(), segment.clone());
+                        transfers.push(message);
+                    }
+                }
+                "UNG" | "UNE" => {}
+                _ => {
+                    if let Some(message) = current.as_mut() {
+                        message.segments.push(segment.clone());
+                    }
+                }
+            }
+        }
+        transfers.extend(current);
+
+        Interchange {
+            interchange_header,
+            interchange_trailer,
+            messages: reassemble(&transfers),
+            transfers,
+            segments,
+            delimiters,
+            syntax_version,
+        }
+    }
+}
+
+/// The syntax version declared in the UNB among the segments, if any.
+pub(crate) fn declared_version(segments: &[Segment]) -> Option<SyntaxVersion> {
+    segments
+        .iter()
+        .find(|s| s.tag == "UNB")
+        .and_then(SyntaxVersion::detect)
+}
+
+// One transfer of a multi-part message along with its parsed header
+type Part<'a> = (MessageHeader, &'a Message);
+
+// Merges the parts of multi-part messages, keeping each logical message where its first
+// part appeared. Parts are only tied together by their common access reference, so parts
+// without one, or without a final transfer, are left as they were received.
+fn reassemble(transfers: &[Message]) -> Vec<Message> {
+    let mut slots: Vec<Vec<Message>> = Vec::new();
+    let mut pending: HashMap<Vec<String>, (usize, Vec<Part>)> = HashMap::new();
+
+    for transfer in transfers {
+        let header = transfer
+            .service_segments
+            .get("UNH")
+            .and_then(|h| MessageHeader::from_segment(h).ok());
+        let part = header.and_then(|h| {
+            let reference = h
+                .common_access_reference
+                .clone()
+                .filter(|r| !r.is_empty())?;
+            h.transfer_sequence.is_some().then_some((h, reference))
+        });
+        let Some((header, reference)) = part else {
+            slots.push(vec![transfer.clone()]);
+            continue;
+        };
+
+        let key = vec![
+            header.message_type.clone(),
+            header.version.clone(),
+            header.release.clone(),
+            header.agency.clone(),
+            reference,
+        ];
+        let is_final = header.first_and_last_transfer.as_deref() == Some("F");
+        pending
+            .entry(key.clone())
+            .or_insert_with(|| {
+                slots.push(Vec::new());
+                (slots.len() - 1, Vec::new())
+            })
+            .1
+            .push((header, transfer));
+
+        if is_final {
+            let (slot, parts) = pending.remove(&key).expect("pending parts");
+            slots[slot] = match merge(&parts) {
+                Some(message) => vec![message],
+                None => parts.iter().map(|(_, part)| (*part).clone()).collect(),
+            };
+        }
+    }
+
+    for (slot, parts) in pending.into_values() {
+        slots[slot] = parts
+            .into_iter()
+            .map(|(_, message)| message.clone())
+            .collect();
+    }
+
+    slots.into_iter().flatten().collect()
+}
+
+fn merge(parts: &[Part]) -> Option<Message> {
+    let mut parts: Vec<&Part> = parts.iter().collect();
+    parts.sort_by_key(|(header, _)| header.transfer_sequence);
+    let sequences: Vec<Option<u32>> = parts.iter().map(|(h, _)| h.transfer_sequence).collect();
+    let expected: Vec<Option<u32>> = (1..=parts.len() as u32).map(Some).collect();
+    if sequences != expected {
+        return None;
+    }
+
+    let (first_header, first) = parts[0];
+    let mut header = first_header.clone();
+    header.transfer_sequence = None;
+    header.first_and_last_transfer = None;
+    let mut header_segment = header.to_segment();
+    header_segment.position = first.service_segments["UNH"].position;
+
+    let mut message = Message::new();
+    for (_, part) in parts.iter() {
+        message.segments.extend(part.segments.iter().cloned());
+    }
+
+    let trailer_position = parts
+        .last()
+        .and_then(|(_, part)| part.service_segments.get("UNT"))
+        .map(|s| s.position)
+        .unwrap_or_default();
+    let trailer = Segment::new(
+        "UNT".to_string(),
+        vec![
+            vec![(message.segments.len() + 2).to_string()],
+            vec![header.message_reference.clone()],
+        ],
+        trailer_position,
+    );
+
+    message
+        .service_segments
+        .insert("UNH".to_string(), header_segment);
+    message.service_segments.insert("UNT".to_string(), trailer);
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const MULTI_PART: &str = "UNA:+.? '
+UNB+UNOC:4+SENDER:14+RECEIVER:14+20240119:1200+ICR1'
+UNH+1+ORDERS:D:96A:UN+CAR9+1:C'
+BGM+220+PO1+9'
+LIN+1++ITEM1:BP'
+UNT+4+1'
+UNH+2+INVOIC:D:96A:UN'
+BGM+380+INV1+9'
+UNT+3+2'
+UNH+3+ORDERS:D:96A:UN+CAR9+2:F'
+LIN+2++ITEM2:BP'
+QTY+21:5'
+UNT+4+3'
+UNZ+3+ICR1'";
+
+    #[test]
+    fn test_parser_splits_on_segment_terminator() {
+        Python::with_gil(|_py| {
+            let mut parser = Parser::new();
+            let segments = parser
+                .parse(
+                    "UNA:+.? 'UNB+UNOA:4+S+R+20240119:1200+1'FTX+AAA+A?'B'
+UNZ+0+1'",
+                )
+                .unwrap();
+
+            let tags: Vec<&str> = segments.iter().map(|s| s.tag.as_str()).collect();
+            assert_eq!(tags, vec!["UNB", "FTX", "UNZ"]);
+            assert_eq!(segments[1].elements[1][0], "A'B");
+            assert_eq!(segments[2].position, 2);
+        });
+    }
+
+    #[test]
+    fn test_interchange_reassembles_multi_part_messages() {
+        Python::with_gil(|_py| {
+            let interchange = Interchange::from_edifact(MULTI_PART).unwrap();
+
+            assert_eq!(interchange.transfers.len(), 3);
+            assert_eq!(interchange.messages.len(), 2);
+            assert_eq!(interchange.validate_service_segments(), Vec::new());
+
+            let order = &interchange.messages[0];
+            let tags: Vec<&str> = order.segments.iter().map(|s| s.tag.as_str()).collect();
+            assert_eq!(tags, vec!["BGM", "LIN", "LIN", "QTY"]);
+
+            let header = order.get_message_header().unwrap().unwrap();
+            assert_eq!(header.message_reference, "1");
+            assert_eq!(header.common_access_reference.as_deref(), Some("CAR9"));
+            assert_eq!(header.transfer_sequence, None);
+            assert_eq!(order.service_segments["UNT"].elements[0][0], "6");
+
+            assert_eq!(interchange.get_messages("INVOIC").len(), 1);
+            assert_eq!(interchange.to_edifact().lines().count(), 14);
+        });
+    }
+
+    #[test]
+    fn test_interchange_keeps_incomplete_transfers() {
+        Python::with_gil(|_py| {
+            let content = MULTI_PART.replace("+2:F'", "+3:F'");
+            let interchange = Interchange::from_edifact(&content).unwrap();
+            assert_eq!(interchange.messages.len(), 3);
+
+            let content = MULTI_PART.replace("+2:F'", "+2'");
+            let interchange = Interchange::from_edifact(&content).unwrap();
+            assert_eq!(interchange.messages.len(), 3);
+
+            // Without a common access reference nothing ties the parts together
+            let content = MULTI_PART.replace("+CAR9+", "++");
+            let interchange = Interchange::from_edifact(&content).unwrap();
+            assert_eq!(interchange.messages.len(), 3);
+        });
+    }
+
+    #[test]
+    fn test_interchange_envelope_validation() {
+        Python::with_gil(|_py| {
+            let content = MULTI_PART
+                .replace("UNZ+3+ICR1", "UNZ+2+ICR1")
+                .replace("UNT+3+2'", "UNT+3+2'\nFTX+AAA+STRAY'");
+            let interchange = Interchange::from_edifact(&content).unwrap();
+
+            let issues = interchange.validate_service_segments();
+            let codes: Vec<(&str, &str)> = issues
+                .iter()
+                .map(|i| (i.code.as_str(), i.tag.as_str()))
+                .collect();
+            assert_eq!(codes, vec![("33", "FTX"), ("29", "UNZ")]);
+        });
+    }
+}
@@ -0,0 +1,712 @@
+use pyo3::prelude::*;
+
+use crate::common::{
+    format_cents, multiply_cents, parse_cents, percentage_cents, AllowanceCharge, Currency,
+    DateTimePeriod, MonetaryAmount, Party, PaymentTerms, Reference, Tax,
+};
+use crate::directory::SegmentGroup;
+use crate::document::{segment_of, Document};
+use crate::header::{InterchangeHeader, MessageHeader};
+use crate::{check_code, check_decimal, EdifactError, Message, OrderLine, Segment};
+
+/// An INVOIC invoice. The BGM document name tells the invoice type: 380 commercial
+/// invoice, 381 credit note, 383 debit note, 384 corrected invoice, 389 self-billed invoice.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Invoice {
+    document: Document,
+}
+
+#[pymethods]
+impl Invoice {
+    #[staticmethod]
+    pub(crate) fn from_edifact(content: &str) -> PyResult<Invoice> {
+        Ok(Invoice {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> Invoice {
+        Invoice {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_name(&self) -> Option<String> {
+        self.document.document_name()
+    }
+
+    pub(crate) fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_message_function(&self) -> Option<String> {
+        self.document.message_function()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    /// The number of the invoiced order, from RFF+ON.
+    fn get_order_number(&self) -> Option<String> {
+        self.reference("ON")
+    }
+
+    /// The number of the despatch advice being invoiced, from RFF+AAK.
+    fn get_despatch_advice_number(&self) -> Option<String> {
+        self.reference("AAK")
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    fn get_party(&self, qualifier: &str) -> Option<Party> {
+        self.get_parties()
+            .into_iter()
+            .find(|party| party.qualifier == qualifier)
+    }
+
+    fn get_currencies(&self) -> Vec<Currency> {
+        self.tree()
+            .groups_by_trigger("CUX")
+            .filter_map(Currency::from_group)
+            .collect()
+    }
+
+    fn get_payment_terms(&self) -> Vec<PaymentTerms> {
+        self.tree()
+            .groups_by_trigger("PAT")
+            .filter_map(PaymentTerms::from_group)
+            .collect()
+    }
+
+    /// Allowances and charges for the whole invoice, from the header section.
+    fn get_allowances_charges(&self) -> Vec<AllowanceCharge> {
+        let tree = self.tree();
+        let summary = summary_start(&tree);
+        tree.groups_by_trigger("ALC")
+            .filter(|group| !is_after(group, summary))
+            .filter_map(AllowanceCharge::from_group)
+            .collect()
+    }
+
+    /// Taxes declared in the header section, which apply to every line.
+    fn get_taxes(&self) -> Vec<Tax> {
+        let tree = self.tree();
+        let summary = summary_start(&tree);
+        tree.groups_by_trigger("TAX")
+            .filter(|group| !is_after(group, summary))
+            .filter_map(Tax::from_group)
+            .collect()
+    }
+
+    fn get_lines(&self) -> Vec<OrderLine> {
+        Document::lines(&self.tree())
+    }
+
+    /// The MOA totals following UNS, e.g. 79 (line items), 125 (taxable), 176 (tax) and 77
+    /// (invoice amount).
+    fn get_summary_amounts(&self) -> Vec<MonetaryAmount> {
+        let tree = self.tree();
+        let summary = summary_start(&tree);
+        tree.groups_by_trigger("MOA")
+            .filter(|group| is_after(group, summary))
+            .filter_map(|group| group.find_segment("MOA"))
+            .map(MonetaryAmount::from_segment)
+            .collect()
+    }
+
+    pub(crate) fn get_summary_amount(&self, qualifier: &str) -> Option<String> {
+        self.get_summary_amounts()
+            .into_iter()
+            .find(|amount| amount.qualifier == qualifier)
+            .and_then(|amount| amount.amount)
+    }
+
+    /// The TAX summaries following UNS, one per tax rate and category.
+    fn get_tax_summary(&self) -> Vec<Tax> {
+        let tree = self.tree();
+        let summary = summary_start(&tree);
+        tree.groups_by_trigger("TAX")
+            .filter(|group| is_after(group, summary))
+            .filter_map(Tax::from_group)
+            .collect()
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl Invoice {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("INVOIC")
+    }
+
+    fn reference(&self, qualifier: &str) -> Option<String> {
+        self.get_references()
+            .into_iter()
+            .find(|reference| reference.qualifier == qualifier)
+            .and_then(|reference| reference.number)
+    }
+}
+
+// Position of the UNS separating the detail section from the summary section
+fn summary_start(tree: &SegmentGroup) -> Option<usize> {
+    tree.find_segment("UNS").map(|uns| uns.position)
+}
+
+fn is_after(group: &SegmentGroup, summary: Option<usize>) -> bool {
+    let position = group.direct_segments().next().map(|s| s.position);
+    matches!((position, summary), (Some(position), Some(summary)) if position > summary)
+}
+
+// A net amount in cents subject to VAT at a rate in a category, kept for the tax summary
+#[derive(Debug, Clone)]
+struct Taxable {
+    amount: i64,
+    rate: String,
+    category: String,
+}
+
+fn tax_segment(rate: &str, category: &str) -> Segment {
+    segment_of(
+        "TAX",
+        &[&["7"], &["VAT"], &[], &[], &["", "", "", rate], &[category]],
+    )
+}
+
+fn invalid(value: &str, what: &str) -> EdifactError {
+    EdifactError::new(format!("Invalid {} '{}'", what, value))
+}
+
+/// Builds an INVOIC, computing line amounts, the tax summary and the invoice totals.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct InvoiceBuilder {
+    document: Document,
+    document_number: String,
+    invoice_type: String,
+    dates: Vec<Segment>,
+    references: Vec<Segment>,
+    parties: Vec<Segment>,
+    currencies: Vec<Segment>,
+    payment_terms: Vec<Segment>,
+    allowances_charges: Vec<(Vec<Segment>, Taxable)>,
+    lines: Vec<(Vec<Segment>, Taxable)>,
+}
+
+#[pymethods]
+impl InvoiceBuilder {
+    #[new]
+    #[pyo3(signature = (
+        document_number,
+        invoice_type="380".to_string(),
+        message_reference="1".to_string(),
+        release="96A".to_string(),
+    ))]
+    fn new(
+        document_number: String,
+        invoice_type: String,
+        message_reference: String,
+        release: String,
+    ) -> PyResult<InvoiceBuilder> {
+        let mut document = Document::new(Default::default(), Default::default());
+        document.set_message_header(&MessageHeader::new(
+            message_reference,
+            "INVOIC".to_string(),
+            "D".to_string(),
+            release,
+            "UN".to_string(),
+            None,
+        ))?;
+        Ok(InvoiceBuilder {
+            document,
+            document_number,
+            invoice_type,
+            dates: Vec::new(),
+            references: Vec::new(),
+            parties: Vec::new(),
+            currencies: Vec::new(),
+            payment_terms: Vec::new(),
+            allowances_charges: Vec::new(),
+            lines: Vec::new(),
+        })
+    }
+
+    fn with_interchange<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        header: InterchangeHeader,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.document.syntax_version = header.syntax_version;
+        slf.document.delimiters = header.syntax_version.default_delimiters();
+        slf.document.set_interchange_header(&header)?;
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (qualifier, value, format="102"))]
+    fn with_date<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        DateTimePeriod::check(value, format)?;
+        slf.dates
+            .push(segment_of("DTM", &[&[qualifier, value, format]]));
+        Ok(slf)
+    }
+
+    fn with_reference<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        number: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.references
+            .push(segment_of("RFF", &[&[qualifier, number]]));
+        Ok(slf)
+    }
+
+    /// Adds a party, with its VAT registration number as RFF+VA when given.
+    #[pyo3(signature = (qualifier, id, code_list_agency="9", vat_number=None))]
+    fn with_party<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        id: &str,
+        code_list_agency: &str,
+        vat_number: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.parties.push(segment_of(
+            "NAD",
+            &[&[qualifier], &[id, "", code_list_agency]],
+        ));
+        if let Some(vat_number) = vat_number {
+            slf.parties.push(segment_of("RFF", &[&["VA", vat_number]]));
+        }
+        Ok(slf)
+    }
+
+    /// Sets the invoicing currency (CUX+2:<currency>:4).
+    fn with_currency<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        currency: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        check_code(currency, 3, "currency")?;
+        slf.currencies
+            .push(segment_of("CUX", &[&["2", currency, "4"]]));
+        Ok(slf)
+    }
+
+    /// Adds payment terms with the date payment is due (DTM+13).
+    #[pyo3(signature = (due_date, type_qualifier="1"))]
+    fn with_payment_terms<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        due_date: &str,
+        type_qualifier: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        DateTimePeriod::check(due_date, "102")?;
+        slf.payment_terms
+            .push(segment_of("PAT", &[&[type_qualifier]]));
+        slf.payment_terms
+            .push(segment_of("DTM", &[&["13", due_date, "102"]]));
+        Ok(slf)
+    }
+
+    /// Adds an allowance (indicator A) or charge (indicator C) on the whole invoice.
+    #[pyo3(signature = (indicator, amount, tax_rate, service_code=None, tax_category="S"))]
+    fn with_allowance_charge<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        indicator: &str,
+        amount: &str,
+        tax_rate: &str,
+        service_code: Option<&str>,
+        tax_category: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let value =
+            parse_cents(amount).ok_or_else(|| invalid(amount, "allowance or charge amount"))?;
+        check_decimal(tax_rate, "tax rate")?;
+        let signed = match indicator {
+            "A" => -value,
+            "C" => value,
+            other => {
+                return Err(EdifactError::new(format!(
+                    "Allowance or charge indicator must be A or C, not '{}'",
+                    other
+                ))
+                .into())
+            }
+        };
+        let segments = vec![
+            segment_of(
+                "ALC",
+                &[
+                    &[indicator],
+                    &[],
+                    &[],
+                    &[],
+                    &[service_code.unwrap_or_default()],
+                ],
+            ),
+            segment_of("MOA", &[&["8", &format_cents(value)]]),
+            tax_segment(tax_rate, tax_category),
+        ];
+        slf.allowances_charges.push((
+            segments,
+            Taxable {
+                amount: signed,
+                rate: tax_rate.to_string(),
+                category: tax_category.to_string(),
+            },
+        ));
+        Ok(slf)
+    }
+
+    /// Adds a line with its invoiced quantity (QTY+47), net price and VAT rate. The line
+    /// amount (MOA+203) is the quantity times the price.
+    #[pyo3(signature = (
+        line_number,
+        item_number,
+        quantity,
+        net_price,
+        tax_rate,
+        description=None,
+        tax_category="S",
+        item_type="SRV",
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn add_line<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        line_number: &str,
+        item_number: &str,
+        quantity: &str,
+        net_price: &str,
+        tax_rate: &str,
+        description: Option<&str>,
+        tax_category: &str,
+        item_type: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        check_decimal(quantity, "quantity")?;
+        check_decimal(net_price, "price")?;
+        check_decimal(tax_rate, "tax rate")?;
+        let line_amount = multiply_cents(quantity, net_price)
+            .ok_or_else(|| invalid(quantity, "line amount for quantity"))?;
+        let mut segments = vec![segment_of(
+            "LIN",
+            &[&[line_number], &[], &[item_number, item_type]],
+        )];
+        if let Some(description) = description {
+            segments.push(segment_of(
+                "IMD",
+                &[&["F"], &[], &["", "", "", description]],
+            ));
+        }
+        segments.push(segment_of("QTY", &[&["47", quantity]]));
+        segments.push(segment_of("MOA", &[&["203", &format_cents(line_amount)]]));
+        segments.push(segment_of("PRI", &[&["AAA", net_price]]));
+        segments.push(tax_segment(tax_rate, tax_category));
+        slf.lines.push((
+            segments,
+            Taxable {
+                amount: line_amount,
+                rate: tax_rate.to_string(),
+                category: tax_category.to_string(),
+            },
+        ));
+        Ok(slf)
+    }
+
+    /// Writes the invoice with its summary section and checks it against the INVOIC
+    /// structure.
+    fn build(&self) -> PyResult<Invoice> {
+        let mut document = self.document.clone();
+        let body = &mut document.segments;
+        body.push(segment_of(
+            "BGM",
+            &[&[&self.invoice_type], &[&self.document_number], &["9"]],
+        ));
+        for group in [
+            &self.dates,
+            &self.references,
+            &self.parties,
+            &self.currencies,
+            &self.payment_terms,
+        ] {
+            body.extend(group.iter().cloned());
+        }
+        for (segments, _) in self.allowances_charges.iter().chain(&self.lines) {
+            body.extend(segments.iter().cloned());
+        }
+
+        // Tax summary per rate and category, in order of first use
+        let mut summary: Vec<Taxable> = Vec::new();
+        for (_, taxable) in self.lines.iter().chain(&self.allowances_charges) {
+            match summary
+                .iter_mut()
+                .find(|s| s.rate == taxable.rate && s.category == taxable.category)
+            {
+                Some(entry) => entry.amount += taxable.amount,
+                None => summary.push(taxable.clone()),
+            }
+        }
+        let mut total_tax = 0;
+        let mut taxes = Vec::new();
+        for entry in &summary {
+            let tax = percentage_cents(entry.amount, &entry.rate)
+                .ok_or_else(|| invalid(&entry.rate, "tax rate"))?;
+            total_tax += tax;
+            taxes.push(tax_segment(&entry.rate, &entry.category));
+            taxes.push(segment_of("MOA", &[&["125", &format_cents(entry.amount)]]));
+            taxes.push(segment_of("MOA", &[&["124", &format_cents(tax)]]));
+        }
+
+        let line_total: i64 = self.lines.iter().map(|(_, t)| t.amount).sum();
+        let taxable_total: i64 = summary.iter().map(|t| t.amount).sum();
+        body.push(segment_of("UNS", &[&["S"]]));
+        body.push(segment_of("CNT", &[&["2", &self.lines.len().to_string()]]));
+        for (qualifier, value) in [
+            ("79", line_total),
+            ("125", taxable_total),
+            ("176", total_tax),
+            ("77", taxable_total + total_tax),
+        ] {
+            body.push(segment_of("MOA", &[&[qualifier, &format_cents(value)]]));
+        }
+        body.extend(taxes);
+        document.close();
+
+        document.check_structure("INVOIC")?;
+        Ok(Invoice { document })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const INVOICE: &str = "UNH+1+INVOIC:D:96A:UN'
+BGM+380+INV001+9'
+DTM+137:20240125:102'
+RFF+ON:PO12345'
+RFF+AAK:DES587441'
+NAD+SU+4012345000016::9'
+RFF+VA:DE123456789'
+NAD+BY+5412345000013::9'
+CUX+2:EUR:4'
+PAT+1'
+DTM+13:20240224:102'
+ALC+C++++FC'
+MOA+8:15.00'
+TAX+7+VAT+++:::21+S'
+LIN+1++4000862141404:SRV'
+QTY+47:48'
+MOA+203:480.00'
+PRI+AAA:10.00'
+TAX+7+VAT+++:::21+S'
+LIN+2++4000862141411:SRV'
+QTY+47:10'
+MOA+203:55.00'
+PRI+AAA:5.50'
+TAX+7+VAT+++:::6+S'
+UNS+S'
+CNT+2:2'
+MOA+79:535.00'
+MOA+125:550.00'
+MOA+176:107.25'
+MOA+77:657.25'
+TAX+7+VAT+++:::21+S'
+MOA+125:495.00'
+MOA+124:103.95'
+TAX+7+VAT+++:::6+S'
+MOA+125:55.00'
+MOA+124:3.30'
+UNT+37+1'";
+
+    #[test]
+    fn test_invoice_model() {
+        Python::with_gil(|_py| {
+            let invoice = Invoice::from_edifact(INVOICE).unwrap();
+            assert_eq!(invoice.get_document_name().as_deref(), Some("380"));
+            assert_eq!(invoice.get_order_number().as_deref(), Some("PO12345"));
+            assert_eq!(
+                invoice.get_despatch_advice_number().as_deref(),
+                Some("DES587441")
+            );
+            let supplier = invoice.get_party("SU").unwrap();
+            assert_eq!(
+                supplier.references[0].number.as_deref(),
+                Some("DE123456789")
+            );
+            assert_eq!(invoice.get_currencies()[0].currency.as_deref(), Some("EUR"));
+            assert_eq!(invoice.get_payment_terms()[0].dates[0].qualifier, "13");
+
+            let charges = invoice.get_allowances_charges();
+            assert_eq!(charges.len(), 1);
+            assert_eq!(charges[0].amounts[0].amount.as_deref(), Some("15.00"));
+            assert!(invoice.get_taxes().is_empty());
+
+            let lines = invoice.get_lines();
+            assert_eq!(lines.len(), 2);
+            assert_eq!(lines[1].amounts()[0].amount.as_deref(), Some("55.00"));
+            assert_eq!(lines[1].taxes()[0].rate.as_deref(), Some("6"));
+
+            assert_eq!(invoice.get_summary_amount("77").as_deref(), Some("657.25"));
+            let summary = invoice.get_tax_summary();
+            assert_eq!(summary.len(), 2);
+            assert_eq!(summary[0].amounts[1].amount.as_deref(), Some("103.95"));
+        });
+    }
+
+    #[test]
+    fn test_invoice_builder_computes_totals() {
+        Python::with_gil(|py| {
+            let builder = InvoiceBuilder::new(
+                "INV001".to_string(),
+                "380".to_string(),
+                "1".to_string(),
+                "96A".to_string(),
+            )
+            .unwrap();
+            let builder = Bound::new(py, builder).unwrap();
+            InvoiceBuilder::with_date(builder.borrow_mut(), "137", "20240125", "102").unwrap();
+            InvoiceBuilder::with_reference(builder.borrow_mut(), "ON", "PO12345").unwrap();
+            InvoiceBuilder::with_reference(builder.borrow_mut(), "AAK", "DES587441").unwrap();
+            InvoiceBuilder::with_party(
+                builder.borrow_mut(),
+                "SU",
+                "4012345000016",
+                "9",
+                Some("DE123456789"),
+            )
+            .unwrap();
+            InvoiceBuilder::with_party(builder.borrow_mut(), "BY", "5412345000013", "9", None)
+                .unwrap();
+            InvoiceBuilder::with_currency(builder.borrow_mut(), "EUR").unwrap();
+            InvoiceBuilder::with_payment_terms(builder.borrow_mut(), "20240224", "1").unwrap();
+            InvoiceBuilder::with_allowance_charge(
+                builder.borrow_mut(),
+                "C",
+                "15",
+                "21",
+                Some("FC"),
+                "S",
+            )
+            .unwrap();
+            InvoiceBuilder::add_line(
+                builder.borrow_mut(),
+                "1",
+                "4000862141404",
+                "48",
+                "10.00",
+                "21",
+                None,
+                "S",
+                "SRV",
+            )
+            .unwrap();
+            InvoiceBuilder::add_line(
+                builder.borrow_mut(),
+                "2",
+                "4000862141411",
+                "10",
+                "5.50",
+                "6",
+                None,
+                "S",
+                "SRV",
+            )
+            .unwrap();
+            assert!(InvoiceBuilder::with_allowance_charge(
+                builder.borrow_mut(),
+                "X",
+                "1",
+                "21",
+                None,
+                "S"
+            )
+            .is_err());
+
+            let invoice = builder.borrow().build().unwrap();
+            assert_eq!(invoice.to_edifact(), format!("{}\n", INVOICE));
+        });
+    }
+
+    #[test]
+    fn test_invoice_builder_rounds_tax_in_cents() {
+        Python::with_gil(|py| {
+            let builder = InvoiceBuilder::new(
+                "INV002".to_string(),
+                "380".to_string(),
+                "1".to_string(),
+                "96A".to_string(),
+            )
+            .unwrap();
+            let builder = Bound::new(py, builder).unwrap();
+            InvoiceBuilder::with_date(builder.borrow_mut(), "137", "20240125", "102").unwrap();
+            assert!(
+                InvoiceBuilder::with_date(builder.borrow_mut(), "137", "20241325", "102").is_err()
+            );
+            assert!(InvoiceBuilder::with_currency(builder.borrow_mut(), "eur").is_err());
+            assert!(
+                InvoiceBuilder::with_payment_terms(builder.borrow_mut(), "2024-02-24", "1")
+                    .is_err()
+            );
+            InvoiceBuilder::add_line(
+                builder.borrow_mut(),
+                "1",
+                "4000862141404",
+                "1",
+                "10.05",
+                "10",
+                None,
+                "S",
+                "SRV",
+            )
+            .unwrap();
+            assert!(InvoiceBuilder::add_line(
+                builder.borrow_mut(),
+                "2",
+                "4000862141411",
+                "1",
+                "1.00",
+                "ten",
+                None,
+                "S",
+                "SRV"
+            )
+            .is_err());
+
+            let invoice = builder.borrow().build().unwrap();
+            let edifact = invoice.to_edifact();
+            assert!(edifact.contains("MOA+124:1.01'"));
+            assert_eq!(invoice.get_summary_amount("77").as_deref(), Some("11.06"));
+        });
+    }
+}
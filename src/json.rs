@@ -0,0 +1,566 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::directory::{Directory, Node, SegmentGroup, SegmentNames};
+use crate::interchange::{declared_version, Interchange};
+use crate::syntax::SyntaxVersion;
+use crate::{Delimiters, EdifactError, Message, Segment};
+
+// In the compact shape a segment is its tag followed by its elements, each a list of
+// components: ["DTM", ["137", "20240301", "102"]]. Positions are not written; they follow
+// from the order of the segments when read back.
+impl Serialize for Segment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.elements.len() + 1))?;
+        seq.serialize_element(&self.tag)?;
+        for element in &self.elements {
+            seq.serialize_element(element)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Segment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SegmentVisitor;
+
+        impl<'de> Visitor<'de> for SegmentVisitor {
+            type Value = Segment;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a segment tag followed by its elements")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Segment, A::Error> {
+                let tag: String = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let mut elements = Vec::new();
+                while let Some(element) = seq.next_element()? {
+                    elements.push(element);
+                }
+                Ok(Segment::new(tag, elements, 0))
+            }
+        }
+
+        deserializer.deserialize_seq(SegmentVisitor)
+    }
+}
+
+// A message is the list of its segments, service segments included
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.all_segments())
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Segment>::deserialize(deserializer).map(Message::from_segments)
+    }
+}
+
+// An interchange is its service characters and every segment in document order
+impl Serialize for Interchange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Interchange", 2)?;
+        state.serialize_field("delimiters", &self.delimiters)?;
+        state.serialize_field("segments", &self.segments)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Interchange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Compact {
+            delimiters: Option<Delimiters>,
+            segments: Vec<Segment>,
+        }
+
+        let compact = Compact::deserialize(deserializer)?;
+        Ok(Interchange::from_read_segments(
+            compact.segments,
+            compact.delimiters,
+        ))
+    }
+}
+
+/// A segment, message or interchange in the directory-aware shape. Elements and components
+/// are named after their EDED/EDCD identifiers (see `SegmentNames`), and the body of a
+/// message is arranged into segment groups when the directory has its structure:
+/// `{"ORDERS": [{"UNH": {...}}, {"BGM": {...}}, {"SG2": [{"NAD": {...}}]}, {"UNT": {...}}]}`.
+pub(crate) struct Named<'a, T> {
+    pub(crate) value: &'a T,
+    pub(crate) directory: &'a Directory,
+}
+
+impl Serialize for Named<'_, Segment> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = NamedSegment {
+            segment: self.value,
+            directory: self.directory,
+            version: SyntaxVersion::default(),
+        };
+        node.serialize(serializer)
+    }
+}
+
+impl Serialize for Named<'_, Message> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let group = self.value.arranged(Some(self.directory));
+        NamedGroup {
+            group: &group,
+            directory: self.directory,
+            version: SyntaxVersion::default(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for Named<'_, Interchange> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let nodes = self.value.arranged(Some(self.directory));
+        let mut state = serializer.serialize_struct("Interchange", 2)?;
+        state.serialize_field("delimiters", &self.value.delimiters)?;
+        state.serialize_field(
+            "segments",
+            &NamedNodes {
+                nodes: &nodes,
+                directory: self.directory,
+                version: self.value.syntax_version,
+            },
+        )?;
+        state.end()
+    }
+}
+
+struct NamedNodes<'a> {
+    nodes: &'a [Node],
+    directory: &'a Directory,
+    version: SyntaxVersion,
+}
+
+impl Serialize for NamedNodes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.nodes.len()))?;
+        for node in self.nodes {
+            match node {
+                Node::Segment(segment) => seq.serialize_element(&NamedSegment {
+                    segment,
+                    directory: self.directory,
+                    version: self.version,
+                })?,
+                Node::Group(group) => seq.serialize_element(&NamedGroup {
+                    group,
+                    directory: self.directory,
+                    version: self.version,
+                })?,
+            }
+        }
+        seq.end()
+    }
+}
+
+struct NamedGroup<'a> {
+    group: &'a SegmentGroup,
+    directory: &'a Directory,
+    version: SyntaxVersion,
+}
+
+impl Serialize for NamedGroup<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(
+            &self.group.name,
+            &NamedNodes {
+                nodes: &self.group.nodes,
+                directory: self.directory,
+                version: self.version,
+            },
+        )?;
+        map.end()
+    }
+}
+
+struct NamedSegment<'a> {
+    segment: &'a Segment,
+    directory: &'a Directory,
+    version: SyntaxVersion,
+}
+
+impl Serialize for NamedSegment<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let names = SegmentNames::new(self.directory.segment(&self.segment.tag, self.version));
+        let elements = NamedElements {
+            elements: &self.segment.elements,
+            names,
+        };
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.segment.tag, &elements)?;
+        map.end()
+    }
+}
+
+struct NamedElements<'a> {
+    elements: &'a [Vec<String>],
+    names: SegmentNames<'a>,
+}
+
+// Every element is written, an empty one as null, so the segment reads back exactly. A
+// single value is written as a string unless the directory defines a composite there.
+impl Serialize for NamedElements<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.elements.len()))?;
+        for (index, components) in self.elements.iter().enumerate() {
+            let name = self.names.element(index);
+            match components.as_slice() {
+                [] => map.serialize_entry(&name, &())?,
+                [value] if !self.names.is_composite(index) => map.serialize_entry(&name, value)?,
+                _ => map.serialize_entry(
+                    &name,
+                    &NamedComponents {
+                        components,
+                        element: index,
+                        names: self.names,
+                    },
+                )?,
+            }
+        }
+        map.end()
+    }
+}
+
+struct NamedComponents<'a> {
+    components: &'a [String],
+    element: usize,
+    names: SegmentNames<'a>,
+}
+
+impl Serialize for NamedComponents<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.components.len()))?;
+        for (index, value) in self.components.iter().enumerate() {
+            map.serialize_entry(&self.names.component(self.element, index), value)?;
+        }
+        map.end()
+    }
+}
+
+/// Reads either shape, even mixed, using the directory to resolve element names.
+pub(crate) struct NamedSeed<'a, T> {
+    directory: &'a Directory,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T> NamedSeed<'a, T> {
+    pub(crate) fn new(directory: &'a Directory) -> Self {
+        NamedSeed {
+            directory,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: FromJson> DeserializeSeed<'de> for NamedSeed<'_, T> {
+    type Value = T;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        T::from_value(&value, self.directory).map_err(|e| de::Error::custom(e.message))
+    }
+}
+
+pub(crate) trait FromJson: Sized {
+    fn from_value(value: &Value, directory: &Directory) -> Result<Self, EdifactError>;
+}
+
+impl FromJson for Segment {
+    fn from_value(value: &Value, directory: &Directory) -> Result<Self, EdifactError> {
+        let mut segments = Vec::new();
+        read_nodes(
+            std::slice::from_ref(value),
+            directory,
+            SyntaxVersion::default(),
+            &mut segments,
+        )?;
+        match <[Segment; 1]>::try_from(segments) {
+            Ok([segment]) => Ok(segment),
+            Err(_) => Err(EdifactError::new("Expected a single segment")),
+        }
+    }
+}
+
+impl FromJson for Message {
+    fn from_value(value: &Value, directory: &Directory) -> Result<Self, EdifactError> {
+        let mut segments = Vec::new();
+        read_nodes(
+            value
+                .as_array()
+                .map_or(std::slice::from_ref(value), Vec::as_slice),
+            directory,
+            SyntaxVersion::default(),
+            &mut segments,
+        )?;
+        Ok(Message::from_segments(segments))
+    }
+}
+
+impl FromJson for Interchange {
+    fn from_value(value: &Value, directory: &Directory) -> Result<Self, EdifactError> {
+        let nodes = value
+            .get("segments")
+            .and_then(Value::as_array)
+            .ok_or_else(|| EdifactError::new("An interchange needs a list of segments"))?;
+        let delimiters = value
+            .get("delimiters")
+            .map(Delimiters::deserialize)
+            .transpose()
+            .map_err(|e| EdifactError::new(format!("Invalid delimiters: {}", e)))?;
+
+        // Service segment names depend on the syntax version, which UNB itself declares
+        let mut version = SyntaxVersion::default();
+        let mut segments = Vec::new();
+        read_nodes(nodes, directory, version, &mut segments)?;
+        if let Some(declared) = declared_version(&segments).filter(|v| *v != version) {
+            version = declared;
+            segments.clear();
+            read_nodes(nodes, directory, version, &mut segments)?;
+        }
+        Ok(Interchange::from_read_segments(segments, delimiters))
+    }
+}
+
+/// Writes the compact shape, or the directory-aware one when a directory is given.
+pub(crate) fn to_json<T: Serialize>(
+    value: &T,
+    directory: Option<&Directory>,
+) -> Result<String, EdifactError>
+where
+    for<'a> Named<'a, T>: Serialize,
+{
+    let result = match directory {
+        Some(directory) => serde_json::to_string(&Named { value, directory }),
+        None => serde_json::to_string(value),
+    };
+    result.map_err(|e| EdifactError::new(format!("Cannot write JSON: {}", e)))
+}
+
+/// Reads either shape; without a directory only service segments have named elements.
+pub(crate) fn from_json<T: FromJson>(
+    content: &str,
+    directory: Option<&Directory>,
+) -> Result<T, EdifactError> {
+    let directory = directory.unwrap_or_else(|| Directory::builtin());
+    let mut deserializer = serde_json::Deserializer::from_str(content);
+    let value = NamedSeed::new(directory)
+        .deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|_| value))
+        .map_err(|e| EdifactError::new(format!("Invalid JSON: {}", e)))?;
+    Ok(value)
+}
+
+// A node is a compact segment, a named segment or a group of nodes, the message itself
+// being a group named after its type
+fn read_nodes(
+    nodes: &[Value],
+    directory: &Directory,
+    version: SyntaxVersion,
+    segments: &mut Vec<Segment>,
+) -> Result<(), EdifactError> {
+    for node in nodes {
+        match node {
+            Value::Array(_) => segments.push(
+                Segment::deserialize(node)
+                    .map_err(|e| EdifactError::new(format!("Invalid segment: {}", e)))?,
+            ),
+            Value::Object(entry) if entry.len() == 1 => {
+                let (name, value) = entry.iter().next().expect("a single entry");
+                match value {
+                    Value::Array(children) => read_nodes(children, directory, version, segments)?,
+                    Value::Object(elements) => {
+                        let names = SegmentNames::new(directory.segment(name, version));
+                        segments.push(read_segment(name, elements, names)?);
+                    }
+                    _ => {
+                        return Err(EdifactError::new(format!(
+                            "{} must hold elements or a list of segments",
+                            name
+                        )))
+                    }
+                }
+            }
+            _ => {
+                return Err(EdifactError::new(format!(
+                    "Expected a segment or segment group, got {}",
+                    node
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+// Elements and components left out are read as empty
+fn read_segment(
+    tag: &str,
+    fields: &Map<String, Value>,
+    names: SegmentNames,
+) -> Result<Segment, EdifactError> {
+    let mut elements: Vec<Vec<String>> = Vec::new();
+    for (name, value) in fields {
+        let index = names
+            .element_index(name)
+            .ok_or_else(|| EdifactError::new(format!("Unknown element {} in {}", name, tag)))?;
+        if elements.len() <= index {
+            elements.resize(index + 1, Vec::new());
+        }
+        elements[index] = match value {
+            Value::Null => Vec::new(),
+            Value::String(value) => vec![value.clone()],
+            Value::Object(components) => {
+                let mut values = Vec::new();
+                for (name, value) in components {
+                    let position = names.component_index(index, name).ok_or_else(|| {
+                        EdifactError::new(format!("Unknown component {} in {}", name, tag))
+                    })?;
+                    let value = value.as_str().ok_or_else(|| {
+                        EdifactError::new(format!("{} in {} must be a string", name, tag))
+                    })?;
+                    if values.len() <= position {
+                        values.resize(position + 1, String::new());
+                    }
+                    values[position] = value.to_string();
+                }
+                values
+            }
+            _ => {
+                return Err(EdifactError::new(format!(
+                    "{} in {} must be a string, null or an object of components",
+                    name, tag
+                )))
+            }
+        };
+    }
+    Ok(Segment::new(tag.to_string(), elements, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const SEGMENTS: &str = "BGM Beginning of message
+  C002 C Document/message name
+    1001 C an..3 Document name code
+    1131 C an..17 Code list identification code
+    3055 C an..3 Code list responsible agency code
+    1000 C an..35 Document name
+  1004 C an..35 Document identifier
+  1225 C an..3 Message function code
+DTM Date/time/period
+  C507 M Date/time/period
+    2005 M an..3 Date or time or period function code qualifier
+    2380 C an..35 Date or time or period value
+    2379 C an..3 Date or time or period format code
+NAD Name and address
+  3035 M an..3 Party function code qualifier
+  C082 C Party identification details
+    3039 M an..35 Party identifier
+    1131 C an..17 Code list identification code
+    3055 C an..3 Code list responsible agency code
+  C058 C Name and address
+    3124 M an..35 Name and address description
+    3124 C an..35 Name and address description
+  C080 C Party name
+    3036 M an..35 Party name
+    3036 C an..35 Party name";
+
+    const ORDER: &str = "UNA:+,? '
+UNB+UNOC:3+SENDER+RECEIVER+240119:1200+REF1'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO1+9'
+DTM+137:20240301:102'
+NAD+BY+5412345000013::9++Buyer NV+Smith?+Sons'
+LIN+1++ITEM1:BP'
+UNT+6+1'
+UNZ+1+REF1'
+";
+
+    fn directory() -> Directory {
+        let mut directory = Directory::new();
+        directory.load_segment_definitions(SEGMENTS).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_compact_json_round_trip() {
+        Python::with_gil(|_py| {
+            let interchange = Interchange::from_edifact(ORDER).unwrap();
+            let dtm = &interchange.segments[3];
+            assert_eq!(
+                to_json(dtm, None).unwrap(),
+                r#"["DTM",["137","20240301","102"]]"#
+            );
+
+            let json = to_json(&interchange, None).unwrap();
+            assert!(json.starts_with(
+                r#"{"delimiters":{"component":":","data":"+","decimal":",","escape":"?","segment":"'","reserved":" "},"segments":[["UNB","#
+            ));
+            let read: Interchange = from_json(&json, None).unwrap();
+            assert_eq!(read.to_edifact(), interchange.to_edifact());
+            assert_eq!(read.syntax_version, SyntaxVersion::V3);
+            assert_eq!(read.transfers.len(), 1);
+
+            let message: Message = from_json(
+                r#"[["UNH",["1"],["ORDERS","D","96A","UN"]],["BGM",["220"]],["UNT",["3"],["1"]]]"#,
+                None,
+            )
+            .unwrap();
+            assert_eq!(message.segments[0].position, 1);
+            assert_eq!(message.service_segments["UNT"].position, 2);
+        });
+    }
+
+    #[test]
+    fn test_named_json() {
+        Python::with_gil(|_py| {
+            let directory = directory();
+            let interchange = Interchange::from_edifact(ORDER).unwrap();
+            assert_eq!(
+                to_json(&interchange.messages[0], Some(&directory)).unwrap(),
+                concat!(
+                    r#"{"ORDERS":["#,
+                    r#"{"UNH":{"0062":"1","S009":{"0065":"ORDERS","0052":"D","0054":"96A","0051":"UN"}}},"#,
+                    r#"{"BGM":{"C002":{"1001":"220"},"1004":"PO1","1225":"9"}},"#,
+                    r#"{"DTM":{"C507":{"2005":"137","2380":"20240301","2379":"102"}}},"#,
+                    r#"{"SG2":[{"NAD":{"3035":"BY","C082":{"3039":"5412345000013","1131":"","3055":"9"},"#,
+                    r#""C058":null,"C080":{"3036":"Buyer NV"},"e5":"Smith+Sons"}}]},"#,
+                    r#"{"SG25":[{"LIN":{"e1":"1","e2":null,"e3":{"c1":"ITEM1","c2":"BP"}}}]},"#,
+                    r#"{"UNT":{"0074":"6","0062":"1"}}]}"#,
+                )
+            );
+
+            let json = to_json(&interchange, Some(&directory)).unwrap();
+            let read: Interchange = from_json(&json, Some(&directory)).unwrap();
+            assert_eq!(read.to_edifact(), interchange.to_edifact());
+            assert_eq!(read.messages[0].segments.len(), 4);
+            // Without the segment definitions BGM has positional names only
+            assert!(from_json::<Interchange>(&json, None).is_err());
+
+            // Elements and components left out are empty
+            let segment: Segment = from_json(
+                r#"{"NAD":{"3035":"SU","C080":{"3036_2":"Ltd"}}}"#,
+                Some(&directory),
+            )
+            .unwrap();
+            assert_eq!(segment.to_edifact(&Delimiters::default()), "NAD+SU+++:Ltd'");
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 #[allow(unused_imports)]
 use pyo3::types::{PyDict, PyList};
@@ -5,12 +6,85 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 
+mod aperak;
+mod builder;
+mod common;
+mod container;
+mod contrl;
+mod customs;
+mod desadv;
+mod directory;
+mod document;
+mod header;
+mod interchange;
+mod invoic;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "serde")]
+mod mig;
+mod ordchg;
+mod ordrsp;
+mod payment;
+mod pricat;
+mod recadv;
+mod remadv;
+mod report;
+mod service;
+mod syntax;
+mod transport;
+mod validation;
+mod xml;
+
+pub use aperak::{ApplicationAcknowledgement, ApplicationAcknowledgementBuilder, ApplicationError};
+pub use builder::MessageBuilder;
+pub use common::{
+    AllowanceCharge, Communication, Contact, Currency, DateTimePeriod, Discrepancy,
+    ItemDescription, ItemNumber, Location, Measurement, MonetaryAmount, Party, PaymentTerms, Price,
+    ProductIdentification, Quantity, Reference, Tax, Transport,
+};
+pub use container::{Container, ContainerMessage, ContainerMessageBuilder, StowagePosition};
+pub use contrl::{ControlAction, ControlError, ControlReport, MessageResponse};
+pub use customs::{
+    CustomsDeclaration, CustomsDeclarationBuilder, CustomsError, CustomsGoodsItem, CustomsResponse,
+};
+pub use desadv::{DespatchAdvice, DespatchAdviceBuilder, Package, Packaging};
+pub use directory::{Directory, SegmentGroup};
+pub use header::{InterchangeHeader, InterchangeParty, MessageHeader};
+pub use interchange::Interchange;
+pub use invoic::{Invoice, InvoiceBuilder};
+#[cfg(feature = "serde")]
+pub use mig::Mig;
+pub use ordchg::OrderChange;
+pub use ordrsp::{LineAction, LineDecision, OrderResponse};
+pub use payment::{Payment, PaymentOrder, PaymentOrderBuilder};
+pub use pricat::{PriceCatalogue, ProductGroup, Products};
+pub use recadv::{ReceiptDifference, ReceiptLine, ReceivingAdvice};
+pub use remadv::{
+    PaymentAdjustment, PaymentMatch, PaymentStatus, RemittanceAdvice, RemittanceLine,
+};
+pub use report::{InventoryReport, ReportRecord, SalesReport};
+pub use syntax::SyntaxVersion;
+pub use transport::{
+    Consignment, Equipment, GoodsItem, StatusEvent, TransportInstruction, TransportStatus,
+};
+pub use validation::ValidationIssue;
+
+use directory::{MessageDefinition, Node};
+use document::segment_of;
+
 #[derive(Debug)]
-#[allow(dead_code)]
-struct EdifactError {
+pub struct EdifactError {
     message: String,
 }
 
+impl EdifactError {
+    fn new(message: impl Into<String>) -> Self {
+        EdifactError {
+            message: message.into(),
+        }
+    }
+}
+
 impl fmt::Display for EdifactError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "EDIFACT Error: {}", self.message)
@@ -19,8 +93,15 @@ impl fmt::Display for EdifactError {
 
 impl Error for EdifactError {}
 
+impl From<EdifactError> for PyErr {
+    fn from(err: EdifactError) -> PyErr {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
 #[pyclass]
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Delimiters {
     component: char,
     data: char,
@@ -30,6 +111,15 @@ struct Delimiters {
     reserved: char,
 }
 
+impl Delimiters {
+    fn to_una(&self) -> String {
+        format!(
+            "UNA{}{}{}{}{}{}",
+            self.component, self.data, self.decimal, self.escape, self.reserved, self.segment
+        )
+    }
+}
+
 impl Default for Delimiters {
     fn default() -> Self {
         Delimiters {
@@ -79,6 +169,22 @@ impl Segment {
         format!("{}: {:?}", self.tag, self.elements)
     }
 
+    /// The segment as JSON: `["DTM", ["137", "20240301", "102"]]`, or with a directory,
+    /// `{"DTM": {"C507": {"2005": "137", "2380": "20240301", "2379": "102"}}}`.
+    #[cfg(feature = "serde")]
+    #[pyo3(signature = (directory=None))]
+    fn to_json(&self, directory: Option<&Directory>) -> PyResult<String> {
+        Ok(json::to_json(self, directory)?)
+    }
+
+    /// Reads a segment written by `to_json` in either shape.
+    #[cfg(feature = "serde")]
+    #[staticmethod]
+    #[pyo3(signature = (content, directory=None))]
+    fn from_json(content: &str, directory: Option<&Directory>) -> PyResult<Segment> {
+        Ok(json::from_json(content, directory)?)
+    }
+
     fn to_edifact(&self, delimiters: &Delimiters) -> String {
         let mut result = self.tag.clone();
 
@@ -89,15 +195,16 @@ impl Segment {
                 if i > 0 {
                     result.push(delimiters.component);
                 }
-                // Escape special characters
+                // Escape service characters; the decimal mark is data, and a space in the
+                // reserved position means there is no repetition separator to escape
                 let escaped = component
                     .chars()
                     .map(|c| {
                         if c == delimiters.data
                             || c == delimiters.component
-                            || c == delimiters.decimal
                             || c == delimiters.segment
-                            || c == delimiters.reserved
+                            || c == delimiters.escape
+                            || (c == delimiters.reserved && delimiters.reserved != ' ')
                         {
                             format!("{}{}", delimiters.escape, c)
                         } else {
@@ -118,6 +225,7 @@ impl Segment {
 #[derive(Debug, Clone)]
 struct Parser {
     delimiters: Delimiters,
+    syntax_version: SyntaxVersion,
 }
 
 #[pymethods]
@@ -126,9 +234,15 @@ impl Parser {
     fn new() -> Self {
         Parser {
             delimiters: Delimiters::default(),
+            syntax_version: SyntaxVersion::default(),
         }
     }
 
+    #[getter]
+    fn syntax_version(&self) -> SyntaxVersion {
+        self.syntax_version
+    }
+
     fn set_delimiters(&mut self, una_segment: &str) -> PyResult<()> {
         if una_segment.len() >= 9 && una_segment.starts_with("UNA") {
             self.delimiters = Delimiters {
@@ -225,11 +339,86 @@ impl Parser {
 
         Ok(Segment::new(tag, elements, position))
     }
+
+    fn parse(&mut self, content: &str) -> PyResult<Vec<Segment>> {
+        let (content, has_una) = self.read_una(content)?;
+
+        let segments = self
+            .split_segments(content)
+            .into_iter()
+            .enumerate()
+            .map(|(position, segment)| self.parse_segment(segment, position))
+            .collect::<PyResult<Vec<Segment>>>()?;
+
+        if let Some(unb) = segments.iter().find(|s| s.tag == "UNB") {
+            self.detect_syntax_version(unb, has_una);
+        }
+        Ok(segments)
+    }
+}
+
+impl Parser {
+    // Adopts the syntax version declared in UNB; without a UNA the version's default
+    // service characters apply
+    fn detect_syntax_version(&mut self, interchange_header: &Segment, has_una: bool) {
+        if let Some(version) = SyntaxVersion::detect(interchange_header) {
+            self.syntax_version = version;
+            if !has_una {
+                self.delimiters = version.default_delimiters();
+            }
+        }
+    }
+
+    // Adopts the delimiters of a leading UNA, which is exactly nine characters and ends with
+    // the segment terminator itself, and returns the content following it
+    fn read_una<'a>(&mut self, content: &'a str) -> PyResult<(&'a str, bool)> {
+        let content = content.trim_start();
+        if !content.starts_with("UNA") {
+            return Ok((content, false));
+        }
+        let una: String = content.chars().take(9).collect();
+        self.set_delimiters(&una)?;
+        Ok((&content[una.len()..], true))
+    }
+
+    // Splits on unescaped segment terminators, dropping line breaks between segments
+    fn split_segments<'a>(&self, content: &'a str) -> Vec<&'a str> {
+        let mut offset = 0;
+        std::iter::from_fn(|| self.next_segment(content, &mut offset)).collect()
+    }
+
+    /// Reads the segment starting at `offset` and moves `offset` past it, so large messages
+    /// can be processed one segment at a time.
+    pub(crate) fn next_segment<'a>(&self, content: &'a str, offset: &mut usize) -> Option<&'a str> {
+        while *offset < content.len() {
+            let start = *offset;
+            let mut end = content.len();
+            let mut is_escaped = false;
+            for (index, c) in content[start..].char_indices() {
+                if is_escaped {
+                    is_escaped = false;
+                } else if c == self.delimiters.escape {
+                    is_escaped = true;
+                } else if c == self.delimiters.segment {
+                    end = start + index + c.len_utf8();
+                    break;
+                }
+            }
+            *offset = end;
+
+            let segment = content[start..end].trim_start_matches(['\r', '\n']);
+            if !segment.trim().is_empty() {
+                return Some(segment);
+            }
+        }
+        None
+    }
 }
 
 #[pyclass]
-#[allow(dead_code)]
-struct Message {
+#[derive(Debug, Clone)]
+pub struct Message {
+    #[pyo3(get)]
     segments: Vec<Segment>,
     service_segments: HashMap<String, Segment>,
 }
@@ -251,11 +440,118 @@ impl Message {
             .cloned()
             .collect()
     }
+
+    fn get_service_segment(&self, tag: &str) -> Option<Segment> {
+        self.service_segments.get(tag).cloned()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.service_segments
+            .get("UNH")
+            .map(MessageHeader::from_segment)
+            .transpose()
+    }
+
+    /// The message as JSON: the list of its segments, or with a directory, the segments
+    /// with named elements arranged into the segment groups of the message structure.
+    #[cfg(feature = "serde")]
+    #[pyo3(signature = (directory=None))]
+    fn to_json(&self, directory: Option<&Directory>) -> PyResult<String> {
+        Ok(json::to_json(self, directory)?)
+    }
+
+    /// Reads a message written by `to_json` in either shape.
+    #[cfg(feature = "serde")]
+    #[staticmethod]
+    #[pyo3(signature = (content, directory=None))]
+    fn from_json(content: &str, directory: Option<&Directory>) -> PyResult<Message> {
+        Ok(json::from_json(content, directory)?)
+    }
+
+    /// The message as XML, with the body arranged into segment groups and elements named
+    /// after the directory when one is given, and positional names otherwise.
+    #[pyo3(signature = (directory=None))]
+    fn to_xml(&self, directory: Option<&Directory>) -> PyResult<String> {
+        Ok(xml::message_to_xml(self, directory)?)
+    }
+
+    /// Reads a message written by `to_xml`, with or without a directory.
+    #[staticmethod]
+    #[pyo3(signature = (content, directory=None))]
+    fn from_xml(content: &str, directory: Option<&Directory>) -> PyResult<Message> {
+        Ok(xml::message_from_xml(content, directory)?)
+    }
+}
+
+// Service segments before and after the body of a message
+const LEADING_SERVICE_SEGMENTS: &[&str] = &["UNB", "UNG", "UNH"];
+const TRAILING_SERVICE_SEGMENTS: &[&str] = &["UNT", "UNE", "UNZ"];
+
+impl Message {
+    /// Splits segments read back from another rendering into a message, numbering them in
+    /// order.
+    pub(crate) fn from_segments(segments: Vec<Segment>) -> Message {
+        let mut message = Message::new();
+        for (position, mut segment) in segments.into_iter().enumerate() {
+            segment.position = position;
+            if directory::is_service_segment(&segment.tag) {
+                message
+                    .service_segments
+                    .insert(segment.tag.clone(), segment);
+            } else {
+                message.segments.push(segment);
+            }
+        }
+        message
+    }
+
+    /// Every segment of the message in document order, service segments included.
+    #[cfg(any(feature = "serde", test))]
+    pub(crate) fn all_segments(&self) -> impl Iterator<Item = &Segment> {
+        let service = |tags: &'static [&'static str]| {
+            tags.iter()
+                .filter_map(|tag| self.service_segments.get(*tag))
+        };
+        service(LEADING_SERVICE_SEGMENTS)
+            .chain(&self.segments)
+            .chain(service(TRAILING_SERVICE_SEGMENTS))
+    }
+
+    /// The message as a group named after its type: its service segments around the body,
+    /// which is arranged by the message structure when the directory has one.
+    pub(crate) fn arranged(&self, directory: Option<&Directory>) -> SegmentGroup {
+        let header = self.service_segments.get("UNH");
+        let message_type = header.and_then(|h| h.get_component(1, 0));
+        let release = header
+            .and_then(|h| h.get_component(1, 2))
+            .map(String::as_str);
+        let definition = message_type
+            .zip(directory)
+            .and_then(|(message_type, directory)| directory.message(message_type, release));
+        let body = match definition {
+            Some(definition) => definition.build_tree(&self.segments).0.nodes,
+            None => self.segments.iter().cloned().map(Node::Segment).collect(),
+        };
+        let service = |tags: &'static [&'static str]| {
+            tags.iter()
+                .filter_map(|tag| self.service_segments.get(*tag).cloned())
+                .map(Node::Segment)
+        };
+        SegmentGroup {
+            name: message_type
+                .cloned()
+                .unwrap_or_else(|| "MESSAGE".to_string()),
+            nodes: service(LEADING_SERVICE_SEGMENTS)
+                .chain(body)
+                .chain(service(TRAILING_SERVICE_SEGMENTS))
+                .collect(),
+        }
+    }
 }
 
 #[pyclass]
 #[derive(Debug, Clone)]
-struct Order {
+pub struct Order {
     #[pyo3(get)]
     segments: Vec<Segment>,
     #[pyo3(get)]
@@ -296,7 +592,12 @@ impl Order {
             let segment = order.parser.parse_segment(line, position)?;
 
             match segment.tag.as_str() {
-                "UNB" => order.interchange_header = Some(segment.clone()),
+                "UNB" => {
+                    order
+                        .parser
+                        .detect_syntax_version(&segment, content.starts_with("UNA"));
+                    order.interchange_header = Some(segment.clone())
+                }
                 "UNH" => order.message_header = Some(segment.clone()),
                 _ => order.segments.push(segment),
             }
@@ -320,47 +621,82 @@ impl Order {
     }
 
     fn get_order_lines(&self) -> PyResult<Vec<OrderLine>> {
-        let mut lines = Vec::new();
-        let mut current_line: Option<OrderLine> = None;
+        let lines = self
+            .tree()
+            .groups_by_trigger("LIN")
+            .map(|group| OrderLine::from_group(group.clone()))
+            .collect();
+        Ok(OrderLine::nest(lines))
+    }
 
-        for segment in &self.segments {
-            match segment.tag.as_str() {
-                "LIN" => {
-                    if let Some(line) = current_line {
-                        lines.push(line);
-                    }
-                    current_line = Some(OrderLine::new(segment.clone()));
-                }
-                "IMD" | "QTY" | "MOA" | "PRI" | "RFF" => {
-                    if let Some(ref mut line) = current_line {
-                        line.add_segment(segment.clone());
-                    }
-                }
-                _ => {}
-            }
-        }
+    /// The order number from BGM C106.
+    fn get_document_number(&self) -> Option<String> {
+        self.get_segment("BGM")
+            .and_then(|bgm| header::component(&bgm, 1, 0))
+    }
 
-        if let Some(line) = current_line {
-            lines.push(line);
-        }
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        self.tree()
+            .segments_by_tag("DTM")
+            .map(DateTimePeriod::from_segment)
+            .collect()
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        self.tree()
+            .groups_by_trigger("RFF")
+            .filter_map(Reference::from_group)
+            .collect()
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        self.tree()
+            .groups_by_trigger("NAD")
+            .filter_map(Party::from_group)
+            .collect()
+    }
+
+    fn get_party(&self, qualifier: &str) -> Option<Party> {
+        self.get_parties()
+            .into_iter()
+            .find(|party| party.qualifier == qualifier)
+    }
 
-        Ok(lines)
+    fn get_currencies(&self) -> Vec<Currency> {
+        self.tree()
+            .groups_by_trigger("CUX")
+            .filter_map(Currency::from_group)
+            .collect()
+    }
+
+    fn get_payment_terms(&self) -> Vec<PaymentTerms> {
+        self.tree()
+            .groups_by_trigger("PAT")
+            .filter_map(PaymentTerms::from_group)
+            .collect()
+    }
+
+    fn get_transport(&self) -> Vec<Transport> {
+        self.tree()
+            .groups_by_trigger("TDT")
+            .filter_map(Transport::from_group)
+            .collect()
+    }
+
+    fn get_allowances_charges(&self) -> Vec<AllowanceCharge> {
+        self.tree()
+            .groups_by_trigger("ALC")
+            .filter_map(AllowanceCharge::from_group)
+            .collect()
     }
 
-    fn to_edifact(&self) -> PyResult<String> {
+    pub fn to_edifact(&self) -> PyResult<String> {
         let mut result = String::new();
 
-        // Add UNA segment if using non-default delimiters
-        if self.parser.delimiters != Delimiters::default() {
-            result.push_str(&format!(
-                "UNA{}{}{}{}{}{}\n",
-                self.parser.delimiters.component,
-                self.parser.delimiters.data,
-                self.parser.delimiters.decimal,
-                self.parser.delimiters.escape,
-                self.parser.delimiters.reserved,
-                self.parser.delimiters.segment
-            ));
+        // Add UNA segment if the delimiters differ from the syntax version's defaults
+        if self.parser.delimiters != self.parser.syntax_version.default_delimiters() {
+            result.push_str(&self.parser.delimiters.to_una());
+            result.push('\n');
         }
 
         // Add interchange header if present
@@ -384,6 +720,33 @@ impl Order {
         Ok(result)
     }
 
+    #[getter]
+    fn syntax_version(&self) -> SyntaxVersion {
+        self.parser.syntax_version
+    }
+
+    fn get_interchange_header(&self) -> PyResult<Option<InterchangeHeader>> {
+        self.interchange_header
+            .as_ref()
+            .map(InterchangeHeader::from_segment)
+            .transpose()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.message_header
+            .as_ref()
+            .map(MessageHeader::from_segment)
+            .transpose()
+    }
+
+    fn validate_service_segments(&self) -> Vec<ValidationIssue> {
+        service::validate_envelope(
+            self.interchange_header.as_ref(),
+            self.message_header.as_ref(),
+            &self.segments,
+        )
+    }
+
     fn create_segment(&self, tag: &str, elements: Vec<Vec<String>>) -> PyResult<Segment> {
         Ok(Segment::new(tag.to_string(), elements, self.segments.len()))
     }
@@ -393,91 +756,853 @@ impl Order {
     }
 }
 
+impl Order {
+    // Arranges the message body into segment groups using the built-in ORDERS structure for
+    // the release named in UNH
+    fn tree(&self) -> SegmentGroup {
+        let release = self
+            .message_header
+            .as_ref()
+            .and_then(|header| header.get_component(1, 2))
+            .map(String::as_str);
+        Directory::builtin()
+            .message("ORDERS", release)
+            .expect("built-in ORDERS definition")
+            .build_tree(&self.segments)
+            .0
+    }
+}
+
+/// A line item (the LIN segment group) with all of its child segments and groups. Sub-lines,
+/// which name their parent line in LIN C829, are nested under that parent.
 #[pyclass]
+#[derive(Debug, Clone)]
 struct OrderLine {
+    group: SegmentGroup,
     #[pyo3(get)]
-    line_segment: Segment,
-    #[pyo3(get)]
-    description: Option<Segment>,
-    #[pyo3(get)]
-    quantity: Option<Segment>,
-    #[pyo3(get)]
-    amount: Option<Segment>,
-    #[pyo3(get)]
-    price: Option<Segment>,
-    #[pyo3(get)]
-    reference: Option<Segment>,
+    sub_lines: Vec<OrderLine>,
 }
 
 #[pymethods]
 impl OrderLine {
     #[new]
-    fn new(line_segment: Segment) -> Self {
-        OrderLine {
-            line_segment,
-            description: None,
-            quantity: None,
-            amount: None,
-            price: None,
-            reference: None,
+    fn new(line_segment: Segment) -> PyResult<Self> {
+        if line_segment.tag != "LIN" {
+            return Err(EdifactError::new(format!(
+                "An order line starts with LIN, not {}",
+                line_segment.tag
+            ))
+            .into());
         }
+        Ok(OrderLine::from_group(SegmentGroup::flat(
+            "LIN",
+            vec![line_segment],
+        )))
     }
 
     fn add_segment(&mut self, segment: Segment) {
-        match segment.tag.as_str() {
-            "IMD" => self.description = Some(segment),
-            "QTY" => self.quantity = Some(segment),
-            "MOA" => self.amount = Some(segment),
-            "PRI" => self.price = Some(segment),
-            "RFF" => self.reference = Some(segment),
-            _ => {}
+        self.group.push_segment(segment);
+    }
+
+    #[getter]
+    fn line_segment(&self) -> Segment {
+        self.line().clone()
+    }
+
+    #[getter]
+    fn description(&self) -> Option<Segment> {
+        self.first("IMD")
+    }
+
+    #[getter]
+    fn quantity(&self) -> Option<Segment> {
+        self.first("QTY")
+    }
+
+    #[getter]
+    fn amount(&self) -> Option<Segment> {
+        self.first("MOA")
+    }
+
+    #[getter]
+    fn price(&self) -> Option<Segment> {
+        self.first("PRI")
+    }
+
+    #[getter]
+    fn reference(&self) -> Option<Segment> {
+        self.first("RFF")
+    }
+
+    #[getter]
+    fn line_number(&self) -> Option<String> {
+        header::component(self.line(), 0, 0)
+    }
+
+    /// LIN 1229 action request/notification code, e.g. 1 (added) or 3 (changed)
+    #[getter]
+    fn action(&self) -> Option<String> {
+        header::component(self.line(), 1, 0)
+    }
+
+    #[getter]
+    fn line_action(&self) -> Option<LineAction> {
+        self.action().as_deref().and_then(LineAction::from_code)
+    }
+
+    #[getter]
+    fn item_number(&self) -> Option<ItemNumber> {
+        ItemNumber::from_element(self.line(), 2)
+    }
+
+    #[getter]
+    fn sub_line_indicator(&self) -> Option<String> {
+        header::component(self.line(), 3, 0)
+    }
+
+    #[getter]
+    fn parent_line_number(&self) -> Option<String> {
+        header::component(self.line(), 3, 1)
+    }
+
+    #[getter]
+    fn product_ids(&self) -> Vec<ProductIdentification> {
+        self.group
+            .segments_by_tag("PIA")
+            .map(ProductIdentification::from_segment)
+            .collect()
+    }
+
+    #[getter]
+    fn descriptions(&self) -> Vec<ItemDescription> {
+        self.group
+            .segments_by_tag("IMD")
+            .map(ItemDescription::from_segment)
+            .collect()
+    }
+
+    #[getter]
+    fn quantities(&self) -> Vec<Quantity> {
+        self.group
+            .segments_by_tag("QTY")
+            .map(Quantity::from_segment)
+            .collect()
+    }
+
+    #[getter]
+    fn discrepancies(&self) -> Vec<Discrepancy> {
+        self.group
+            .groups_by_trigger("QVR")
+            .filter_map(Discrepancy::from_group)
+            .collect()
+    }
+
+    #[getter]
+    fn measurements(&self) -> Vec<Measurement> {
+        self.group
+            .segments_by_tag("MEA")
+            .map(Measurement::from_segment)
+            .collect()
+    }
+
+    #[getter]
+    fn dates(&self) -> Vec<DateTimePeriod> {
+        self.group
+            .segments_by_tag("DTM")
+            .map(DateTimePeriod::from_segment)
+            .collect()
+    }
+
+    #[getter]
+    fn amounts(&self) -> Vec<MonetaryAmount> {
+        self.group
+            .segments_by_tag("MOA")
+            .chain(
+                self.group
+                    .groups_by_trigger("MOA")
+                    .filter_map(|g| g.find_segment("MOA")),
+            )
+            .map(MonetaryAmount::from_segment)
+            .collect()
+    }
+
+    #[getter]
+    fn taxes(&self) -> Vec<Tax> {
+        self.group
+            .groups_by_trigger("TAX")
+            .filter_map(Tax::from_group)
+            .collect()
+    }
+
+    #[getter]
+    fn prices(&self) -> Vec<Price> {
+        self.group
+            .segments_by_tag("PRI")
+            .chain(
+                self.group
+                    .groups_by_trigger("PRI")
+                    .filter_map(|g| g.find_segment("PRI")),
+            )
+            .map(Price::from_segment)
+            .collect()
+    }
+
+    #[getter]
+    fn references(&self) -> Vec<Reference> {
+        self.group
+            .segments_by_tag("RFF")
+            .map(Reference::from_segment)
+            .chain(
+                self.group
+                    .groups_by_trigger("RFF")
+                    .filter_map(Reference::from_group),
+            )
+            .collect()
+    }
+
+    #[getter]
+    fn locations(&self) -> Vec<Location> {
+        self.group
+            .segments_by_tag("LOC")
+            .map(Location::from_segment)
+            .chain(
+                self.group
+                    .groups_by_trigger("LOC")
+                    .filter_map(Location::from_group),
+            )
+            .collect()
+    }
+
+    #[getter]
+    fn parties(&self) -> Vec<Party> {
+        self.group
+            .groups_by_trigger("NAD")
+            .filter_map(Party::from_group)
+            .collect()
+    }
+
+    #[getter]
+    fn allowances_charges(&self) -> Vec<AllowanceCharge> {
+        self.group
+            .groups_by_trigger("ALC")
+            .filter_map(AllowanceCharge::from_group)
+            .collect()
+    }
+
+    /// Every segment of the line in document order, excluding its sub-lines.
+    fn segments(&self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        self.group
+            .walk(&mut |_, segment| segments.push(segment.clone()));
+        segments
+    }
+}
+
+impl OrderLine {
+    fn from_group(group: SegmentGroup) -> Self {
+        OrderLine {
+            group,
+            sub_lines: Vec::new(),
+        }
+    }
+
+    fn line(&self) -> &Segment {
+        self.group
+            .find_segment("LIN")
+            .expect("line group starts with LIN")
+    }
+
+    // The first segment with the tag anywhere in the line, nested groups included
+    fn first(&self, tag: &str) -> Option<Segment> {
+        let mut found = None;
+        self.group.walk(&mut |_, segment| {
+            if found.is_none() && segment.tag == tag {
+                found = Some(segment.clone());
+            }
+        });
+        found
+    }
+
+    // Nests each sub-line under the line named in its C829, when that line precedes it
+    fn nest(lines: Vec<OrderLine>) -> Vec<OrderLine> {
+        let mut nested: Vec<OrderLine> = Vec::new();
+        for line in lines {
+            let parent = line
+                .parent_line_number()
+                .and_then(|number| OrderLine::find_mut(&mut nested, &number));
+            match parent {
+                Some(parent) => parent.sub_lines.push(line),
+                None => nested.push(line),
+            }
+        }
+        nested
+    }
+
+    fn find_mut<'a>(lines: &'a mut [OrderLine], number: &str) -> Option<&'a mut OrderLine> {
+        for line in lines {
+            if line.line_number().as_deref() == Some(number) {
+                return Some(line);
+            }
+            if let Some(found) = OrderLine::find_mut(&mut line.sub_lines, number) {
+                return Some(found);
+            }
         }
+        None
     }
 }
 
 // Add builder patterns for creating EDIFACT messages
+/// Builds an ORDERS message. Header sections and line details can be added in any order:
+/// they are arranged in the order of the ORDERS segment groups, and every addition is
+/// checked against that structure, so a segment the message has no room for is rejected
+/// when it is added. Every method changes the builder in place and returns it.
 #[pyclass]
 #[derive(Debug, Clone)]
 pub struct OrderBuilder {
     order: Order,
+    syntax_identifier: String,
+    // Header entries and the entries of every line, each a segment with the segments it
+    // carries in its group, in the order they were added
+    header: Vec<Vec<Segment>>,
+    lines: Vec<Vec<Vec<Segment>>>,
 }
 
 #[pymethods]
 impl OrderBuilder {
     #[new]
-    fn new() -> Self {
+    pub fn new() -> Self {
         OrderBuilder {
             order: Order::new(),
+            syntax_identifier: "UNOA".to_string(),
+            header: Vec::new(),
+            lines: Vec::new(),
         }
     }
 
-    fn with_interchange_header(
+    fn with_syntax<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        syntax_identifier: &str,
+        syntax_version: SyntaxVersion,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.set_syntax(syntax_identifier, syntax_version)?;
+        Ok(slf)
+    }
+
+    fn with_interchange_header<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        sender: &str,
+        recipient: &str,
+        date: &str,
+        control_ref: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.set_interchange_header(sender, recipient, date, control_ref)?;
+        Ok(slf)
+    }
+
+    fn with_interchange<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        header: InterchangeHeader,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.set_interchange(header)?;
+        Ok(slf)
+    }
+
+    fn with_message_header<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        message_ref: &str,
+        message_type: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.set_message_header(message_ref, message_type)?;
+        Ok(slf)
+    }
+
+    fn with_bgm<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        message_name: &str,
+        doc_number: &str,
+        message_function: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_bgm(message_name, doc_number, message_function)?;
+        Ok(slf)
+    }
+
+    /// Adds a header date, checking the value against its format code, e.g. 102 (CCYYMMDD),
+    /// 203 (CCYYMMDDHHMM) or 718 (a CCYYMMDD-CCYYMMDD period).
+    #[pyo3(signature = (qualifier, value, format="102"))]
+    fn with_date<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_date(qualifier, value, format)?;
+        Ok(slf)
+    }
+
+    fn with_reference<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        number: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_reference(qualifier, number)?;
+        Ok(slf)
+    }
+
+    /// Adds a party (NAD) identified by code, by its structured name and address, or both.
+    /// The country is an ISO 3166 two-letter code.
+    #[pyo3(signature = (
+        qualifier,
+        id=None,
+        code_list_agency="9",
+        name=None,
+        street=None,
+        city=None,
+        postal_code=None,
+        country=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn with_party<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        id: Option<&str>,
+        code_list_agency: &str,
+        name: Option<&str>,
+        street: Option<&str>,
+        city: Option<&str>,
+        postal_code: Option<&str>,
+        country: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let address = Address {
+            name,
+            street,
+            city,
+            postal_code,
+            country,
+        };
+        slf.add_party(qualifier, id, code_list_agency, &address)?;
+        Ok(slf)
+    }
+
+    /// Adds a contact (CTA) with its phone number and e-mail address (COM) to the party
+    /// added last.
+    #[pyo3(signature = (function="IC", name=None, phone=None, email=None))]
+    fn with_contact<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        function: &str,
+        name: Option<&str>,
+        phone: Option<&str>,
+        email: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_contact(function, name, phone, email)?;
+        Ok(slf)
+    }
+
+    /// Adds a currency (CUX), by default the order currency (usage 2, qualifier 9).
+    #[pyo3(signature = (currency, usage="2", qualifier="9"))]
+    fn with_currency<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        currency: &str,
+        usage: &str,
+        qualifier: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_currency(currency, usage, qualifier)?;
+        Ok(slf)
+    }
+
+    /// Adds payment terms (PAT), e.g. 30 days (period type D) after (relation 3) the invoice
+    /// date (reference 5).
+    #[pyo3(signature = (
+        type_qualifier="1",
+        terms_id=None,
+        periods=None,
+        period_type="D",
+        reference="5",
+        relation="3",
+    ))]
+    fn with_payment_terms<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        type_qualifier: &str,
+        terms_id: Option<&str>,
+        periods: Option<&str>,
+        period_type: &str,
+        reference: &str,
+        relation: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let time = periods.map(|periods| [reference, relation, period_type, periods]);
+        slf.add_payment_terms(type_qualifier, terms_id, time)?;
+        Ok(slf)
+    }
+
+    /// Adds a means of transport (TDT), by default the main carriage (stage 20), with its
+    /// mode code, e.g. 30 for road transport, and carrier.
+    #[pyo3(signature = (stage="20", mode=None, conveyance_reference=None, carrier=None))]
+    fn with_transport<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        stage: &str,
+        mode: Option<&str>,
+        conveyance_reference: Option<&str>,
+        carrier: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_transport(stage, mode, conveyance_reference, carrier)?;
+        Ok(slf)
+    }
+
+    /// Adds an allowance (indicator A) or charge (indicator C) on the whole order, as a
+    /// percentage (PCD+3) and/or an amount (MOA+8).
+    #[pyo3(signature = (indicator, service_code=None, percentage=None, amount=None))]
+    fn with_allowance_charge<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        indicator: &str,
+        service_code: Option<&str>,
+        percentage: Option<&str>,
+        amount: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_allowance_charge(indicator, service_code, percentage, amount)?;
+        Ok(slf)
+    }
+
+    fn add_order_line<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        line_number: &str,
+        item_number: &str,
+        quantity: &str,
+        price: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_line(line_number, item_number, quantity, price)?;
+        Ok(slf)
+    }
+
+    /// Adds a free-text description (IMD, format F) to the line added last.
+    #[pyo3(signature = (description, format="F"))]
+    fn with_line_description<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        description: &str,
+        format: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_line_description(description, format)?;
+        Ok(slf)
+    }
+
+    /// Adds an additional product id (PIA) to the line added last, by default the
+    /// supplier's article number (SA) as additional identification (function 5).
+    #[pyo3(signature = (number, number_type="SA", function="5"))]
+    fn with_line_product_id<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        number: &str,
+        number_type: &str,
+        function: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_line_product_id(number, number_type, function)?;
+        Ok(slf)
+    }
+
+    /// Adds an amount (MOA) to the line added last, e.g. qualifier 203 for the line amount.
+    #[pyo3(signature = (qualifier, amount, currency=None))]
+    fn with_line_amount<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        amount: &str,
+        currency: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_line_amount(qualifier, amount, currency)?;
+        Ok(slf)
+    }
+
+    /// Adds a date to the line added last, e.g. DTM+2 for the requested delivery date.
+    #[pyo3(signature = (qualifier, value, format="102"))]
+    fn with_line_date<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_line_date(qualifier, value, format)?;
+        Ok(slf)
+    }
+
+    /// Adds a location (LOC) to the line added last, e.g. LOC+7 for the place of delivery,
+    /// with the quantity (QTY+11) to deliver there.
+    #[pyo3(signature = (qualifier, id, quantity=None))]
+    fn with_line_location<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        id: &str,
+        quantity: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.add_line_location(qualifier, id, quantity)?;
+        Ok(slf)
+    }
+
+    /// The order as built so far; the builder can carry on afterwards.
+    pub fn build(&self) -> Order {
+        let mut order = self.order.clone();
+        order.segments = self.arrange();
+        order
+    }
+}
+
+/// The name and address of a party, as written in NAD.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Address<'a> {
+    pub name: Option<&'a str>,
+    pub street: Option<&'a str>,
+    pub city: Option<&'a str>,
+    pub postal_code: Option<&'a str>,
+    pub country: Option<&'a str>,
+}
+
+impl Default for OrderBuilder {
+    fn default() -> Self {
+        OrderBuilder::new()
+    }
+}
+
+// The same builder for use from Rust, taking the builder by value and handing it back, so
+// calls chain with `?`
+impl OrderBuilder {
+    pub fn syntax(
+        mut self,
+        syntax_identifier: &str,
+        syntax_version: SyntaxVersion,
+    ) -> Result<Self, EdifactError> {
+        self.set_syntax(syntax_identifier, syntax_version)?;
+        Ok(self)
+    }
+
+    pub fn interchange_header(
+        mut self,
+        sender: &str,
+        recipient: &str,
+        date: &str,
+        control_ref: &str,
+    ) -> Result<Self, EdifactError> {
+        self.set_interchange_header(sender, recipient, date, control_ref)?;
+        Ok(self)
+    }
+
+    pub fn interchange(mut self, header: InterchangeHeader) -> Result<Self, EdifactError> {
+        self.set_interchange(header)?;
+        Ok(self)
+    }
+
+    pub fn message_header(
+        mut self,
+        message_ref: &str,
+        message_type: &str,
+    ) -> Result<Self, EdifactError> {
+        self.set_message_header(message_ref, message_type)?;
+        Ok(self)
+    }
+
+    pub fn bgm(
+        mut self,
+        message_name: &str,
+        doc_number: &str,
+        message_function: &str,
+    ) -> Result<Self, EdifactError> {
+        self.add_bgm(message_name, doc_number, message_function)?;
+        Ok(self)
+    }
+
+    pub fn date(
+        mut self,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> Result<Self, EdifactError> {
+        self.add_date(qualifier, value, format)?;
+        Ok(self)
+    }
+
+    pub fn reference(mut self, qualifier: &str, number: &str) -> Result<Self, EdifactError> {
+        self.add_reference(qualifier, number)?;
+        Ok(self)
+    }
+
+    pub fn party(
+        mut self,
+        qualifier: &str,
+        id: Option<&str>,
+        code_list_agency: &str,
+        address: &Address,
+    ) -> Result<Self, EdifactError> {
+        self.add_party(qualifier, id, code_list_agency, address)?;
+        Ok(self)
+    }
+
+    pub fn contact(
+        mut self,
+        function: &str,
+        name: Option<&str>,
+        phone: Option<&str>,
+        email: Option<&str>,
+    ) -> Result<Self, EdifactError> {
+        self.add_contact(function, name, phone, email)?;
+        Ok(self)
+    }
+
+    pub fn currency(
+        mut self,
+        currency: &str,
+        usage: &str,
+        qualifier: &str,
+    ) -> Result<Self, EdifactError> {
+        self.add_currency(currency, usage, qualifier)?;
+        Ok(self)
+    }
+
+    /// The time is the reference, relation, period type and number of periods of C112.
+    pub fn payment_terms(
+        mut self,
+        type_qualifier: &str,
+        terms_id: Option<&str>,
+        time: Option<[&str; 4]>,
+    ) -> Result<Self, EdifactError> {
+        self.add_payment_terms(type_qualifier, terms_id, time)?;
+        Ok(self)
+    }
+
+    pub fn transport(
+        mut self,
+        stage: &str,
+        mode: Option<&str>,
+        conveyance_reference: Option<&str>,
+        carrier: Option<&str>,
+    ) -> Result<Self, EdifactError> {
+        self.add_transport(stage, mode, conveyance_reference, carrier)?;
+        Ok(self)
+    }
+
+    pub fn allowance_charge(
+        mut self,
+        indicator: &str,
+        service_code: Option<&str>,
+        percentage: Option<&str>,
+        amount: Option<&str>,
+    ) -> Result<Self, EdifactError> {
+        self.add_allowance_charge(indicator, service_code, percentage, amount)?;
+        Ok(self)
+    }
+
+    pub fn line(
+        mut self,
+        line_number: &str,
+        item_number: &str,
+        quantity: &str,
+        price: &str,
+    ) -> Result<Self, EdifactError> {
+        self.add_line(line_number, item_number, quantity, price)?;
+        Ok(self)
+    }
+
+    pub fn line_description(
+        mut self,
+        description: &str,
+        format: &str,
+    ) -> Result<Self, EdifactError> {
+        self.add_line_description(description, format)?;
+        Ok(self)
+    }
+
+    pub fn line_product_id(
+        mut self,
+        number: &str,
+        number_type: &str,
+        function: &str,
+    ) -> Result<Self, EdifactError> {
+        self.add_line_product_id(number, number_type, function)?;
+        Ok(self)
+    }
+
+    pub fn line_amount(
+        mut self,
+        qualifier: &str,
+        amount: &str,
+        currency: Option<&str>,
+    ) -> Result<Self, EdifactError> {
+        self.add_line_amount(qualifier, amount, currency)?;
+        Ok(self)
+    }
+
+    pub fn line_date(
+        mut self,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> Result<Self, EdifactError> {
+        self.add_line_date(qualifier, value, format)?;
+        Ok(self)
+    }
+
+    pub fn line_location(
+        mut self,
+        qualifier: &str,
+        id: &str,
+        quantity: Option<&str>,
+    ) -> Result<Self, EdifactError> {
+        self.add_line_location(qualifier, id, quantity)?;
+        Ok(self)
+    }
+}
+
+impl OrderBuilder {
+    fn set_syntax(
+        &mut self,
+        syntax_identifier: &str,
+        syntax_version: SyntaxVersion,
+    ) -> Result<(), EdifactError> {
+        if !syntax_version.allows_syntax_identifier(syntax_identifier) {
+            return Err(EdifactError::new(format!(
+                "Syntax identifier {} is not allowed in syntax version {}",
+                syntax_identifier,
+                syntax_version.number()
+            )));
+        }
+        self.syntax_identifier = syntax_identifier.to_string();
+        self.set_syntax_version(syntax_version);
+        Ok(())
+    }
+
+    fn set_interchange_header(
         &mut self,
         sender: &str,
         recipient: &str,
         date: &str,
         control_ref: &str,
-        py: Python,
-    ) -> PyResult<Py<OrderBuilder>> {
-        let elements = vec![
-            vec!["UNOA".to_string(), "4".to_string()],
-            vec![sender.to_string()],
-            vec![recipient.to_string()],
-            vec![date.to_string()],
-            vec![control_ref.to_string()],
-            vec!["ORDERS".to_string()],
-        ];
+    ) -> Result<(), EdifactError> {
+        let (date, time) = date.split_once(':').unwrap_or((date, ""));
+        let version = self.order.parser.syntax_version;
+        let prepared = header::parse_preparation_time(date, time, version).ok_or_else(|| {
+            EdifactError::new(format!("Invalid preparation date/time '{}:{}'", date, time))
+        })?;
+
+        let header = InterchangeHeader::new(
+            InterchangeParty::new(sender.to_string(), None, None, None),
+            InterchangeParty::new(recipient.to_string(), None, None, None),
+            control_ref.to_string(),
+            Some(prepared),
+            self.syntax_identifier.clone(),
+            version,
+            Some("ORDERS".to_string()),
+            false,
+        );
+        self.set_interchange(header)
+    }
 
-        self.order.interchange_header = Some(Segment::new("UNB".to_string(), elements, 0));
-        Py::new(py, self.clone())
+    fn set_interchange(&mut self, header: InterchangeHeader) -> Result<(), EdifactError> {
+        let segment = header.to_segment();
+        check_service_segment(&segment, header.syntax_version)?;
+        self.syntax_identifier = header.syntax_identifier.clone();
+        self.set_syntax_version(header.syntax_version);
+        self.order.interchange_header = Some(segment);
+        Ok(())
     }
 
-    fn with_message_header(
+    fn set_message_header(
         &mut self,
         message_ref: &str,
         message_type: &str,
-        py: Python,
-    ) -> PyResult<Py<OrderBuilder>> {
+    ) -> Result<(), EdifactError> {
         let elements = vec![
             vec![message_ref.to_string()],
             vec![
@@ -488,73 +1613,389 @@ impl OrderBuilder {
             ],
         ];
 
-        self.order.message_header = Some(Segment::new("UNH".to_string(), elements, 1));
-        Py::new(py, self.clone())
+        let header = Segment::new("UNH".to_string(), elements, 1);
+        check_service_segment(&header, self.order.parser.syntax_version)?;
+        self.order.message_header = Some(header);
+        Ok(())
+    }
+
+    // Switching version also switches to that version's default service characters
+    fn set_syntax_version(&mut self, version: SyntaxVersion) {
+        self.order.parser.syntax_version = version;
+        self.order.parser.delimiters = version.default_delimiters();
     }
 
-    fn with_bgm(
+    fn add_bgm(
         &mut self,
         message_name: &str,
         doc_number: &str,
         message_function: &str,
-        py: Python,
-    ) -> PyResult<Py<OrderBuilder>> {
-        let elements = vec![
-            vec![message_name.to_string()],
-            vec![doc_number.to_string()],
-            vec![message_function.to_string()],
-        ];
+    ) -> Result<(), EdifactError> {
+        let bgm = segment_of(
+            "BGM",
+            &[&[message_name], &[doc_number], &[message_function]],
+        );
+        self.add_to_header(vec![bgm])
+    }
 
-        self.order.add_segment(Segment::new(
-            "BGM".to_string(),
-            elements,
-            self.order.segments.len(),
+    fn add_date(&mut self, qualifier: &str, value: &str, format: &str) -> Result<(), EdifactError> {
+        DateTimePeriod::check(value, format)?;
+        self.add_to_header(vec![segment_of("DTM", &[&[qualifier, value, format]])])
+    }
+
+    fn add_reference(&mut self, qualifier: &str, number: &str) -> Result<(), EdifactError> {
+        self.add_to_header(vec![segment_of("RFF", &[&[qualifier, number]])])
+    }
+
+    fn add_party(
+        &mut self,
+        qualifier: &str,
+        id: Option<&str>,
+        code_list_agency: &str,
+        address: &Address,
+    ) -> Result<(), EdifactError> {
+        if let Some(country) = address.country {
+            check_code(country, 2, "country")?;
+        }
+        let party_id: &[&str] = match id {
+            Some(id) => &[id, "", code_list_agency],
+            None => &[],
+        };
+        let nad = segment_of(
+            "NAD",
+            &[
+                &[qualifier],
+                party_id,
+                &[],
+                &[address.name.unwrap_or_default()],
+                &[address.street.unwrap_or_default()],
+                &[address.city.unwrap_or_default()],
+                &[],
+                &[address.postal_code.unwrap_or_default()],
+                &[address.country.unwrap_or_default()],
+            ],
+        );
+        self.add_to_header(vec![nad])
+    }
+
+    fn add_contact(
+        &mut self,
+        function: &str,
+        name: Option<&str>,
+        phone: Option<&str>,
+        email: Option<&str>,
+    ) -> Result<(), EdifactError> {
+        let index = self
+            .header
+            .iter()
+            .rposition(|entry| entry[0].tag == "NAD")
+            .ok_or_else(|| EdifactError::new("A contact needs a party to belong to"))?;
+        let length = self.header[index].len();
+        let party = &mut self.header[index];
+        party.push(segment_of(
+            "CTA",
+            &[&[function], &["", name.unwrap_or_default()]],
         ));
-        Py::new(py, self.clone())
+        for (number, channel) in [(phone, "TE"), (email, "EM")] {
+            if let Some(number) = number {
+                party.push(segment_of("COM", &[&[number, channel]]));
+            }
+        }
+        let checked = self.check_header();
+        if checked.is_err() {
+            self.header[index].truncate(length);
+        }
+        checked
+    }
+
+    fn add_currency(
+        &mut self,
+        currency: &str,
+        usage: &str,
+        qualifier: &str,
+    ) -> Result<(), EdifactError> {
+        check_code(currency, 3, "currency")?;
+        self.add_to_header(vec![segment_of("CUX", &[&[usage, currency, qualifier]])])
+    }
+
+    fn add_payment_terms(
+        &mut self,
+        type_qualifier: &str,
+        terms_id: Option<&str>,
+        time: Option<[&str; 4]>,
+    ) -> Result<(), EdifactError> {
+        let pat = segment_of(
+            "PAT",
+            &[
+                &[type_qualifier],
+                &[terms_id.unwrap_or_default()],
+                time.as_ref().map_or(&[], |time| time.as_slice()),
+            ],
+        );
+        self.add_to_header(vec![pat])
+    }
+
+    fn add_transport(
+        &mut self,
+        stage: &str,
+        mode: Option<&str>,
+        conveyance_reference: Option<&str>,
+        carrier: Option<&str>,
+    ) -> Result<(), EdifactError> {
+        let tdt = segment_of(
+            "TDT",
+            &[
+                &[stage],
+                &[conveyance_reference.unwrap_or_default()],
+                &[mode.unwrap_or_default()],
+                &[],
+                &[carrier.unwrap_or_default()],
+            ],
+        );
+        self.add_to_header(vec![tdt])
     }
 
-    fn add_order_line(
+    fn add_allowance_charge(
+        &mut self,
+        indicator: &str,
+        service_code: Option<&str>,
+        percentage: Option<&str>,
+        amount: Option<&str>,
+    ) -> Result<(), EdifactError> {
+        if !matches!(indicator, "A" | "C") {
+            return Err(EdifactError::new(format!(
+                "Allowance or charge indicator must be A or C, not '{}'",
+                indicator
+            )));
+        }
+        let mut segments = vec![segment_of(
+            "ALC",
+            &[
+                &[indicator],
+                &[],
+                &[],
+                &[],
+                &[service_code.unwrap_or_default()],
+            ],
+        )];
+        if let Some(percentage) = percentage {
+            check_decimal(percentage, "percentage")?;
+            segments.push(segment_of("PCD", &[&["3", percentage]]));
+        }
+        if let Some(amount) = amount {
+            check_decimal(amount, "amount")?;
+            segments.push(segment_of("MOA", &[&["8", amount]]));
+        }
+        self.add_to_header(segments)
+    }
+
+    fn add_line(
         &mut self,
         line_number: &str,
         item_number: &str,
         quantity: &str,
         price: &str,
-        py: Python,
-    ) -> PyResult<Py<OrderBuilder>> {
-        // LIN segment
-        let lin_elements = vec![
-            vec![line_number.to_string()],
-            vec![],
-            vec![item_number.to_string(), "BP".to_string()],
-        ];
-        self.order.add_segment(Segment::new(
-            "LIN".to_string(),
-            lin_elements,
-            self.order.segments.len(),
-        ));
+    ) -> Result<(), EdifactError> {
+        check_decimal(quantity, "quantity")?;
+        check_decimal(price, "price")?;
+        self.lines.push(vec![
+            vec![segment_of(
+                "LIN",
+                &[&[line_number], &[], &[item_number, "BP"]],
+            )],
+            vec![segment_of("QTY", &[&["21", quantity]])],
+            vec![segment_of("PRI", &[&["AAA", price]])],
+        ]);
+        let checked = self.check_line();
+        if checked.is_err() {
+            self.lines.pop();
+        }
+        checked
+    }
 
-        // QTY segment
-        let qty_elements = vec![vec!["21".to_string()], vec![quantity.to_string()]];
-        self.order.add_segment(Segment::new(
-            "QTY".to_string(),
-            qty_elements,
-            self.order.segments.len(),
-        ));
+    fn add_line_description(
+        &mut self,
+        description: &str,
+        format: &str,
+    ) -> Result<(), EdifactError> {
+        let imd = segment_of("IMD", &[&[format], &[], &["", "", "", description]]);
+        self.add_to_line(vec![imd])
+    }
 
-        // PRI segment
-        let pri_elements = vec![vec!["AAA".to_string()], vec![price.to_string()]];
-        self.order.add_segment(Segment::new(
-            "PRI".to_string(),
-            pri_elements,
-            self.order.segments.len(),
-        ));
+    fn add_line_product_id(
+        &mut self,
+        number: &str,
+        number_type: &str,
+        function: &str,
+    ) -> Result<(), EdifactError> {
+        let pia = segment_of("PIA", &[&[function], &[number, number_type]]);
+        self.add_to_line(vec![pia])
+    }
+
+    fn add_line_amount(
+        &mut self,
+        qualifier: &str,
+        amount: &str,
+        currency: Option<&str>,
+    ) -> Result<(), EdifactError> {
+        check_decimal(amount, "amount")?;
+        if let Some(currency) = currency {
+            check_code(currency, 3, "currency")?;
+        }
+        let moa = segment_of("MOA", &[&[qualifier, amount, currency.unwrap_or_default()]]);
+        self.add_to_line(vec![moa])
+    }
+
+    fn add_line_date(
+        &mut self,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> Result<(), EdifactError> {
+        DateTimePeriod::check(value, format)?;
+        self.add_to_line(vec![segment_of("DTM", &[&[qualifier, value, format]])])
+    }
+
+    fn add_line_location(
+        &mut self,
+        qualifier: &str,
+        id: &str,
+        quantity: Option<&str>,
+    ) -> Result<(), EdifactError> {
+        let mut segments = vec![segment_of("LOC", &[&[qualifier], &[id]])];
+        if let Some(quantity) = quantity {
+            check_decimal(quantity, "quantity")?;
+            segments.push(segment_of("QTY", &[&["11", quantity]]));
+        }
+        self.add_to_line(segments)
+    }
+
+    fn add_to_header(&mut self, entry: Vec<Segment>) -> Result<(), EdifactError> {
+        self.header.push(entry);
+        let checked = self.check_header();
+        if checked.is_err() {
+            self.header.pop();
+        }
+        checked
+    }
+
+    fn add_to_line(&mut self, entry: Vec<Segment>) -> Result<(), EdifactError> {
+        self.lines
+            .last_mut()
+            .ok_or_else(|| EdifactError::new("Line details need an order line"))?
+            .push(entry);
+        let checked = self.check_line();
+        if checked.is_err() {
+            self.lines.last_mut().map(Vec::pop);
+        }
+        checked
+    }
+
+    // Only the part that changed is checked, so each addition costs the size of the header
+    // or of the line rather than of the whole order. Mandatory segments may still follow,
+    // so they are left out.
+    fn check_header(&self) -> Result<(), EdifactError> {
+        let segments = self.sorted(&self.header, None);
+        check_issues(self.definition().build_tree(&segments).1)
+    }
+
+    fn check_line(&self) -> Result<(), EdifactError> {
+        let line = self.lines.last().map(Vec::as_slice).unwrap_or_default();
+        let segments = self.sorted(line, Some("LIN"));
+        check_issues(self.definition().group_issues("LIN", &segments))
+    }
+
+    // The header entries followed by the lines, each sorted into the order of the segment
+    // groups they start
+    fn arrange(&self) -> Vec<Segment> {
+        let mut segments = self.sorted(&self.header, None);
+        for line in &self.lines {
+            segments.extend(self.sorted(line, Some("LIN")));
+        }
+        for (position, segment) in segments.iter_mut().enumerate() {
+            segment.position = position;
+        }
+        segments
+    }
+
+    // Entries of the same kind keep the order they were added in
+    fn sorted(&self, entries: &[Vec<Segment>], group: Option<&str>) -> Vec<Segment> {
+        let definition = self.definition();
+        let mut entries: Vec<&Vec<Segment>> = entries.iter().collect();
+        entries.sort_by_key(|entry| {
+            definition
+                .entry_index(group, &entry[0].tag)
+                .unwrap_or(usize::MAX)
+        });
+        entries.into_iter().flatten().cloned().collect()
+    }
+
+    fn definition(&self) -> &'static MessageDefinition {
+        let release = self
+            .order
+            .message_header
+            .as_ref()
+            .and_then(|header| header.get_component(1, 2))
+            .map(String::as_str);
+        Directory::builtin()
+            .message("ORDERS", release)
+            .expect("built-in ORDERS definition")
+    }
+}
+
+fn check_issues(issues: Vec<ValidationIssue>) -> Result<(), EdifactError> {
+    let details: Vec<String> = issues
+        .iter()
+        .filter(|issue| issue.code != validation::MISSING)
+        .map(|issue| issue.to_string())
+        .collect();
+    if details.is_empty() {
+        Ok(())
+    } else {
+        Err(EdifactError::new(format!(
+            "Invalid ORDERS: {}",
+            details.join("; ")
+        )))
+    }
+}
+
+// Checks an alphabetic code list value of fixed length, such as an ISO country or currency
+pub(crate) fn check_code(value: &str, length: usize, what: &str) -> Result<(), EdifactError> {
+    if value.len() == length && value.chars().all(|c| c.is_ascii_uppercase()) {
+        Ok(())
+    } else {
+        Err(EdifactError::new(format!(
+            "Invalid {} code '{}'",
+            what, value
+        )))
+    }
+}
 
-        Py::new(py, self.clone())
+pub(crate) fn check_decimal(value: &str, what: &str) -> Result<(), EdifactError> {
+    if common::is_decimal(value) {
+        Ok(())
+    } else {
+        Err(EdifactError::new(format!("Invalid {} '{}'", what, value)))
     }
+}
 
-    fn build(&self) -> Order {
-        self.order.clone()
+fn check_service_segment(segment: &Segment, version: SyntaxVersion) -> Result<(), EdifactError> {
+    let issues = if segment.tag == "UNB" {
+        let mut issues = Vec::new();
+        service::check_interchange_header(Some(segment), &mut issues);
+        issues
+    } else {
+        service::validate_service_segment(segment, version)
+    };
+    if issues.is_empty() {
+        return Ok(());
     }
+    let details: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+    Err(EdifactError::new(format!(
+        "Invalid {} segment: {}",
+        segment.tag,
+        details.join("; ")
+    )))
 }
 
 #[pymodule]
@@ -565,12 +2006,92 @@ fn edifact_parser(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<OrderLine>()?;
     m.add_class::<Order>()?;
     m.add_class::<OrderBuilder>()?;
+    m.add_class::<MessageBuilder>()?;
+    m.add_class::<ValidationIssue>()?;
+    m.add_class::<SegmentGroup>()?;
+    m.add_class::<Directory>()?;
+    #[cfg(feature = "serde")]
+    m.add_class::<Mig>()?;
+    m.add_class::<InterchangeParty>()?;
+    m.add_class::<InterchangeHeader>()?;
+    m.add_class::<MessageHeader>()?;
+    m.add_class::<Interchange>()?;
+    m.add_class::<SyntaxVersion>()?;
+    m.add_class::<DateTimePeriod>()?;
+    m.add_class::<Reference>()?;
+    m.add_class::<Communication>()?;
+    m.add_class::<Contact>()?;
+    m.add_class::<Location>()?;
+    m.add_class::<Party>()?;
+    m.add_class::<Quantity>()?;
+    m.add_class::<Measurement>()?;
+    m.add_class::<Discrepancy>()?;
+    m.add_class::<MonetaryAmount>()?;
+    m.add_class::<Price>()?;
+    m.add_class::<Currency>()?;
+    m.add_class::<PaymentTerms>()?;
+    m.add_class::<Transport>()?;
+    m.add_class::<AllowanceCharge>()?;
+    m.add_class::<ItemNumber>()?;
+    m.add_class::<ProductIdentification>()?;
+    m.add_class::<ItemDescription>()?;
+    m.add_class::<Tax>()?;
+    m.add_class::<LineAction>()?;
+    m.add_class::<LineDecision>()?;
+    m.add_class::<OrderResponse>()?;
+    m.add_class::<OrderChange>()?;
+    m.add_class::<Packaging>()?;
+    m.add_class::<Package>()?;
+    m.add_class::<DespatchAdvice>()?;
+    m.add_class::<DespatchAdviceBuilder>()?;
+    m.add_class::<Invoice>()?;
+    m.add_class::<InvoiceBuilder>()?;
+    m.add_class::<PriceCatalogue>()?;
+    m.add_class::<ProductGroup>()?;
+    m.add_class::<Products>()?;
+    m.add_class::<ReportRecord>()?;
+    m.add_class::<InventoryReport>()?;
+    m.add_class::<SalesReport>()?;
+    m.add_class::<ReceiptLine>()?;
+    m.add_class::<ReceiptDifference>()?;
+    m.add_class::<ReceivingAdvice>()?;
+    m.add_class::<RemittanceLine>()?;
+    m.add_class::<PaymentAdjustment>()?;
+    m.add_class::<PaymentStatus>()?;
+    m.add_class::<PaymentMatch>()?;
+    m.add_class::<RemittanceAdvice>()?;
+    m.add_class::<Equipment>()?;
+    m.add_class::<GoodsItem>()?;
+    m.add_class::<StatusEvent>()?;
+    m.add_class::<Consignment>()?;
+    m.add_class::<TransportInstruction>()?;
+    m.add_class::<TransportStatus>()?;
+    m.add_class::<StowagePosition>()?;
+    m.add_class::<Container>()?;
+    m.add_class::<ContainerMessage>()?;
+    m.add_class::<ContainerMessageBuilder>()?;
+    m.add_class::<CustomsError>()?;
+    m.add_class::<CustomsGoodsItem>()?;
+    m.add_class::<CustomsDeclaration>()?;
+    m.add_class::<CustomsResponse>()?;
+    m.add_class::<CustomsDeclarationBuilder>()?;
+    m.add_class::<Payment>()?;
+    m.add_class::<PaymentOrder>()?;
+    m.add_class::<PaymentOrderBuilder>()?;
+    m.add_class::<ControlAction>()?;
+    m.add_class::<ControlError>()?;
+    m.add_class::<MessageResponse>()?;
+    m.add_class::<ControlReport>()?;
+    m.add_class::<ApplicationError>()?;
+    m.add_class::<ApplicationAcknowledgement>()?;
+    m.add_class::<ApplicationAcknowledgementBuilder>()?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{NaiveDate, NaiveTime};
     use pretty_assertions::assert_eq;
     use pyo3::Python;
 
@@ -734,15 +2255,15 @@ PRI+AAA+10.00'";
             assert_eq!(lines.len(), 1);
             let line = &lines[0];
 
-            assert_eq!(line.line_segment.tag, "LIN");
-            assert_eq!(line.line_segment.elements[0][0], "1");
-            assert_eq!(line.line_segment.elements[2][0], "ITEM123");
+            assert_eq!(line.line_segment().tag, "LIN");
+            assert_eq!(line.line_segment().elements[0][0], "1");
+            assert_eq!(line.line_segment().elements[2][0], "ITEM123");
 
-            if let Some(ref qty) = line.quantity {
+            if let Some(ref qty) = line.quantity() {
                 assert_eq!(qty.elements[1][0], "5");
             }
 
-            if let Some(ref price) = line.price {
+            if let Some(ref price) = line.price() {
                 assert_eq!(price.elements[1][0], "10.00");
             }
         });
@@ -766,6 +2287,46 @@ PRI+AAA+10.00'";
         });
     }
 
+    #[test]
+    fn test_segment_escaping_follows_syntax_version() {
+        Python::with_gil(|_py| {
+            let segment = Segment::new(
+                "FTX".to_string(),
+                vec![vec!["AAA".to_string()], vec!["10.00? 2*3".to_string()]],
+                0,
+            );
+
+            assert_eq!(
+                segment.to_edifact(&SyntaxVersion::V4.default_delimiters()),
+                "FTX+AAA+10.00?? 2?*3'"
+            );
+            assert_eq!(
+                segment.to_edifact(&SyntaxVersion::V3.default_delimiters()),
+                "FTX+AAA+10.00?? 2*3'"
+            );
+        });
+    }
+
+    #[test]
+    fn test_order_syntax_version_round_trip() {
+        Python::with_gil(|_py| {
+            let content = "UNB+UNOA:3+SENDER+RECEIVER+240119:1200+REF1'\nBGM+220+PO*1+9'\n";
+            let order = Order::from_edifact(content.to_string()).unwrap();
+
+            assert_eq!(order.syntax_version(), SyntaxVersion::V3);
+            assert_eq!(order.parser.delimiters.reserved, ' ');
+            assert_eq!(order.to_edifact().unwrap(), content);
+            assert_eq!(
+                order
+                    .get_interchange_header()
+                    .unwrap()
+                    .unwrap()
+                    .syntax_version,
+                SyntaxVersion::V3
+            );
+        });
+    }
+
     #[test]
     fn test_get_component() {
         Python::with_gil(|_py| {
@@ -787,7 +2348,7 @@ PRI+AAA+10.00'";
     #[test]
     fn test_order_builder() {
         Python::with_gil(|_py| {
-            let mut builder = OrderBuilder::new();
+            let builder = OrderBuilder::new();
             let order = builder.build();
 
             assert!(order.segments.is_empty());
@@ -796,6 +2357,162 @@ PRI+AAA+10.00'";
         });
     }
 
+    #[test]
+    fn test_order_builder_interchange_header() {
+        Python::with_gil(|_py| {
+            let builder = OrderBuilder::new()
+                .interchange_header("SENDER", "RECEIVER", "20240119:1200", "REF123")
+                .unwrap();
+
+            let header = builder.order.interchange_header.clone().unwrap();
+            assert_eq!(
+                header.to_edifact(&Delimiters::default()),
+                "UNB+UNOA:4+SENDER+RECEIVER+20240119:1200+REF123++ORDERS'"
+            );
+
+            let typed = builder.order.get_interchange_header().unwrap().unwrap();
+            assert_eq!(typed.to_segment().elements, header.elements);
+            assert!(builder
+                .interchange_header("SENDER", "RECEIVER", "20241319:1200", "REF123")
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_order_builder_syntax_version() {
+        Python::with_gil(|_py| {
+            assert!(OrderBuilder::new()
+                .syntax("UNOY", SyntaxVersion::V3)
+                .is_err());
+
+            let builder = OrderBuilder::new()
+                .syntax("UNOC", SyntaxVersion::V3)
+                .unwrap();
+            assert!(builder
+                .clone()
+                .interchange_header("SENDER", "RECEIVER", "20240119:1200", "REF123")
+                .is_err());
+            let builder = builder
+                .interchange_header("SENDER", "RECEIVER", "240119:1200", "REF123")
+                .unwrap();
+
+            let order = builder.build();
+            assert_eq!(
+                order.to_edifact().unwrap(),
+                "UNB+UNOC:3+SENDER+RECEIVER+240119:1200+REF123++ORDERS'\n"
+            );
+        });
+    }
+
+    #[test]
+    fn test_order_builder_header_and_lines() -> Result<(), EdifactError> {
+        Python::with_gil(|_py| {
+            // Sections are arranged in segment group order whatever order they come in
+            let buyer = Address {
+                name: Some("Buyer NV"),
+                street: Some("Main Street 1"),
+                city: Some("Brussels"),
+                postal_code: Some("1000"),
+                country: Some("BE"),
+            };
+            let builder = OrderBuilder::new()
+                .message_header("1", "ORDERS")?
+                .party("BY", Some("5412345000013"), "9", &buyer)?
+                .contact("OC", Some("J. Smith"), Some("+3221234567"), None)?
+                .currency("EUR", "2", "9")?
+                .bgm("220", "PO001", "9")?
+                .date("137", "20240301", "102")?
+                .party("SU", Some("4012345000016"), "9", &Address::default())?
+                .reference("CT", "CONTRACT1")?
+                .payment_terms("1", None, Some(["5", "3", "D", "30"]))?
+                .transport("20", Some("30"), None, Some("CARRIER"))?
+                .allowance_charge("A", Some("DI"), Some("2"), None)?
+                .line("1", "4000862141404", "10", "9.95")?
+                .line_location("7", "4012345000023", Some("10"))?
+                .line_date("2", "20240315", "102")?
+                .line_amount("203", "99.50", None)?
+                .line_description("Widget, blue", "F")?
+                .line_product_id("W-100", "SA", "5")?;
+
+            let order = builder.build();
+            assert_eq!(
+                order.to_edifact().unwrap(),
+                "UNH+1+ORDERS:D:01B:UN'
+BGM+220+PO001+9'
+DTM+137:20240301:102'
+RFF+CT:CONTRACT1'
+NAD+BY+5412345000013::9++Buyer NV+Main Street 1+Brussels++1000+BE'
+CTA+OC+:J. Smith'
+COM+?+3221234567:TE'
+NAD+SU+4012345000016::9'
+CUX+2:EUR:9'
+PAT+1++5:3:D:30'
+TDT+20++30++CARRIER'
+ALC+A++++DI'
+PCD+3:2'
+LIN+1++4000862141404:BP'
+PIA+5+W-100:SA'
+IMD+F++:::Widget, blue'
+QTY+21:10'
+DTM+2:20240315:102'
+MOA+203:99.50'
+PRI+AAA:9.95'
+LOC+7+4012345000023'
+QTY+11:10'
+"
+            );
+            let parties = order.get_parties();
+            assert_eq!(parties[0].contacts[0].name.as_deref(), Some("J. Smith"));
+            assert_eq!(parties[0].postal_code.as_deref(), Some("1000"));
+            assert_eq!(order.get_currencies().len(), 1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_order_builder_validates_as_it_goes() {
+        Python::with_gil(|_py| {
+            let mut builder = OrderBuilder::new();
+            assert!(builder.add_contact("OC", None, None, None).is_err());
+            assert!(builder.add_line_date("2", "20240315", "102").is_err());
+            assert!(builder.add_date("137", "20240230", "102").is_err());
+            assert!(builder.add_date("137", "2024", "XX").is_err());
+            assert!(builder.add_currency("euro", "2", "9").is_err());
+            assert!(builder.add_allowance_charge("X", None, None, None).is_err());
+
+            builder.add_bgm("220", "PO001", "9").unwrap();
+            // BGM occurs once; the rejected segment leaves the order as it was
+            assert!(builder.add_bgm("220", "PO002", "9").is_err());
+            assert_eq!(builder.build().segments.len(), 1);
+            builder.add_date("137", "20240301-20240331", "718").unwrap();
+            assert_eq!(builder.build().segments.len(), 2);
+
+            // Quantities, prices and amounts are plain decimals
+            for (quantity, price) in [
+                ("abc", "9.95"),
+                ("10", "xyz"),
+                ("inf", "9.95"),
+                ("1e3", "9.95"),
+            ] {
+                assert!(builder
+                    .add_line("1", "4000862141404", quantity, price)
+                    .is_err());
+            }
+            assert!(builder.lines.is_empty());
+
+            // A line takes one LIN; a second one starts the next line
+            builder
+                .add_line("1", "4000862141404", "10", "9.95")
+                .unwrap();
+            assert!(builder.add_line_product_id("W-100", "SA", "5").is_ok());
+            assert!(builder.add_line_amount("203", "NaN", None).is_err());
+            assert!(builder
+                .add_to_line(vec![segment_of("LIN", &[&["2"]])])
+                .is_err());
+            assert_eq!(builder.lines[0].len(), 4);
+        });
+    }
+
     #[test]
     fn test_message_creation() {
         Python::with_gil(|_py| {
@@ -805,6 +2522,142 @@ PRI+AAA+10.00'";
         });
     }
 
+    #[test]
+    fn test_order_service_segment_validation() {
+        Python::with_gil(|_py| {
+            let sample_order = "UNB+UNOA:4+SENDER+RECEIVER+20240119:1200+REF123'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+123456+9'
+UNT+3+1'
+UNZ+1+REF123'";
+
+            let order = Order::from_edifact(sample_order.to_string()).unwrap();
+            assert!(order.validate_service_segments().is_empty());
+
+            let broken = sample_order
+                .replace("20240119:1200", "20241319:1200")
+                .replace("UNT+3+1", "UNT+4+2")
+                .replace("UNZ+1+REF123", "UNZ+1+REF124");
+            let order = Order::from_edifact(broken).unwrap();
+            let codes: Vec<(String, String)> = order
+                .validate_service_segments()
+                .into_iter()
+                .map(|issue| (issue.code, issue.tag))
+                .collect();
+            assert_eq!(
+                codes,
+                vec![
+                    ("12".to_string(), "UNB".to_string()),
+                    ("29".to_string(), "UNT".to_string()),
+                    ("28".to_string(), "UNT".to_string()),
+                    ("28".to_string(), "UNZ".to_string()),
+                ]
+            );
+        });
+    }
+
+    const FULL_ORDER: &str = "UNB+UNOC:4+SENDER:14+RECEIVER:14+20240119:1200+REF123'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240119:102'
+DTM+2:20240126:102'
+RFF+CT:CONTRACT1'
+DTM+171:20240101:102'
+NAD+BY+5412345000013::9++Buyer Ltd+Main Street 1+Brussels++1000+BE'
+RFF+VA:BE0123456789'
+CTA+OC+:Jane Doe'
+COM+3212345678:TE'
+COM+jane@example.com:EM'
+NAD+SU+4012345000016::9'
+CUX+2:EUR:9'
+PAT+1++5:3:D:30'
+TDT+20++30+31'
+LOC+11+BRU'
+ALC+A++++ABC'
+PCD+1:2.5'
+LIN+1++4000862141404:SRV'
+PIA+1+ABC123:SA'
+IMD+F++:::Blue widget'
+IMD+F++:::Large'
+QTY+21:48:PCE'
+QTY+192:2:PCE'
+DTM+2:20240130:102'
+PRI+AAA:10.00'
+RFF+LI:7'
+LOC+7+STORE1'
+QTY+11:24'
+LOC+7+STORE2'
+QTY+11:24'
+LIN+2++4000862141411:SRV+1:1'
+QTY+21:12'
+LIN+3++4000862141428:SRV'
+QTY+21:6'
+UNS+S'
+CNT+2:3'
+UNT+35+1'
+UNZ+1+REF123'";
+
+    #[test]
+    fn test_full_order_model() {
+        Python::with_gil(|_py| {
+            let order = Order::from_edifact(FULL_ORDER.to_string()).unwrap();
+            assert_eq!(order.get_document_number().as_deref(), Some("PO12345"));
+
+            let dates = order.get_dates();
+            assert_eq!(dates.len(), 2);
+            assert_eq!(
+                dates[0].to_datetime(),
+                NaiveDate::from_ymd_opt(2024, 1, 19).map(|d| d.and_time(NaiveTime::MIN))
+            );
+
+            let references = order.get_references();
+            assert_eq!(references[0].number.as_deref(), Some("CONTRACT1"));
+            assert_eq!(references[0].dates[0].qualifier, "171");
+
+            let buyer = order.get_party("BY").unwrap();
+            assert_eq!(buyer.id.as_deref(), Some("5412345000013"));
+            assert_eq!(buyer.name.as_deref(), Some("Buyer Ltd"));
+            assert_eq!(buyer.postal_code.as_deref(), Some("1000"));
+            assert_eq!(buyer.references[0].qualifier, "VA");
+            assert_eq!(buyer.contacts[0].name.as_deref(), Some("Jane Doe"));
+            assert_eq!(buyer.contacts[0].communications.len(), 2);
+            assert_eq!(order.get_parties().len(), 2);
+
+            assert_eq!(order.get_currencies()[0].currency.as_deref(), Some("EUR"));
+            assert_eq!(
+                order.get_payment_terms()[0].period_count.as_deref(),
+                Some("30")
+            );
+            let transport = &order.get_transport()[0];
+            assert_eq!(transport.mode.as_deref(), Some("30"));
+            assert_eq!(transport.locations[0].id.as_deref(), Some("BRU"));
+            let allowance = &order.get_allowances_charges()[0];
+            assert_eq!(allowance.indicator, "A");
+            assert_eq!(allowance.percentage.as_deref(), Some("2.5"));
+
+            let lines = order.get_order_lines().unwrap();
+            assert_eq!(lines.len(), 2);
+            let line = &lines[0];
+            assert_eq!(line.item_number().unwrap().number, "4000862141404");
+            assert_eq!(line.product_ids()[0].item_numbers[0].number, "ABC123");
+            assert_eq!(line.descriptions().len(), 2);
+            assert_eq!(line.quantities().len(), 2);
+            assert_eq!(line.quantities()[1].qualifier, "192");
+            assert_eq!(line.prices()[0].amount.as_deref(), Some("10.00"));
+            assert_eq!(line.references()[0].number.as_deref(), Some("7"));
+            let locations = line.locations();
+            assert_eq!(locations.len(), 2);
+            assert_eq!(
+                locations[1].quantity.as_ref().unwrap().value.as_deref(),
+                Some("24")
+            );
+
+            assert_eq!(line.sub_lines.len(), 1);
+            assert_eq!(line.sub_lines[0].parent_line_number().as_deref(), Some("1"));
+            assert_eq!(lines[1].line_number().as_deref(), Some("3"));
+        });
+    }
+
     #[test]
     fn test_order_line_creation() {
         Python::with_gil(|_py| {
@@ -818,12 +2671,19 @@ PRI+AAA+10.00'";
                 0,
             );
 
-            let order_line = OrderLine::new(line_segment);
-            assert!(order_line.quantity.is_none());
-            assert!(order_line.price.is_none());
-            assert!(order_line.description.is_none());
-            assert!(order_line.amount.is_none());
-            assert!(order_line.reference.is_none());
+            let order_line = OrderLine::new(line_segment).unwrap();
+            assert!(order_line.quantity().is_none());
+            assert!(order_line.price().is_none());
+            assert!(order_line.description().is_none());
+            assert!(order_line.amount().is_none());
+            assert!(order_line.reference().is_none());
+
+            let quantity = Segment::new(
+                "QTY".to_string(),
+                vec![vec!["21".to_string(), "5".to_string()]],
+                0,
+            );
+            assert!(OrderLine::new(quantity).is_err());
         });
     }
 }
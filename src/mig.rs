@@ -0,0 +1,646 @@
+use pyo3::prelude::*;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::directory::{Directory, MessageDefinition, Node, SegmentGroup, Status, StructureEntry};
+use crate::validation::{self, ValidationIssue};
+use crate::{EdifactError, Segment};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RuleStatus {
+    #[serde(alias = "required", alias = "M")]
+    Mandatory,
+    #[serde(alias = "optional", alias = "C")]
+    Conditional,
+    #[serde(alias = "not_used", alias = "X")]
+    Forbidden,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ElementRule {
+    element: usize,
+    #[serde(default)]
+    component: usize,
+    status: Option<RuleStatus>,
+    allowed_values: Option<Vec<String>>,
+    max_length: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SegmentRule {
+    tag: String,
+    group: Option<String>,
+    qualifier: Option<String>,
+    status: Option<RuleStatus>,
+    max_repeat: Option<usize>,
+    #[serde(default)]
+    elements: Vec<ElementRule>,
+}
+
+impl SegmentRule {
+    fn describe(&self) -> String {
+        match &self.qualifier {
+            Some(qualifier) => format!("{}+{}", self.tag, qualifier),
+            None => self.tag.clone(),
+        }
+    }
+
+    fn matches(&self, group: &str, segment: &Segment) -> bool {
+        segment.tag == self.tag
+            && self.group.as_deref().is_none_or(|g| g == group)
+            && self
+                .qualifier
+                .as_ref()
+                .is_none_or(|q| segment.get_component(0, 0).map(String::as_str) == Some(q.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GroupRule {
+    name: String,
+    status: Option<RuleStatus>,
+    max_repeat: Option<usize>,
+}
+
+/// A Message Implementation Guideline: a trading partner's restrictions layered over a
+/// directory message definition.
+#[pyclass]
+#[derive(Debug, Clone, Deserialize)]
+pub struct Mig {
+    #[pyo3(get)]
+    #[serde(default)]
+    name: String,
+    #[pyo3(get)]
+    message_type: String,
+    #[pyo3(get)]
+    release: Option<String>,
+    #[serde(default)]
+    segments: Vec<SegmentRule>,
+    #[serde(default)]
+    groups: Vec<GroupRule>,
+}
+
+#[pymethods]
+impl Mig {
+    #[staticmethod]
+    fn from_json(content: &str) -> PyResult<Mig> {
+        let mig: Mig = serde_json::from_str(content)
+            .map_err(|e| EdifactError::new(format!("Invalid MIG: {}", e)))?;
+        Ok(mig.checked()?)
+    }
+
+    #[staticmethod]
+    fn from_toml(content: &str) -> PyResult<Mig> {
+        let mig: Mig = toml::from_str(content)
+            .map_err(|e| EdifactError::new(format!("Invalid MIG: {}", e)))?;
+        Ok(mig.checked()?)
+    }
+
+    #[staticmethod]
+    fn from_yaml(content: &str) -> PyResult<Mig> {
+        let mig: Mig = serde_yaml::from_str(content)
+            .map_err(|e| EdifactError::new(format!("Invalid MIG: {}", e)))?;
+        Ok(mig.checked()?)
+    }
+
+    #[staticmethod]
+    fn from_file(path: &str) -> PyResult<Mig> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| EdifactError::new(format!("Cannot read {}: {}", path, e)))?;
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("json") => Mig::from_json(&content),
+            Some("toml") => Mig::from_toml(&content),
+            Some("yaml") | Some("yml") => Mig::from_yaml(&content),
+            _ => Err(EdifactError::new(format!("Unknown MIG format for {}", path)).into()),
+        }
+    }
+
+    #[pyo3(signature = (segments, directory=None))]
+    fn validate(
+        &self,
+        segments: Vec<Segment>,
+        directory: Option<&Directory>,
+    ) -> PyResult<Vec<ValidationIssue>> {
+        let directory = directory.unwrap_or_else(|| Directory::builtin());
+        let definition = directory.require_message(&self.message_type, self.release.as_deref())?;
+        // The lookup falls back to any release, but a MIG is written against the one it names
+        if let Some(release) = self
+            .release
+            .as_deref()
+            .filter(|release| *release != definition.release)
+        {
+            return Err(EdifactError::new(format!(
+                "No message definition for {} release {}",
+                self.message_type, release
+            ))
+            .into());
+        }
+        Ok(self.validate_segments(definition, &segments)?)
+    }
+}
+
+impl Mig {
+    // A MIG can only tighten the directory, so a conditional status would either repeat
+    // the directory or relax it; both are better left out than silently ignored
+    fn checked(self) -> Result<Self, EdifactError> {
+        let conditional = self
+            .segments
+            .iter()
+            .find(|rule| {
+                rule.status == Some(RuleStatus::Conditional)
+                    || rule
+                        .elements
+                        .iter()
+                        .any(|e| e.status == Some(RuleStatus::Conditional))
+            })
+            .map(SegmentRule::describe)
+            .or_else(|| {
+                self.groups
+                    .iter()
+                    .find(|rule| rule.status == Some(RuleStatus::Conditional))
+                    .map(|rule| rule.name.clone())
+            });
+        match conditional {
+            Some(name) => Err(EdifactError::new(format!(
+                "Invalid MIG: {} is conditional; a MIG can only make entries mandatory or forbidden",
+                name
+            ))),
+            None => Ok(self),
+        }
+    }
+
+    /// Restricts a directory definition with the structural rules of this MIG. Rules that
+    /// carry a qualifier cannot be expressed structurally and are checked during validation.
+    pub(crate) fn apply(
+        &self,
+        definition: &MessageDefinition,
+    ) -> Result<MessageDefinition, EdifactError> {
+        let mut overlay = definition.clone();
+
+        for rule in self.segments.iter().filter(|r| r.qualifier.is_none()) {
+            let applied =
+                apply_segment_rule(&mut overlay.entries, 0, &definition.message_type, rule);
+            if applied == 0 {
+                return Err(EdifactError::new(format!(
+                    "MIG '{}' refers to {} which is not part of {}",
+                    self.name,
+                    rule.describe(),
+                    definition.message_type
+                )));
+            }
+        }
+
+        for rule in &self.groups {
+            if !apply_group_rule(&mut overlay.entries, rule) {
+                return Err(EdifactError::new(format!(
+                    "MIG '{}' refers to segment group {} which is not part of {}",
+                    self.name, rule.name, definition.message_type
+                )));
+            }
+        }
+
+        Ok(overlay)
+    }
+
+    pub(crate) fn validate_segments(
+        &self,
+        definition: &MessageDefinition,
+        segments: &[Segment],
+    ) -> Result<Vec<ValidationIssue>, EdifactError> {
+        let overlay = self.apply(definition)?;
+        let (tree, mut issues) = overlay.build_tree(segments);
+
+        tree.walk(&mut |group, segment| {
+            for rule in self
+                .segments
+                .iter()
+                .filter(|rule| rule.matches(group, segment))
+            {
+                check_elements(rule, segment, &mut issues);
+            }
+        });
+
+        for rule in self.segments.iter().filter(|r| r.qualifier.is_some()) {
+            let scopes = rule_scopes(rule, &overlay);
+            check_occurrences(rule, &scopes, &tree, true, &mut issues);
+        }
+
+        Ok(issues)
+    }
+}
+
+// The groups a mandatory qualified rule is expected in, once per occurrence. Without a group
+// in the rule, that is wherever the segment first appears nearest the message level.
+fn rule_scopes<'a>(rule: &SegmentRule, definition: &'a MessageDefinition) -> Vec<&'a str> {
+    let mut scopes = Vec::new();
+    let mut level = vec![(definition.message_type.as_str(), &definition.entries[..], 0)];
+    while !level.is_empty() {
+        let mut next = Vec::new();
+        for (name, entries, skip) in level {
+            for entry in entries.iter().skip(skip) {
+                let scope = match entry {
+                    StructureEntry::Segment(_) => name,
+                    StructureEntry::Group(child) => {
+                        next.push((child.name.as_str(), &child.entries[..], 1));
+                        &child.name
+                    }
+                };
+                let in_scope = rule.group.as_deref().is_none_or(|g| g == scope);
+                if in_scope && entry.trigger() == rule.tag && !scopes.contains(&name) {
+                    scopes.push(name);
+                }
+            }
+        }
+        if rule.group.is_none() && !scopes.is_empty() {
+            scopes.truncate(1);
+            break;
+        }
+        level = next;
+    }
+    scopes
+}
+
+// Counts a qualified rule per occurrence of each group: a segment counts towards the group
+// holding it, and a group's trigger towards the group the nested group sits in
+fn check_occurrences(
+    rule: &SegmentRule,
+    scopes: &[&str],
+    group: &SegmentGroup,
+    is_message: bool,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let mut count = 0;
+    for (index, node) in group.nodes.iter().enumerate() {
+        match node {
+            Node::Segment(segment) => {
+                if (is_message || index > 0) && rule.matches(&group.name, segment) {
+                    count += 1;
+                }
+            }
+            Node::Group(child) => {
+                let trigger = child.direct_segments().next();
+                if trigger.is_some_and(|segment| rule.matches(&child.name, segment)) {
+                    count += 1;
+                }
+                check_occurrences(rule, scopes, child, false, issues);
+            }
+        }
+    }
+
+    let position = match is_message {
+        true => None,
+        false => group.direct_segments().next().map(|s| s.position),
+    };
+    let expected = scopes.contains(&group.name.as_str());
+    match rule.status {
+        Some(RuleStatus::Mandatory) if expected && count == 0 => {
+            issues.push(ValidationIssue::segment(
+                validation::MISSING,
+                &rule.tag,
+                position,
+                format!("{} is required by the MIG", rule.describe()),
+            ))
+        }
+        Some(RuleStatus::Forbidden) if count > 0 => issues.push(ValidationIssue::segment(
+            validation::NOT_SUPPORTED_IN_POSITION,
+            &rule.tag,
+            position,
+            format!("{} is not allowed by the MIG", rule.describe()),
+        )),
+        _ => {}
+    }
+    if let Some(max_repeat) = rule.max_repeat {
+        if count > max_repeat {
+            issues.push(ValidationIssue::segment(
+                validation::TOO_MANY_REPETITIONS,
+                &rule.tag,
+                position,
+                format!(
+                    "{} occurs more than {} times in {}",
+                    rule.describe(),
+                    max_repeat,
+                    group.name
+                ),
+            ));
+        }
+    }
+}
+
+// Returns how many entries the rule matched so unknown tags can be reported
+fn apply_segment_rule(
+    entries: &mut Vec<StructureEntry>,
+    skip: usize,
+    group: &str,
+    rule: &SegmentRule,
+) -> usize {
+    let mut applied = 0;
+
+    // A group's trigger segment is matched here, in scope of the group it starts
+    let found = entries
+        .iter()
+        .enumerate()
+        .skip(skip)
+        .find_map(|(index, entry)| {
+            let scope = match entry {
+                StructureEntry::Segment(_) => group,
+                StructureEntry::Group(child) => &child.name,
+            };
+            let in_scope = rule.group.as_deref().is_none_or(|g| g == scope);
+            (in_scope && entry.trigger() == rule.tag).then_some(index)
+        });
+
+    if let Some(index) = found {
+        applied += 1;
+        if rule.status == Some(RuleStatus::Forbidden) {
+            entries.remove(index);
+        } else {
+            let (status, max_repeat) = match &mut entries[index] {
+                StructureEntry::Segment(segment) => (&mut segment.status, &mut segment.max_repeat),
+                StructureEntry::Group(child) => (&mut child.status, &mut child.max_repeat),
+            };
+            restrict(status, max_repeat, rule.status, rule.max_repeat);
+        }
+    }
+
+    for entry in entries.iter_mut() {
+        if let StructureEntry::Group(child) = entry {
+            let name = child.name.clone();
+            applied += apply_segment_rule(&mut child.entries, 1, &name, rule);
+        }
+    }
+
+    applied
+}
+
+fn apply_group_rule(entries: &mut Vec<StructureEntry>, rule: &GroupRule) -> bool {
+    for index in 0..entries.len() {
+        if let StructureEntry::Group(group) = &mut entries[index] {
+            if group.name == rule.name {
+                if rule.status == Some(RuleStatus::Forbidden) {
+                    entries.remove(index);
+                } else {
+                    restrict(
+                        &mut group.status,
+                        &mut group.max_repeat,
+                        rule.status,
+                        rule.max_repeat,
+                    );
+                }
+                return true;
+            }
+            if apply_group_rule(&mut group.entries, rule) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn restrict(
+    status: &mut Status,
+    max_repeat: &mut usize,
+    rule_status: Option<RuleStatus>,
+    rule_max_repeat: Option<usize>,
+) {
+    if rule_status == Some(RuleStatus::Mandatory) {
+        *status = Status::Mandatory;
+    }
+    if let Some(rule_max_repeat) = rule_max_repeat {
+        *max_repeat = (*max_repeat).min(rule_max_repeat);
+    }
+}
+
+fn check_elements(rule: &SegmentRule, segment: &Segment, issues: &mut Vec<ValidationIssue>) {
+    for element in &rule.elements {
+        let value = segment
+            .get_component(element.element, element.component)
+            .filter(|v| !v.is_empty());
+        let issue = |code: &str, message: String| {
+            ValidationIssue::element(
+                code,
+                &segment.tag,
+                Some(segment.position),
+                element.element,
+                element.component,
+                message,
+            )
+        };
+
+        match (value, element.status) {
+            (None, Some(RuleStatus::Mandatory)) => issues.push(issue(
+                validation::MISSING,
+                format!("{} requires a value here", rule.describe()),
+            )),
+            (Some(_), Some(RuleStatus::Forbidden)) => issues.push(issue(
+                validation::NOT_SUPPORTED_IN_POSITION,
+                format!("{} must not carry a value here", rule.describe()),
+            )),
+            _ => {}
+        }
+
+        let Some(value) = value else { continue };
+        if let Some(allowed) = &element.allowed_values {
+            if !allowed.contains(value) {
+                issues.push(issue(
+                    validation::INVALID_VALUE,
+                    format!("'{}' is not one of {}", value, allowed.join(", ")),
+                ));
+            }
+        }
+        if let Some(max_length) = element.max_length {
+            if value.chars().count() > max_length {
+                issues.push(issue(
+                    validation::DATA_ELEMENT_TOO_LONG,
+                    format!("'{}' is longer than {} characters", value, max_length),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const SAMPLE_ORDER: &str = "UNH+1+ORDERS:D:96A:UN'
+BGM+220+123456+9'
+DTM+137:20240119:203'
+ALI+X7'
+NAD+BY+5021376940009::9'
+LIN+1++ITEM123:BP'
+QTY+21:5'
+UNS+S'
+UNT+8+1'";
+
+    fn codes(issues: &[ValidationIssue]) -> Vec<(&str, &str)> {
+        issues
+            .iter()
+            .map(|i| (i.code.as_str(), i.tag.as_str()))
+            .collect()
+    }
+
+    fn validate(mig: &Mig) -> Vec<ValidationIssue> {
+        let order = crate::Order::from_edifact(SAMPLE_ORDER.to_string()).unwrap();
+        let definition = Directory::builtin()
+            .require_message("ORDERS", None)
+            .unwrap();
+        mig.validate_segments(definition, &order.segments).unwrap()
+    }
+
+    #[test]
+    fn test_mig_formats() {
+        Python::with_gil(|_py| {
+            let json = Mig::from_json(
+                r#"{"message_type": "ORDERS", "segments": [{"tag": "ALI", "status": "forbidden"}]}"#,
+            )
+            .unwrap();
+            let toml = Mig::from_toml(
+                "message_type = \"ORDERS\"\n[[segments]]\ntag = \"ALI\"\nstatus = \"forbidden\"\n",
+            )
+            .unwrap();
+            let yaml = Mig::from_yaml(
+                "message_type: ORDERS\nsegments:\n  - tag: ALI\n    status: forbidden\n",
+            )
+            .unwrap();
+
+            for mig in [json, toml, yaml] {
+                assert_eq!(codes(&validate(&mig)), vec![("15", "ALI")]);
+            }
+            assert!(Mig::from_json(r#"{"segments": []}"#).is_err());
+
+            // A MIG for a release that is not loaded is not checked against another one
+            let order = crate::Order::from_edifact(SAMPLE_ORDER.to_string()).unwrap();
+            let mig = |release: &str| {
+                Mig::from_json(&format!(
+                    r#"{{"message_type": "ORDERS", "release": "{}"}}"#,
+                    release
+                ))
+                .unwrap()
+            };
+            assert!(mig("96A").validate(order.segments.clone(), None).is_ok());
+            assert!(mig("01B").validate(order.segments, None).is_err());
+        });
+    }
+
+    #[test]
+    fn test_mig_qualified_and_element_rules() {
+        Python::with_gil(|_py| {
+            let mig = Mig::from_toml(
+                r#"
+name = "Retailer"
+message_type = "ORDERS"
+
+[[segments]]
+tag = "NAD"
+qualifier = "SU"
+status = "mandatory"
+
+[[segments]]
+tag = "NAD"
+qualifier = "BY"
+max_repeat = 1
+
+[[segments]]
+tag = "DTM"
+
+[[segments.elements]]
+element = 0
+component = 2
+allowed_values = ["102"]
+
+[[segments]]
+tag = "LIN"
+group = "SG25"
+
+[[segments.elements]]
+element = 2
+status = "mandatory"
+max_length = 5
+"#,
+            )
+            .unwrap();
+
+            let issues = validate(&mig);
+            assert_eq!(
+                codes(&issues),
+                vec![("12", "DTM"), ("39", "LIN"), ("13", "NAD")]
+            );
+            assert_eq!(issues[0].element, Some(0));
+            assert_eq!(issues[0].component, Some(2));
+        });
+    }
+
+    #[test]
+    fn test_mig_structural_overlay() {
+        Python::with_gil(|_py| {
+            let mig = Mig::from_json(
+                r#"{
+                    "message_type": "ORDERS",
+                    "segments": [
+                        {"tag": "CUX", "status": "mandatory"},
+                        {"tag": "QTY", "group": "SG25", "max_repeat": 0}
+                    ],
+                    "groups": [{"name": "SG2", "max_repeat": 0}]
+                }"#,
+            )
+            .unwrap();
+
+            assert_eq!(
+                codes(&validate(&mig)),
+                vec![("36", "NAD"), ("13", "CUX"), ("35", "QTY")]
+            );
+
+            let unknown = Mig::from_json(
+                r#"{"message_type": "ORDERS", "segments": [{"tag": "CUX", "group": "SG25"}]}"#,
+            )
+            .unwrap();
+            let directory = Directory::new();
+            let definition = directory.require_message("ORDERS", None).unwrap();
+            assert!(unknown.apply(definition).is_err());
+        });
+    }
+    #[test]
+    fn test_mig_qualified_rules_per_group_occurrence() {
+        Python::with_gil(|_py| {
+            let mig = Mig::from_json(
+                r#"{
+                    "message_type": "ORDERS",
+                    "segments": [
+                        {"tag": "QTY", "qualifier": "21", "group": "SG25", "status": "mandatory", "max_repeat": 1}
+                    ]
+                }"#,
+            )
+            .unwrap();
+            let directory = Directory::new();
+            let definition = directory.require_message("ORDERS", None).unwrap();
+            let validate = |lines: &str| {
+                let content = format!(
+                    "UNH+1+ORDERS:D:96A:UN'\nBGM+220+123456+9'\nDTM+137:20240119:203'\n{}UNS+S'\nUNT+9+1'",
+                    lines
+                );
+                let order = crate::Order::from_edifact(content).unwrap();
+                mig.validate_segments(definition, &order.segments).unwrap()
+            };
+
+            assert_eq!(
+                codes(&validate(
+                    "LIN+1++A:BP'\nQTY+21:5'\nLIN+2++B:BP'\nQTY+21:3'\n"
+                )),
+                vec![]
+            );
+
+            let issues = validate("LIN+1++A:BP'\nQTY+21:5'\nQTY+21:1'\nLIN+2++B:BP'\n");
+            assert_eq!(codes(&issues), vec![("35", "QTY"), ("13", "QTY")]);
+            assert_eq!(issues[0].position, Some(3));
+            assert_eq!(issues[1].position, Some(6));
+
+            let conditional = Mig::from_json(
+                r#"{"message_type": "ORDERS", "segments": [{"tag": "ALI", "status": "conditional"}]}"#,
+            );
+            assert!(conditional.is_err());
+        });
+    }
+}
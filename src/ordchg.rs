@@ -0,0 +1,578 @@
+use std::ops::Range;
+
+use chrono::NaiveDateTime;
+use pyo3::prelude::*;
+
+use crate::common::{DateTimePeriod, Party, Reference};
+use crate::directory::{Directory, MessageDefinition, Node, SegmentGroup};
+use crate::document::{segment_of, Document};
+use crate::header::{component, MessageHeader};
+use crate::ordrsp::LineAction;
+use crate::{EdifactError, Message, Order, OrderLine, Segment};
+
+/// An ORDCHG purchase order change request. Each line carries a LIN action code: 1 adds
+/// the line, 2 deletes it and 3 changes it, where a changed line only needs the segments
+/// that change.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct OrderChange {
+    document: Document,
+}
+
+#[pymethods]
+impl OrderChange {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<OrderChange> {
+        Ok(OrderChange {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> OrderChange {
+        OrderChange {
+            document: Document::from_message(message),
+        }
+    }
+
+    /// The change turning `original` into `updated`, comparing their lines by line number.
+    /// An interchange header addressed back to the original's sender is added when a
+    /// control reference is given and the original has one.
+    #[staticmethod]
+    #[pyo3(signature = (
+        original,
+        updated,
+        document_number,
+        issued=None,
+        message_reference="1".to_string(),
+        control_reference=None,
+    ))]
+    fn from_orders(
+        original: &Order,
+        updated: &Order,
+        document_number: &str,
+        issued: Option<NaiveDateTime>,
+        message_reference: String,
+        control_reference: Option<String>,
+    ) -> PyResult<OrderChange> {
+        let order_number = original
+            .get_document_number()
+            .ok_or_else(|| EdifactError::new("Order has no BGM document number"))?;
+
+        let mut document = Document::new(
+            original.parser.delimiters.clone(),
+            original.parser.syntax_version,
+        );
+        if let (Some(control_reference), Some(header)) =
+            (control_reference, original.get_interchange_header()?)
+        {
+            document.set_interchange_header(&header.reply(
+                control_reference,
+                issued,
+                Some("ORDCHG".to_string()),
+            ))?;
+        }
+        let mut header = MessageHeader::new(
+            message_reference,
+            "ORDCHG".to_string(),
+            "D".to_string(),
+            "96A".to_string(),
+            "UN".to_string(),
+            None,
+        );
+        if let Some(original_header) = original.get_message_header()? {
+            header.version = original_header.version;
+            header.release = original_header.release;
+            header.agency = original_header.agency;
+            header.association_code = original_header.association_code;
+        }
+        document.set_message_header(&header)?;
+
+        let body = &mut document.segments;
+        body.push(segment_of("BGM", &[&["230"], &[document_number], &["4"]]));
+        if let Some(issued) = issued {
+            let date = issued.format("%Y%m%d").to_string();
+            body.push(segment_of("DTM", &[&["137", &date, "102"]]));
+        }
+        body.push(segment_of("RFF", &[&["ON", &order_number]]));
+        if let Some(ordered) = original
+            .get_dates()
+            .into_iter()
+            .find(|d| d.qualifier == "137")
+        {
+            let value = ordered.value.unwrap_or_default();
+            let format = ordered.format.unwrap_or_default();
+            body.push(segment_of("DTM", &[&["171", &value, &format]]));
+        }
+        let tree = updated.tree();
+        for tag in ["NAD", "CUX"] {
+            body.extend(
+                tree.groups_by_trigger(tag)
+                    .filter_map(|g| g.find_segment(tag).cloned()),
+            );
+        }
+
+        let before = line_ranges(&original.segments);
+        let after = line_ranges(&updated.segments);
+        let mut changed = 0;
+        for (number, range) in &after {
+            let segments = &updated.segments[range.clone()];
+            match before.iter().find(|(n, _)| n == number) {
+                None => body.extend(with_action(segments, LineAction::Added)),
+                Some((_, old)) if !same_segments(&original.segments[old.clone()], segments) => {
+                    body.extend(with_action(segments, LineAction::Changed))
+                }
+                Some(_) => continue,
+            }
+            changed += 1;
+        }
+        for (number, range) in &before {
+            if !after.iter().any(|(n, _)| n == number) {
+                body.extend(with_action(
+                    &original.segments[range.start..range.start + 1],
+                    LineAction::Deleted,
+                ));
+                changed += 1;
+            }
+        }
+
+        body.push(segment_of("UNS", &[&["S"]]));
+        body.push(segment_of("CNT", &[&["2", &changed.to_string()]]));
+        document.close();
+        Ok(OrderChange { document })
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_message_function(&self) -> Option<String> {
+        self.document.message_function()
+    }
+
+    /// The number of the order being changed, from RFF+ON.
+    fn get_order_number(&self) -> Option<String> {
+        self.get_references()
+            .into_iter()
+            .find(|reference| reference.qualifier == "ON")
+            .and_then(|reference| reference.number)
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    fn get_lines(&self) -> Vec<OrderLine> {
+        Document::lines(&self.tree())
+    }
+
+    /// Applies the line changes to `order`, returning the updated order. Added lines are
+    /// appended after the last line, deleted lines are removed, and the segments of a
+    /// changed line replace the order line's segments with the same tag and qualifier,
+    /// taking their place in the line's structure.
+    /// The UNT segment count and the CNT+2 line count are kept up to date.
+    fn apply_to(&self, order: &Order) -> PyResult<Order> {
+        let release = order
+            .message_header
+            .as_ref()
+            .and_then(|header| header.get_component(1, 2))
+            .map(String::as_str);
+        let definition = Directory::builtin()
+            .message("ORDERS", release)
+            .expect("built-in ORDERS definition");
+        let mut segments = order.segments.clone();
+        for (number, range) in line_ranges(&self.document.segments) {
+            let change = &self.document.segments[range];
+            let action = component(&change[0], 1, 0).and_then(|code| LineAction::from_code(&code));
+            let existing = line_ranges(&segments)
+                .into_iter()
+                .find(|(n, _)| *n == number)
+                .map(|(_, range)| range);
+            match (action, existing) {
+                (Some(LineAction::Added), None) => {
+                    let end = line_ranges(&segments)
+                        .last()
+                        .map(|(_, range)| range.end)
+                        .or_else(|| {
+                            segments
+                                .iter()
+                                .position(|s| s.tag == "UNS" || s.tag == "UNT")
+                        })
+                        .unwrap_or(segments.len());
+                    segments.splice(end..end, without_action(change));
+                }
+                (Some(LineAction::Added), Some(_)) => {
+                    return Err(
+                        EdifactError::new(format!("Order already has line {}", number)).into(),
+                    )
+                }
+                (Some(LineAction::Deleted), Some(range)) => {
+                    segments.drain(range);
+                }
+                (Some(LineAction::Changed), Some(range)) => {
+                    let merged = merge(definition, &segments[range.clone()], change);
+                    segments.splice(range, merged);
+                }
+                (Some(LineAction::Deleted | LineAction::Changed), None) => {
+                    return Err(EdifactError::new(format!("Order has no line {}", number)).into())
+                }
+                _ => {}
+            }
+        }
+
+        recount(&mut segments);
+        let mut updated = order.clone();
+        updated.segments = segments;
+        Ok(updated)
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl OrderChange {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("ORDCHG")
+    }
+}
+
+// Each line's number and the range of its LIN group, which runs to the next LIN or to the
+// summary section
+fn line_ranges(segments: &[Segment]) -> Vec<(String, Range<usize>)> {
+    let mut ranges: Vec<(String, Range<usize>)> = Vec::new();
+    for (index, segment) in segments.iter().enumerate() {
+        match segment.tag.as_str() {
+            "LIN" => {
+                let number = component(segment, 0, 0).unwrap_or_default();
+                ranges.push((number, index..index + 1));
+            }
+            "UNS" | "CNT" | "UNT" | "UNZ" if !ranges.is_empty() => break,
+            _ => {
+                if let Some((_, range)) = ranges.last_mut() {
+                    range.end = index + 1;
+                }
+            }
+        }
+    }
+    ranges
+}
+
+fn same_segments(left: &[Segment], right: &[Segment]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .all(|(l, r)| l.tag == r.tag && l.elements == r.elements)
+}
+
+// Copies a line's segments with the LIN action code (1229) set
+fn with_action(segments: &[Segment], action: LineAction) -> Vec<Segment> {
+    let mut segments = segments.to_vec();
+    set_action(&mut segments[0], Some(action.code()));
+    segments
+}
+
+fn without_action(segments: &[Segment]) -> Vec<Segment> {
+    let mut segments = segments.to_vec();
+    set_action(&mut segments[0], None);
+    segments
+}
+
+fn set_action(line: &mut Segment, action: Option<String>) {
+    while line.elements.len() < 2 {
+        line.elements.push(Vec::new());
+    }
+    line.elements[1] = action.into_iter().collect();
+    while line.elements.last().is_some_and(Vec::is_empty) {
+        line.elements.pop();
+    }
+}
+
+// Replaces the entries of the order line, each a segment or a nested group such as a PRI
+// group, that share a tag and first qualifier with an entry of the change; every such
+// occurrence goes. The entries are then put in their order in the LIN group, so a new
+// segment lands where ORDERS expects it rather than inside the last nested group. An item
+// number on the changed LIN replaces the original one.
+fn merge(definition: &MessageDefinition, line: &[Segment], change: &[Segment]) -> Vec<Segment> {
+    let mut entries = line_entries(definition, line);
+    if let Some(item) = change[0].elements.get(2).filter(|item| !item.is_empty()) {
+        let lin = &mut entries[0][0];
+        while lin.elements.len() < 3 {
+            lin.elements.push(Vec::new());
+        }
+        lin.elements[2] = item.clone();
+    }
+    let mut replaced = Vec::new();
+    for entry in line_entries(definition, change).into_iter().skip(1) {
+        let key = entry_key(&entry);
+        if !replaced.contains(&key) {
+            entries.retain(|existing| entry_key(existing) != key);
+            replaced.push(key);
+        }
+        entries.push(entry);
+    }
+    // Entries of the same kind keep their order
+    entries.sort_by_key(|entry| {
+        definition
+            .entry_index(Some("LIN"), &entry[0].tag)
+            .unwrap_or(usize::MAX)
+    });
+    entries.concat()
+}
+
+// The segments of a LIN group split into its entries, keeping each nested group together
+fn line_entries(definition: &MessageDefinition, segments: &[Segment]) -> Vec<Vec<Segment>> {
+    let entries: Vec<Vec<Segment>> = definition
+        .build_group("LIN", segments)
+        .map(|group| {
+            group
+                .nodes
+                .iter()
+                .map(|node| match node {
+                    Node::Segment(segment) => vec![segment.clone()],
+                    Node::Group(group) => {
+                        let mut segments = Vec::new();
+                        group.walk(&mut |_, segment| segments.push(segment.clone()));
+                        segments
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // A line the structure cannot hold is taken a segment at a time, so nothing is lost
+    if entries.iter().map(Vec::len).sum::<usize>() == segments.len() {
+        entries
+    } else {
+        segments
+            .iter()
+            .map(|segment| vec![segment.clone()])
+            .collect()
+    }
+}
+
+fn entry_key(entry: &[Segment]) -> (String, Option<String>) {
+    (entry[0].tag.clone(), component(&entry[0], 0, 0))
+}
+
+// Renumbers the segments and refreshes the UNT segment count and the CNT+2 line count
+fn recount(segments: &mut [Segment]) {
+    let lines = segments.iter().filter(|s| s.tag == "LIN").count();
+    // UNH is not among the body segments but counts towards UNT
+    let message_length = segments
+        .iter()
+        .position(|s| s.tag == "UNT")
+        .map(|index| index + 2);
+    for (position, segment) in segments.iter_mut().enumerate() {
+        segment.position = position;
+        match segment.tag.as_str() {
+            "CNT" if component(segment, 0, 0).as_deref() == Some("2") => {
+                segment.elements[0] = vec!["2".to_string(), lines.to_string()];
+            }
+            "UNT" => {
+                if let Some(length) = message_length {
+                    segment.elements[0] = vec![length.to_string()];
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const ORDER: &str = "UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240115:102'
+NAD+BY+5412345000013::9'
+LIN+1++4000862141404:SRV'
+QTY+21:48'
+PRI+AAA:10.00'
+LIN+2++4000862141411:SRV'
+QTY+21:10'
+UNS+S'
+CNT+2:2'
+UNT+12+1'";
+
+    const UPDATED: &str = "UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240115:102'
+NAD+BY+5412345000013::9'
+LIN+1++4000862141404:SRV'
+QTY+21:60'
+PRI+AAA:10.00'
+LIN+3++4000862141428:SRV'
+QTY+21:5'
+UNS+S'
+CNT+2:2'
+UNT+12+1'";
+
+    fn order(content: &str) -> Order {
+        Order::from_edifact(content.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_order_change_from_orders() {
+        Python::with_gil(|_py| {
+            let change = OrderChange::from_orders(
+                &order(ORDER),
+                &order(UPDATED),
+                "CHG1",
+                None,
+                "1".to_string(),
+                None,
+            )
+            .unwrap();
+            assert_eq!(
+                change.to_edifact(),
+                "UNH+1+ORDCHG:D:96A:UN'
+BGM+230+CHG1+4'
+RFF+ON:PO12345'
+DTM+171:20240115:102'
+NAD+BY+5412345000013::9'
+LIN+1+3+4000862141404:SRV'
+QTY+21:60'
+PRI+AAA:10.00'
+LIN+3+1+4000862141428:SRV'
+QTY+21:5'
+LIN+2+2+4000862141411:SRV'
+UNS+S'
+CNT+2:3'
+UNT+14+1'
+"
+            );
+            let actions: Vec<Option<LineAction>> = change
+                .get_lines()
+                .iter()
+                .map(|line| line.line_action())
+                .collect();
+            assert_eq!(
+                actions,
+                vec![
+                    Some(LineAction::Changed),
+                    Some(LineAction::Added),
+                    Some(LineAction::Deleted)
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn test_order_change_applies_to_order() {
+        Python::with_gil(|_py| {
+            let change = OrderChange::from_edifact(
+                "UNH+1+ORDCHG:D:96A:UN'
+BGM+230+CHG1+4'
+RFF+ON:PO12345'
+LIN+1+3'
+QTY+21:60'
+DTM+2:20240201:102'
+LIN+3+1+4000862141428:SRV'
+QTY+21:5'
+LIN+2+2'
+UNS+S'
+UNT+11+1'",
+            )
+            .unwrap();
+            let updated = change.apply_to(&order(ORDER)).unwrap();
+            assert_eq!(
+                updated.to_edifact().unwrap(),
+                "UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240115:102'
+NAD+BY+5412345000013::9'
+LIN+1++4000862141404:SRV'
+QTY+21:60'
+DTM+2:20240201:102'
+PRI+AAA:10.00'
+LIN+3++4000862141428:SRV'
+QTY+21:5'
+UNS+S'
+CNT+2:2'
+UNT+13+1'
+"
+            );
+
+            // The delivery date sits in the line itself, not in its PRI group
+            let lines = updated.get_order_lines().unwrap();
+            let dates = lines[0].dates();
+            assert_eq!(dates.len(), 1);
+            assert_eq!(dates[0].qualifier, "2");
+            assert_eq!(dates[0].value.as_deref(), Some("20240201"));
+
+            // Every occurrence of a changed segment is replaced, and a nested group is
+            // replaced as a whole
+            let repeated = order(
+                "UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+LIN+1++4000862141404:SRV'
+QTY+21:48'
+QTY+21:12'
+PRI+AAA:10.00'
+DTM+194:20240101:102'
+UNS+S'
+UNT+9+1'",
+            );
+            let change = OrderChange::from_edifact(
+                "UNH+1+ORDCHG:D:96A:UN'
+BGM+230+CHG1+4'
+LIN+1+3'
+QTY+21:50'
+PRI+AAA:9.50'
+UNT+6+1'",
+            )
+            .unwrap();
+            assert_eq!(
+                change.apply_to(&repeated).unwrap().to_edifact().unwrap(),
+                "UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+LIN+1++4000862141404:SRV'
+QTY+21:50'
+PRI+AAA:9.50'
+UNS+S'
+UNT+7+1'
+"
+            );
+
+            let unknown = OrderChange::from_edifact(
+                "UNH+1+ORDCHG:D:96A:UN'
+BGM+230+CHG2+4'
+LIN+9+2'
+UNT+4+1'",
+            )
+            .unwrap();
+            assert!(unknown.apply_to(&order(ORDER)).is_err());
+        });
+    }
+}
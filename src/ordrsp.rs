@@ -0,0 +1,527 @@
+use chrono::NaiveDateTime;
+use pyo3::prelude::*;
+
+use crate::common::{DateTimePeriod, Party, Reference};
+use crate::directory::SegmentGroup;
+use crate::document::{segment_of, Document};
+use crate::header::{self, MessageHeader};
+use crate::{check_decimal, EdifactError, Message, Order, OrderLine, Segment};
+
+/// LIN 1229 action codes used to answer an order line.
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineAction {
+    Added = 1,
+    Deleted = 2,
+    Changed = 3,
+    NoAction = 4,
+    Accepted = 5,
+    AcceptedWithAmendment = 6,
+    NotAccepted = 7,
+}
+
+#[pymethods]
+impl LineAction {
+    #[staticmethod]
+    pub(crate) fn from_code(code: &str) -> Option<LineAction> {
+        match code {
+            "1" => Some(LineAction::Added),
+            "2" => Some(LineAction::Deleted),
+            "3" => Some(LineAction::Changed),
+            "4" => Some(LineAction::NoAction),
+            "5" => Some(LineAction::Accepted),
+            "6" => Some(LineAction::AcceptedWithAmendment),
+            "7" => Some(LineAction::NotAccepted),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn code(&self) -> String {
+        (*self as u8).to_string()
+    }
+}
+
+/// The answer to one order line. Quantity, delivery date (CCYYMMDD) and price are only
+/// needed where they differ from the order.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct LineDecision {
+    #[pyo3(get, set)]
+    line_number: String,
+    #[pyo3(get, set)]
+    action: LineAction,
+    #[pyo3(get, set)]
+    quantity: Option<String>,
+    #[pyo3(get, set)]
+    delivery_date: Option<String>,
+    #[pyo3(get, set)]
+    price: Option<String>,
+}
+
+#[pymethods]
+impl LineDecision {
+    #[new]
+    #[pyo3(signature = (line_number, action, quantity=None, delivery_date=None, price=None))]
+    fn new(
+        line_number: String,
+        action: LineAction,
+        quantity: Option<String>,
+        delivery_date: Option<String>,
+        price: Option<String>,
+    ) -> Self {
+        LineDecision {
+            line_number,
+            action,
+            quantity,
+            delivery_date,
+            price,
+        }
+    }
+}
+
+impl LineDecision {
+    fn check(&self) -> Result<(), EdifactError> {
+        if let Some(quantity) = &self.quantity {
+            check_decimal(quantity, "quantity")?;
+        }
+        if let Some(date) = &self.delivery_date {
+            DateTimePeriod::check(date, "102")?;
+        }
+        if let Some(price) = &self.price {
+            check_decimal(price, "price")?;
+        }
+        Ok(())
+    }
+}
+
+/// An ORDRSP purchase order response, parsed or generated from the order it answers.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct OrderResponse {
+    document: Document,
+}
+
+#[pymethods]
+impl OrderResponse {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<OrderResponse> {
+        Ok(OrderResponse {
+            document: Document::parse(content)?,
+        })
+    }
+
+    /// Reads an ORDRSP taken from an `Interchange`.
+    #[staticmethod]
+    fn from_message(message: &Message) -> OrderResponse {
+        OrderResponse {
+            document: Document::from_message(message),
+        }
+    }
+
+    /// Answers `order` line by line, dated `issued` (DTM+137, which ORDRSP requires). Lines
+    /// without a decision are accepted without amendment; a line takes at most one decision,
+    /// and its quantity, price and delivery date are checked before anything is written. An interchange header addressed
+    /// back to the order's sender is added when a control reference is given and the order
+    /// has one.
+    #[staticmethod]
+    #[pyo3(signature = (
+        order,
+        document_number,
+        decisions,
+        issued,
+        message_reference="1".to_string(),
+        control_reference=None,
+    ))]
+    pub(crate) fn from_order(
+        order: &Order,
+        document_number: &str,
+        decisions: Vec<LineDecision>,
+        issued: NaiveDateTime,
+        message_reference: String,
+        control_reference: Option<String>,
+    ) -> PyResult<OrderResponse> {
+        let order_number = order
+            .get_document_number()
+            .ok_or_else(|| EdifactError::new("Order has no BGM document number"))?;
+        let lines = flatten(order.get_order_lines()?);
+        if let Some(unknown) = decisions.iter().find(|decision| {
+            !lines
+                .iter()
+                .any(|line| line.line_number().as_ref() == Some(&decision.line_number))
+        }) {
+            return Err(EdifactError::new(format!(
+                "Order {} has no line {}",
+                order_number, unknown.line_number
+            ))
+            .into());
+        }
+        for (index, decision) in decisions.iter().enumerate() {
+            if decisions[..index]
+                .iter()
+                .any(|earlier| earlier.line_number == decision.line_number)
+            {
+                return Err(EdifactError::new(format!(
+                    "More than one decision for line {}",
+                    decision.line_number
+                ))
+                .into());
+            }
+            decision.check()?;
+        }
+
+        let mut document =
+            Document::new(order.parser.delimiters.clone(), order.parser.syntax_version);
+        if let (Some(control_reference), Some(order_header)) =
+            (control_reference, order.get_interchange_header()?)
+        {
+            document.set_interchange_header(&order_header.reply(
+                control_reference,
+                Some(issued),
+                Some("ORDRSP".to_string()),
+            ))?;
+        }
+
+        let order_header = order.get_message_header()?;
+        let mut header = MessageHeader::new(
+            message_reference,
+            "ORDRSP".to_string(),
+            "D".to_string(),
+            "96A".to_string(),
+            "UN".to_string(),
+            None,
+        );
+        if let Some(order_header) = order_header {
+            header.version = order_header.version;
+            header.release = order_header.release;
+            header.agency = order_header.agency;
+            header.association_code = order_header.association_code;
+        }
+        document.set_message_header(&header)?;
+
+        let actions: Vec<LineAction> = lines
+            .iter()
+            .map(|line| decision_for(&decisions, line).map_or(LineAction::Accepted, |d| d.action))
+            .collect();
+        let function = if actions.iter().all(|a| *a == LineAction::Accepted) {
+            "29"
+        } else if actions.iter().all(|a| *a == LineAction::NotAccepted) {
+            "27"
+        } else {
+            "4"
+        };
+
+        let body = &mut document.segments;
+        body.push(segment_of(
+            "BGM",
+            &[&["231"], &[document_number], &[function]],
+        ));
+        let date = issued.format("%Y%m%d").to_string();
+        body.push(segment_of("DTM", &[&["137", &date, "102"]]));
+        body.push(segment_of("RFF", &[&["ON", &order_number]]));
+        if let Some(ordered) = order.get_dates().into_iter().find(|d| d.qualifier == "137") {
+            let value = ordered.value.unwrap_or_default();
+            let format = ordered.format.unwrap_or_default();
+            body.push(segment_of("DTM", &[&["171", &value, &format]]));
+        }
+        let tree = order.tree();
+        for tag in ["NAD", "CUX"] {
+            body.extend(
+                tree.groups_by_trigger(tag)
+                    .filter_map(|g| g.find_segment(tag).cloned()),
+            );
+        }
+
+        for (line, action) in lines.iter().zip(&actions) {
+            let decision = decision_for(&decisions, line);
+            answer_line(body, line, *action, decision, &order_number);
+        }
+
+        body.push(segment_of("UNS", &[&["S"]]));
+        body.push(segment_of("CNT", &[&["2", &lines.len().to_string()]]));
+
+        document.close();
+
+        document.check_structure("ORDRSP")?;
+        Ok(OrderResponse { document })
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    #[getter]
+    fn message_trailer(&self) -> Option<Segment> {
+        self.document.message_trailer.clone()
+    }
+
+    #[getter]
+    fn interchange_trailer(&self) -> Option<Segment> {
+        self.document.interchange_trailer.clone()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    /// BGM 1225: 29 accepted without amendment, 4 changed or 27 not accepted.
+    fn get_message_function(&self) -> Option<String> {
+        self.document.message_function()
+    }
+
+    /// The number of the order being answered, from RFF+ON.
+    fn get_order_number(&self) -> Option<String> {
+        self.get_references()
+            .into_iter()
+            .find(|reference| reference.qualifier == "ON")
+            .and_then(|reference| reference.number)
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    fn get_lines(&self) -> Vec<OrderLine> {
+        Document::lines(&self.tree())
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl OrderResponse {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("ORDRSP")
+    }
+}
+
+// Writes the LIN group answering one order line, referring back to it with RFF+ON
+fn answer_line(
+    body: &mut Vec<Segment>,
+    line: &OrderLine,
+    action: LineAction,
+    decision: Option<&LineDecision>,
+    order_number: &str,
+) {
+    let original = line.line_segment();
+    let line_number = line.line_number().unwrap_or_default();
+    let mut elements = vec![vec![line_number.clone()], vec![action.code()]];
+    elements.extend(original.elements.iter().skip(2).cloned());
+    body.push(Segment::new(
+        "LIN".to_string(),
+        header::trim_elements(elements),
+        0,
+    ));
+    body.extend(line.segments().into_iter().filter(|s| s.tag == "PIA"));
+
+    let ordered = line.quantities().into_iter().find(|q| q.qualifier == "21");
+    let quantity = decision
+        .and_then(|d| d.quantity.clone())
+        .or_else(|| ordered.as_ref().and_then(|q| q.value.clone()));
+    if let Some(quantity) = quantity {
+        let unit = ordered.and_then(|q| q.unit).unwrap_or_default();
+        body.push(segment_of("QTY", &[&["21", &quantity, &unit]]));
+    }
+    if let Some(date) = decision.and_then(|d| d.delivery_date.as_deref()) {
+        body.push(segment_of("DTM", &[&["2", date, "102"]]));
+    }
+
+    match decision.and_then(|d| d.price.as_deref()) {
+        Some(price) => body.push(segment_of("PRI", &[&["AAA", price]])),
+        None => body.extend(line.segments().into_iter().filter(|s| s.tag == "PRI")),
+    }
+    body.push(segment_of("RFF", &[&["ON", order_number, &line_number]]));
+}
+
+fn decision_for<'a>(decisions: &'a [LineDecision], line: &OrderLine) -> Option<&'a LineDecision> {
+    let number = line.line_number()?;
+    decisions.iter().find(|d| d.line_number == number)
+}
+
+// Lines in document order, with sub-lines following their parent
+fn flatten(lines: Vec<OrderLine>) -> Vec<OrderLine> {
+    let mut flat = Vec::new();
+    for mut line in lines {
+        let sub_lines = std::mem::take(&mut line.sub_lines);
+        flat.push(line);
+        flat.extend(flatten(sub_lines));
+    }
+    flat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const ORDER: &str = "UNB+UNOC:4+BUYER:14+SUPPLIER:14+20240119:1200+ICR1'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240119:102'
+NAD+BY+5412345000013::9'
+NAD+SU+4012345000016::9'
+LIN+1++4000862141404:SRV'
+QTY+21:48:PCE'
+PRI+AAA:10.00'
+LIN+2++4000862141411:SRV'
+QTY+21:12:PCE'
+LIN+3++4000862141428:SRV'
+QTY+21:6:PCE'
+UNS+S'
+UNT+13+1'
+UNZ+1+ICR1'";
+
+    fn issued() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 20)
+            .and_then(|d| d.and_hms_opt(9, 30, 0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_order_response_from_order() {
+        Python::with_gil(|_py| {
+            let order = Order::from_edifact(ORDER.to_string()).unwrap();
+            let decisions = vec![
+                LineDecision::new(
+                    "2".to_string(),
+                    LineAction::Changed,
+                    Some("10".to_string()),
+                    Some("20240130".to_string()),
+                    None,
+                ),
+                LineDecision::new("3".to_string(), LineAction::NotAccepted, None, None, None),
+            ];
+            let issued = issued();
+            let response = OrderResponse::from_order(
+                &order,
+                "RSP1",
+                decisions,
+                issued,
+                "1".to_string(),
+                Some("ICR2".to_string()),
+            )
+            .unwrap();
+
+            let edifact = response.to_edifact();
+            let lines: Vec<&str> = edifact.lines().collect();
+            assert_eq!(
+                lines[0],
+                "UNB+UNOC:4+SUPPLIER:14+BUYER:14+20240120:0930+ICR2++ORDRSP'"
+            );
+            assert_eq!(lines[1], "UNH+1+ORDRSP:D:96A:UN'");
+            assert_eq!(lines[2], "BGM+231+RSP1+4'");
+            let trailer = response.message_trailer().unwrap();
+            assert_eq!(trailer.elements[1], vec!["1"]);
+            assert!(response.interchange_trailer().is_some());
+            assert!(lines.contains(&"RFF+ON:PO12345'"));
+            assert!(lines.contains(&"DTM+171:20240119:102'"));
+            assert!(lines.contains(&"LIN+2+3+4000862141411:SRV'"));
+            assert!(lines.contains(&"QTY+21:10:PCE'"));
+            assert!(lines.contains(&"RFF+ON:PO12345:3'"));
+            assert_eq!(
+                lines[lines.len() - 2],
+                format!("UNT+{}+1'", lines.len() - 2)
+            );
+            assert_eq!(lines[lines.len() - 1], "UNZ+1+ICR2'");
+
+            let parsed = OrderResponse::from_edifact(&edifact).unwrap();
+            assert_eq!(parsed.get_order_number().as_deref(), Some("PO12345"));
+            assert_eq!(parsed.get_message_function().as_deref(), Some("4"));
+            let actions: Vec<Option<LineAction>> =
+                parsed.get_lines().iter().map(|l| l.line_action()).collect();
+            assert_eq!(
+                actions,
+                vec![
+                    Some(LineAction::Accepted),
+                    Some(LineAction::Changed),
+                    Some(LineAction::NotAccepted)
+                ]
+            );
+            assert_eq!(parsed.get_parties().len(), 2);
+        });
+    }
+
+    #[test]
+    fn test_order_response_rejects_unknown_lines() {
+        Python::with_gil(|_py| {
+            let order = Order::from_edifact(ORDER.to_string()).unwrap();
+            let decisions = vec![LineDecision::new(
+                "9".to_string(),
+                LineAction::Accepted,
+                None,
+                None,
+                None,
+            )];
+            let result = OrderResponse::from_order(
+                &order,
+                "RSP1",
+                decisions,
+                issued(),
+                "1".to_string(),
+                None,
+            );
+            assert!(result.is_err());
+
+            let decision = |quantity: Option<&str>, date: Option<&str>, price: Option<&str>| {
+                LineDecision::new(
+                    "1".to_string(),
+                    LineAction::AcceptedWithAmendment,
+                    quantity.map(str::to_string),
+                    date.map(str::to_string),
+                    price.map(str::to_string),
+                )
+            };
+            for decisions in [
+                vec![decision(Some("abc"), None, None)],
+                vec![decision(None, Some("2024-13-99"), None)],
+                vec![decision(None, Some("20241399"), None)],
+                vec![decision(None, None, Some("x y"))],
+                vec![
+                    decision(Some("5"), None, None),
+                    decision(Some("6"), None, None),
+                ],
+            ] {
+                assert!(OrderResponse::from_order(
+                    &order,
+                    "RSP1",
+                    decisions,
+                    issued(),
+                    "1".to_string(),
+                    None
+                )
+                .is_err());
+            }
+
+            let all_accepted =
+                OrderResponse::from_order(&order, "RSP1", vec![], issued(), "1".to_string(), None)
+                    .unwrap();
+            assert_eq!(all_accepted.get_message_function().as_deref(), Some("29"));
+            assert!(all_accepted.document.interchange_header.is_none());
+        });
+    }
+}
@@ -0,0 +1,750 @@
+use pyo3::prelude::*;
+
+use crate::common::{format_cents, parse_whole_cents, DateTimePeriod, MonetaryAmount, Reference};
+use crate::directory::SegmentGroup;
+use crate::document::{segment_of, Document};
+use crate::header::{component, InterchangeHeader, MessageHeader};
+use crate::{check_code, EdifactError, Message, Segment};
+
+pub(crate) const PAYMENT_MESSAGES: &[&str] = &["PAYMUL", "PAYORD"];
+
+/// Checks an IBAN's ISO 13616 check digits (mod 97 of the rearranged number).
+pub(crate) fn is_valid_iban(iban: &str) -> bool {
+    let iban: String = iban.chars().filter(|c| !c.is_whitespace()).collect();
+    if !(15..=34).contains(&iban.len()) || !iban.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return false;
+    }
+    let (head, tail) = iban.split_at(4);
+    let mut remainder = 0u32;
+    for c in tail.chars().chain(head.chars()) {
+        let Some(value) = c.to_ascii_uppercase().to_digit(36) else {
+            return false;
+        };
+        remainder = if value < 10 {
+            (remainder * 10 + value) % 97
+        } else {
+            (remainder * 100 + value) % 97
+        };
+    }
+    remainder == 1
+}
+
+// The first segment in the group, nested groups included, with the tag and qualifier
+fn first<'a>(group: &'a SegmentGroup, tag: &str, qualifier: &str) -> Option<&'a Segment> {
+    let mut found = None;
+    group.walk(&mut |_, segment| {
+        if found.is_none()
+            && segment.tag == tag
+            && component(segment, 0, 0).as_deref() == Some(qualifier)
+        {
+            found = Some(segment);
+        }
+    });
+    found
+}
+
+/// One credit transfer of a payment order: the beneficiary's account (FII+BF) and name
+/// (NAD+BE), the amount (MOA+9), the customer reference (RFF+CR) and any remittance
+/// information, with the ordering account (FII+OR) and execution date (DTM+203) it is
+/// debited from and on.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payment {
+    #[pyo3(get)]
+    beneficiary: Option<String>,
+    /// FII C078 3194 account number, usually an IBAN
+    #[pyo3(get)]
+    account: Option<String>,
+    /// FII C088 3433 institution code, usually a BIC
+    #[pyo3(get)]
+    bic: Option<String>,
+    #[pyo3(get)]
+    amount: Option<String>,
+    #[pyo3(get)]
+    currency: Option<String>,
+    #[pyo3(get)]
+    reference: Option<String>,
+    #[pyo3(get)]
+    remittance_information: Option<String>,
+    #[pyo3(get)]
+    execution_date: Option<String>,
+    #[pyo3(get)]
+    ordering_account: Option<String>,
+}
+
+impl Payment {
+    // The payment's own details come from its group, the ordering account and execution
+    // date from the batch it belongs to
+    fn from_groups(batch: &SegmentGroup, group: &SegmentGroup) -> Option<Self> {
+        let amount = first(group, "MOA", "9").map(MonetaryAmount::from_segment)?;
+        let account = first(group, "FII", "BF");
+        let text: Vec<String> = group
+            .groups_by_trigger("PRC")
+            .flat_map(|prc| prc.segments_by_tag("FTX"))
+            .flat_map(|ftx| (0..5).filter_map(move |index| component(ftx, 3, index)))
+            .collect();
+        Some(Payment {
+            beneficiary: first(group, "NAD", "BE")
+                .and_then(|nad| component(nad, 3, 0))
+                .or_else(|| account.and_then(|fii| component(fii, 1, 1))),
+            account: account.and_then(|fii| component(fii, 1, 0)),
+            bic: account.and_then(|fii| component(fii, 2, 0)),
+            amount: amount.amount,
+            currency: amount.currency,
+            reference: first(group, "RFF", "CR").and_then(|rff| component(rff, 0, 1)),
+            remittance_information: (!text.is_empty()).then(|| text.join(" ")),
+            execution_date: first(batch, "DTM", "203").and_then(|dtm| component(dtm, 0, 1)),
+            ordering_account: first(batch, "FII", "OR").and_then(|fii| component(fii, 1, 0)),
+        })
+    }
+}
+
+/// A PAYMUL multiple payment order or a PAYORD single payment order. PAYMUL batches its
+/// payments in LIN groups, one per ordering account, currency and execution date, each
+/// with an MOA control total.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct PaymentOrder {
+    document: Document,
+    message_type: String,
+}
+
+#[pymethods]
+impl PaymentOrder {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<PaymentOrder> {
+        PaymentOrder::new(Document::parse(content)?)
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> PyResult<PaymentOrder> {
+        PaymentOrder::new(Document::from_message(message))
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    /// PAYMUL or PAYORD.
+    #[getter]
+    fn message_type(&self) -> String {
+        self.message_type.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_payments(&self) -> Vec<Payment> {
+        let tree = self.tree();
+        self.batches(&tree)
+            .into_iter()
+            .flat_map(|batch| {
+                self.payment_groups(batch)
+                    .into_iter()
+                    .filter_map(|group| Payment::from_groups(batch, group))
+            })
+            .collect()
+    }
+
+    /// The MOA control total of every batch; for PAYORD, the amount of its one payment.
+    fn get_control_totals(&self) -> Vec<MonetaryAmount> {
+        let tree = self.tree();
+        self.batches(&tree)
+            .into_iter()
+            .filter_map(|batch| batch.groups_by_trigger("MOA").next())
+            .filter_map(|group| group.find_segment("MOA"))
+            .map(MonetaryAmount::from_segment)
+            .collect()
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl PaymentOrder {
+    fn new(document: Document) -> PyResult<PaymentOrder> {
+        let message_type = document
+            .message_header
+            .as_ref()
+            .and_then(|header| component(header, 1, 0))
+            .filter(|message_type| PAYMENT_MESSAGES.contains(&message_type.as_str()))
+            .ok_or_else(|| EdifactError::new("Not a PAYMUL or PAYORD message"))?;
+        Ok(PaymentOrder {
+            document,
+            message_type,
+        })
+    }
+
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree(&self.message_type)
+    }
+
+    // PAYMUL's LIN groups; a PAYORD is a single batch of a single payment
+    fn batches<'a>(&self, tree: &'a SegmentGroup) -> Vec<&'a SegmentGroup> {
+        if self.message_type == "PAYMUL" {
+            tree.groups_by_trigger("LIN").collect()
+        } else {
+            vec![tree]
+        }
+    }
+
+    fn payment_groups<'a>(&self, batch: &'a SegmentGroup) -> Vec<&'a SegmentGroup> {
+        if self.message_type == "PAYMUL" {
+            batch.groups_by_trigger("SEQ").collect()
+        } else {
+            vec![batch]
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PaymentRecord {
+    beneficiary: String,
+    iban: String,
+    bic: Option<String>,
+    cents: i64,
+    currency: String,
+    reference: String,
+    remittance_information: Option<String>,
+}
+
+impl PaymentRecord {
+    // FII+BF with the beneficiary as account holder and the BIC as institution
+    fn account(&self) -> Segment {
+        let bic: &[&str] = match &self.bic {
+            Some(bic) => &[bic, "25", "5"],
+            None => &[],
+        };
+        segment_of("FII", &[&["BF"], &[&self.iban, &self.beneficiary], bic])
+    }
+
+    fn amount(&self) -> Segment {
+        segment_of("MOA", &[&["9", &format_cents(self.cents), &self.currency]])
+    }
+
+    fn remittance(&self) -> Vec<Segment> {
+        match &self.remittance_information {
+            Some(text) => vec![
+                segment_of("PRC", &[&["11"]]),
+                segment_of("FTX", &[&["PMD"], &[], &[], &[text]]),
+            ],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Builds a PAYMUL or PAYORD from payment records. PAYMUL batches the payments per
+/// currency in LIN groups, computing each batch's MOA control total and the CNT totals of
+/// the message; PAYORD carries exactly one payment.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct PaymentOrderBuilder {
+    document: Document,
+    message_type: String,
+    document_number: String,
+    dates: Vec<Segment>,
+    execution_date: Option<String>,
+    ordering_account: Option<Segment>,
+    payments: Vec<PaymentRecord>,
+}
+
+#[pymethods]
+impl PaymentOrderBuilder {
+    #[new]
+    #[pyo3(signature = (
+        message_type,
+        document_number,
+        message_reference="1".to_string(),
+        release="96A".to_string(),
+        association_code=None,
+    ))]
+    fn new(
+        message_type: &str,
+        document_number: String,
+        message_reference: String,
+        release: String,
+        association_code: Option<String>,
+    ) -> PyResult<PaymentOrderBuilder> {
+        if !PAYMENT_MESSAGES.contains(&message_type) {
+            return Err(EdifactError::new(format!(
+                "Unsupported payment message type '{}'",
+                message_type
+            ))
+            .into());
+        }
+        let mut document = Document::new(Default::default(), Default::default());
+        document.set_message_header(&MessageHeader::new(
+            message_reference,
+            message_type.to_string(),
+            "D".to_string(),
+            release,
+            "UN".to_string(),
+            association_code,
+        ))?;
+        Ok(PaymentOrderBuilder {
+            document,
+            message_type: message_type.to_string(),
+            document_number,
+            dates: Vec::new(),
+            execution_date: None,
+            ordering_account: None,
+            payments: Vec::new(),
+        })
+    }
+
+    fn with_interchange<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        header: InterchangeHeader,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.document.syntax_version = header.syntax_version;
+        slf.document.delimiters = header.syntax_version.default_delimiters();
+        slf.document.set_interchange_header(&header)?;
+        Ok(slf)
+    }
+
+    #[pyo3(signature = (qualifier, value, format="102"))]
+    fn with_date<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        qualifier: &str,
+        value: &str,
+        format: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        DateTimePeriod::check(value, format)?;
+        slf.dates
+            .push(segment_of("DTM", &[&[qualifier, value, format]]));
+        Ok(slf)
+    }
+
+    /// Sets the requested execution date (DTM+203, CCYYMMDD) of the payments.
+    fn with_execution_date<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        date: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        DateTimePeriod::check(date, "102")?;
+        slf.execution_date = Some(date.to_string());
+        Ok(slf)
+    }
+
+    /// Sets the account the payments are debited from (FII+OR).
+    #[pyo3(signature = (iban, bic=None, holder=None))]
+    fn with_ordering_account<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        iban: &str,
+        bic: Option<&str>,
+        holder: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        check_iban(iban)?;
+        let bic: &[&str] = match bic {
+            Some(bic) => &[bic, "25", "5"],
+            None => &[],
+        };
+        slf.ordering_account = Some(segment_of(
+            "FII",
+            &[&["OR"], &[iban, holder.unwrap_or_default()], bic],
+        ));
+        Ok(slf)
+    }
+
+    /// Adds a credit transfer to the beneficiary's account. The IBAN's check digits are
+    /// verified; the reference is the ordering customer's (RFF+CR).
+    #[pyo3(signature = (
+        beneficiary,
+        iban,
+        amount,
+        currency,
+        reference,
+        bic=None,
+        remittance_information=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn add_payment<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        beneficiary: &str,
+        iban: &str,
+        amount: &str,
+        currency: &str,
+        reference: &str,
+        bic: Option<&str>,
+        remittance_information: Option<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        check_iban(iban)?;
+        // An amount is transferred to the cent, so anything finer is a mistake, not rounding
+        let cents = parse_whole_cents(amount)
+            .filter(|cents| *cents > 0)
+            .ok_or_else(|| EdifactError::new(format!("Invalid payment amount '{}'", amount)))?;
+        check_code(currency, 3, "currency")?;
+        // The batch totals and the hash total must still fit
+        if slf
+            .payments
+            .iter()
+            .try_fold(cents, |total, payment| total.checked_add(payment.cents))
+            .is_none()
+        {
+            return Err(EdifactError::new("The payment total is too large").into());
+        }
+        if slf.message_type == "PAYORD" && !slf.payments.is_empty() {
+            return Err(EdifactError::new("A PAYORD carries a single payment").into());
+        }
+        slf.payments.push(PaymentRecord {
+            beneficiary: beneficiary.to_string(),
+            iban: iban.to_string(),
+            bic: bic.map(str::to_string),
+            cents,
+            currency: currency.to_string(),
+            reference: reference.to_string(),
+            remittance_information: remittance_information.map(str::to_string),
+        });
+        Ok(slf)
+    }
+
+    /// Writes the message with its control totals and checks it against the structure for
+    /// its type.
+    fn build(&self) -> PyResult<PaymentOrder> {
+        if self.payments.is_empty() {
+            return Err(EdifactError::new("A payment order needs at least one payment").into());
+        }
+        let Some(ordering_account) = &self.ordering_account else {
+            return Err(EdifactError::new("A payment order needs an ordering account").into());
+        };
+        let mut document = self.document.clone();
+        let body = &mut document.segments;
+        let name = if self.message_type == "PAYMUL" {
+            "452"
+        } else {
+            "450"
+        };
+        body.push(segment_of(
+            "BGM",
+            &[&[name], &[&self.document_number], &["9"]],
+        ));
+        body.extend(self.dates.iter().cloned());
+        let execution_date = self
+            .execution_date
+            .as_deref()
+            .map(|date| segment_of("DTM", &[&["203", date, "102"]]));
+
+        if self.message_type == "PAYMUL" {
+            // One batch per currency, in order of first use
+            let mut currencies: Vec<&str> = Vec::new();
+            for payment in &self.payments {
+                if !currencies.contains(&payment.currency.as_str()) {
+                    currencies.push(&payment.currency);
+                }
+            }
+            for (index, currency) in currencies.iter().enumerate() {
+                let batch: Vec<&PaymentRecord> = self
+                    .payments
+                    .iter()
+                    .filter(|payment| payment.currency == *currency)
+                    .collect();
+                let total: i64 = batch.iter().map(|payment| payment.cents).sum();
+                body.push(segment_of("LIN", &[&[&(index + 1).to_string()]]));
+                body.extend(execution_date.clone());
+                body.push(segment_of("MOA", &[&["9", &format_cents(total), currency]]));
+                body.push(ordering_account.clone());
+                for (number, payment) in batch.iter().enumerate() {
+                    body.push(segment_of("SEQ", &[&[], &[&(number + 1).to_string()]]));
+                    body.push(payment.amount());
+                    body.push(segment_of("RFF", &[&["CR", &payment.reference]]));
+                    body.push(payment.account());
+                    body.push(segment_of(
+                        "NAD",
+                        &[&["BE"], &[], &[], &[&payment.beneficiary]],
+                    ));
+                    body.extend(payment.remittance());
+                }
+            }
+            // CNT+1 is the hash total of all amounts, CNT+2 the number of batches
+            let total: i64 = self.payments.iter().map(|payment| payment.cents).sum();
+            body.push(segment_of("CNT", &[&["1", &format_cents(total)]]));
+            body.push(segment_of("CNT", &[&["2", &currencies.len().to_string()]]));
+        } else {
+            let payment = &self.payments[0];
+            body.extend(execution_date);
+            body.push(segment_of("RFF", &[&["CR", &payment.reference]]));
+            body.push(payment.amount());
+            body.push(ordering_account.clone());
+            body.push(payment.account());
+            body.push(segment_of(
+                "NAD",
+                &[&["BE"], &[], &[], &[&payment.beneficiary]],
+            ));
+            body.extend(payment.remittance());
+        }
+        document.close();
+
+        document.check_structure(&self.message_type)?;
+        PaymentOrder::new(document)
+    }
+}
+
+fn check_iban(iban: &str) -> Result<(), EdifactError> {
+    if is_valid_iban(iban) {
+        Ok(())
+    } else {
+        Err(EdifactError::new(format!(
+            "IBAN '{}' fails the ISO 13616 check digits",
+            iban
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    #[test]
+    fn test_iban_check_digits() {
+        assert!(is_valid_iban("GB82WEST12345698765432"));
+        assert!(is_valid_iban("DE89 3704 0044 0532 0130 00"));
+        assert!(!is_valid_iban("GB82WEST12345698765433"));
+        assert!(!is_valid_iban("NL91"));
+    }
+
+    #[test]
+    fn test_multiple_payment_order_totals() {
+        Python::with_gil(|py| {
+            let builder = PaymentOrderBuilder::new(
+                "PAYMUL",
+                "PM0001".to_string(),
+                "1".to_string(),
+                "96A".to_string(),
+                None,
+            )
+            .unwrap();
+            let builder = Bound::new(py, builder).unwrap();
+            PaymentOrderBuilder::with_date(builder.borrow_mut(), "137", "20240301", "102").unwrap();
+            PaymentOrderBuilder::with_execution_date(builder.borrow_mut(), "20240305").unwrap();
+            PaymentOrderBuilder::with_ordering_account(
+                builder.borrow_mut(),
+                "NL91ABNA0417164300",
+                Some("ABNANL2A"),
+                Some("Acme BV"),
+            )
+            .unwrap();
+            PaymentOrderBuilder::add_payment(
+                builder.borrow_mut(),
+                "Supplier GmbH",
+                "DE89370400440532013000",
+                "1190",
+                "EUR",
+                "INV001",
+                Some("COBADEFF"),
+                Some("Invoice INV001"),
+            )
+            .unwrap();
+            PaymentOrderBuilder::add_payment(
+                builder.borrow_mut(),
+                "Widgets Ltd",
+                "GB82WEST12345698765432",
+                "250.5",
+                "GBP",
+                "INV002",
+                None,
+                None,
+            )
+            .unwrap();
+            PaymentOrderBuilder::add_payment(
+                builder.borrow_mut(),
+                "Parts GmbH",
+                "DE89370400440532013000",
+                "10.25",
+                "EUR",
+                "INV003",
+                None,
+                None,
+            )
+            .unwrap();
+            let order = builder.borrow().build().unwrap();
+            assert_eq!(
+                order.to_edifact(),
+                "UNH+1+PAYMUL:D:96A:UN'
+BGM+452+PM0001+9'
+DTM+137:20240301:102'
+LIN+1'
+DTM+203:20240305:102'
+MOA+9:1200.25:EUR'
+FII+OR+NL91ABNA0417164300:Acme BV+ABNANL2A:25:5'
+SEQ++1'
+MOA+9:1190.00:EUR'
+RFF+CR:INV001'
+FII+BF+DE89370400440532013000:Supplier GmbH+COBADEFF:25:5'
+NAD+BE+++Supplier GmbH'
+PRC+11'
+FTX+PMD+++Invoice INV001'
+SEQ++2'
+MOA+9:10.25:EUR'
+RFF+CR:INV003'
+FII+BF+DE89370400440532013000:Parts GmbH'
+NAD+BE+++Parts GmbH'
+LIN+2'
+DTM+203:20240305:102'
+MOA+9:250.50:GBP'
+FII+OR+NL91ABNA0417164300:Acme BV+ABNANL2A:25:5'
+SEQ++1'
+MOA+9:250.50:GBP'
+RFF+CR:INV002'
+FII+BF+GB82WEST12345698765432:Widgets Ltd'
+NAD+BE+++Widgets Ltd'
+CNT+1:1450.75'
+CNT+2:2'
+UNT+31+1'
+"
+            );
+
+            let parsed = PaymentOrder::from_edifact(&order.to_edifact()).unwrap();
+            let totals: Vec<Option<String>> = parsed
+                .get_control_totals()
+                .into_iter()
+                .map(|total| total.amount)
+                .collect();
+            assert_eq!(
+                totals,
+                vec![Some("1200.25".to_string()), Some("250.50".to_string())]
+            );
+            let payments = parsed.get_payments();
+            assert_eq!(payments.len(), 3);
+            assert_eq!(
+                payments[0],
+                Payment {
+                    beneficiary: Some("Supplier GmbH".to_string()),
+                    account: Some("DE89370400440532013000".to_string()),
+                    bic: Some("COBADEFF".to_string()),
+                    amount: Some("1190.00".to_string()),
+                    currency: Some("EUR".to_string()),
+                    reference: Some("INV001".to_string()),
+                    remittance_information: Some("Invoice INV001".to_string()),
+                    execution_date: Some("20240305".to_string()),
+                    ordering_account: Some("NL91ABNA0417164300".to_string()),
+                }
+            );
+            assert_eq!(payments[2].currency.as_deref(), Some("GBP"));
+        });
+    }
+
+    #[test]
+    fn test_single_payment_order() {
+        Python::with_gil(|py| {
+            let builder = PaymentOrderBuilder::new(
+                "PAYORD",
+                "PO0001".to_string(),
+                "1".to_string(),
+                "96A".to_string(),
+                None,
+            )
+            .unwrap();
+            let builder = Bound::new(py, builder).unwrap();
+            PaymentOrderBuilder::with_date(builder.borrow_mut(), "137", "20240301", "102").unwrap();
+            PaymentOrderBuilder::with_ordering_account(
+                builder.borrow_mut(),
+                "NL91ABNA0417164300",
+                None,
+                None,
+            )
+            .unwrap();
+            // Amounts are whole cents above zero, currencies ISO 4217 codes
+            for (amount, currency) in [
+                ("inf", "GBP"),
+                ("1e3", "GBP"),
+                ("0.001", "GBP"),
+                ("1.005", "GBP"),
+                ("0", "GBP"),
+                ("-5", "GBP"),
+                ("10", "gbp"),
+            ] {
+                assert!(PaymentOrderBuilder::add_payment(
+                    builder.borrow_mut(),
+                    "Widgets Ltd",
+                    "GB82WEST12345698765432",
+                    amount,
+                    currency,
+                    "R1",
+                    None,
+                    None
+                )
+                .is_err());
+            }
+            assert!(
+                PaymentOrderBuilder::with_execution_date(builder.borrow_mut(), "20241301").is_err()
+            );
+            assert!(PaymentOrderBuilder::with_date(
+                builder.borrow_mut(),
+                "137",
+                "2024-03-01",
+                "102"
+            )
+            .is_err());
+            assert!(PaymentOrderBuilder::add_payment(
+                builder.borrow_mut(),
+                "Bad",
+                "NL91ABNA0417164301",
+                "10",
+                "EUR",
+                "R1",
+                None,
+                None
+            )
+            .is_err());
+            PaymentOrderBuilder::add_payment(
+                builder.borrow_mut(),
+                "Widgets Ltd",
+                "GB82WEST12345698765432",
+                "99.99",
+                "GBP",
+                "R1",
+                None,
+                None,
+            )
+            .unwrap();
+            assert!(PaymentOrderBuilder::add_payment(
+                builder.borrow_mut(),
+                "Again",
+                "GB82WEST12345698765432",
+                "1",
+                "GBP",
+                "R2",
+                None,
+                None
+            )
+            .is_err());
+
+            let order = builder.borrow().build().unwrap();
+            assert_eq!(order.message_type, "PAYORD");
+            let payments = order.get_payments();
+            assert_eq!(payments.len(), 1);
+            assert_eq!(payments[0].amount.as_deref(), Some("99.99"));
+            assert_eq!(payments[0].beneficiary.as_deref(), Some("Widgets Ltd"));
+            assert_eq!(
+                payments[0].ordering_account.as_deref(),
+                Some("NL91ABNA0417164300")
+            );
+        });
+    }
+}
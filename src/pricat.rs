@@ -0,0 +1,425 @@
+use std::sync::Arc;
+
+use pyo3::prelude::*;
+
+use crate::common::{Currency, DateTimePeriod, Party, Reference};
+use crate::directory::{Directory, SegmentGroup};
+use crate::document::Document;
+use crate::header::{component, MessageHeader};
+use crate::{OrderLine, Parser, Segment};
+
+/// A PRICAT price catalogue. Only the catalogue header is read up front; the product (LIN)
+/// groups are parsed one at a time as they are iterated, so catalogues with tens of
+/// thousands of products never hold more than one product's segments at once.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct PriceCatalogue {
+    content: Arc<str>,
+    parser: Parser,
+    header: Document,
+    // Byte offset and segment position where the product groups start
+    body_offset: usize,
+    body_position: usize,
+}
+
+#[pymethods]
+impl PriceCatalogue {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<PriceCatalogue> {
+        let mut parser = Parser::new();
+        let (body, has_una) = parser.read_una(content)?;
+        let content: Arc<str> = Arc::from(body);
+
+        let mut header = Document::new(parser.delimiters.clone(), parser.syntax_version);
+        let mut offset = 0;
+        let mut position = 0;
+        loop {
+            let start = offset;
+            let Some(text) = parser.next_segment(&content, &mut offset) else {
+                break;
+            };
+            let segment = parser.parse_segment(text, position)?;
+            match segment.tag.as_str() {
+                "PGI" | "LIN" => {
+                    offset = start;
+                    break;
+                }
+                "UNB" => {
+                    parser.detect_syntax_version(&segment, has_una);
+                    header.delimiters = parser.delimiters.clone();
+                    header.syntax_version = parser.syntax_version;
+                    header.interchange_header = Some(segment);
+                }
+                "UNH" => header.message_header = Some(segment),
+                _ => header.segments.push(segment),
+            }
+            position += 1;
+        }
+
+        Ok(PriceCatalogue {
+            content,
+            parser,
+            header,
+            body_offset: offset,
+            body_position: position,
+        })
+    }
+
+    /// Reads a catalogue from a file.
+    #[staticmethod]
+    fn from_file(path: &str) -> PyResult<PriceCatalogue> {
+        let content = std::fs::read_to_string(path)?;
+        PriceCatalogue::from_edifact(&content)
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.header.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.header.message_header.clone()
+    }
+
+    /// The catalogue header segments, before the first product group.
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.header.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.header.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.header.document_number()
+    }
+
+    fn get_message_function(&self) -> Option<String> {
+        self.header.message_function()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    /// Start of the validity period (DTM+194).
+    fn get_valid_from(&self) -> Option<DateTimePeriod> {
+        self.date("194")
+    }
+
+    /// End of the validity period (DTM+206).
+    fn get_valid_until(&self) -> Option<DateTimePeriod> {
+        self.date("206")
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    /// The supplier issuing the catalogue (NAD+SU).
+    fn get_supplier(&self) -> Option<Party> {
+        self.get_parties()
+            .into_iter()
+            .find(|party| party.qualifier == "SU")
+    }
+
+    fn get_currencies(&self) -> Vec<Currency> {
+        self.tree()
+            .groups_by_trigger("CUX")
+            .filter_map(Currency::from_group)
+            .collect()
+    }
+
+    /// The catalogue currency, from the first CUX.
+    fn get_currency(&self) -> Option<String> {
+        self.get_currencies()
+            .into_iter()
+            .find_map(|currency| currency.currency)
+    }
+
+    /// Iterates the product (LIN) groups, parsing each one as it is reached. The product
+    /// group (PGI) of the product yielded last is kept as `product_group`.
+    fn products(&self) -> Products {
+        let release = self
+            .header
+            .message_header
+            .as_ref()
+            .and_then(|header| component(header, 1, 2));
+        Products {
+            content: Arc::clone(&self.content),
+            parser: self.parser.clone(),
+            release,
+            offset: self.body_offset,
+            position: self.body_position,
+            pending: None,
+            group_segments: Vec::new(),
+            group: None,
+        }
+    }
+
+    fn __iter__(&self) -> Products {
+        self.products()
+    }
+}
+
+impl PriceCatalogue {
+    fn tree(&self) -> SegmentGroup {
+        self.header.tree("PRICAT")
+    }
+
+    fn date(&self, qualifier: &str) -> Option<DateTimePeriod> {
+        self.get_dates()
+            .into_iter()
+            .find(|date| date.qualifier == qualifier)
+    }
+}
+
+/// A PGI product group: the group type and the dates, quantities, texts and allowances
+/// that apply to every product in it.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ProductGroup {
+    group: SegmentGroup,
+}
+
+#[pymethods]
+impl ProductGroup {
+    /// PGI 5379 product group type code.
+    #[getter]
+    fn code(&self) -> Option<String> {
+        self.group
+            .find_segment("PGI")
+            .and_then(|pgi| component(pgi, 0, 0))
+    }
+
+    /// The PGI segment and the group's own segments, without its products.
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        self.group
+            .walk(&mut |_, segment| segments.push(segment.clone()));
+        segments
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.group)
+    }
+
+    /// Start of the group's validity period (DTM+194).
+    fn get_valid_from(&self) -> Option<DateTimePeriod> {
+        self.date("194")
+    }
+
+    /// End of the group's validity period (DTM+206).
+    fn get_valid_until(&self) -> Option<DateTimePeriod> {
+        self.date("206")
+    }
+}
+
+impl ProductGroup {
+    fn date(&self, qualifier: &str) -> Option<DateTimePeriod> {
+        self.get_dates()
+            .into_iter()
+            .find(|date| date.qualifier == qualifier)
+    }
+}
+
+/// Iterator over the products of a `PriceCatalogue`, yielding each LIN group as an
+/// `OrderLine`.
+#[pyclass]
+#[derive(Debug)]
+pub struct Products {
+    content: Arc<str>,
+    parser: Parser,
+    release: Option<String>,
+    offset: usize,
+    position: usize,
+    // The segment that ended the previous product
+    pending: Option<Segment>,
+    // A product group read up to its first product
+    group_segments: Vec<Segment>,
+    group: Option<ProductGroup>,
+}
+
+#[pymethods]
+impl Products {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// The product group (PGI) of the product yielded last.
+    #[getter]
+    fn product_group(&self) -> Option<ProductGroup> {
+        self.group.clone()
+    }
+
+    fn __next__(&mut self) -> PyResult<Option<OrderLine>> {
+        let definition = Directory::builtin().require_message("PRICAT", self.release.as_deref())?;
+        let tags = definition.group_tags("LIN");
+        let group_tags = definition.group_tags("PGI");
+
+        // Segments between products start a product group or belong to the one just started
+        let line = loop {
+            let Some(segment) = self.next_segment()? else {
+                return Ok(None);
+            };
+            match segment.tag.as_str() {
+                "LIN" => break segment,
+                "PGI" => self.group_segments = vec![segment],
+                tag if !self.group_segments.is_empty() && group_tags.contains(&tag) => {
+                    self.group_segments.push(segment)
+                }
+                _ => {}
+            }
+        };
+        if !self.group_segments.is_empty() {
+            let segments = std::mem::take(&mut self.group_segments);
+            let group = definition
+                .build_group("PGI", &segments)
+                .unwrap_or_else(|| SegmentGroup::flat("PGI", segments));
+            self.group = Some(ProductGroup { group });
+        }
+
+        let mut segments = vec![line];
+        while let Some(segment) = self.read()? {
+            if segment.tag != "LIN" && tags.contains(&segment.tag.as_str()) {
+                segments.push(segment);
+            } else {
+                self.pending = Some(segment);
+                break;
+            }
+        }
+
+        let group = definition
+            .build_group("LIN", &segments)
+            .unwrap_or_else(|| SegmentGroup::flat("LIN", segments));
+        Ok(Some(OrderLine::from_group(group)))
+    }
+}
+
+impl Products {
+    fn next_segment(&mut self) -> PyResult<Option<Segment>> {
+        match self.pending.take() {
+            Some(segment) => Ok(Some(segment)),
+            None => self.read(),
+        }
+    }
+
+    fn read(&mut self) -> PyResult<Option<Segment>> {
+        let Some(text) = self.parser.next_segment(&self.content, &mut self.offset) else {
+            return Ok(None);
+        };
+        let segment = self.parser.parse_segment(text, self.position)?;
+        self.position += 1;
+        Ok(Some(segment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const CATALOGUE: &str = "UNA:+.? '
+UNB+UNOC:3+4012345000016:14+5412345000013:14+240110:0900+CAT1'
+UNH+1+PRICAT:D:96A:UN'
+BGM+9+CAT2024+9'
+DTM+137:20240110:102'
+DTM+194:20240201:102'
+DTM+206:20241231:102'
+NAD+SU+4012345000016::9'
+CUX+2:EUR:8'
+PGI+1'
+LIN+1++4000862141404:SRV'
+PIA+5+ART-100:SA'
+IMD+F++:::Widget?+ small'
+MEA+PD+AAB+KGM:0.25'
+PRI+AAA:10.00'
+CUX+2:EUR:8'
+PRI+AAB:12.10'
+LIN+2++4000862141411:SRV'
+IMD+F++:::Widget large'
+PRI+AAA:15.50'
+PGI+2'
+DTM+194:20240301:102'
+LIN+3++4000862141428:SRV'
+PRI+AAA:7.25'
+CNT+2:3'
+UNT+26+1'
+UNZ+1+CAT1'";
+
+    #[test]
+    fn test_catalogue_header() {
+        Python::with_gil(|_py| {
+            let catalogue = PriceCatalogue::from_edifact(CATALOGUE).unwrap();
+            assert_eq!(catalogue.get_document_number().as_deref(), Some("CAT2024"));
+            assert_eq!(
+                catalogue.get_valid_from().unwrap().value.as_deref(),
+                Some("20240201")
+            );
+            assert_eq!(
+                catalogue.get_valid_until().unwrap().value.as_deref(),
+                Some("20241231")
+            );
+            assert_eq!(catalogue.get_currency().as_deref(), Some("EUR"));
+            assert_eq!(
+                catalogue.get_supplier().unwrap().id.as_deref(),
+                Some("4012345000016")
+            );
+            assert_eq!(catalogue.segments().len(), 6);
+        });
+    }
+
+    #[test]
+    fn test_catalogue_products_are_read_lazily() {
+        Python::with_gil(|_py| {
+            let catalogue = PriceCatalogue::from_edifact(CATALOGUE).unwrap();
+            let mut products = catalogue.products();
+
+            let first = products.__next__().unwrap().unwrap();
+            assert_eq!(first.line_number().as_deref(), Some("1"));
+            assert_eq!(
+                products.product_group().unwrap().code().as_deref(),
+                Some("1")
+            );
+            assert_eq!(
+                first.descriptions()[0].description.as_deref(),
+                Some("Widget+ small")
+            );
+            assert_eq!(first.measurements()[0].value.as_deref(), Some("0.25"));
+            let prices: Vec<Option<String>> = first
+                .prices()
+                .into_iter()
+                .map(|price| price.amount)
+                .collect();
+            assert_eq!(
+                prices,
+                vec![Some("10.00".to_string()), Some("12.10".to_string())]
+            );
+            // Nothing past the LIN starting the second product has been read yet
+            assert!(products.offset <= products.content.find("IMD+F++:::Widget large").unwrap());
+
+            let rest: Vec<OrderLine> =
+                std::iter::from_fn(|| products.__next__().unwrap()).collect();
+            let numbers: Vec<Option<String>> = rest.iter().map(|line| line.line_number()).collect();
+            assert_eq!(numbers, vec![Some("2".to_string()), Some("3".to_string())]);
+            assert_eq!(rest[1].segments().len(), 2);
+
+            // The last product comes from the second group, with its own validity
+            let group = products.product_group().unwrap();
+            assert_eq!(group.code().as_deref(), Some("2"));
+            assert_eq!(
+                group.get_valid_from().unwrap().value.as_deref(),
+                Some("20240301")
+            );
+            assert_eq!(group.segments().len(), 2);
+        });
+    }
+}
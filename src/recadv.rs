@@ -0,0 +1,408 @@
+use pyo3::prelude::*;
+
+use crate::common::{
+    add_exact, format_exact, parse_exact, DateTimePeriod, Discrepancy, Party, Reference,
+};
+use crate::desadv::{DespatchAdvice, Package};
+use crate::directory::SegmentGroup;
+use crate::document::Document;
+use crate::header::MessageHeader;
+use crate::{Message, OrderLine, Segment};
+
+/// The quantities of one received line: what the supplier despatched (QTY+12), what arrived
+/// (QTY+48) and how much of it was accepted (QTY+194) or not (QTY+195), with the reasons
+/// for any difference.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptLine {
+    #[pyo3(get)]
+    line_number: Option<String>,
+    #[pyo3(get)]
+    item_number: Option<String>,
+    /// CPS id of the package the line was received in
+    #[pyo3(get)]
+    package_id: Option<String>,
+    #[pyo3(get)]
+    despatched: Option<String>,
+    #[pyo3(get)]
+    received: Option<String>,
+    #[pyo3(get)]
+    accepted: Option<String>,
+    #[pyo3(get)]
+    rejected: Option<String>,
+    #[pyo3(get)]
+    discrepancies: Vec<Discrepancy>,
+}
+
+impl ReceiptLine {
+    fn from_line(line: &OrderLine, package_id: Option<String>) -> Self {
+        let quantity = |qualifier: &str| {
+            line.quantities()
+                .into_iter()
+                .find(|quantity| quantity.qualifier == qualifier)
+                .and_then(|quantity| quantity.value)
+        };
+        ReceiptLine {
+            line_number: line.line_number(),
+            item_number: item_key(line),
+            package_id,
+            despatched: quantity("12"),
+            received: quantity("48"),
+            accepted: quantity("194"),
+            rejected: quantity("195"),
+            discrepancies: line.discrepancies(),
+        }
+    }
+}
+
+/// How the received quantity of an item differs from what its despatch advice announced.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptDifference {
+    #[pyo3(get)]
+    item_number: String,
+    #[pyo3(get)]
+    despatched: String,
+    #[pyo3(get)]
+    received: String,
+    /// Received minus despatched
+    #[pyo3(get)]
+    difference: String,
+    /// Discrepancy reason codes given in the receiving advice
+    #[pyo3(get)]
+    reasons: Vec<String>,
+}
+
+/// A RECADV receiving advice, confirming what arrived from a despatch.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ReceivingAdvice {
+    document: Document,
+}
+
+#[pymethods]
+impl ReceivingAdvice {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<ReceivingAdvice> {
+        Ok(ReceivingAdvice {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> ReceivingAdvice {
+        ReceivingAdvice {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    /// The number of the despatch advice being answered, from RFF+AAK.
+    fn get_despatch_advice_number(&self) -> Option<String> {
+        self.get_references()
+            .into_iter()
+            .find(|reference| reference.qualifier == "AAK")
+            .and_then(|reference| reference.number)
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    /// The received packages, nested by their CPS parent references.
+    fn get_packages(&self) -> Vec<Package> {
+        let packages = self
+            .tree()
+            .groups_by_trigger("CPS")
+            .filter_map(Package::from_group)
+            .collect();
+        Package::nest(packages)
+    }
+
+    fn get_lines(&self) -> Vec<OrderLine> {
+        self.tree()
+            .groups_by_trigger("CPS")
+            .flat_map(Document::lines)
+            .collect()
+    }
+
+    /// The despatched and received quantities of every line, with the package it was in.
+    fn get_receipt_lines(&self) -> Vec<ReceiptLine> {
+        let mut lines = Vec::new();
+        for package in self.get_packages() {
+            receipt_lines(&package, &mut lines);
+        }
+        lines
+    }
+
+    /// Compares the received quantities per item against a despatch advice. Items whose
+    /// received quantity (QTY+48, or QTY+194 when only acceptance is reported) differs from
+    /// the despatched quantity are reported, including items missing on either side.
+    fn compare_with_despatch(&self, despatch_advice: &DespatchAdvice) -> Vec<ReceiptDifference> {
+        // Quantities are summed exactly, so 0.1 + 0.2 matches 0.3
+        let mut totals: Vec<(String, Exact, Exact)> = Vec::new();
+        let mut add = |item: String, despatched: Exact, received: Exact| match totals
+            .iter_mut()
+            .find(|(key, _, _)| *key == item)
+        {
+            Some(total) => {
+                total.1 = add_exact(total.1, despatched).unwrap_or(total.1);
+                total.2 = add_exact(total.2, received).unwrap_or(total.2);
+            }
+            None => totals.push((item, despatched, received)),
+        };
+        for line in despatch_advice.get_lines() {
+            let despatched = quantity(&line, &["12"]);
+            add(item_key(&line).unwrap_or_default(), despatched, ZERO);
+        }
+        let lines = self.get_lines();
+        for line in &lines {
+            let received = quantity(line, &["48", "194"]);
+            add(item_key(line).unwrap_or_default(), ZERO, received);
+        }
+
+        totals
+            .into_iter()
+            .filter_map(|(item, despatched, received)| {
+                let difference = add_exact(received, (-despatched.0, despatched.1))?;
+                (difference.0 != 0).then_some((item, despatched, received, difference))
+            })
+            .map(|(item, despatched, received, difference)| {
+                let reasons = lines
+                    .iter()
+                    .filter(|line| item_key(line).as_deref() == Some(item.as_str()))
+                    .flat_map(|line| line.discrepancies())
+                    .filter_map(|discrepancy| discrepancy.reason.or(discrepancy.discrepancy_code))
+                    .collect();
+                ReceiptDifference {
+                    item_number: item,
+                    despatched: format_exact(despatched),
+                    received: format_exact(received),
+                    difference: format_exact(difference),
+                    reasons,
+                }
+            })
+            .collect()
+    }
+
+    /// SSCCs announced in the despatch advice that do not appear in this receiving advice.
+    fn missing_packages(&self, despatch_advice: &DespatchAdvice) -> Vec<String> {
+        let received = ssccs(&self.get_packages());
+        ssccs(&despatch_advice.get_packages())
+            .into_iter()
+            .filter(|sscc| !received.contains(sscc))
+            .collect()
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl ReceivingAdvice {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("RECADV")
+    }
+}
+
+// Lines are matched by item number, falling back to the line number
+fn item_key(line: &OrderLine) -> Option<String> {
+    line.item_number()
+        .map(|item| item.number)
+        .or_else(|| line.line_number())
+}
+
+// An exact decimal quantity, as read by `parse_exact`
+type Exact = (i128, u32);
+
+const ZERO: Exact = (0, 0);
+
+// Sums the quantities with the first of the qualifiers the line reports
+fn quantity(line: &OrderLine, qualifiers: &[&str]) -> Exact {
+    let quantities = line.quantities();
+    qualifiers
+        .iter()
+        .map(|qualifier| {
+            quantities
+                .iter()
+                .filter(|quantity| quantity.qualifier == *qualifier)
+                .filter_map(|quantity| quantity.value.as_deref().and_then(parse_exact))
+                .collect::<Vec<Exact>>()
+        })
+        .find(|values| !values.is_empty())
+        .and_then(|values| values.into_iter().try_fold(ZERO, add_exact))
+        .unwrap_or(ZERO)
+}
+
+fn receipt_lines(package: &Package, lines: &mut Vec<ReceiptLine>) {
+    for item in &package.items {
+        lines.push(ReceiptLine::from_line(item, package.id.clone()));
+    }
+    for child in &package.children {
+        receipt_lines(child, lines);
+    }
+}
+
+fn ssccs(packages: &[Package]) -> Vec<String> {
+    packages
+        .iter()
+        .flat_map(|package| {
+            let mut ssccs = package.sscc();
+            ssccs.extend(self::ssccs(&package.children));
+            ssccs
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const DESPATCH: &str = "UNH+1+DESADV:D:96A:UN'
+BGM+351+DES587441+9'
+CPS+1'
+PAC+1++201'
+CPS+2+1'
+PAC+1++CT'
+PCI+33E'
+GIN+BJ+354123450000000014'
+LIN+1++4000862141404:SRV'
+QTY+12:24'
+CPS+3+1'
+PAC+1++CT'
+PCI+33E'
+GIN+BJ+354123450000000021'
+LIN+2++4000862141411:SRV'
+QTY+12:10'
+UNT+17+1'";
+
+    const RECEIPT: &str = "UNH+1+RECADV:D:96A:UN'
+BGM+632+REC001+9'
+DTM+50:20240123:102'
+RFF+AAK:DES587441'
+NAD+DP+5412345000020::9'
+CPS+1'
+CPS+2+1'
+PAC+1++CT'
+PCI+33E'
+GIN+BJ+354123450000000014'
+LIN+1++4000862141404:SRV'
+QTY+12:24'
+QTY+48:22'
+QTY+194:20'
+QTY+195:2'
+QVR+-2:48+BP+AG'
+UNT+17+1'";
+
+    #[test]
+    fn test_receipt_lines() {
+        Python::with_gil(|_py| {
+            let receipt = ReceivingAdvice::from_edifact(RECEIPT).unwrap();
+            assert_eq!(
+                receipt.get_despatch_advice_number().as_deref(),
+                Some("DES587441")
+            );
+            let lines = receipt.get_receipt_lines();
+            assert_eq!(lines.len(), 1);
+            let line = &lines[0];
+            assert_eq!(line.package_id.as_deref(), Some("2"));
+            assert_eq!(line.despatched.as_deref(), Some("24"));
+            assert_eq!(line.received.as_deref(), Some("22"));
+            assert_eq!(line.accepted.as_deref(), Some("20"));
+            assert_eq!(line.rejected.as_deref(), Some("2"));
+            assert_eq!(line.discrepancies[0].difference.as_deref(), Some("-2"));
+            assert_eq!(line.discrepancies[0].reason.as_deref(), Some("AG"));
+        });
+    }
+
+    #[test]
+    fn test_compare_with_despatch() {
+        Python::with_gil(|_py| {
+            let receipt = ReceivingAdvice::from_edifact(RECEIPT).unwrap();
+            let despatch = DespatchAdvice::from_edifact(DESPATCH).unwrap();
+
+            let differences = receipt.compare_with_despatch(&despatch);
+            assert_eq!(
+                differences,
+                vec![
+                    ReceiptDifference {
+                        item_number: "4000862141404".to_string(),
+                        despatched: "24".to_string(),
+                        received: "22".to_string(),
+                        difference: "-2".to_string(),
+                        reasons: vec!["AG".to_string()],
+                    },
+                    ReceiptDifference {
+                        item_number: "4000862141411".to_string(),
+                        despatched: "10".to_string(),
+                        received: "0".to_string(),
+                        difference: "-10".to_string(),
+                        reasons: Vec::new(),
+                    },
+                ]
+            );
+            assert_eq!(
+                receipt.missing_packages(&despatch),
+                vec!["354123450000000021".to_string()]
+            );
+        });
+    }
+
+    #[test]
+    fn test_compare_with_despatch_sums_exactly() {
+        Python::with_gil(|_py| {
+            let despatch = DespatchAdvice::from_edifact(
+                &DESPATCH.replace("QTY+12:24", "QTY+12:0.1:KGM").replace(
+                    "LIN+2++4000862141411:SRV'\nQTY+12:10",
+                    "LIN+2++4000862141404:SRV'\nQTY+12:0.2:KGM",
+                ),
+            )
+            .unwrap();
+            let receipt =
+                ReceivingAdvice::from_edifact(&RECEIPT.replace("QTY+48:22", "QTY+48:0.3:KGM"))
+                    .unwrap();
+            assert!(receipt.compare_with_despatch(&despatch).is_empty());
+
+            let short =
+                ReceivingAdvice::from_edifact(&RECEIPT.replace("QTY+48:22", "QTY+48:0.25:KGM"))
+                    .unwrap();
+            let differences = short.compare_with_despatch(&despatch);
+            assert_eq!(differences.len(), 1);
+            assert_eq!(differences[0].despatched, "0.3");
+            assert_eq!(differences[0].received, "0.25");
+            assert_eq!(differences[0].difference, "-0.05");
+        });
+    }
+}
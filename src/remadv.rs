@@ -0,0 +1,386 @@
+use pyo3::prelude::*;
+
+use crate::common::{
+    format_cents, parse_cents, Currency, DateTimePeriod, MonetaryAmount, Party, Reference,
+};
+use crate::directory::SegmentGroup;
+use crate::document::Document;
+use crate::header::{component, MessageHeader};
+use crate::invoic::Invoice;
+use crate::{Message, Segment};
+
+/// An AJT adjustment explaining why the remitted amount differs from the amount due, with
+/// the adjusted amount and any reference and free text.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentAdjustment {
+    /// 4465 adjustment reason, e.g. 1 (agreed settlement) or 2 (below specification goods)
+    #[pyo3(get)]
+    reason: String,
+    #[pyo3(get)]
+    amount: Option<MonetaryAmount>,
+    #[pyo3(get)]
+    reference: Option<Reference>,
+    #[pyo3(get)]
+    text: Option<String>,
+}
+
+impl PaymentAdjustment {
+    fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("AJT")?;
+        let text: Vec<String> = group
+            .segments_by_tag("FTX")
+            .flat_map(|ftx| (0..5).filter_map(move |index| component(ftx, 3, index)))
+            .collect();
+        Some(PaymentAdjustment {
+            reason: component(segment, 0, 0).unwrap_or_default(),
+            amount: group.find_segment("MOA").map(MonetaryAmount::from_segment),
+            reference: group.find_segment("RFF").map(Reference::from_segment),
+            text: (!text.is_empty()).then(|| text.join(" ")),
+        })
+    }
+}
+
+/// One remitted document, usually an invoice: DOC with the amount due (MOA+9) and the
+/// amount remitted (MOA+12), and the adjustments accounting for the difference.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemittanceLine {
+    /// DOC 1001, e.g. 380 for an invoice or 381 for a credit note
+    #[pyo3(get)]
+    document_name: Option<String>,
+    #[pyo3(get)]
+    document_number: Option<String>,
+    #[pyo3(get)]
+    amounts: Vec<MonetaryAmount>,
+    #[pyo3(get)]
+    dates: Vec<DateTimePeriod>,
+    #[pyo3(get)]
+    references: Vec<Reference>,
+    #[pyo3(get)]
+    adjustments: Vec<PaymentAdjustment>,
+}
+
+#[pymethods]
+impl RemittanceLine {
+    fn get_amount(&self, qualifier: &str) -> Option<String> {
+        self.amounts
+            .iter()
+            .find(|amount| amount.qualifier == qualifier)
+            .and_then(|amount| amount.amount.clone())
+    }
+
+    /// The amount remitted (MOA+12), or the amount due (MOA+9) when the line does not
+    /// state what was paid.
+    fn remitted_amount(&self) -> Option<String> {
+        self.get_amount("12").or_else(|| self.get_amount("9"))
+    }
+}
+
+impl RemittanceLine {
+    fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("DOC")?;
+        Some(RemittanceLine {
+            document_name: component(segment, 0, 0),
+            document_number: component(segment, 1, 0),
+            amounts: group
+                .segments_by_tag("MOA")
+                .map(MonetaryAmount::from_segment)
+                .collect(),
+            dates: group
+                .segments_by_tag("DTM")
+                .map(DateTimePeriod::from_segment)
+                .collect(),
+            references: group
+                .segments_by_tag("RFF")
+                .map(Reference::from_segment)
+                .collect(),
+            adjustments: group
+                .groups_by_trigger("AJT")
+                .filter_map(PaymentAdjustment::from_group)
+                .collect(),
+        })
+    }
+}
+
+/// How a remitted amount compares with the invoice it pays.
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaymentStatus {
+    Paid,
+    Underpaid,
+    Overpaid,
+    /// No invoice has the remitted document number
+    Unmatched,
+    /// The invoice matched, but it has no total (MOA+77 or MOA+86) or the line no remitted
+    /// amount to compare
+    AmountUnknown,
+}
+
+/// The outcome of matching one remittance line against the invoices.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentMatch {
+    #[pyo3(get)]
+    document_number: Option<String>,
+    #[pyo3(get)]
+    status: PaymentStatus,
+    /// The invoice amount (MOA+77), when an invoice matched
+    #[pyo3(get)]
+    invoiced: Option<String>,
+    #[pyo3(get)]
+    remitted: Option<String>,
+    /// Remitted minus invoiced
+    #[pyo3(get)]
+    difference: Option<String>,
+    /// Adjustment reason codes given for the line
+    #[pyo3(get)]
+    reasons: Vec<String>,
+}
+
+/// A REMADV remittance advice, listing the documents settled by a payment.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct RemittanceAdvice {
+    document: Document,
+}
+
+#[pymethods]
+impl RemittanceAdvice {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<RemittanceAdvice> {
+        Ok(RemittanceAdvice {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> RemittanceAdvice {
+        RemittanceAdvice {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    fn get_currencies(&self) -> Vec<Currency> {
+        self.tree()
+            .groups_by_trigger("CUX")
+            .filter_map(Currency::from_group)
+            .collect()
+    }
+
+    /// The total amount of the payment, from the MOA following UNS.
+    fn get_total_amount(&self) -> Option<MonetaryAmount> {
+        self.tree()
+            .find_segment("MOA")
+            .map(MonetaryAmount::from_segment)
+    }
+
+    fn get_lines(&self) -> Vec<RemittanceLine> {
+        self.tree()
+            .groups_by_trigger("DOC")
+            .filter_map(RemittanceLine::from_group)
+            .collect()
+    }
+
+    /// Matches every remittance line against the invoices by document number and compares
+    /// the remitted amount with the invoice amount (MOA+77, or MOA+86 when there is none).
+    /// Amounts are compared to the cent.
+    fn reconcile(&self, invoices: Vec<Invoice>) -> Vec<PaymentMatch> {
+        self.get_lines()
+            .iter()
+            .map(|line| reconcile_line(line, &invoices))
+            .collect()
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl RemittanceAdvice {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("REMADV")
+    }
+}
+
+fn reconcile_line(line: &RemittanceLine, invoices: &[Invoice]) -> PaymentMatch {
+    let invoice = invoices.iter().find(|invoice| {
+        line.document_number.is_some() && invoice.get_document_number() == line.document_number
+    });
+    let invoiced = invoice.and_then(|invoice| {
+        invoice
+            .get_summary_amount("77")
+            .or_else(|| invoice.get_summary_amount("86"))
+    });
+    let remitted = line.remitted_amount();
+    let cents = |amount: &Option<String>| amount.as_deref().and_then(parse_cents);
+    let difference = match (cents(&remitted), cents(&invoiced)) {
+        (Some(remitted), Some(invoiced)) => remitted.checked_sub(invoiced),
+        _ => None,
+    };
+    let status = match (invoice, difference) {
+        (None, _) => PaymentStatus::Unmatched,
+        (Some(_), Some(0)) => PaymentStatus::Paid,
+        (Some(_), Some(difference)) if difference > 0 => PaymentStatus::Overpaid,
+        (Some(_), Some(_)) => PaymentStatus::Underpaid,
+        (Some(_), None) => PaymentStatus::AmountUnknown,
+    };
+    PaymentMatch {
+        document_number: line.document_number.clone(),
+        status,
+        invoiced,
+        remitted,
+        difference: difference.map(format_cents),
+        reasons: line
+            .adjustments
+            .iter()
+            .map(|adjustment| adjustment.reason.clone())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const REMITTANCE: &str = "UNH+1+REMADV:D:96A:UN'
+BGM+481+RA0001+9'
+DTM+137:20240215:102'
+NAD+PR+5412345000013::9'
+NAD+PE+4012345000016::9'
+CUX+2:EUR:11'
+DOC+380+INV001'
+MOA+9:1190.00'
+MOA+12:1190.00'
+DTM+137:20240120:102'
+DOC+380+INV002'
+MOA+9:595.00'
+MOA+12:565.00'
+AJT+2'
+MOA+8:30.00'
+FTX+AAO+++Damaged goods'
+DOC+380+INV999'
+MOA+12:100.00'
+UNS+S'
+MOA+12:1855.00'
+UNT+21+1'";
+
+    fn invoice(number: &str, amount: &str) -> Invoice {
+        Invoice::from_edifact(&format!(
+            "UNH+1+INVOIC:D:96A:UN'
+BGM+380+{}+9'
+UNS+S'
+MOA+77:{}'
+UNT+5+1'",
+            number, amount
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_remittance_lines() {
+        Python::with_gil(|_py| {
+            let advice = RemittanceAdvice::from_edifact(REMITTANCE).unwrap();
+            assert_eq!(
+                advice.get_total_amount().unwrap().amount.as_deref(),
+                Some("1855.00")
+            );
+            assert_eq!(advice.get_currencies()[0].currency.as_deref(), Some("EUR"));
+            let lines = advice.get_lines();
+            assert_eq!(lines.len(), 3);
+            assert_eq!(lines[0].document_number.as_deref(), Some("INV001"));
+            assert_eq!(lines[0].dates[0].qualifier, "137");
+            assert_eq!(lines[1].remitted_amount().as_deref(), Some("565.00"));
+            assert_eq!(
+                lines[1].adjustments,
+                vec![PaymentAdjustment {
+                    reason: "2".to_string(),
+                    amount: Some(MonetaryAmount {
+                        qualifier: "8".to_string(),
+                        amount: Some("30.00".to_string()),
+                        currency: None,
+                    }),
+                    reference: None,
+                    text: Some("Damaged goods".to_string()),
+                }]
+            );
+        });
+    }
+
+    #[test]
+    fn test_reconcile_with_invoices() {
+        Python::with_gil(|_py| {
+            let advice = RemittanceAdvice::from_edifact(REMITTANCE).unwrap();
+            let matches = advice.reconcile(vec![
+                invoice("INV001", "1190.00"),
+                invoice("INV002", "595.00"),
+            ]);
+            let statuses: Vec<PaymentStatus> = matches.iter().map(|m| m.status).collect();
+            assert_eq!(
+                statuses,
+                vec![
+                    PaymentStatus::Paid,
+                    PaymentStatus::Underpaid,
+                    PaymentStatus::Unmatched
+                ]
+            );
+            assert_eq!(matches[1].difference.as_deref(), Some("-30.00"));
+            assert_eq!(matches[1].reasons, vec!["2".to_string()]);
+            assert_eq!(matches[2].invoiced, None);
+
+            let overpaid = advice.reconcile(vec![invoice("INV001", "1000")]);
+            assert_eq!(overpaid[0].status, PaymentStatus::Overpaid);
+            assert_eq!(overpaid[0].difference.as_deref(), Some("190.00"));
+
+            // Amounts are read exactly, so 1189.995 rounds to 1190.00 and is paid in full
+            let exact = advice.reconcile(vec![invoice("INV001", "1189.995")]);
+            assert_eq!(exact[0].status, PaymentStatus::Paid);
+            assert_eq!(exact[0].difference.as_deref(), Some("0.00"));
+
+            // A matching invoice without a total cannot tell how much was due
+            let without_total =
+                Invoice::from_edifact("UNH+1+INVOIC:D:96A:UN'\nBGM+380+INV001+9'\nUNT+3+1'")
+                    .unwrap();
+            let unknown = advice.reconcile(vec![without_total]);
+            assert_eq!(unknown[0].status, PaymentStatus::AmountUnknown);
+            assert_eq!(unknown[0].difference, None);
+        });
+    }
+}
@@ -0,0 +1,363 @@
+use pyo3::prelude::*;
+
+use crate::common::{DateTimePeriod, ItemNumber, Party, ProductIdentification, Reference};
+use crate::directory::SegmentGroup;
+use crate::document::Document;
+use crate::header::{component, MessageHeader};
+use crate::{Message, Segment};
+
+/// One quantity from an inventory or sales report, flattened together with the location,
+/// item and period it applies to.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportRecord {
+    /// LOC place identification of the store or warehouse
+    #[pyo3(get)]
+    pub(crate) location: Option<String>,
+    /// LIN item number, or the first PIA number when LIN carries none
+    #[pyo3(get)]
+    pub(crate) item: Option<String>,
+    /// QTY 6063, e.g. 145 (actual stock) or 153 (sold quantity)
+    #[pyo3(get)]
+    pub(crate) quantity_qualifier: String,
+    #[pyo3(get)]
+    pub(crate) quantity: Option<String>,
+    #[pyo3(get)]
+    pub(crate) unit: Option<String>,
+    #[pyo3(get)]
+    pub(crate) period: Option<DateTimePeriod>,
+}
+
+#[pymethods]
+impl ReportRecord {
+    /// The record as a `(location, item, quantity_qualifier, quantity, period)` tuple, with
+    /// the period's transmitted value.
+    #[allow(clippy::type_complexity)]
+    fn to_tuple(
+        &self,
+    ) -> (
+        Option<String>,
+        Option<String>,
+        String,
+        Option<String>,
+        Option<String>,
+    ) {
+        (
+            self.location.clone(),
+            self.item.clone(),
+            self.quantity_qualifier.clone(),
+            self.quantity.clone(),
+            self.period.as_ref().and_then(|period| period.value.clone()),
+        )
+    }
+}
+
+// What a quantity inherits from the groups enclosing it
+#[derive(Clone, Default)]
+struct Context {
+    location: Option<String>,
+    item: Option<String>,
+    period: Option<DateTimePeriod>,
+}
+
+/// Flattens every QTY in the report into a record. The period is the DTM closest to the
+/// quantity: its own group's, then an enclosing group's, then the report period in the
+/// header.
+fn records(tree: &SegmentGroup) -> Vec<ReportRecord> {
+    let period = tree
+        .segments_by_tag("DTM")
+        .map(DateTimePeriod::from_segment)
+        .find(|date| date.qualifier != "137");
+    let context = Context {
+        period,
+        ..Context::default()
+    };
+    let mut records = Vec::new();
+    for group in tree.child_groups() {
+        collect(group, &context, &mut records);
+    }
+    records
+}
+
+fn collect(group: &SegmentGroup, context: &Context, records: &mut Vec<ReportRecord>) {
+    let mut context = context.clone();
+    match group.direct_segments().next() {
+        Some(trigger) if trigger.tag == "LOC" => context.location = component(trigger, 1, 0),
+        Some(trigger) if trigger.tag == "LIN" => {
+            context.item = ItemNumber::from_element(trigger, 2)
+                .or_else(|| {
+                    group
+                        .segments_by_tag("PIA")
+                        .map(ProductIdentification::from_segment)
+                        .find_map(|pia| pia.item_numbers.into_iter().next())
+                })
+                .map(|item| item.number);
+        }
+        _ => {}
+    }
+    if let Some(date) = group.find_segment("DTM") {
+        context.period = Some(DateTimePeriod::from_segment(date));
+    }
+
+    for quantity in group.segments_by_tag("QTY") {
+        records.push(ReportRecord {
+            location: context.location.clone(),
+            item: context.item.clone(),
+            quantity_qualifier: component(quantity, 0, 0).unwrap_or_default(),
+            quantity: component(quantity, 0, 1),
+            unit: component(quantity, 0, 2),
+            period: context.period.clone(),
+        });
+    }
+    for child in group.child_groups() {
+        collect(child, &context, records);
+    }
+}
+
+/// An INVRPT inventory report, giving stock quantities per item and location.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct InventoryReport {
+    document: Document,
+}
+
+/// An SLSRPT sales data report, giving sold quantities per location and item.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct SalesReport {
+    document: Document,
+}
+
+#[pymethods]
+impl InventoryReport {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<InventoryReport> {
+        Ok(InventoryReport {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> InventoryReport {
+        InventoryReport {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    /// Every reported quantity as a flat record.
+    fn get_records(&self) -> Vec<ReportRecord> {
+        records(&self.tree())
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl InventoryReport {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("INVRPT")
+    }
+}
+
+#[pymethods]
+impl SalesReport {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<SalesReport> {
+        Ok(SalesReport {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> SalesReport {
+        SalesReport {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    /// Every reported quantity as a flat record.
+    fn get_records(&self) -> Vec<ReportRecord> {
+        records(&self.tree())
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl SalesReport {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("SLSRPT")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    fn tuples(records: Vec<ReportRecord>) -> Vec<(String, String, String, String, String)> {
+        records
+            .iter()
+            .map(|record| {
+                let (location, item, qualifier, quantity, period) = record.to_tuple();
+                (
+                    location.unwrap_or_default(),
+                    item.unwrap_or_default(),
+                    qualifier,
+                    quantity.unwrap_or_default(),
+                    period.unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    fn record(values: [&str; 5]) -> (String, String, String, String, String) {
+        let [location, item, qualifier, quantity, period] = values.map(String::from);
+        (location, item, qualifier, quantity, period)
+    }
+
+    #[test]
+    fn test_inventory_report_records() {
+        Python::with_gil(|_py| {
+            let report = InventoryReport::from_edifact(
+                "UNH+1+INVRPT:D:96A:UN'
+BGM+35+INV0001+9'
+DTM+137:20240131:102'
+DTM+366:20240131:102'
+NAD+SE+4012345000016::9'
+LIN+1++4000862141404:SRV'
+QTY+145:120:PCE'
+LOC+18+STORE1'
+QTY+145:70'
+DTM+366:20240130:102'
+LOC+18+STORE2'
+QTY+145:50'
+LIN+2'
+PIA+5+ART-200:SA'
+LOC+18+STORE1'
+QTY+145:12'
+UNT+17+1'",
+            )
+            .unwrap();
+            assert_eq!(
+                tuples(report.get_records()),
+                vec![
+                    record(["", "4000862141404", "145", "120", "20240131"]),
+                    record(["STORE1", "4000862141404", "145", "70", "20240130"]),
+                    record(["STORE2", "4000862141404", "145", "50", "20240131"]),
+                    record(["STORE1", "ART-200", "145", "12", "20240131"]),
+                ]
+            );
+            assert_eq!(report.get_records()[0].unit.as_deref(), Some("PCE"));
+        });
+    }
+
+    #[test]
+    fn test_sales_report_records() {
+        Python::with_gil(|_py| {
+            let report = SalesReport::from_edifact(
+                "UNH+1+SLSRPT:D:96A:UN'
+BGM+73E+SLS0001+9'
+DTM+137:20240108:102'
+DTM+90:20240101:102'
+DTM+91:20240107:102'
+LOC+162+STORE1'
+LIN+1++4000862141404:SRV'
+QTY+153:5'
+DTM+356:20240102:102'
+QTY+153:3'
+DTM+356:20240103:102'
+LOC+162+STORE2'
+LIN+1++4000862141404:SRV'
+QTY+153:7'
+UNT+14+1'",
+            )
+            .unwrap();
+            assert_eq!(
+                tuples(report.get_records()),
+                vec![
+                    record(["STORE1", "4000862141404", "153", "5", "20240102"]),
+                    record(["STORE1", "4000862141404", "153", "3", "20240103"]),
+                    record(["STORE2", "4000862141404", "153", "7", "20240101"]),
+                ]
+            );
+            assert_eq!(
+                report.get_records()[2].period.as_ref().unwrap().qualifier,
+                "90"
+            );
+        });
+    }
+}
@@ -0,0 +1,417 @@
+use std::sync::OnceLock;
+
+use crate::directory::SegmentDefinition;
+use crate::syntax::SyntaxVersion;
+use crate::validation::{self, ValidationIssue};
+use crate::Segment;
+
+// Service segment directories (ISO 9735), one for syntax versions 1 to 3 and one for version 4
+const SERVICE_V3: &str = include_str!("directory/service_v3.txt");
+const SERVICE_V4: &str = include_str!("directory/service_v4.txt");
+
+// Identification code qualifiers (code list 0007) accepted in S002 and S003
+const IDENTIFICATION_QUALIFIERS: &[&str] = &[
+    "1", "4", "5", "8", "9", "12", "14", "18", "22", "30", "31", "33", "34", "51", "52", "53",
+    "54", "55", "57", "58", "59", "61", "63", "65", "80", "82", "84", "85", "86", "87", "89", "90",
+    "91", "92", "103", "128", "129", "144", "145", "146", "147", "148", "ZZ", "ZZZ",
+];
+
+// Controlling agencies (code list 0051)
+const CONTROLLING_AGENCIES: &[&str] = &[
+    "AA", "AB", "AC", "AD", "AE", "CC", "CE", "EC", "ED", "EE", "EN", "ER", "EU", "EX", "IA", "KE",
+    "LI", "OD", "RI", "RT", "UN",
+];
+
+fn code_list(id: &str, version: SyntaxVersion) -> Option<&'static [&'static str]> {
+    match id {
+        "0007" => Some(IDENTIFICATION_QUALIFIERS),
+        "0025" => Some(&["AA", "BB"]),
+        "0029" => Some(&["A"]),
+        "0031" if version >= SyntaxVersion::V4 => Some(&["1", "2"]),
+        "0031" => Some(&["1"]),
+        "0035" => Some(&["1"]),
+        "0051" => Some(CONTROLLING_AGENCIES),
+        "0073" => Some(&["C", "F"]),
+        _ => None,
+    }
+}
+
+fn service_definitions(version: SyntaxVersion) -> &'static [SegmentDefinition] {
+    static V3: OnceLock<Vec<SegmentDefinition>> = OnceLock::new();
+    static V4: OnceLock<Vec<SegmentDefinition>> = OnceLock::new();
+
+    let (cell, text) = if version >= SyntaxVersion::V4 {
+        (&V4, SERVICE_V4)
+    } else {
+        (&V3, SERVICE_V3)
+    };
+    cell.get_or_init(|| {
+        SegmentDefinition::parse_all(text).expect("built-in service segment definitions")
+    })
+}
+
+pub(crate) fn service_segment_definition(
+    tag: &str,
+    version: SyntaxVersion,
+) -> Option<&'static SegmentDefinition> {
+    service_definitions(version).iter().find(|d| d.tag == tag)
+}
+
+pub(crate) fn validate_service_segment(
+    segment: &Segment,
+    version: SyntaxVersion,
+) -> Vec<ValidationIssue> {
+    let Some(definition) = service_segment_definition(&segment.tag, version) else {
+        return Vec::new();
+    };
+    let mut issues = definition.validate(segment, '.');
+
+    let issue = |code: &str, (element, component): (usize, usize), message: String| {
+        ValidationIssue::element(
+            code,
+            &segment.tag,
+            Some(segment.position),
+            element,
+            component,
+            message,
+        )
+    };
+
+    for (index, element) in definition.elements.iter().enumerate() {
+        for (position, component) in element.components().iter().enumerate() {
+            let Some(value) = segment
+                .get_component(index, position)
+                .filter(|v| !v.is_empty())
+            else {
+                continue;
+            };
+            if let Some(codes) = code_list(&component.id, version) {
+                if !codes.contains(&value.as_str()) {
+                    issues.push(issue(
+                        validation::INVALID_VALUE,
+                        (index, position),
+                        format!("'{}' is not a valid code for {}", value, component.id),
+                    ));
+                }
+            }
+        }
+    }
+
+    if segment.tag == "UNB" {
+        if let Some(location) = definition.locate("0017") {
+            if let Some(date) = segment.get_component(location.0, location.1) {
+                if !date.is_empty() && !is_valid_date(date) {
+                    issues.push(issue(
+                        validation::INVALID_VALUE,
+                        location,
+                        format!("'{}' is not a valid preparation date", date),
+                    ));
+                }
+            }
+        }
+        if let Some(location) = definition.locate("0019") {
+            if let Some(time) = segment.get_component(location.0, location.1) {
+                if !time.is_empty() && !is_valid_time(time) {
+                    issues.push(issue(
+                        validation::INVALID_VALUE,
+                        location,
+                        format!("'{}' is not a valid preparation time", time),
+                    ));
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+/// Validates the service segments around a single message: UNB and UNH themselves, the
+/// UNT segment count and reference, and the UNZ control reference.
+pub(crate) fn validate_envelope(
+    interchange_header: Option<&Segment>,
+    message_header: Option<&Segment>,
+    segments: &[Segment],
+) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let version = check_interchange_header(interchange_header, &mut issues);
+
+    let trailer_index = segments.iter().position(|s| s.tag == "UNT");
+    let body = &segments[..trailer_index.unwrap_or(segments.len())];
+    check_message(
+        message_header,
+        body,
+        trailer_index.map(|index| &segments[index]),
+        version,
+        &mut issues,
+    );
+
+    let trailer = segments.iter().find(|s| s.tag == "UNZ");
+    check_interchange_trailer(interchange_header, trailer, None, version, &mut issues);
+    issues
+}
+
+// Validates UNB and returns the syntax version the rest of the interchange is checked against
+pub(crate) fn check_interchange_header(
+    header: Option<&Segment>,
+    issues: &mut Vec<ValidationIssue>,
+) -> SyntaxVersion {
+    let Some(header) = header else {
+        issues.push(ValidationIssue::segment(
+            validation::MISSING,
+            "UNB",
+            None,
+            "Interchange header is missing".to_string(),
+        ));
+        return SyntaxVersion::default();
+    };
+
+    let version = SyntaxVersion::detect(header).unwrap_or_else(|| {
+        issues.push(ValidationIssue::element(
+            validation::SYNTAX_VERSION_NOT_SUPPORTED,
+            "UNB",
+            Some(header.position),
+            0,
+            1,
+            "Syntax version must be 1, 2, 3 or 4".to_string(),
+        ));
+        SyntaxVersion::default()
+    });
+    if let Some(identifier) = header.get_component(0, 0) {
+        if !version.allows_syntax_identifier(identifier) {
+            issues.push(ValidationIssue::element(
+                validation::SYNTAX_VERSION_NOT_SUPPORTED,
+                "UNB",
+                Some(header.position),
+                0,
+                0,
+                format!(
+                    "Syntax identifier {} is not allowed in syntax version {}",
+                    identifier,
+                    version.number()
+                ),
+            ));
+        }
+    }
+    issues.extend(validate_service_segment(header, version));
+    version
+}
+
+/// Validates UNH and, when present, UNT, including the UNT segment count (which runs from
+/// UNH to UNT inclusive) and the message reference repeated in UNT.
+pub(crate) fn check_message(
+    header: Option<&Segment>,
+    body: &[Segment],
+    trailer: Option<&Segment>,
+    version: SyntaxVersion,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    match header {
+        Some(header) => issues.extend(validate_service_segment(header, version)),
+        None => issues.push(ValidationIssue::segment(
+            validation::MISSING,
+            "UNH",
+            body.first().map(|s| s.position),
+            "Message header is missing".to_string(),
+        )),
+    }
+
+    let Some(trailer) = trailer else {
+        return;
+    };
+    issues.extend(validate_service_segment(trailer, version));
+
+    let expected = (body.len() + 2).to_string();
+    if trailer
+        .get_component(0, 0)
+        .is_some_and(|count| *count != expected)
+    {
+        issues.push(ValidationIssue::element(
+            validation::CONTROL_COUNT_MISMATCH,
+            "UNT",
+            Some(trailer.position),
+            0,
+            0,
+            format!("Message contains {} segments", expected),
+        ));
+    }
+    let reference = header.and_then(|h| h.get_component(0, 0));
+    if reference.is_some() && trailer.get_component(1, 0) != reference {
+        issues.push(ValidationIssue::element(
+            validation::REFERENCES_DO_NOT_MATCH,
+            "UNT",
+            Some(trailer.position),
+            1,
+            0,
+            "Message reference does not match UNH".to_string(),
+        ));
+    }
+}
+
+/// Validates UNZ against UNB. The message count is only checked when the caller knows how
+/// many messages the interchange carried.
+pub(crate) fn check_interchange_trailer(
+    header: Option<&Segment>,
+    trailer: Option<&Segment>,
+    message_count: Option<usize>,
+    version: SyntaxVersion,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let Some(trailer) = trailer else {
+        if message_count.is_some() {
+            issues.push(ValidationIssue::segment(
+                validation::MISSING,
+                "UNZ",
+                None,
+                "Interchange trailer is missing".to_string(),
+            ));
+        }
+        return;
+    };
+    issues.extend(validate_service_segment(trailer, version));
+
+    if let Some(count) = message_count {
+        if trailer
+            .get_component(0, 0)
+            .is_some_and(|value| *value != count.to_string())
+        {
+            issues.push(ValidationIssue::element(
+                validation::CONTROL_COUNT_MISMATCH,
+                "UNZ",
+                Some(trailer.position),
+                0,
+                0,
+                format!("Interchange contains {} messages", count),
+            ));
+        }
+    }
+    let reference = header.and_then(|h| h.get_component(4, 0));
+    if reference.is_some() && trailer.get_component(1, 0) != reference {
+        issues.push(ValidationIssue::element(
+            validation::REFERENCES_DO_NOT_MATCH,
+            "UNZ",
+            Some(trailer.position),
+            1,
+            0,
+            "Interchange control reference does not match UNB".to_string(),
+        ));
+    }
+}
+
+// Accepts YYMMDD (syntax versions 1 to 3) and CCYYMMDD (syntax version 4)
+fn is_valid_date(date: &str) -> bool {
+    if !date.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let (year, rest) = match date.len() {
+        6 => (2000 + date[..2].parse::<u32>().unwrap_or(0), &date[2..]),
+        8 => (date[..4].parse::<u32>().unwrap_or(0), &date[4..]),
+        _ => return false,
+    };
+    let month = rest[..2].parse::<u32>().unwrap_or(0);
+    let day = rest[2..].parse::<u32>().unwrap_or(0);
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days_in_month).contains(&day)
+}
+
+fn is_valid_time(time: &str) -> bool {
+    time.len() == 4
+        && time.chars().all(|c| c.is_ascii_digit())
+        && time[..2].parse::<u32>().unwrap_or(99) < 24
+        && time[2..].parse::<u32>().unwrap_or(99) < 60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    fn parse(segment: &str) -> Segment {
+        crate::Parser::new().parse_segment(segment, 0).unwrap()
+    }
+
+    fn codes(issues: &[ValidationIssue]) -> Vec<(&str, Option<usize>)> {
+        issues
+            .iter()
+            .map(|i| (i.code.as_str(), i.element))
+            .collect()
+    }
+
+    #[test]
+    fn test_service_definitions_per_version() {
+        Python::with_gil(|_py| {
+            let v3 = service_segment_definition("UNB", SyntaxVersion::V3).unwrap();
+            let v4 = service_segment_definition("UNB", SyntaxVersion::V4).unwrap();
+            assert_eq!(v3.locate("0017"), Some((3, 0)));
+            assert_eq!(v4.locate("0042"), Some((1, 3)));
+            assert_eq!(v3.locate("0042"), None);
+            assert!(service_segment_definition("UNT", SyntaxVersion::V1).is_some());
+            assert!(service_segment_definition("BGM", SyntaxVersion::V4).is_none());
+        });
+    }
+
+    #[test]
+    fn test_valid_interchange_header() {
+        Python::with_gil(|_py| {
+            let v4 = parse(
+                "UNB+UNOC:4+5412345678908:14+8798765432106:14+20240119:1200+REF123++ORDERS++++1'",
+            );
+            assert_eq!(validate_service_segment(&v4, SyntaxVersion::V4), Vec::new());
+
+            let v3 = parse("UNB+UNOA:3+SENDER:ZZ+RECEIVER:ZZ+240119:1200+REF123'");
+            assert_eq!(validate_service_segment(&v3, SyntaxVersion::V3), Vec::new());
+        });
+    }
+
+    #[test]
+    fn test_invalid_interchange_header() {
+        Python::with_gil(|_py| {
+            // Version 4 requires CCYYMMDD, qualifier 99 and test indicator 2 are not codes
+            let segment = parse("UNB+UNOC:4+SENDER:99+RECEIVER+240119:2460+REF123++++++2'");
+            let issues = validate_service_segment(&segment, SyntaxVersion::V4);
+            assert_eq!(
+                codes(&issues),
+                vec![
+                    ("40", Some(3)),
+                    ("12", Some(1)),
+                    ("12", Some(10)),
+                    ("12", Some(3)),
+                ]
+            );
+
+            assert!(!is_valid_date("20230229"));
+            assert!(is_valid_date("20240229"));
+            assert!(is_valid_date("240229"));
+        });
+    }
+
+    #[test]
+    fn test_message_header_and_trailers() {
+        Python::with_gil(|_py| {
+            let header = parse("UNH+1+ORDERS:D:96A:UN+REF+1:F'");
+            assert_eq!(
+                validate_service_segment(&header, SyntaxVersion::V3),
+                Vec::new()
+            );
+
+            let header = parse("UNH+1+ORDERS:D:96A+REF+X:Z'");
+            assert_eq!(
+                codes(&validate_service_segment(&header, SyntaxVersion::V3)),
+                vec![("13", Some(1)), ("37", Some(3)), ("12", Some(3))]
+            );
+
+            let trailer = parse("UNT+ABC'");
+            assert_eq!(
+                codes(&validate_service_segment(&trailer, SyntaxVersion::V4)),
+                vec![("37", Some(0)), ("13", Some(1))]
+            );
+        });
+    }
+}
@@ -0,0 +1,139 @@
+use pyo3::prelude::*;
+
+use crate::{Delimiters, Segment};
+
+/// ISO 9735 syntax version, as declared in UNB S001. It decides the service characters in
+/// use, the UNB date format and which character repertoires (UNOx levels) are allowed.
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SyntaxVersion {
+    V1 = 1,
+    V2 = 2,
+    V3 = 3,
+    #[default]
+    V4 = 4,
+}
+
+#[pymethods]
+impl SyntaxVersion {
+    #[staticmethod]
+    pub(crate) fn from_number(number: u8) -> Option<SyntaxVersion> {
+        match number {
+            1 => Some(SyntaxVersion::V1),
+            2 => Some(SyntaxVersion::V2),
+            3 => Some(SyntaxVersion::V3),
+            4 => Some(SyntaxVersion::V4),
+            _ => None,
+        }
+    }
+
+    /// Reads the syntax version number from a UNB segment.
+    #[staticmethod]
+    pub(crate) fn detect(interchange_header: &Segment) -> Option<SyntaxVersion> {
+        interchange_header
+            .get_component(0, 1)
+            .and_then(|v| v.parse::<u8>().ok())
+            .and_then(SyntaxVersion::from_number)
+    }
+
+    pub(crate) fn number(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The syntax identifiers (UNOx character repertoires) this version allows.
+    pub(crate) fn syntax_identifiers(&self) -> Vec<&'static str> {
+        let mut identifiers = vec!["UNOA", "UNOB"];
+        if *self >= SyntaxVersion::V2 {
+            identifiers.extend(["UNOC", "UNOD", "UNOE", "UNOF"]);
+        }
+        if *self >= SyntaxVersion::V3 {
+            identifiers.extend(["UNOG", "UNOH", "UNOI", "UNOJ", "UNOK"]);
+        }
+        if *self >= SyntaxVersion::V4 {
+            identifiers.extend(["UNOW", "UNOX", "UNOY", "KECA"]);
+        }
+        identifiers
+    }
+
+    pub(crate) fn allows_syntax_identifier(&self, identifier: &str) -> bool {
+        self.syntax_identifiers().contains(&identifier)
+    }
+
+    /// Only syntax version 4 has a repetition separator; earlier versions reserve the
+    /// position in UNA and leave it as a space.
+    pub(crate) fn has_repetition_separator(&self) -> bool {
+        *self >= SyntaxVersion::V4
+    }
+
+    pub(crate) fn default_delimiters(&self) -> Delimiters {
+        Delimiters {
+            reserved: if self.has_repetition_separator() {
+                '*'
+            } else {
+                ' '
+            },
+            ..Delimiters::default()
+        }
+    }
+}
+
+impl SyntaxVersion {
+    /// The chrono format of the UNB preparation date: YYMMDD before version 4, CCYYMMDD from it.
+    pub(crate) fn date_format(&self) -> &'static str {
+        if *self >= SyntaxVersion::V4 {
+            "%Y%m%d"
+        } else {
+            "%y%m%d"
+        }
+    }
+
+    pub(crate) fn date_length(&self) -> usize {
+        if *self >= SyntaxVersion::V4 {
+            8
+        } else {
+            6
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    #[test]
+    fn test_detect_syntax_version() {
+        Python::with_gil(|_py| {
+            let parser = crate::Parser::new();
+            let unb = parser
+                .parse_segment("UNB+UNOA:3+SENDER+RECEIVER+240119:1200+REF1'", 0)
+                .unwrap();
+            assert_eq!(SyntaxVersion::detect(&unb), Some(SyntaxVersion::V3));
+
+            let unb = parser
+                .parse_segment("UNB+UNOA:5+SENDER+RECEIVER+240119:1200+REF1'", 0)
+                .unwrap();
+            assert_eq!(SyntaxVersion::detect(&unb), None);
+        });
+    }
+
+    #[test]
+    fn test_syntax_version_rules() {
+        Python::with_gil(|_py| {
+            assert!(SyntaxVersion::V1.allows_syntax_identifier("UNOB"));
+            assert!(!SyntaxVersion::V1.allows_syntax_identifier("UNOC"));
+            assert!(SyntaxVersion::V3.allows_syntax_identifier("UNOK"));
+            assert!(!SyntaxVersion::V3.allows_syntax_identifier("UNOY"));
+            assert!(SyntaxVersion::V4.allows_syntax_identifier("UNOY"));
+
+            assert_eq!(SyntaxVersion::V3.default_delimiters().reserved, ' ');
+            assert_eq!(
+                SyntaxVersion::V4.default_delimiters(),
+                Delimiters::default()
+            );
+            assert_eq!(SyntaxVersion::V2.date_length(), 6);
+            assert_eq!(SyntaxVersion::V4.date_format(), "%Y%m%d");
+        });
+    }
+}
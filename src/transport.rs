@@ -0,0 +1,550 @@
+use pyo3::prelude::*;
+
+use crate::common::{DateTimePeriod, Location, Measurement, Party, Reference, Transport};
+use crate::directory::SegmentGroup;
+use crate::document::Document;
+use crate::header::{component, MessageHeader};
+use crate::{Message, Segment};
+
+// Joins the free text (C108) of the FTX segments directly in a group
+fn free_text(group: &SegmentGroup) -> Option<String> {
+    let text: Vec<String> = group
+        .segments_by_tag("FTX")
+        .flat_map(|ftx| (0..5).filter_map(move |index| component(ftx, 3, index)))
+        .collect();
+    (!text.is_empty()).then(|| text.join(" "))
+}
+
+// Segments of a tag that either follow the trigger directly or open nested groups
+fn nested<'a>(group: &'a SegmentGroup, tag: &'a str) -> impl Iterator<Item = &'a Segment> {
+    group.segments_by_tag(tag).chain(
+        group
+            .groups_by_trigger(tag)
+            .filter_map(move |g| g.find_segment(tag)),
+    )
+}
+
+fn references(group: &SegmentGroup) -> Vec<Reference> {
+    nested(group, "RFF").map(Reference::from_segment).collect()
+}
+
+fn dates(group: &SegmentGroup) -> Vec<DateTimePeriod> {
+    group
+        .segments_by_tag("DTM")
+        .map(DateTimePeriod::from_segment)
+        .collect()
+}
+
+fn transport(group: &SegmentGroup) -> Vec<Transport> {
+    group
+        .groups_by_trigger("TDT")
+        .filter_map(Transport::from_group)
+        .collect()
+}
+
+fn equipment(group: &SegmentGroup) -> Vec<Equipment> {
+    group
+        .groups_by_trigger("EQD")
+        .filter_map(Equipment::from_group)
+        .collect()
+}
+
+fn goods_items(group: &SegmentGroup) -> Vec<GoodsItem> {
+    group
+        .groups_by_trigger("GID")
+        .filter_map(GoodsItem::from_group)
+        .collect()
+}
+
+/// An EQD piece of transport equipment, usually a container, with its measurements and
+/// seal numbers.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Equipment {
+    /// 8053: e.g. CN (container) or TE (trailer)
+    #[pyo3(get)]
+    pub(crate) qualifier: String,
+    #[pyo3(get)]
+    pub(crate) id: Option<String>,
+    /// 8155 size and type, e.g. 22G1 for a 20' general purpose container
+    #[pyo3(get)]
+    pub(crate) size_type: Option<String>,
+    /// 8077: 1 (shipper supplied) or 2 (carrier supplied)
+    #[pyo3(get)]
+    pub(crate) supplier: Option<String>,
+    /// 8249 status, e.g. 2 (export) or 3 (import)
+    #[pyo3(get)]
+    pub(crate) status: Option<String>,
+    /// 8169: 4 (empty) or 5 (full)
+    #[pyo3(get)]
+    pub(crate) full_empty: Option<String>,
+    #[pyo3(get)]
+    pub(crate) measurements: Vec<Measurement>,
+    #[pyo3(get)]
+    pub(crate) seals: Vec<String>,
+}
+
+impl Equipment {
+    pub(crate) fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("EQD")?;
+        Some(Equipment {
+            qualifier: component(segment, 0, 0).unwrap_or_default(),
+            id: component(segment, 1, 0),
+            size_type: component(segment, 2, 0),
+            supplier: component(segment, 3, 0),
+            status: component(segment, 4, 0),
+            full_empty: component(segment, 5, 0),
+            measurements: nested(group, "MEA")
+                .map(Measurement::from_segment)
+                .collect(),
+            seals: group
+                .segments_by_tag("SEL")
+                .filter_map(|sel| component(sel, 0, 0))
+                .collect(),
+        })
+    }
+}
+
+/// A GID goods item: the number and type of packages, their description, marks and
+/// measurements, and the equipment (SGP) they are stowed in.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoodsItem {
+    #[pyo3(get)]
+    item_number: Option<String>,
+    #[pyo3(get)]
+    package_count: Option<String>,
+    /// 7065 type of packages, e.g. CT (carton) or PX (pallet)
+    #[pyo3(get)]
+    package_type: Option<String>,
+    #[pyo3(get)]
+    description: Option<String>,
+    /// PCI shipping marks (C210)
+    #[pyo3(get)]
+    marks: Vec<String>,
+    #[pyo3(get)]
+    measurements: Vec<Measurement>,
+    #[pyo3(get)]
+    references: Vec<Reference>,
+    #[pyo3(get)]
+    equipment_ids: Vec<String>,
+}
+
+impl GoodsItem {
+    fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("GID")?;
+        Some(GoodsItem {
+            item_number: component(segment, 0, 0),
+            package_count: component(segment, 1, 0),
+            package_type: component(segment, 1, 1),
+            description: free_text(group),
+            marks: nested(group, "PCI")
+                .flat_map(|pci| (0..10).filter_map(move |index| component(pci, 1, index)))
+                .collect(),
+            measurements: nested(group, "MEA")
+                .map(Measurement::from_segment)
+                .collect(),
+            references: references(group),
+            equipment_ids: nested(group, "SGP")
+                .filter_map(|sgp| component(sgp, 0, 0))
+                .collect(),
+        })
+    }
+}
+
+/// An STS status event of a consignment, such as a departure or a delivery, with where and
+/// when it happened and the transport, equipment and goods it concerns.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEvent {
+    /// 9015 status category, e.g. 1 (transport) or 3 (customs)
+    #[pyo3(get)]
+    category: Option<String>,
+    /// 4405 status event, e.g. 21 (delivered) or 24 (departed)
+    #[pyo3(get)]
+    code: Option<String>,
+    /// 9013 status reason
+    #[pyo3(get)]
+    reason: Option<String>,
+    #[pyo3(get)]
+    dates: Vec<DateTimePeriod>,
+    #[pyo3(get)]
+    locations: Vec<Location>,
+    #[pyo3(get)]
+    references: Vec<Reference>,
+    #[pyo3(get)]
+    parties: Vec<Party>,
+    #[pyo3(get)]
+    text: Option<String>,
+    #[pyo3(get)]
+    transport: Vec<Transport>,
+    #[pyo3(get)]
+    equipment: Vec<Equipment>,
+    #[pyo3(get)]
+    goods_items: Vec<GoodsItem>,
+}
+
+impl StatusEvent {
+    fn from_group(group: &SegmentGroup) -> Option<Self> {
+        let segment = group.find_segment("STS")?;
+        Some(StatusEvent {
+            category: component(segment, 0, 0),
+            code: component(segment, 1, 0),
+            reason: component(segment, 2, 0),
+            dates: dates(group),
+            locations: group
+                .segments_by_tag("LOC")
+                .map(Location::from_segment)
+                .collect(),
+            references: references(group),
+            parties: group
+                .segments_by_tag("NAD")
+                .map(Party::from_segment)
+                .collect(),
+            text: free_text(group),
+            transport: transport(group),
+            equipment: equipment(group),
+            goods_items: goods_items(group),
+        })
+    }
+}
+
+/// A consignment with its transport stages, goods items, equipment and status events.
+/// In IFTSTA every CNI group is a consignment, while an IFTMIN instruction is one
+/// consignment as a whole.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consignment {
+    /// CNI 1490 consolidation item number
+    #[pyo3(get)]
+    sequence: Option<String>,
+    /// CNI 1004 document number, or the RFF+CN consignment reference number in IFTMIN
+    #[pyo3(get)]
+    number: Option<String>,
+    #[pyo3(get)]
+    references: Vec<Reference>,
+    #[pyo3(get)]
+    dates: Vec<DateTimePeriod>,
+    #[pyo3(get)]
+    locations: Vec<Location>,
+    #[pyo3(get)]
+    parties: Vec<Party>,
+    #[pyo3(get)]
+    transport: Vec<Transport>,
+    #[pyo3(get)]
+    goods_items: Vec<GoodsItem>,
+    #[pyo3(get)]
+    equipment: Vec<Equipment>,
+    #[pyo3(get)]
+    events: Vec<StatusEvent>,
+}
+
+impl Consignment {
+    fn from_group(group: &SegmentGroup) -> Self {
+        let cni = group.find_segment("CNI");
+        Consignment {
+            sequence: cni.and_then(|cni| component(cni, 0, 0)),
+            number: cni.and_then(|cni| component(cni, 1, 0)),
+            references: references(group),
+            dates: dates(group),
+            locations: nested(group, "LOC").map(Location::from_segment).collect(),
+            parties: group
+                .groups_by_trigger("NAD")
+                .filter_map(Party::from_group)
+                .collect(),
+            transport: transport(group),
+            goods_items: goods_items(group),
+            equipment: equipment(group),
+            events: group
+                .groups_by_trigger("STS")
+                .filter_map(StatusEvent::from_group)
+                .collect(),
+        }
+    }
+}
+
+/// An IFTMIN instruction, booking the transport of one consignment with a carrier.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct TransportInstruction {
+    document: Document,
+}
+
+#[pymethods]
+impl TransportInstruction {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<TransportInstruction> {
+        Ok(TransportInstruction {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> TransportInstruction {
+        TransportInstruction {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    fn get_consignment(&self) -> Consignment {
+        let mut consignment = Consignment::from_group(&self.tree());
+        consignment.number = consignment
+            .references
+            .iter()
+            .find(|reference| reference.qualifier == "CN")
+            .and_then(|reference| reference.number.clone());
+        consignment
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl TransportInstruction {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("IFTMIN")
+    }
+}
+
+/// An IFTSTA status report, giving the status events of one or more consignments.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct TransportStatus {
+    document: Document,
+}
+
+#[pymethods]
+impl TransportStatus {
+    #[staticmethod]
+    fn from_edifact(content: &str) -> PyResult<TransportStatus> {
+        Ok(TransportStatus {
+            document: Document::parse(content)?,
+        })
+    }
+
+    #[staticmethod]
+    fn from_message(message: &Message) -> TransportStatus {
+        TransportStatus {
+            document: Document::from_message(message),
+        }
+    }
+
+    #[getter(interchange_header)]
+    fn interchange_header_segment(&self) -> Option<Segment> {
+        self.document.interchange_header.clone()
+    }
+
+    #[getter(message_header)]
+    fn message_header_segment(&self) -> Option<Segment> {
+        self.document.message_header.clone()
+    }
+
+    #[getter]
+    fn segments(&self) -> Vec<Segment> {
+        self.document.segments.clone()
+    }
+
+    fn get_message_header(&self) -> PyResult<Option<MessageHeader>> {
+        self.document.message_header()
+    }
+
+    fn get_document_number(&self) -> Option<String> {
+        self.document.document_number()
+    }
+
+    fn get_dates(&self) -> Vec<DateTimePeriod> {
+        Document::dates(&self.tree())
+    }
+
+    fn get_references(&self) -> Vec<Reference> {
+        Document::references(&self.tree())
+    }
+
+    fn get_parties(&self) -> Vec<Party> {
+        Document::parties(&self.tree())
+    }
+
+    fn get_consignments(&self) -> Vec<Consignment> {
+        self.tree()
+            .groups_by_trigger("CNI")
+            .map(Consignment::from_group)
+            .collect()
+    }
+
+    fn to_edifact(&self) -> String {
+        self.document.to_edifact()
+    }
+}
+
+impl TransportStatus {
+    fn tree(&self) -> SegmentGroup {
+        self.document.tree("IFTSTA")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    #[test]
+    fn test_transport_instruction() {
+        Python::with_gil(|_py| {
+            let instruction = TransportInstruction::from_edifact(
+                "UNH+1+IFTMIN:D:96A:UN'
+BGM+340+BKG0001+9'
+DTM+137:20240301:102'
+RFF+CN:CONS77'
+TDT+20+V123+1++MAEU:172:20+++9321483::11:EMMA MAERSK'
+DTM+133:20240305:102'
+LOC+9+NLRTM'
+DTM+133:202403051200:203'
+LOC+11+USNYC'
+NAD+CZ+5412345000013::9'
+NAD+CN+4012345000016::9'
+GID+1+20:CT'
+FTX+AAA+++Machine parts'
+MEA+WT+AAB+KGM:1250'
+PCI+24+MARKS 1-20'
+SGP+MSKU1234565'
+EQD+CN+MSKU1234565+22G1+2++5'
+MEA+AAE+G+KGM:3450'
+SEL+SEAL001'
+UNT+21+1'",
+            )
+            .unwrap();
+            let consignment = instruction.get_consignment();
+            assert_eq!(consignment.number.as_deref(), Some("CONS77"));
+            assert_eq!(consignment.parties.len(), 2);
+
+            let stage = &consignment.transport[0];
+            assert_eq!(stage.stage_qualifier, "20");
+            assert_eq!(stage.vessel_name.as_deref(), Some("EMMA MAERSK"));
+            assert_eq!(stage.dates[0].value.as_deref(), Some("20240305"));
+            let ports: Vec<(&str, Option<&str>)> = stage
+                .locations
+                .iter()
+                .map(|loc| (loc.qualifier.as_str(), loc.id.as_deref()))
+                .collect();
+            assert_eq!(ports, vec![("9", Some("NLRTM")), ("11", Some("USNYC"))]);
+            assert_eq!(stage.locations[0].dates.len(), 1);
+
+            let item = &consignment.goods_items[0];
+            assert_eq!(item.package_count.as_deref(), Some("20"));
+            assert_eq!(item.package_type.as_deref(), Some("CT"));
+            assert_eq!(item.description.as_deref(), Some("Machine parts"));
+            assert_eq!(item.marks, vec!["MARKS 1-20".to_string()]);
+            assert_eq!(item.measurements[0].value.as_deref(), Some("1250"));
+            assert_eq!(item.equipment_ids, vec!["MSKU1234565".to_string()]);
+
+            assert_eq!(
+                consignment.equipment,
+                vec![Equipment {
+                    qualifier: "CN".to_string(),
+                    id: Some("MSKU1234565".to_string()),
+                    size_type: Some("22G1".to_string()),
+                    supplier: Some("2".to_string()),
+                    status: None,
+                    full_empty: Some("5".to_string()),
+                    measurements: vec![Measurement {
+                        qualifier: "AAE".to_string(),
+                        dimension: Some("G".to_string()),
+                        unit: Some("KGM".to_string()),
+                        value: Some("3450".to_string()),
+                    }],
+                    seals: vec!["SEAL001".to_string()],
+                }]
+            );
+        });
+    }
+
+    #[test]
+    fn test_transport_status_events() {
+        Python::with_gil(|_py| {
+            let status = TransportStatus::from_edifact(
+                "UNH+1+IFTSTA:D:96A:UN'
+BGM+77+STS0001+9'
+DTM+137:20240306:102'
+NAD+CA+MAEU'
+CNI+1+CONS77'
+RFF+BN:BKG0001'
+STS+1+24'
+DTM+334:202403051830:203'
+LOC+9+NLRTM'
+TDT+20+V123+1'
+EQD+CN+MSKU1234565'
+STS+1+21+ZZZ'
+DTM+334:202403200900:203'
+LOC+11+USNYC'
+FTX+AAA+++Delivered to consignee'
+CNI+2+CONS78'
+STS+1+16'
+UNT+18+1'",
+            )
+            .unwrap();
+            let consignments = status.get_consignments();
+            assert_eq!(consignments.len(), 2);
+            let consignment = &consignments[0];
+            assert_eq!(consignment.sequence.as_deref(), Some("1"));
+            assert_eq!(consignment.number.as_deref(), Some("CONS77"));
+            assert_eq!(consignment.references[0].qualifier, "BN");
+
+            let events: Vec<(Option<&str>, Option<&str>)> = consignment
+                .events
+                .iter()
+                .map(|event| (event.code.as_deref(), event.locations[0].id.as_deref()))
+                .collect();
+            assert_eq!(
+                events,
+                vec![(Some("24"), Some("NLRTM")), (Some("21"), Some("USNYC"))]
+            );
+            let departure = &consignment.events[0];
+            assert_eq!(
+                departure.transport[0].conveyance_reference.as_deref(),
+                Some("V123")
+            );
+            assert_eq!(departure.equipment[0].id.as_deref(), Some("MSKU1234565"));
+            let delivery = &consignment.events[1];
+            assert_eq!(delivery.reason.as_deref(), Some("ZZZ"));
+            assert_eq!(delivery.text.as_deref(), Some("Delivered to consignee"));
+            assert_eq!(consignments[1].events[0].code.as_deref(), Some("16"));
+        });
+    }
+}
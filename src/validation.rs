@@ -0,0 +1,90 @@
+use pyo3::prelude::*;
+use std::fmt;
+
+// Syntax error codes (UN/EDIFACT code list 0085) used when reporting findings
+pub(crate) const SYNTAX_VERSION_NOT_SUPPORTED: &str = "2";
+pub(crate) const INVALID_VALUE: &str = "12";
+pub(crate) const MISSING: &str = "13";
+pub(crate) const NOT_SUPPORTED_IN_POSITION: &str = "15";
+pub(crate) const TOO_MANY_CONSTITUENTS: &str = "16";
+pub(crate) const REFERENCES_DO_NOT_MATCH: &str = "28";
+pub(crate) const CONTROL_COUNT_MISMATCH: &str = "29";
+pub(crate) const INVALID_OCCURRENCE_OUTSIDE_MESSAGE: &str = "33";
+pub(crate) const TOO_MANY_REPETITIONS: &str = "35";
+pub(crate) const TOO_MANY_GROUP_REPETITIONS: &str = "36";
+pub(crate) const INVALID_CHARACTERS: &str = "37";
+pub(crate) const DATA_ELEMENT_TOO_LONG: &str = "39";
+pub(crate) const DATA_ELEMENT_TOO_SHORT: &str = "40";
+
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    #[pyo3(get)]
+    pub(crate) code: String,
+    #[pyo3(get)]
+    pub(crate) tag: String,
+    #[pyo3(get)]
+    pub(crate) position: Option<usize>,
+    #[pyo3(get)]
+    pub(crate) element: Option<usize>,
+    #[pyo3(get)]
+    pub(crate) component: Option<usize>,
+    #[pyo3(get)]
+    pub(crate) message: String,
+}
+
+impl ValidationIssue {
+    pub(crate) fn segment(code: &str, tag: &str, position: Option<usize>, message: String) -> Self {
+        ValidationIssue {
+            code: code.to_string(),
+            tag: tag.to_string(),
+            position,
+            element: None,
+            component: None,
+            message,
+        }
+    }
+
+    pub(crate) fn element(
+        code: &str,
+        tag: &str,
+        position: Option<usize>,
+        element: usize,
+        component: usize,
+        message: String,
+    ) -> Self {
+        ValidationIssue {
+            code: code.to_string(),
+            tag: tag.to_string(),
+            position,
+            element: Some(element),
+            component: Some(component),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.position, self.element) {
+            (Some(position), Some(element)) => write!(
+                f,
+                "[{}] {} (segment {}, element {}): {}",
+                self.code, self.tag, position, element, self.message
+            ),
+            (Some(position), None) => write!(
+                f,
+                "[{}] {} (segment {}): {}",
+                self.code, self.tag, position, self.message
+            ),
+            _ => write!(f, "[{}] {}: {}", self.code, self.tag, self.message),
+        }
+    }
+}
+
+#[pymethods]
+impl ValidationIssue {
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+}
@@ -0,0 +1,526 @@
+use roxmltree::Document;
+
+use crate::directory::{is_group_name, Directory, Node, SegmentGroup, SegmentNames};
+use crate::interchange::{declared_version, Interchange};
+use crate::syntax::SyntaxVersion;
+use crate::{Delimiters, EdifactError, Message, Segment};
+
+// The XML rendering follows the common layout of ISO 20625 tools: the message is an element
+// named after its type holding its segments, segment groups are elements named after the
+// group (SG2, SG25...), and each segment holds its data elements (D_1004) and composites
+// (C_C002) named after their EDED/EDCD identifiers. Without a directory, or beyond what it
+// defines, elements and components have positional names (e1, c1).
+//
+// Every element is written, so a message reads back to exactly the same segments: an empty
+// element is an empty XML element, and a single value that could be mistaken for it is
+// written as a component instead.
+
+const DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+pub(crate) fn message_to_xml(
+    message: &Message,
+    directory: Option<&Directory>,
+) -> Result<String, EdifactError> {
+    let mut writer = Writer::new(directory, SyntaxVersion::default());
+    writer.group(&message.arranged(directory))?;
+    Ok(writer.out)
+}
+
+pub(crate) fn interchange_to_xml(
+    interchange: &Interchange,
+    directory: Option<&Directory>,
+) -> Result<String, EdifactError> {
+    let mut writer = Writer::new(directory, interchange.syntax_version);
+    let delimiters = &interchange.delimiters;
+    let attributes = [
+        ("component", delimiters.component),
+        ("data", delimiters.data),
+        ("decimal", delimiters.decimal),
+        ("escape", delimiters.escape),
+        ("segment", delimiters.segment),
+        ("reserved", delimiters.reserved),
+    ];
+    writer.open("Interchange", &attributes)?;
+    for node in interchange.arranged(directory) {
+        writer.node(&node)?;
+    }
+    writer.close("Interchange");
+    Ok(writer.out)
+}
+
+pub(crate) fn message_from_xml(
+    content: &str,
+    directory: Option<&Directory>,
+) -> Result<Message, EdifactError> {
+    let document = parse(content)?;
+    let mut segments = Vec::new();
+    read_nodes(
+        document.root_element(),
+        directory.unwrap_or_else(|| Directory::builtin()),
+        SyntaxVersion::default(),
+        &mut segments,
+    )?;
+    Ok(Message::from_segments(segments))
+}
+
+pub(crate) fn interchange_from_xml(
+    content: &str,
+    directory: Option<&Directory>,
+) -> Result<Interchange, EdifactError> {
+    let document = parse(content)?;
+    let root = document.root_element();
+    let directory = directory.unwrap_or_else(|| Directory::builtin());
+
+    // Service segment names depend on the syntax version, which UNB itself declares
+    let mut version = SyntaxVersion::default();
+    let mut segments = Vec::new();
+    read_nodes(root, directory, version, &mut segments)?;
+    if let Some(declared) = declared_version(&segments).filter(|v| *v != version) {
+        version = declared;
+        segments.clear();
+        read_nodes(root, directory, version, &mut segments)?;
+    }
+    Ok(Interchange::from_read_segments(
+        segments,
+        read_delimiters(root)?,
+    ))
+}
+
+fn parse(content: &str) -> Result<Document<'_>, EdifactError> {
+    Document::parse(content).map_err(|e| EdifactError::new(format!("Invalid XML: {}", e)))
+}
+
+// Either all six service characters are given or the syntax version's defaults apply
+fn read_delimiters(root: roxmltree::Node) -> Result<Option<Delimiters>, EdifactError> {
+    let names = [
+        "component",
+        "data",
+        "decimal",
+        "escape",
+        "segment",
+        "reserved",
+    ];
+    if names.iter().all(|name| root.attribute(*name).is_none()) {
+        return Ok(None);
+    }
+    let mut characters = Vec::new();
+    for name in names {
+        let value = root.attribute(name).unwrap_or_default();
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => characters.push(c),
+            _ => {
+                return Err(EdifactError::new(format!(
+                    "The {} service character must be a single character, not '{}'",
+                    name, value
+                )))
+            }
+        }
+    }
+    Ok(Some(Delimiters {
+        component: characters[0],
+        data: characters[1],
+        decimal: characters[2],
+        escape: characters[3],
+        segment: characters[4],
+        reserved: characters[5],
+    }))
+}
+
+// Segment tags have three characters; anything else holding segments is a segment group
+// or a message
+fn read_nodes(
+    parent: roxmltree::Node,
+    directory: &Directory,
+    version: SyntaxVersion,
+    segments: &mut Vec<Segment>,
+) -> Result<(), EdifactError> {
+    for child in parent.children().filter(roxmltree::Node::is_element) {
+        let name = child.tag_name().name();
+        if is_group_name(name) || name.chars().count() != 3 {
+            read_nodes(child, directory, version, segments)?;
+        } else {
+            let names = SegmentNames::new(directory.segment(name, version));
+            segments.push(read_segment(child, names)?);
+        }
+    }
+    Ok(())
+}
+
+// Elements and components left out are read as empty
+fn read_segment(node: roxmltree::Node, names: SegmentNames) -> Result<Segment, EdifactError> {
+    let tag = node.tag_name().name();
+    let mut elements: Vec<Vec<String>> = Vec::new();
+    for element in node.children().filter(roxmltree::Node::is_element) {
+        let name = element.tag_name().name();
+        let index = names
+            .element_index(directory_name(name))
+            .ok_or_else(|| EdifactError::new(format!("Unknown element {} in {}", name, tag)))?;
+        if elements.len() <= index {
+            elements.resize(index + 1, Vec::new());
+        }
+
+        let components: Vec<roxmltree::Node> = element
+            .children()
+            .filter(roxmltree::Node::is_element)
+            .collect();
+        elements[index] = if components.is_empty() {
+            match element.text().unwrap_or_default() {
+                "" => Vec::new(),
+                value => vec![value.to_string()],
+            }
+        } else {
+            let mut values = Vec::new();
+            for component in components {
+                let name = component.tag_name().name();
+                let position = names
+                    .component_index(index, directory_name(name))
+                    .ok_or_else(|| {
+                        EdifactError::new(format!("Unknown component {} in {}", name, tag))
+                    })?;
+                if values.len() <= position {
+                    values.resize(position + 1, String::new());
+                }
+                values[position] = component.text().unwrap_or_default().to_string();
+            }
+            values
+        };
+    }
+    Ok(Segment::new(tag.to_string(), elements, 0))
+}
+
+// Identifiers start with a digit (data elements) or a capital letter (composites), which
+// XML names cannot or should not, so they are prefixed as in ISO 20625
+fn xml_name(name: String) -> String {
+    match name.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("D_{}", name),
+        Some(c) if c.is_ascii_uppercase() => format!("C_{}", name),
+        _ => name,
+    }
+}
+
+fn directory_name(name: &str) -> &str {
+    name.strip_prefix("D_")
+        .or_else(|| name.strip_prefix("C_"))
+        .unwrap_or(name)
+}
+
+struct Writer<'a> {
+    out: String,
+    depth: usize,
+    directory: Option<&'a Directory>,
+    version: SyntaxVersion,
+}
+
+impl<'a> Writer<'a> {
+    fn new(directory: Option<&'a Directory>, version: SyntaxVersion) -> Self {
+        Writer {
+            out: DECLARATION.to_string(),
+            depth: 0,
+            directory,
+            version,
+        }
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), EdifactError> {
+        match node {
+            Node::Segment(segment) => self.segment(segment),
+            Node::Group(group) => self.group(group),
+        }
+    }
+
+    fn group(&mut self, group: &SegmentGroup) -> Result<(), EdifactError> {
+        self.open(&group.name, &[])?;
+        for node in &group.nodes {
+            self.node(node)?;
+        }
+        self.close(&group.name);
+        Ok(())
+    }
+
+    fn segment(&mut self, segment: &Segment) -> Result<(), EdifactError> {
+        if segment.tag.chars().count() != 3 || is_group_name(&segment.tag) {
+            return Err(EdifactError::new(format!(
+                "Segment tag '{}' cannot be written as XML",
+                segment.tag
+            )));
+        }
+        if segment.elements.is_empty() {
+            return self.leaf(&segment.tag, "");
+        }
+
+        let definition = self
+            .directory
+            .and_then(|directory| directory.segment(&segment.tag, self.version));
+        let names = SegmentNames::new(definition);
+        self.open(&segment.tag, &[])?;
+        for (index, components) in segment.elements.iter().enumerate() {
+            let name = xml_name(names.element(index));
+            match components.as_slice() {
+                [] => self.leaf(&name, "")?,
+                [value] if !value.is_empty() && !names.is_composite(index) => {
+                    self.leaf(&name, value)?
+                }
+                _ => {
+                    self.open(&name, &[])?;
+                    for (position, value) in components.iter().enumerate() {
+                        self.leaf(&xml_name(names.component(index, position)), value)?;
+                    }
+                    self.close(&name);
+                }
+            }
+        }
+        self.close(&segment.tag);
+        Ok(())
+    }
+
+    fn open(&mut self, name: &str, attributes: &[(&str, char)]) -> Result<(), EdifactError> {
+        check_name(name)?;
+        self.indent();
+        self.out.push('<');
+        self.out.push_str(name);
+        for (attribute, value) in attributes {
+            self.out.push(' ');
+            self.out.push_str(attribute);
+            self.out.push_str("=\"");
+            self.out.push_str(&escape(&value.to_string())?);
+            self.out.push('"');
+        }
+        self.out.push_str(">\n");
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        self.out.push_str("</");
+        self.out.push_str(name);
+        self.out.push_str(">\n");
+    }
+
+    fn leaf(&mut self, name: &str, value: &str) -> Result<(), EdifactError> {
+        check_name(name)?;
+        self.indent();
+        if value.is_empty() {
+            self.out.push_str(&format!("<{}/>\n", name));
+        } else {
+            self.out
+                .push_str(&format!("<{}>{}</{}>\n", name, escape(value)?, name));
+        }
+        Ok(())
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+    }
+}
+
+fn check_name(name: &str) -> Result<(), EdifactError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(EdifactError::new(format!(
+            "'{}' cannot be used as an XML element name",
+            name
+        )))
+    }
+}
+
+// Line breaks and tabs are written as character references, which XML parsers keep as
+// they are rather than normalising them
+fn escape(value: &str) -> Result<String, EdifactError> {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push_str(&format!("&#{};", c as u32)),
+            c if (c as u32) < 0x20 => {
+                return Err(EdifactError::new(format!(
+                    "Character U+{:04X} cannot be written as XML",
+                    c as u32
+                )))
+            }
+            c => escaped.push(c),
+        }
+    }
+    Ok(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pyo3::Python;
+
+    const SEGMENTS: &str = "BGM Beginning of message
+  C002 C Document/message name
+    1001 C an..3 Document name code
+    1131 C an..17 Code list identification code
+    3055 C an..3 Code list responsible agency code
+    1000 C an..35 Document name
+  1004 C an..35 Document identifier
+  1225 C an..3 Message function code
+NAD Name and address
+  3035 M an..3 Party function code qualifier
+  C082 C Party identification details
+    3039 M an..35 Party identifier
+    1131 C an..17 Code list identification code
+    3055 C an..3 Code list responsible agency code
+  C058 C Name and address
+    3124 M an..35 Name and address description
+    3124 C an..35 Name and address description";
+
+    const ORDER: &str = "UNA:+.? '
+UNB+UNOC:3+SENDER+RECEIVER+240119:1200+REF1'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO1+9'
+NAD+BY+5412345000013::9+Smith & Sons <Ltd>:'
+LIN+1++ITEM1:BP'
+FTX+AAI+++ line one?+: '
+UNT+6+1'
+UNZ+1+REF1'
+";
+
+    fn directory() -> Directory {
+        let mut directory = Directory::new();
+        directory.load_segment_definitions(SEGMENTS).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_message_xml_with_directory() {
+        Python::with_gil(|_py| {
+            let directory = directory();
+            let interchange = Interchange::from_edifact(ORDER).unwrap();
+            let message = &interchange.messages[0];
+
+            let xml = message_to_xml(message, Some(&directory)).unwrap();
+            assert_eq!(
+                xml,
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<ORDERS>
+  <UNH>
+    <D_0062>1</D_0062>
+    <C_S009>
+      <D_0065>ORDERS</D_0065>
+      <D_0052>D</D_0052>
+      <D_0054>96A</D_0054>
+      <D_0051>UN</D_0051>
+    </C_S009>
+  </UNH>
+  <BGM>
+    <C_C002>
+      <D_1001>220</D_1001>
+    </C_C002>
+    <D_1004>PO1</D_1004>
+    <D_1225>9</D_1225>
+  </BGM>
+  <SG2>
+    <NAD>
+      <D_3035>BY</D_3035>
+      <C_C082>
+        <D_3039>5412345000013</D_3039>
+        <D_1131/>
+        <D_3055>9</D_3055>
+      </C_C082>
+      <C_C058>
+        <D_3124>Smith &amp; Sons &lt;Ltd&gt;</D_3124>
+      </C_C058>
+    </NAD>
+  </SG2>
+  <SG25>
+    <LIN>
+      <e1>1</e1>
+      <e2/>
+      <e3>
+        <c1>ITEM1</c1>
+        <c2>BP</c2>
+      </e3>
+    </LIN>
+    <FTX>
+      <e1>AAI</e1>
+      <e2/>
+      <e3/>
+      <e4>
+        <c1> line one+</c1>
+        <c2> </c2>
+      </e4>
+    </FTX>
+  </SG25>
+  <UNT>
+    <D_0074>6</D_0074>
+    <D_0062>1</D_0062>
+  </UNT>
+</ORDERS>
+"#
+            );
+
+            let read = message_from_xml(&xml, Some(&directory)).unwrap();
+            let elements = |message: &Message| -> Vec<(String, Vec<Vec<String>>)> {
+                message
+                    .all_segments()
+                    .map(|s| (s.tag.clone(), s.elements.clone()))
+                    .collect()
+            };
+            assert_eq!(elements(&read), elements(message));
+            assert!(message_from_xml(&xml, None).is_err());
+        });
+    }
+
+    #[test]
+    fn test_xml_round_trip_without_directory() {
+        Python::with_gil(|_py| {
+            let mut message = Message::new();
+            for elements in [
+                vec![vec![]],
+                vec![vec![String::new()]],
+                vec![vec!["a".to_string(), String::new()], vec![]],
+                vec![vec!["two\r\nlines\ttab".to_string()]],
+            ] {
+                message
+                    .segments
+                    .push(Segment::new("FTX".to_string(), elements, 0));
+            }
+
+            let xml = message_to_xml(&message, None).unwrap();
+            assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<MESSAGE>\n"));
+            let read = message_from_xml(&xml, None).unwrap();
+            let elements: Vec<&Vec<Vec<String>>> =
+                read.segments.iter().map(|s| &s.elements).collect();
+            let expected: Vec<&Vec<Vec<String>>> =
+                message.segments.iter().map(|s| &s.elements).collect();
+            assert_eq!(elements, expected);
+
+            message.segments[0].tag = "F X".to_string();
+            assert!(message_to_xml(&message, None).is_err());
+        });
+    }
+
+    #[test]
+    fn test_interchange_xml_round_trip() {
+        Python::with_gil(|_py| {
+            let interchange = Interchange::from_edifact(ORDER).unwrap();
+            for directory in [None, Some(directory())] {
+                let xml = interchange_to_xml(&interchange, directory.as_ref()).unwrap();
+                assert!(xml.contains(
+                    r#"<Interchange component=":" data="+" decimal="." escape="?" segment="'" reserved=" ">"#
+                ));
+                let read = interchange_from_xml(&xml, directory.as_ref()).unwrap();
+                assert_eq!(read.to_edifact(), interchange.to_edifact());
+                assert_eq!(read.syntax_version, SyntaxVersion::V3);
+                assert_eq!(read.messages.len(), 1);
+            }
+        });
+    }
+}
@@ -0,0 +1,57 @@
+use edifact_parser::{Address, EdifactError, MessageBuilder, OrderBuilder};
+use pyo3::prelude::*;
+
+#[test]
+fn test_order_builder_by_value() -> Result<(), EdifactError> {
+    Python::with_gil(|_py| {
+        let order = OrderBuilder::new()
+            .message_header("1", "ORDERS")?
+            .bgm("220", "PO001", "9")?
+            .date("137", "20240301", "102")?
+            .party("BY", Some("5412345000013"), "9", &Address::default())?
+            .line("1", "4000862141404", "10", "9.95")?
+            .build();
+        assert_eq!(
+            order.to_edifact().unwrap(),
+            "UNH+1+ORDERS:D:01B:UN'
+BGM+220+PO001+9'
+DTM+137:20240301:102'
+NAD+BY+5412345000013::9'
+LIN+1++4000862141404:BP'
+QTY+21:10'
+PRI+AAA:9.95'
+"
+        );
+        assert!(OrderBuilder::new()
+            .bgm("220", "PO001", "9")?
+            .bgm("220", "PO002", "9")
+            .is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_message_builder_by_value() {
+    Python::with_gil(|_py| {
+        let builder = MessageBuilder::new(
+            "INVOIC",
+            "D".into(),
+            "96A".into(),
+            "UN".into(),
+            None,
+            "42".into(),
+            None,
+        )
+        .unwrap()
+        .segment("BGM", vec![vec!["380".into()], vec!["INV001".into()]])
+        .unwrap();
+        assert!(builder
+            .clone()
+            .segment("UNT", vec![vec!["1".into()]])
+            .is_err());
+        assert_eq!(
+            builder.to_edifact().unwrap(),
+            "UNH+42+INVOIC:D:96A:UN'\nBGM+380+INV001'\nUNT+3+42'\n"
+        );
+    });
+}
@@ -0,0 +1,32 @@
+from edifact_parser import (
+    ApplicationAcknowledgement,
+    ApplicationAcknowledgementBuilder,
+    ApplicationError,
+    Order,
+)
+
+ORDER = """UNH+ME000001+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240115:102'
+LIN+1++4000862141404:SRV'
+QTY+21:48'
+UNT+6+ME000001'"""
+
+def test_aperak_round_trip():
+    order = Order.from_edifact(ORDER)
+    aperak = (ApplicationAcknowledgementBuilder("APE001")
+        .for_order(order)
+        .add_error(ApplicationError("45", text="Unknown item", line_number="1"))
+        .build())
+
+    assert "RFF+ACW:ME000001'" in aperak.to_edifact()
+    parsed = ApplicationAcknowledgement.from_edifact(aperak.to_edifact())
+    assert parsed.get_message_function() == "27"
+    assert parsed.get_referenced_message() == "ME000001"
+    error, = parsed.get_errors()
+    assert (error.code, error.text, error.line_number) == ("45", "Unknown item", "1")
+
+def test_acknowledgement_builder_chains_in_place():
+    builder = ApplicationAcknowledgementBuilder("APE001")
+    assert builder.with_date("137", "20240116") is builder
+    assert builder.with_reference("ON", "PO12345") is builder
@@ -13,3 +13,109 @@ def test_order_builder():
     assert order.interchange_header is not None
     assert order.message_header is not None
     assert len(order.segments) > 0
+
+def test_order_builder_rejects_invalid_interchange_header():
+    with pytest.raises(ValueError):
+        OrderBuilder().with_interchange_header("SENDER", "RECEIVER", "2024-01-19", "REF123")
+
+def test_order_builder_typed_interchange_header():
+    from datetime import datetime
+    from edifact_parser import InterchangeHeader, InterchangeParty
+
+    header = InterchangeHeader(
+        InterchangeParty("5412345678908", "14"),
+        InterchangeParty("8798765432106", "14"),
+        "REF123",
+        prepared=datetime(2024, 1, 19, 12, 0),
+        syntax_identifier="UNOC",
+        application_reference="ORDERS",
+    )
+    order = OrderBuilder().with_interchange(header).build()
+
+    typed = order.get_interchange_header()
+    assert typed.sender.qualifier == "14"
+    assert typed.prepared == datetime(2024, 1, 19, 12, 0)
+    assert typed.application_reference == "ORDERS"
+
+def test_order_builder_syntax_version():
+    from edifact_parser import SyntaxVersion
+
+    with pytest.raises(ValueError):
+        OrderBuilder().with_syntax("UNOY", SyntaxVersion.V3)
+
+    order = (OrderBuilder()
+        .with_syntax("UNOC", SyntaxVersion.V3)
+        .with_interchange_header("SENDER", "RECEIVER", "240119:1200", "REF123")
+        .build())
+    assert order.syntax_version == SyntaxVersion.V3
+    assert order.to_edifact().startswith("UNB+UNOC:3+SENDER+RECEIVER+240119:1200+REF123")
+
+def test_order_builder_header_and_parties():
+    order = (OrderBuilder()
+        .with_message_header("1", "ORDERS")
+        .with_party("BY", "5412345000013", name="Buyer NV", street="Main Street 1",
+                    city="Brussels", postal_code="1000", country="BE")
+        .with_contact("OC", name="J. Smith", email="j.smith@example.com")
+        .with_bgm("220", "PO001", "9")
+        .with_date("137", "20240301")
+        .with_date("2", "202403150900", format="203")
+        .with_reference("CT", "CONTRACT1")
+        .with_currency("EUR")
+        .with_payment_terms(periods="30")
+        .with_transport(mode="30")
+        .with_allowance_charge("A", service_code="DI", percentage="2")
+        .add_order_line("1", "4000862141404", "10", "9.95")
+        .with_line_product_id("W-100")
+        .with_line_description("Widget")
+        .with_line_amount("203", "99.50")
+        .with_line_date("2", "20240315")
+        .with_line_location("7", "4012345000023", quantity="10")
+        .build())
+
+    tags = [segment.tag for segment in order.segments]
+    assert tags[:4] == ["BGM", "DTM", "DTM", "RFF"]
+    buyer = order.get_party("BY")
+    assert buyer.city == "Brussels"
+    assert buyer.contacts[0].communications[0].channel == "EM"
+    assert order.get_payment_terms()[0].period_count == "30"
+    assert order.get_transport()[0].mode == "30"
+    assert order.get_allowances_charges()[0].percentage == "2"
+
+    line = order.get_order_lines()[0]
+    assert line.product_ids[0].item_numbers[0].number == "W-100"
+    assert line.descriptions[0].description == "Widget"
+    assert line.locations[0].quantity.value == "10"
+
+def test_order_builder_validates_as_it_goes():
+    builder = OrderBuilder()
+    with pytest.raises(ValueError):
+        builder.with_contact(name="Nobody")
+    with pytest.raises(ValueError):
+        builder.with_line_amount("203", "1.00")
+    with pytest.raises(ValueError):
+        builder.with_date("137", "20240230")
+    with pytest.raises(ValueError):
+        builder.with_party("BY", country="Belgium")
+
+    builder = builder.with_bgm("220", "PO001", "9")
+    with pytest.raises(ValueError):
+        builder.with_bgm("220", "PO002", "9")
+    for quantity, price in [("abc", "9.95"), ("10", "xyz"), ("inf", "9.95"), ("1e3", "9.95")]:
+        with pytest.raises(ValueError):
+            builder.add_order_line("1", "ITEM1", quantity, price)
+    builder.add_order_line("1", "ITEM1", "5", "1.00")
+    with pytest.raises(ValueError):
+        builder.with_line_amount("203", "NaN")
+
+def test_order_builder_changes_in_place():
+    builder = OrderBuilder()
+    assert builder.with_message_header("1", "ORDERS") is builder
+    assert builder.with_bgm("220", "PO001", "9").add_order_line("1", "ITEM1", "5", "1.00") is builder
+    builder.with_line_description("Widget")
+
+    order = builder.build()
+    assert len(order.segments) == 5
+    # A rejected segment leaves the builder as it was
+    with pytest.raises(ValueError):
+        builder.with_bgm("220", "PO002", "9")
+    assert len(builder.build().segments) == 5
@@ -0,0 +1,42 @@
+import pytest
+from edifact_parser import ContainerMessage, ContainerMessageBuilder, StowagePosition
+
+def test_stowage_position():
+    position = StowagePosition.from_code("0120386")
+    assert (position.bay, position.row, position.tier) == ("012", "03", "86")
+    assert str(StowagePosition(12, 3, 86)) == "0120386"
+
+def test_bay_plan_round_trip():
+    bay_plan = (ContainerMessageBuilder("BAPLIE", "BP0001", association_code="SMDG20")
+        .with_date("137", "202403011200")
+        .with_vessel("V123", "9321483", vessel_name="EMMA MAERSK", port="NLRTM",
+                     next_port="USNYC", departure="202403051800")
+        .add_container("MSKU1234565", "22G1", full_empty="5", position="0120386",
+                       weight="21500", port_of_loading="NLRTM", port_of_discharge="USNYC")
+        .build())
+
+    parsed = ContainerMessage.from_edifact(bay_plan.to_edifact())
+    assert parsed.message_type == "BAPLIE"
+    assert parsed.validate() == []
+    container = parsed.get_containers()[0]
+    assert container.id == "MSKU1234565"
+    assert container.position.bay == "012"
+    assert container.gross_weight().value == "21500"
+
+def test_gate_report_rejects_bad_check_digit():
+    builder = ContainerMessageBuilder("CODECO", "GATE0001", document_name="34")
+    with pytest.raises(ValueError):
+        builder.add_container("MSKU1234566", "22G1")
+
+    report = (builder
+        .add_container("CSQU3054383", "45G1", full_empty="5", weight="28750",
+                       booking="BKG0001", seals=["SEAL001"])
+        .build())
+    container = report.get_containers()[0]
+    assert container.has_valid_id()
+    assert container.equipment.seals == ["SEAL001"]
+    assert "CNT+16:1'" in report.to_edifact()
+
+def test_container_builder_chains_in_place():
+    builder = ContainerMessageBuilder("CODECO", "GATE0001", document_name="34")
+    assert builder.with_date("137", "202403011200", "203") is builder
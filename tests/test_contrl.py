@@ -0,0 +1,26 @@
+from edifact_parser import ControlAction, ControlReport, Interchange
+
+INTERCHANGE = """UNB+UNOC:3+SENDER1:14+RECEIVER1:14+240115:1030+REF001'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO1+9'
+UNT+3+1'
+UNZ+1+REF001'"""
+
+def test_contrl_round_trip():
+    interchange = Interchange.from_edifact(INTERCHANGE)
+    report = ControlReport.for_interchange(interchange, "CTL1")
+    assert "UCI+REF001+SENDER1:14+RECEIVER1:14+7'" in report.to_edifact()
+
+    parsed = ControlReport.from_edifact(report.to_edifact())
+    assert parsed.get_control_reference() == "REF001"
+    assert parsed.is_accepted()
+    response, = parsed.reconcile(interchange)
+    assert response.message_reference == "1"
+    assert response.action == ControlAction.Acknowledged
+
+def test_contrl_rejects_bad_trailer():
+    interchange = Interchange.from_edifact(INTERCHANGE.replace("UNT+3+1", "UNT+9+1"))
+    report = ControlReport.for_interchange(interchange, "CTL1")
+    response, = report.get_message_responses()
+    assert response.action == ControlAction.Rejected
+    assert response.errors[0].code == "29"
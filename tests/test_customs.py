@@ -0,0 +1,55 @@
+from edifact_parser import CustomsDeclaration, CustomsDeclarationBuilder, CustomsResponse
+
+def test_declaration_computes_duties():
+    declaration = (CustomsDeclarationBuilder("DEC0001")
+        .with_location("22", "NL000396")
+        .with_party("DT", "NL123456789", name="Declarant BV", city="Rotterdam", country="NL")
+        .with_currency("EUR")
+        .add_goods_item("62034231", "Cotton trousers", "2000", duty_rate="12", vat_rate="21",
+                        origin="BD", net_weight="250", package_count="10", package_type="CT",
+                        parties=[("CZ", "BD987654")])
+        .build())
+
+    parsed = CustomsDeclaration.from_edifact(declaration.to_edifact())
+    assert parsed.get_document_name() == "929"
+    item = parsed.get_goods_items()[0]
+    assert item.commodity_code == "62034231"
+    assert item.customs_value() == "2000.00"
+    assert item.duty_amount("CUD") == "240.00"
+    assert item.duty_amount("VAT") == "470.40"
+    assert item.weight().value == "250"
+    assert item.parties[0].qualifier == "CZ"
+    assert parsed.get_duty_amount("VAT") == "470.40"
+
+def test_response_errors_and_goods_items():
+    response = CustomsResponse.from_edifact(
+        "UNH+1+CUSRES:D:96B:UN'"
+        "BGM+962+RES0001+9'"
+        "GIS+4'"
+        "RFF+ABT:24NL000396ABC123'"
+        "TAX+5+CUD'"
+        "MOA+124:240.00:EUR'"
+        "ERP+1'"
+        "ERC+12'"
+        "FTX+AAO+++Missing transport document'"
+        "CST+1+84713000'"
+        "GIR+3+SN001:BN'"
+        "NAD+CZ+CN987654::9'"
+        "ERP+3:1'"
+        "ERC+15'"
+        "UNT+15+1'"
+    )
+    assert response.get_declaration_number() == "24NL000396ABC123"
+    assert response.get_processing_indicators() == ["4"]
+    assert response.get_duty_amount("CUD") == "240.00"
+    assert [error.code for error in response.get_errors()] == ["12"]
+
+    item = response.get_goods_items()[0]
+    assert item.related_numbers == ["SN001"]
+    assert item.parties[0].id == "CN987654"
+    assert item.errors[0].item_number == "1"
+
+def test_declaration_builder_chains_in_place():
+    builder = CustomsDeclarationBuilder("DEC0001")
+    assert builder.with_currency("EUR") is builder
+    assert builder.add_goods_item("84713000", "Laptops", "100", duty_rate="0") is builder
@@ -0,0 +1,30 @@
+import pytest
+from edifact_parser import DespatchAdvice, DespatchAdviceBuilder, Package
+
+def test_despatch_advice_round_trip():
+    carton = Package("CT", sscc="354123450000000014")
+    carton.add_item("1", "4000862141404", "24", unit="PCE")
+    pallet = Package("201")
+    pallet.add_child(carton)
+
+    despatch = (DespatchAdviceBuilder("DES1")
+        .with_date("11", "20240122")
+        .with_reference("ON", "PO12345")
+        .with_party("SU", "4012345000016")
+        .add_package(pallet)
+        .build())
+
+    parsed = DespatchAdvice.from_edifact(despatch.to_edifact())
+    packages = parsed.get_packages()
+    assert len(packages) == 1
+    assert packages[0].children[0].sscc == ["354123450000000014"]
+    assert packages[0].children[0].items[0].quantities[0].value == "24"
+
+def test_package_rejects_invalid_sscc():
+    with pytest.raises(ValueError):
+        Package("CT", sscc="354123450000000015")
+
+def test_despatch_builder_chains_in_place():
+    builder = DespatchAdviceBuilder("DES1")
+    assert builder.with_date("11", "20240122") is builder
+    assert builder.with_reference("ON", "PO12345").with_party("SU", "4012345000016") is builder
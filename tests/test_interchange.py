@@ -0,0 +1,27 @@
+import pytest
+from edifact_parser import Interchange
+
+MULTI_PART = """UNA:+.? '
+UNB+UNOC:4+SENDER:14+RECEIVER:14+20240119:1200+ICR1'
+UNH+1+ORDERS:D:96A:UN+CAR9+1:C'
+BGM+220+PO1+9'
+UNT+3+1'
+UNH+2+ORDERS:D:96A:UN+CAR9+2:F'
+LIN+1++ITEM1:BP'
+UNT+3+2'
+UNZ+2+ICR1'"""
+
+def test_interchange_message_header():
+    interchange = Interchange.from_edifact(MULTI_PART)
+    assert len(interchange.messages) == 1
+
+    header = interchange.messages[0].get_message_header()
+    assert header.message_type == "ORDERS"
+    assert header.release == "96A"
+    assert header.common_access_reference == "CAR9"
+    assert not header.is_partial()
+
+def test_interchange_service_segments():
+    interchange = Interchange.from_edifact(MULTI_PART)
+    assert interchange.validate_service_segments() == []
+    assert interchange.get_interchange_header().control_reference == "ICR1"
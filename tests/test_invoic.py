@@ -0,0 +1,43 @@
+import pytest
+from edifact_parser import Invoice, InvoiceBuilder
+
+def test_invoice_totals():
+    invoice = (InvoiceBuilder("INV001")
+        .with_date("137", "20240125")
+        .with_reference("ON", "PO12345")
+        .with_party("SU", "4012345000016", vat_number="DE123456789")
+        .with_currency("EUR")
+        .with_payment_terms("20240224")
+        .add_line("1", "4000862141404", "48", "10.00", "21")
+        .add_line("2", "4000862141411", "10", "5.50", "6")
+        .build())
+
+    parsed = Invoice.from_edifact(invoice.to_edifact())
+    assert parsed.get_order_number() == "PO12345"
+    assert parsed.get_summary_amount("79") == "535.00"
+    assert parsed.get_summary_amount("176") == "104.10"
+    assert parsed.get_summary_amount("77") == "639.10"
+    assert [tax.rate for tax in parsed.get_tax_summary()] == ["21", "6"]
+
+def test_invoice_builder_rejects_unknown_indicator():
+    with pytest.raises(ValueError):
+        InvoiceBuilder("INV001").with_allowance_charge("X", "10", "21")
+
+def test_invoice_tax_rounds_in_cents():
+    invoice = (InvoiceBuilder("INV002")
+        .with_date("137", "20240125")
+        .add_line("1", "4000862141404", "1", "10.05", "10")
+        .build())
+    assert invoice.get_summary_amount("176") == "1.01"
+    assert invoice.get_summary_amount("77") == "11.06"
+
+def test_invoice_builder_validates_values():
+    with pytest.raises(ValueError):
+        InvoiceBuilder("INV001").with_date("137", "20241325")
+    with pytest.raises(ValueError):
+        InvoiceBuilder("INV001").with_currency("euro")
+
+def test_invoice_builder_chains_in_place():
+    builder = InvoiceBuilder("INV001")
+    assert builder.with_date("137", "20240125") is builder
+    assert builder.with_currency("EUR").add_line("1", "4000862141404", "1", "10.00", "21") is builder
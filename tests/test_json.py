@@ -0,0 +1,54 @@
+import json
+
+import pytest
+from edifact_parser import Directory, Interchange, Message, Segment
+
+CONTENT = """UNA:+.? '
+UNB+UNOC:4+SENDER+RECEIVER+20240119:1200+REF1'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO1+9'
+DTM+137:20240301:102'
+NAD+BY+5412345000013::9'
+UNT+5+1'
+UNZ+1+REF1'
+"""
+
+SEGMENTS = """BGM Beginning of message
+  C002 C Document/message name
+    1001 C an..3 Document name code
+  1004 C an..35 Document identifier
+  1225 C an..3 Message function code
+"""
+
+def test_compact_json():
+    interchange = Interchange.from_edifact(CONTENT)
+    data = json.loads(interchange.to_json())
+    assert data["segments"][0][0] == "UNB"
+    assert data["segments"][3] == ["DTM", ["137", "20240301", "102"]]
+
+    read = Interchange.from_json(interchange.to_json())
+    assert read.to_edifact() == interchange.to_edifact()
+
+    segment = Segment.from_json('["RFF", ["ON", "PO1"]]')
+    assert segment.tag == "RFF"
+    assert segment.elements == [["ON", "PO1"]]
+
+def test_directory_aware_json():
+    directory = Directory()
+    directory.load_segment_definitions(SEGMENTS)
+    message = Interchange.from_edifact(CONTENT).get_messages("ORDERS")[0]
+
+    data = json.loads(message.to_json(directory))
+    nodes = data["ORDERS"]
+    assert nodes[0]["UNH"]["S009"]["0065"] == "ORDERS"
+    assert nodes[1] == {"BGM": {"C002": {"1001": "220"}, "1004": "PO1", "1225": "9"}}
+    assert nodes[3]["SG2"][0]["NAD"] == {"e1": "BY", "e2": {"c1": "5412345000013", "c2": "", "c3": "9"}}
+
+    read = Message.from_json(message.to_json(directory), directory)
+    assert [s.elements for s in read.segments] == [s.elements for s in message.segments]
+
+def test_invalid_json():
+    with pytest.raises(ValueError):
+        Message.from_json('{"ORDERS": [{"BGM": {"XXXX": "1"}}]}')
+    with pytest.raises(ValueError):
+        Segment.from_json("not json")
@@ -0,0 +1,43 @@
+import pytest
+from edifact_parser import Directory, MessageBuilder, OrderResponse
+
+def test_build_any_message_type():
+    builder = (MessageBuilder("ORDRSP", release="96A")
+        .add_segment("BGM", ["231", "RSP001", "29"])
+        .add_segment("DTM", [["137", "20240301", "102"]]))
+    assert builder.message_type == "ORDRSP"
+    assert builder.to_edifact().startswith("UNH+1+ORDRSP:D:96A:UN'")
+
+    response = OrderResponse.from_message(builder.build())
+    assert response.get_document_number() == "RSP001"
+
+def test_service_segments_are_rejected():
+    with pytest.raises(ValueError):
+        MessageBuilder("ORDERS").add_segment("UNT", ["1"])
+
+def test_directory_enforces_structure():
+    builder = MessageBuilder("INVOIC", release="96A", directory=Directory())
+    with pytest.raises(ValueError):
+        builder.add_segment("DTM", [["137", "20240301", "102"]])
+
+    builder = (builder
+        .add_segment("BGM", ["380", "INV001", "9"])
+        .add_segment("DTM", [["137", "20240301", "102"]])
+        .add_segment("LIN", ["1"]))
+    with pytest.raises(ValueError):
+        builder.add_segment("BGM", ["380", "INV002", "9"])
+    with pytest.raises(ValueError):
+        builder.build()
+
+    message = (builder
+        .add_segment("UNS", ["S"])
+        .add_segment("MOA", [["77", "100"]])
+        .build())
+    assert len(message.segments) == 5
+    assert message.get_service_segment("UNT").elements == [["7"], ["1"]]
+
+def test_message_builder_changes_in_place():
+    builder = MessageBuilder("INVOIC", release="96A")
+    assert builder.add_segment("BGM", ["380", "INV001", "9"]) is builder
+    builder.add_segment("DTM", [["137", "20240301", "102"]])
+    assert len(builder.segments) == 2
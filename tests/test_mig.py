@@ -0,0 +1,35 @@
+import pytest
+from edifact_parser import Directory, Mig, Order
+
+MIG = """
+message_type = "ORDERS"
+
+[[segments]]
+tag = "NAD"
+qualifier = "SU"
+status = "mandatory"
+"""
+
+def test_directory_builds_segment_groups(sample_edifact):
+    order = Order.from_edifact(sample_edifact)
+    tree = Directory().build_tree("ORDERS", order.segments)
+    lines = tree.get_groups("SG25")
+    assert len(lines) == 1
+    assert lines[0].trigger().tag == "LIN"
+
+def test_mig_reports_missing_party(sample_edifact):
+    order = Order.from_edifact(sample_edifact)
+    issues = Mig.from_toml(MIG).validate(order.segments)
+    assert any(issue.tag == "NAD" and issue.code == "13" for issue in issues)
+
+def test_mig_rejects_invalid_config():
+    with pytest.raises(ValueError):
+        Mig.from_json("{}")
+    with pytest.raises(ValueError):
+        Mig.from_json('{"message_type": "ORDERS", "segments": [{"tag": "ALI", "status": "C"}]}')
+
+def test_mig_requires_its_release(sample_edifact):
+    order = Order.from_edifact(sample_edifact)
+    Mig.from_toml('release = "96A"\n' + MIG).validate(order.segments)
+    with pytest.raises(ValueError):
+        Mig.from_toml('release = "01B"\n' + MIG).validate(order.segments)
@@ -0,0 +1,63 @@
+import pytest
+from edifact_parser import LineAction, Order, OrderChange
+
+ORDER = """UNB+UNOC:4+BUYER:14+SUPPLIER:14+20240119:1200+ICR1'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240119:102'
+NAD+BY+5412345000013::9'
+LIN+1++4000862141404:SRV'
+QTY+21:48:PCE'
+LIN+2++4000862141411:SRV'
+QTY+21:12:PCE'
+UNS+S'
+CNT+2:2'
+UNT+11+1'
+UNZ+1+ICR1'"""
+
+CHANGE = """UNH+1+ORDCHG:D:96A:UN'
+BGM+230+CHG1+4'
+RFF+ON:PO12345'
+LIN+1+3'
+QTY+21:60:PCE'
+LIN+2+2'
+LIN+3+1+4000862141428:SRV'
+QTY+21:5:PCE'
+UNS+S'
+UNT+10+1'"""
+
+def test_apply_order_change():
+    order = Order.from_edifact(ORDER)
+    change = OrderChange.from_edifact(CHANGE)
+    assert change.get_order_number() == "PO12345"
+
+    updated = change.apply_to(order)
+    lines = updated.get_order_lines()
+    assert [line.line_number for line in lines] == ["1", "3"]
+    assert lines[0].quantities[0].value == "60"
+    assert "CNT+2:2'" in updated.to_edifact()
+
+def test_order_change_places_new_segments_in_the_line():
+    order = Order.from_edifact(ORDER.replace("QTY+21:48:PCE'", "QTY+21:48:PCE'\nPRI+AAA:10.00'"))
+    change = OrderChange.from_edifact(CHANGE.replace("QTY+21:60:PCE'", "QTY+21:60:PCE'\nDTM+2:20240201:102'"))
+
+    line = change.apply_to(order).get_order_lines()[0]
+    assert [(date.qualifier, date.value) for date in line.dates] == [("2", "20240201")]
+
+def test_order_change_from_orders():
+    order = Order.from_edifact(ORDER)
+    updated = OrderChange.from_edifact(CHANGE).apply_to(order)
+
+    change = OrderChange.from_orders(order, updated, "CHG2", control_reference="ICR2")
+    parsed = OrderChange.from_edifact(change.to_edifact())
+    assert [line.line_action for line in parsed.get_lines()] == [
+        LineAction.Changed,
+        LineAction.Added,
+        LineAction.Deleted,
+    ]
+
+def test_order_change_rejects_unknown_line():
+    order = Order.from_edifact(ORDER)
+    change = OrderChange.from_edifact(CHANGE.replace("LIN+2+2'", "LIN+9+2'"))
+    with pytest.raises(ValueError):
+        change.apply_to(order)
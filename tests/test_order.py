@@ -1,5 +1,5 @@
 import pytest
-from edifact_parser import Order
+from edifact_parser import Order, OrderLine, Segment
 
 def test_order_from_edifact(sample_edifact):
     order = Order.from_edifact(sample_edifact)
@@ -20,3 +20,63 @@ def test_order_lines(sample_edifact):
     assert lines[0].line_segment.tag == "LIN"
     assert lines[0].quantity is not None
     assert lines[0].price is not None
+
+def test_order_service_segment_validation(sample_edifact):
+    order = Order.from_edifact(sample_edifact)
+    assert order.validate_service_segments() == []
+
+    broken = Order.from_edifact(sample_edifact.replace("20240119", "20241319"))
+    issues = broken.validate_service_segments()
+    assert [issue.tag for issue in issues] == ["UNB"]
+    assert issues[0].code == "12"
+
+def test_order_interchange_header(sample_edifact):
+    from datetime import datetime
+
+    header = Order.from_edifact(sample_edifact).get_interchange_header()
+    assert header.syntax_identifier == "UNOA"
+    assert header.syntax_version == 4
+    assert header.sender.id == "SENDER"
+    assert header.recipient.id == "RECEIVER"
+    assert header.prepared == datetime(2024, 1, 19, 12, 0)
+    assert header.control_reference == "REF123"
+    assert not header.test_indicator
+
+def test_order_header_and_line_details():
+    content = """UNB+UNOC:4+SENDER+RECEIVER+20240119:1200+REF123'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240119:102'
+NAD+BY+5412345000013::9++Buyer Ltd'
+CTA+OC+:Jane Doe'
+COM+jane@example.com:EM'
+CUX+2:EUR:9'
+LIN+1++4000862141404:SRV'
+PIA+1+ABC123:SA'
+QTY+21:48:PCE'
+QTY+192:2:PCE'
+LOC+7+STORE1'
+QTY+11:48'
+LIN+2++4000862141411:SRV+1:1'
+QTY+21:12'
+UNS+S'"""
+    order = Order.from_edifact(content)
+    assert order.get_document_number() == "PO12345"
+    assert order.get_dates()[0].value == "20240119"
+
+    buyer = order.get_party("BY")
+    assert buyer.name == "Buyer Ltd"
+    assert buyer.contacts[0].communications[0].number == "jane@example.com"
+    assert order.get_currencies()[0].currency == "EUR"
+
+    lines = order.get_order_lines()
+    assert len(lines) == 1
+    assert [q.qualifier for q in lines[0].quantities] == ["21", "192"]
+    assert lines[0].product_ids[0].item_numbers[0].number == "ABC123"
+    assert lines[0].locations[0].quantity.value == "48"
+    assert lines[0].sub_lines[0].line_number == "2"
+
+def test_order_line_starts_with_lin():
+    assert OrderLine(Segment("LIN", [["1"]], 0)).line_number == "1"
+    with pytest.raises(ValueError):
+        OrderLine(Segment("QTY", [["21", "5"]], 0))
@@ -0,0 +1,58 @@
+from datetime import datetime
+
+import pytest
+from edifact_parser import LineAction, LineDecision, Order, OrderResponse
+
+ORDER = """UNB+UNOC:4+BUYER:14+SUPPLIER:14+20240119:1200+ICR1'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO12345+9'
+DTM+137:20240119:102'
+NAD+BY+5412345000013::9'
+LIN+1++4000862141404:SRV'
+QTY+21:48:PCE'
+LIN+2++4000862141411:SRV'
+QTY+21:12:PCE'
+UNS+S'
+UNT+9+1'
+UNZ+1+ICR1'"""
+
+def test_order_response_generation_and_parsing():
+    order = Order.from_edifact(ORDER)
+    response = OrderResponse.from_order(
+        order,
+        "RSP1",
+        [LineDecision("2", LineAction.Changed, quantity="10")],
+        datetime(2024, 1, 20, 9, 30),
+        control_reference="ICR2",
+    )
+
+    edifact = response.to_edifact()
+    assert "BGM+231+RSP1+4'" in edifact
+    assert "DTM+137:20240120:102'" in edifact
+    assert "RFF+ON:PO12345'" in edifact
+
+    parsed = OrderResponse.from_edifact(edifact)
+    assert parsed.message_trailer.tag == "UNT"
+    assert parsed.interchange_trailer.elements[1] == ["ICR2"]
+    assert parsed.get_order_number() == "PO12345"
+    lines = parsed.get_lines()
+    assert [line.line_action for line in lines] == [LineAction.Accepted, LineAction.Changed]
+    assert lines[1].quantities[0].value == "10"
+
+def test_order_response_rejects_unknown_line():
+    order = Order.from_edifact(ORDER)
+    with pytest.raises(ValueError):
+        OrderResponse.from_order(
+            order, "RSP1", [LineDecision("9", LineAction.Accepted)], datetime(2024, 1, 20)
+        )
+
+def test_order_response_checks_decisions():
+    order = Order.from_edifact(ORDER)
+    for decisions in [
+        [LineDecision("2", LineAction.Changed, quantity="abc")],
+        [LineDecision("2", LineAction.Changed, delivery_date="2024-13-99")],
+        [LineDecision("2", LineAction.Changed, price="x y")],
+        [LineDecision("2", LineAction.Changed, quantity="10"), LineDecision("2", LineAction.NotAccepted)],
+    ]:
+        with pytest.raises(ValueError):
+            OrderResponse.from_order(order, "RSP1", decisions, datetime(2024, 1, 20))
@@ -0,0 +1,64 @@
+import pytest
+from edifact_parser import PaymentOrder, PaymentOrderBuilder
+
+def test_multiple_payment_order_totals():
+    order = (PaymentOrderBuilder("PAYMUL", "PM0001")
+        .with_date("137", "20240301")
+        .with_execution_date("20240305")
+        .with_ordering_account("NL91ABNA0417164300", bic="ABNANL2A", holder="Acme BV")
+        .add_payment("Supplier GmbH", "DE89370400440532013000", "1190", "EUR", "INV001",
+                     bic="COBADEFF", remittance_information="Invoice INV001")
+        .add_payment("Widgets Ltd", "GB82WEST12345698765432", "250.5", "GBP", "INV002")
+        .add_payment("Parts GmbH", "DE89370400440532013000", "10.25", "EUR", "INV003")
+        .build())
+
+    edifact = order.to_edifact()
+    assert "CNT+1:1450.75'" in edifact
+    assert "CNT+2:2'" in edifact
+
+    parsed = PaymentOrder.from_edifact(edifact)
+    assert [total.amount for total in parsed.get_control_totals()] == ["1200.25", "250.50"]
+    payments = parsed.get_payments()
+    assert [payment.reference for payment in payments] == ["INV001", "INV003", "INV002"]
+    assert payments[0].bic == "COBADEFF"
+    assert payments[0].execution_date == "20240305"
+
+def test_single_payment_order():
+    builder = (PaymentOrderBuilder("PAYORD", "PO0001")
+        .with_date("137", "20240301")
+        .with_ordering_account("NL91ABNA0417164300"))
+    with pytest.raises(ValueError):
+        builder.add_payment("Bad", "NL91ABNA0417164301", "10", "EUR", "R1")
+
+    builder = builder.add_payment("Widgets Ltd", "GB82WEST12345698765432", "99.99", "GBP", "R1")
+    with pytest.raises(ValueError):
+        builder.add_payment("Again", "GB82WEST12345698765432", "1", "GBP", "R2")
+
+    payment = builder.build().get_payments()[0]
+    assert payment.amount == "99.99"
+    assert payment.beneficiary == "Widgets Ltd"
+
+def test_payment_amounts_are_whole_cents():
+    builder = (PaymentOrderBuilder("PAYMUL", "PM0001")
+        .with_date("137", "20240301")
+        .with_ordering_account("NL91ABNA0417164300"))
+    for amount in ["inf", "1e3", "0.001", "1.005", "0"]:
+        with pytest.raises(ValueError):
+            builder.add_payment("Widgets Ltd", "GB82WEST12345698765432", amount, "GBP", "R1")
+    with pytest.raises(ValueError):
+        builder.add_payment("Widgets Ltd", "GB82WEST12345698765432", "10", "gbp", "R1")
+    with pytest.raises(ValueError):
+        builder.with_execution_date("20241301")
+
+    builder.add_payment("Widgets Ltd", "GB82WEST12345698765432", "0.10", "GBP", "R1")
+    builder.add_payment("Parts Ltd", "GB82WEST12345698765432", "0.20", "GBP", "R2")
+    assert builder.build().get_payments()[1].amount == "0.20"
+
+def test_unsupported_message_type():
+    with pytest.raises(ValueError):
+        PaymentOrderBuilder("ORDERS", "1")
+
+def test_payment_builder_chains_in_place():
+    builder = PaymentOrderBuilder("PAYMUL", "PM0001")
+    assert builder.with_date("137", "20240301") is builder
+    assert builder.with_execution_date("20240305") is builder
@@ -0,0 +1,41 @@
+from edifact_parser import PriceCatalogue
+
+CATALOGUE = """UNA:+.? '
+UNH+1+PRICAT:D:96A:UN'
+BGM+9+CAT2024+9'
+DTM+194:20240201:102'
+DTM+206:20241231:102'
+NAD+SU+4012345000016::9'
+CUX+2:EUR:8'
+PGI+1'
+LIN+1++4000862141404:SRV'
+IMD+F++:::Widget'
+MEA+PD+AAB+KGM:0.25'
+PRI+AAA:10.00'
+LIN+2++4000862141411:SRV'
+PRI+AAA:15.50'
+PGI+2'
+DTM+194:20240301:102'
+LIN+3++4000862141428:SRV'
+PRI+AAA:7.25'
+UNT+17+1'"""
+
+def test_catalogue_header():
+    catalogue = PriceCatalogue.from_edifact(CATALOGUE)
+    assert catalogue.get_valid_from().value == "20240201"
+    assert catalogue.get_valid_until().value == "20241231"
+    assert catalogue.get_currency() == "EUR"
+    assert catalogue.get_supplier().id == "4012345000016"
+
+def test_catalogue_products():
+    catalogue = PriceCatalogue.from_edifact(CATALOGUE)
+    products = list(catalogue)
+    assert [product.line_number for product in products] == ["1", "2", "3"]
+    assert products[0].measurements[0].value == "0.25"
+    assert products[1].prices[0].amount == "15.50"
+
+def test_catalogue_product_groups():
+    products = PriceCatalogue.from_edifact(CATALOGUE).products()
+    groups = [(product.line_number, products.product_group.code) for product in products]
+    assert groups == [("1", "1"), ("2", "1"), ("3", "2")]
+    assert products.product_group.get_valid_from().value == "20240301"
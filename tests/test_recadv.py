@@ -0,0 +1,53 @@
+from edifact_parser import DespatchAdvice, ReceivingAdvice
+
+DESPATCH = """UNH+1+DESADV:D:96A:UN'
+BGM+351+DES587441+9'
+CPS+1'
+PAC+1++CT'
+PCI+33E'
+GIN+BJ+354123450000000014'
+LIN+1++4000862141404:SRV'
+QTY+12:24'
+UNT+9+1'"""
+
+RECEIPT = """UNH+1+RECADV:D:96A:UN'
+BGM+632+REC001+9'
+RFF+AAK:DES587441'
+CPS+1'
+LIN+1++4000862141404:SRV'
+QTY+12:24'
+QTY+48:22'
+QVR+-2:48+BP+AG'
+UNT+9+1'"""
+
+def test_receipt_lines():
+    receipt = ReceivingAdvice.from_edifact(RECEIPT)
+    line = receipt.get_receipt_lines()[0]
+    assert (line.despatched, line.received) == ("24", "22")
+    assert line.discrepancies[0].reason == "AG"
+
+def test_compare_with_despatch():
+    receipt = ReceivingAdvice.from_edifact(RECEIPT)
+    despatch = DespatchAdvice.from_edifact(DESPATCH)
+    difference, = receipt.compare_with_despatch(despatch)
+    assert difference.item_number == "4000862141404"
+    assert difference.difference == "-2"
+    assert difference.reasons == ["AG"]
+    assert receipt.missing_packages(despatch) == ["354123450000000014"]
+
+def test_compare_sums_quantities_exactly():
+    despatch = DespatchAdvice.from_edifact("""UNH+1+DESADV:D:96A:UN'
+BGM+351+DES1+9'
+CPS+1'
+LIN+1++4000862141404:SRV'
+QTY+12:0.1:KGM'
+LIN+2++4000862141404:SRV'
+QTY+12:0.2:KGM'
+UNT+8+1'""")
+    receipt = ReceivingAdvice.from_edifact("""UNH+1+RECADV:D:96A:UN'
+BGM+632+REC1+9'
+CPS+1'
+LIN+1++4000862141404:SRV'
+QTY+48:0.3:KGM'
+UNT+6+1'""")
+    assert receipt.compare_with_despatch(despatch) == []
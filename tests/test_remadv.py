@@ -0,0 +1,50 @@
+from edifact_parser import Invoice, PaymentStatus, RemittanceAdvice
+
+REMITTANCE = """UNH+1+REMADV:D:96A:UN'
+BGM+481+RA0001+9'
+DTM+137:20240215:102'
+NAD+PR+5412345000013::9'
+CUX+2:EUR:11'
+DOC+380+INV001'
+MOA+9:1190.00'
+MOA+12:1190.00'
+DOC+380+INV002'
+MOA+9:595.00'
+MOA+12:565.00'
+AJT+2'
+MOA+8:30.00'
+DOC+380+INV999'
+MOA+12:100.00'
+UNS+S'
+MOA+12:1855.00'
+UNT+18+1'"""
+
+def invoice(number, amount):
+    return Invoice.from_edifact(
+        f"UNH+1+INVOIC:D:96A:UN'BGM+380+{number}+9'UNS+S'MOA+77:{amount}'UNT+5+1'"
+    )
+
+def test_remittance_lines():
+    advice = RemittanceAdvice.from_edifact(REMITTANCE)
+    assert advice.get_total_amount().amount == "1855.00"
+    lines = advice.get_lines()
+    assert [line.document_number for line in lines] == ["INV001", "INV002", "INV999"]
+    assert lines[1].remitted_amount() == "565.00"
+    assert lines[1].adjustments[0].reason == "2"
+
+def test_reconcile_with_invoices():
+    advice = RemittanceAdvice.from_edifact(REMITTANCE)
+    matches = advice.reconcile([invoice("INV001", "1190.00"), invoice("INV002", "595.00")])
+    assert [match.status for match in matches] == [
+        PaymentStatus.Paid,
+        PaymentStatus.Underpaid,
+        PaymentStatus.Unmatched,
+    ]
+    assert matches[1].difference == "-30.00"
+
+def test_reconcile_without_invoice_total():
+    advice = RemittanceAdvice.from_edifact(REMITTANCE)
+    invoice = Invoice.from_edifact("UNH+1+INVOIC:D:96A:UN'BGM+380+INV001+9'UNT+3+1'")
+    match = advice.reconcile([invoice])[0]
+    assert match.status == PaymentStatus.AmountUnknown
+    assert match.difference is None
@@ -0,0 +1,27 @@
+from edifact_parser import InventoryReport, SalesReport
+
+def test_inventory_report_records():
+    report = InventoryReport.from_edifact("""UNH+1+INVRPT:D:96A:UN'
+BGM+35+INV0001+9'
+DTM+137:20240131:102'
+DTM+366:20240131:102'
+LIN+1++4000862141404:SRV'
+LOC+18+STORE1'
+QTY+145:70'
+UNT+7+1'""")
+    assert [r.to_tuple() for r in report.get_records()] == [
+        ("STORE1", "4000862141404", "145", "70", "20240131"),
+    ]
+
+def test_sales_report_records():
+    report = SalesReport.from_edifact("""UNH+1+SLSRPT:D:96A:UN'
+BGM+73E+SLS0001+9'
+DTM+137:20240108:102'
+LOC+162+STORE1'
+LIN+1++4000862141404:SRV'
+QTY+153:5'
+DTM+356:20240102:102'
+UNT+7+1'""")
+    record = report.get_records()[0]
+    assert (record.location, record.item, record.quantity) == ("STORE1", "4000862141404", "5")
+    assert record.period.qualifier == "356"
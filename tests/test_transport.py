@@ -0,0 +1,41 @@
+from edifact_parser import TransportInstruction, TransportStatus
+
+INSTRUCTION = """UNH+1+IFTMIN:D:96A:UN'
+BGM+340+BKG0001+9'
+RFF+CN:CONS77'
+TDT+20+V123+1++MAEU:172:20+++9321483::11:EMMA MAERSK'
+LOC+9+NLRTM'
+NAD+CZ+5412345000013::9'
+GID+1+20:CT'
+FTX+AAA+++Machine parts'
+EQD+CN+MSKU1234565+22G1+2++5'
+SEL+SEAL001'
+UNT+11+1'"""
+
+STATUS = """UNH+1+IFTSTA:D:96A:UN'
+BGM+77+STS0001+9'
+CNI+1+CONS77'
+STS+1+24'
+DTM+334:202403051830:203'
+LOC+9+NLRTM'
+STS+1+21'
+LOC+11+USNYC'
+UNT+9+1'"""
+
+def test_transport_instruction():
+    consignment = TransportInstruction.from_edifact(INSTRUCTION).get_consignment()
+    assert consignment.number == "CONS77"
+    assert consignment.transport[0].vessel_name == "EMMA MAERSK"
+    assert consignment.transport[0].locations[0].id == "NLRTM"
+    assert consignment.goods_items[0].description == "Machine parts"
+    assert consignment.equipment[0].seals == ["SEAL001"]
+
+def test_transport_status():
+    consignments = TransportStatus.from_edifact(STATUS).get_consignments()
+    assert consignments[0].number == "CONS77"
+    events = consignments[0].events
+    assert [(event.code, event.locations[0].id) for event in events] == [
+        ("24", "NLRTM"),
+        ("21", "USNYC"),
+    ]
+    assert events[0].dates[0].qualifier == "334"
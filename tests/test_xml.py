@@ -0,0 +1,56 @@
+import pytest
+from edifact_parser import Directory, Interchange, Message
+
+CONTENT = """UNA:+.? '
+UNB+UNOC:3+SENDER+RECEIVER+240119:1200+REF1'
+UNH+1+ORDERS:D:96A:UN'
+BGM+220+PO1+9'
+NAD+BY+5412345000013::9+Smith & Sons <Ltd>'
+LIN+1++ITEM1:BP'
+UNT+5+1'
+UNZ+1+REF1'
+"""
+
+SEGMENTS = """BGM Beginning of message
+  C002 C Document/message name
+    1001 C an..3 Document name code
+  1004 C an..35 Document identifier
+  1225 C an..3 Message function code
+"""
+
+def test_message_xml_without_directory():
+    message = Interchange.from_edifact(CONTENT).get_messages("ORDERS")[0]
+    xml = message.to_xml()
+    assert xml.startswith('<?xml version="1.0" encoding="UTF-8"?>\n<ORDERS>')
+    assert "<e1>220</e1>" in xml
+    assert "<e3>Smith &amp; Sons &lt;Ltd&gt;</e3>" in xml
+
+    read = Message.from_xml(xml)
+    assert [s.elements for s in read.segments] == [s.elements for s in message.segments]
+
+def test_message_xml_with_directory():
+    directory = Directory()
+    directory.load_segment_definitions(SEGMENTS)
+    message = Interchange.from_edifact(CONTENT).get_messages("ORDERS")[0]
+
+    xml = message.to_xml(directory)
+    assert "<C_C002>\n      <D_1001>220</D_1001>\n    </C_C002>" in xml
+    assert "<D_1004>PO1</D_1004>" in xml
+    assert "<SG2>" in xml and "<SG25>" in xml
+
+    read = Message.from_xml(xml, directory)
+    assert [s.elements for s in read.segments] == [s.elements for s in message.segments]
+
+def test_interchange_xml_round_trip():
+    interchange = Interchange.from_edifact(CONTENT)
+    xml = interchange.to_xml()
+    assert '<Interchange component=":" data="+" decimal="." escape="?" segment="\'"' in xml
+
+    read = Interchange.from_xml(xml)
+    assert read.to_edifact() == interchange.to_edifact()
+
+def test_invalid_xml():
+    with pytest.raises(ValueError):
+        Message.from_xml("<ORDERS><BGM>")
+    with pytest.raises(ValueError):
+        Message.from_xml("<ORDERS><BGM><D_9999>1</D_9999></BGM></ORDERS>")